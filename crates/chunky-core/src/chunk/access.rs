@@ -0,0 +1,24 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The frame a chunk was last accessed on. Chunks are read through shared references from many
+/// threads at once, so the stamp is atomic.
+#[derive(Debug, Default)]
+pub(super) struct AccessStamp(AtomicU64);
+
+impl AccessStamp {
+    /// Record an access on the given frame.
+    pub(super) fn touch(&self, frame: u64) {
+        self.0.store(frame, Ordering::Relaxed);
+    }
+
+    /// Return the frame of the last access.
+    pub(super) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Clone for AccessStamp {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.get()))
+    }
+}
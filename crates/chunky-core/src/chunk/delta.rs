@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{state::BlockState, BlockData, BlockPos, BlockType, Chunk, ChunkPos};
+
+/// The changes made to a chunk between two of its revisions, so that edits can be saved, undone,
+/// or sent over the network without copying the whole chunk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkDelta {
+    /// The position of the chunk the changes were made to.
+    pub position: ChunkPos,
+    /// The revision of the chunk the changes apply on top of.
+    pub base_revision: u64,
+    /// The revision of the chunk once the changes have been made.
+    pub revision: u64,
+    /// The new type of each changed block.
+    blocks: BTreeMap<BlockPos, BlockType>,
+    /// The new extra data of each block whose data changed, or `None` if it was removed.
+    block_data: BTreeMap<BlockPos, Option<BlockData>>,
+    /// The new state of each block whose state changed.
+    states: BTreeMap<BlockPos, BlockState>,
+}
+
+impl ChunkDelta {
+    /// Create an empty delta on top of the given revision of a chunk.
+    pub fn new(position: ChunkPos, base_revision: u64) -> Self {
+        Self {
+            position,
+            base_revision,
+            revision: base_revision,
+            blocks: BTreeMap::new(),
+            block_data: BTreeMap::new(),
+            states: BTreeMap::new(),
+        }
+    }
+
+    /// Record the changes between an earlier copy of a chunk and the chunk as it is now. Only the
+    /// sections modified since the earlier copy are compared.
+    pub fn between(base: &Chunk, chunk: &Chunk) -> Self {
+        let mut delta = Self::new(chunk.position, base.revision());
+        delta.revision = chunk.revision();
+        for index in chunk.sections_modified_since(base.revision()) {
+            let positions = base.sections[index]
+                .blocks()
+                .chain(chunk.sections[index].blocks())
+                .map(|(pos, _)| pos)
+                .collect::<BTreeSet<_>>();
+            for pos in positions {
+                let block = *chunk.block_at(pos);
+                if *base.block_at(pos) != block {
+                    delta.blocks.insert(pos, block);
+                }
+                let state = chunk.state_at(pos);
+                if base.state_at(pos) != state {
+                    delta.states.insert(pos, state);
+                }
+            }
+        }
+        let positions = base
+            .block_data
+            .keys()
+            .chain(chunk.block_data.keys())
+            .collect::<BTreeSet<_>>();
+        for &pos in positions {
+            let data = chunk.block_data.get(&pos);
+            if base.block_data.get(&pos) != data {
+                delta.block_data.insert(pos, data.cloned());
+            }
+        }
+        delta
+    }
+
+    /// Record the changes that undo this delta, given the chunk as it was before the delta was
+    /// applied.
+    pub fn reverted(&self, base: &Chunk) -> Self {
+        debug_assert_eq!(base.position, self.position);
+        let mut reverted = Self::new(self.position, self.revision);
+        reverted.revision = base.revision();
+        for &pos in self.blocks.keys() {
+            reverted.blocks.insert(pos, *base.block_at(pos));
+            // the block's data and state went with it, and come back with it
+            if let Some(data) = base.block_data_at(pos) {
+                reverted.block_data.insert(pos, Some(data.clone()));
+            }
+            if base.state_at(pos) != BlockState::DEFAULT {
+                reverted.states.insert(pos, base.state_at(pos));
+            }
+        }
+        for &pos in self.states.keys() {
+            reverted.states.insert(pos, base.state_at(pos));
+        }
+        for &pos in self.block_data.keys() {
+            reverted
+                .block_data
+                .insert(pos, base.block_data_at(pos).cloned());
+        }
+        reverted
+    }
+
+    /// Return an estimate of the memory used by the delta, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.blocks.len() * (size_of::<BlockPos>() + size_of::<BlockType>())
+            + self.block_data.len() * (size_of::<BlockPos>() + size_of::<Option<BlockData>>())
+            + self.states.len() * (size_of::<BlockPos>() + size_of::<BlockState>())
+    }
+
+    /// Record a change to a block.
+    pub fn set_block<I: Into<BlockPos>>(&mut self, pos: I, block: BlockType) {
+        self.blocks.insert(pos.into(), block);
+    }
+
+    /// Record a change to a block's extra data, where `None` removes it.
+    pub fn set_block_data<I: Into<BlockPos>>(&mut self, pos: I, data: Option<BlockData>) {
+        self.block_data.insert(pos.into(), data);
+    }
+
+    /// Record a change to a block's state.
+    pub fn set_block_state<I: Into<BlockPos>>(&mut self, pos: I, state: BlockState) {
+        self.states.insert(pos.into(), state);
+    }
+
+    /// Return an iterator over the changed blocks and their new types, ordered by their position.
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockPos, BlockType)> + '_ {
+        self.blocks.iter().map(|(&pos, &block)| (pos, block))
+    }
+
+    /// Return the number of changed blocks, including blocks whose only change was to their data or
+    /// state.
+    pub fn len(&self) -> usize {
+        self.positions().count()
+    }
+
+    /// Return an iterator over the positions of the changed blocks, including blocks whose only
+    /// change was to their data or state, ordered by their position.
+    pub fn positions(&self) -> impl Iterator<Item = BlockPos> {
+        self.blocks
+            .keys()
+            .chain(self.block_data.keys())
+            .chain(self.states.keys())
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+    }
+
+    /// Check if the delta records no changes.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.block_data.is_empty() && self.states.is_empty()
+    }
+
+    /// Apply the changes to a chunk, which must be the chunk the delta was recorded from.
+    pub fn apply(&self, chunk: &mut Chunk) {
+        debug_assert_eq!(chunk.position, self.position);
+        chunk.set_blocks(self.blocks());
+        for (&pos, data) in &self.block_data {
+            match data {
+                Some(data) => chunk.set_block_data(pos, data.clone()),
+                None => {
+                    chunk.remove_block_data(pos);
+                }
+            }
+        }
+        for (&pos, &state) in &self.states {
+            chunk.set_block_state(pos, state);
+        }
+    }
+
+    /// Combine this delta with one recorded after it, so that applying the result is the same as
+    /// applying both in turn.
+    pub fn merge(&mut self, later: ChunkDelta) {
+        debug_assert_eq!(later.position, self.position);
+        self.revision = self.revision.max(later.revision);
+        for (pos, block) in later.blocks {
+            self.blocks.insert(pos, block);
+            // changing the block type discards the previous block's data and state
+            if !later.block_data.contains_key(&pos) {
+                self.block_data.remove(&pos);
+            }
+            if !later.states.contains_key(&pos) {
+                self.states.remove(&pos);
+            }
+        }
+        self.block_data.extend(later.block_data);
+        self.states.extend(later.states);
+    }
+}
@@ -0,0 +1,95 @@
+use super::{BlockPos, CHUNK_SIZE};
+
+/// The number of blocks in a chunk.
+const VOLUME: usize = (CHUNK_SIZE as usize).pow(3);
+
+/// The density of every block in a chunk, kept alongside the blocks for smooth meshing. Densities
+/// range from -1 to 1, where positive densities are inside the terrain, and are quantized to a
+/// byte each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DensityVolume {
+    values: Box<[i8]>,
+}
+
+impl DensityVolume {
+    /// Create a volume with every block at the given density.
+    pub fn filled(density: f32) -> Self {
+        Self {
+            values: vec![Self::quantize(density); VOLUME].into_boxed_slice(),
+        }
+    }
+
+    /// Create a volume from quantized densities, or `None` if there is not one for every block.
+    pub fn from_quantized(values: Vec<i8>) -> Option<Self> {
+        (values.len() == VOLUME).then(|| Self {
+            values: values.into_boxed_slice(),
+        })
+    }
+
+    /// Return the quantized densities, in the order expected by [`DensityVolume::from_quantized`].
+    pub fn quantized(&self) -> &[i8] {
+        &self.values
+    }
+
+    /// Create a volume from runs of quantized densities, or `None` if the runs do not cover every
+    /// block exactly.
+    pub fn from_runs(runs: &[(i8, u16)]) -> Option<Self> {
+        let values = runs
+            .iter()
+            .flat_map(|&(value, length)| std::iter::repeat_n(value, length as usize))
+            .collect();
+        Self::from_quantized(values)
+    }
+
+    /// Return the quantized densities as runs of equal values. Densities saturate away from the
+    /// surface, so most of a chunk collapses into a few long runs.
+    pub fn runs(&self) -> Vec<(i8, u16)> {
+        let mut runs: Vec<(i8, u16)> = Vec::new();
+        for &value in self.values.iter() {
+            match runs.last_mut() {
+                Some((last, length)) if *last == value && *length < u16::MAX => *length += 1,
+                _ => runs.push((value, 1)),
+            }
+        }
+        runs
+    }
+
+    /// Return the index of the given block.
+    fn index(pos: BlockPos) -> usize {
+        let size = CHUNK_SIZE as usize;
+        (pos.z as usize * size + pos.x as usize) * size + pos.y as usize
+    }
+
+    /// Quantize a density to a byte.
+    fn quantize(density: f32) -> i8 {
+        (density.clamp(-1.0, 1.0) * i8::MAX as f32).round() as i8
+    }
+
+    /// Get the density at the given position.
+    pub fn get(&self, pos: BlockPos) -> f32 {
+        self.values[Self::index(pos)] as f32 / i8::MAX as f32
+    }
+
+    /// Set the density at the given position.
+    pub fn set(&mut self, pos: BlockPos, density: f32) {
+        self.values[Self::index(pos)] = Self::quantize(density);
+    }
+}
+
+/// A brush that sculpts smooth terrain by raising or lowering the density of the blocks around a
+/// point, turning them solid or empty as the density crosses the surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SculptBrush {
+    /// The radius of the brush, in blocks.
+    pub radius: f32,
+    /// The density added at the centre of the brush, falling off to nothing at its edge. Negative
+    /// strengths dig into the terrain.
+    pub strength: f32,
+}
+
+impl SculptBrush {
+    /// Return the density the brush adds at the given distance from its centre.
+    pub fn falloff(&self, distance: f32) -> f32 {
+        self.strength * (1.0 - distance / self.radius).max(0.0)
+    }
+}
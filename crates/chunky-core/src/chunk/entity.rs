@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// An entity kept in the chunk it stands in, such as a dropped item or a prop placed by a
+/// generator, so it is saved and reloaded along with the chunk. The entity's own data is opaque to
+/// the chunk, and read back by whatever registered its kind.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityRecord {
+    /// The name of the kind of entity, such as `dropped_item`.
+    pub kind: String,
+    /// The version of the kind's data layout the entity was saved in, so older records can still be
+    /// read once the layout changes.
+    pub version: u32,
+    /// The position of the entity, in world coordinates.
+    pub position: [f32; 3],
+    /// The entity's own data, serialized with bincode.
+    pub data: Vec<u8>,
+}
@@ -0,0 +1,59 @@
+use glam::IVec3;
+
+use super::{state::BlockState, BlockType};
+
+/// The level of the weakest flowing water. Water spreads one level weaker with each block it
+/// flows sideways, and stops spreading once it reaches this level.
+pub const MAX_LEVEL: u8 = 7;
+
+/// The directions water spreads in once it can no longer fall.
+const SIDEWAYS: [IVec3; 4] = [IVec3::NEG_Z, IVec3::X, IVec3::Z, IVec3::NEG_X];
+
+/// Return the level of water with the given state, stored in the bits the state leaves for the
+/// block's own use. Sources have a level of zero, and flowing water counts up from one to
+/// [`MAX_LEVEL`] as it spreads away from them.
+pub fn level(state: BlockState) -> u8 {
+    state.extra().min(MAX_LEVEL)
+}
+
+/// Return the state of water at the given level.
+pub fn state(level: u8) -> BlockState {
+    BlockState::DEFAULT.with_extra(level.min(MAX_LEVEL))
+}
+
+/// Return the height of the surface of water at the given level, as a fraction of a block.
+pub fn surface_height(level: u8) -> f32 {
+    1.0 - level.min(MAX_LEVEL) as f32 / (MAX_LEVEL + 1) as f32
+}
+
+/// Return the blocks the water at the given position flows into, and the level of the water each
+/// gets. Water falls into the block below it if it can, and otherwise spreads sideways one level
+/// weaker. It only flows into empty space, or into weaker flowing water. `block_at` returns the
+/// block and state at a position, or `None` if it is not loaded, which water never flows into.
+pub fn flow<F>(pos: IVec3, level: u8, block_at: F) -> Vec<(IVec3, u8)>
+where
+    F: Fn(IVec3) -> Option<(BlockType, BlockState)>,
+{
+    let accepts = |target: IVec3, level: u8| match block_at(target) {
+        Some((BlockType::EMPTY, _)) => true,
+        Some((BlockType::WATER, state)) => self::level(state) > level,
+        _ => false,
+    };
+    let below = pos + IVec3::NEG_Y;
+    match block_at(below) {
+        // falling water spreads out again wherever it lands
+        _ if accepts(below, 1) => return vec![(below, 1)],
+        // water resting on water has nowhere to go
+        Some((BlockType::WATER, _)) | None => return Vec::new(),
+        _ => {}
+    }
+    if level >= MAX_LEVEL {
+        return Vec::new();
+    }
+    SIDEWAYS
+        .iter()
+        .map(|offset| pos + *offset)
+        .filter(|&target| accepts(target, level + 1))
+        .map(|target| (target, level + 1))
+        .collect()
+}
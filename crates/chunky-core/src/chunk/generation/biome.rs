@@ -0,0 +1,330 @@
+use std::sync::Arc;
+
+use glam::IVec3;
+use itertools::{iproduct, Itertools};
+use noise::{NoiseFn, Seedable};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pipeline::{GenerationContext, GenerationStage},
+    template::StructureTemplate,
+};
+use crate::chunk::{generation, BlockPos, BlockType, CHUNK_SIZE};
+
+/// The horizontal size of climate regions, in blocks.
+const CLIMATE_SCALE: f64 = 512.0;
+
+/// An offset applied to the temperature noise, so it does not line up with other noise.
+const TEMPERATURE_OFFSET: f64 = -20_000.0;
+
+/// An offset applied to the humidity noise, so it does not line up with other noise.
+const HUMIDITY_OFFSET: f64 = 30_000.0;
+
+/// The distance either side of a column that terrain amplitude is averaged over, in blocks.
+const BLEND_RADIUS: i64 = 8;
+
+/// The number of blocks under a biome's surface block that are made of its filler block.
+pub const SUBSURFACE_DEPTH: i64 = 3;
+
+/// The climate zone of a column of the world, which shapes its terrain, the blocks on its surface,
+/// and the features placed on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Biome {
+    /// Gentle grassland with the odd tree.
+    Plains,
+    /// Wet, rolling grassland covered in trees.
+    Forest,
+    /// Hot, dry, and flat sand dotted with cacti.
+    Desert,
+    /// Frozen hills under snow.
+    Tundra,
+    /// Dry, high, bare rock.
+    Mountains,
+}
+
+impl Biome {
+    /// Return the biome of the given world block column.
+    pub fn at(noise: &impl NoiseFn<f64, 2>, x: i64, z: i64) -> Self {
+        let sample =
+            |offset: f64| noise.get([x as f64 / CLIMATE_SCALE + offset, z as f64 / CLIMATE_SCALE]);
+        Self::from_climate(sample(TEMPERATURE_OFFSET), sample(HUMIDITY_OFFSET))
+    }
+
+    /// Return the biome of a climate, where temperature and humidity range from -1 to 1.
+    pub fn from_climate(temperature: f64, humidity: f64) -> Self {
+        if temperature < -0.2 {
+            Self::Tundra
+        } else if temperature > 0.2 && humidity < 0.0 {
+            Self::Desert
+        } else if humidity < -0.2 {
+            Self::Mountains
+        } else if humidity > 0.2 {
+            Self::Forest
+        } else {
+            Self::Plains
+        }
+    }
+
+    /// Return how tall the biome's hills are, relative to the base terrain.
+    pub fn amplitude(self) -> f32 {
+        match self {
+            Self::Plains => 0.5,
+            Self::Forest => 0.8,
+            Self::Desert => 0.3,
+            Self::Tundra => 0.7,
+            Self::Mountains => 2.0,
+        }
+    }
+
+    /// Return the terrain amplitude at the given world block column, averaged over the biomes
+    /// around it so the terrain slopes rather than steps at biome borders.
+    pub fn blended_amplitude(noise: &impl NoiseFn<f64, 2>, x: i64, z: i64) -> f32 {
+        let total = iproduct!(-1..=1, -1..=1)
+            .map(|(dx, dz)| {
+                Self::at(noise, x + dx * BLEND_RADIUS, z + dz * BLEND_RADIUS).amplitude()
+            })
+            .sum::<f32>();
+        total / 9.0
+    }
+
+    /// Return the blocks the biome's terrain is covered in, unless the terrain settings say
+    /// otherwise.
+    pub fn default_palette(self) -> BiomePalette {
+        use BlockType as B;
+        let (surface, filler, underwater) = match self {
+            Self::Plains | Self::Forest => (B::GRASS, B::DIRT, B::SAND),
+            Self::Desert => (B::SAND, B::SAND, B::SAND),
+            Self::Tundra => (B::SNOW, B::DIRT, B::DIRT),
+            Self::Mountains => (B::STONE, B::STONE, B::STONE),
+        };
+        BiomePalette {
+            surface,
+            filler,
+            underwater,
+        }
+    }
+
+    /// Return the block erosion deposits in the biome, which lies under the surface block where
+    /// sediment has settled.
+    pub fn sediment(self) -> BlockType {
+        match self {
+            Self::Desert => BlockType::SAND,
+            Self::Plains | Self::Forest | Self::Tundra | Self::Mountains => BlockType::GRAVEL,
+        }
+    }
+
+    /// Return the feature placed on the biome's surface, and the one in however many columns it
+    /// is placed on.
+    pub fn decoration(self) -> Option<(Decoration, u64)> {
+        match self {
+            Self::Plains => Some((Decoration::Tree, 400)),
+            Self::Forest => Some((Decoration::Tree, 40)),
+            Self::Desert => Some((Decoration::Cactus, 150)),
+            Self::Tundra | Self::Mountains => None,
+        }
+    }
+}
+
+/// The blocks a biome's terrain is covered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BiomePalette {
+    /// The block on top of the terrain.
+    pub surface: BlockType,
+    /// The blocks under the surface block, above the stone.
+    pub filler: BlockType,
+    /// The block on top of the terrain where it lies under the sea.
+    pub underwater: BlockType,
+}
+
+/// The palette of each biome. Biomes missing from a settings file keep their default palettes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BiomePalettes {
+    pub plains: BiomePalette,
+    pub forest: BiomePalette,
+    pub desert: BiomePalette,
+    pub tundra: BiomePalette,
+    pub mountains: BiomePalette,
+}
+
+impl Default for BiomePalettes {
+    fn default() -> Self {
+        Self {
+            plains: Biome::Plains.default_palette(),
+            forest: Biome::Forest.default_palette(),
+            desert: Biome::Desert.default_palette(),
+            tundra: Biome::Tundra.default_palette(),
+            mountains: Biome::Mountains.default_palette(),
+        }
+    }
+}
+
+impl BiomePalettes {
+    /// Return the palette of the given biome.
+    pub fn get(&self, biome: Biome) -> BiomePalette {
+        match biome {
+            Biome::Plains => self.plains,
+            Biome::Forest => self.forest,
+            Biome::Desert => self.desert,
+            Biome::Tundra => self.tundra,
+            Biome::Mountains => self.mountains,
+        }
+    }
+}
+
+/// A feature placed on the surface of the terrain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Decoration {
+    /// A trunk of wood under a ball of leaves.
+    Tree,
+    /// A short column of cactus.
+    Cactus,
+    /// The structure template at the given index in the pipeline's templates.
+    Template(usize),
+}
+
+impl Decoration {
+    /// The blocks of the feature standing on the given surface block, in world block coordinates,
+    /// varying its size with the given random roll, and looking templates up in the given list.
+    pub fn blocks(
+        self,
+        surface: IVec3,
+        roll: u64,
+        templates: &[Arc<StructureTemplate>],
+    ) -> Vec<(IVec3, BlockType)> {
+        let (height, stem) = match self {
+            Self::Tree => (4 + (roll % 3) as i32, BlockType::WOOD),
+            Self::Cactus => (1 + (roll % 3) as i32, BlockType::CACTUS),
+            Self::Template(index) => {
+                return templates.get(index).map_or_else(Vec::new, |template| {
+                    template.blocks_at(surface + IVec3::Y).collect()
+                })
+            }
+        };
+        let top = surface.y + height;
+        let mut blocks = (surface.y + 1..=top)
+            .map(|y| (IVec3::new(surface.x, y, surface.z), stem))
+            .collect_vec();
+        if self == Self::Tree {
+            // a ball of leaves around the top of the trunk, without its corners
+            let leaves =
+                iproduct!(-2..=2, -1..=1, -2..=2).filter(|&(x, y, z): &(i32, i32, i32)| {
+                    let corner = x.abs() == 2 && z.abs() == 2;
+                    !corner && !(y == 1 && (x.abs() == 2 || z.abs() == 2))
+                });
+            blocks.extend(
+                leaves.map(|(x, y, z)| (surface + IVec3::new(x, height + y, z), BlockType::LEAVES)),
+            );
+        }
+        blocks
+    }
+}
+
+/// The stage covering the terrain in the blocks of each column's biome palette, with the
+/// underwater block in place of the surface block below sea level, and in place of both the
+/// surface and filler blocks along rivers and their banks. Sediment left by erosion lies between
+/// the surface and filler blocks.
+pub struct Surface;
+
+impl GenerationStage for Surface {
+    fn name(&self) -> &'static str {
+        "surface"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let (ox, oy, oz) = context.origin();
+        let sea_level = context.terrain.sea_level;
+        let water = context.water_tile();
+        let mut blocks = Vec::new();
+        for (x, z) in iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE) {
+            let (biome, _) = context.column(x, z);
+            let mut palette = context.terrain.palettes.get(biome);
+            // eroded sediment lies under the surface block, in place of the top of the filler
+            let deposit = (context.sediment(x, z).round() as i64).clamp(0, SUBSURFACE_DEPTH);
+            let (cx, cz) = water.cell_at(ox + x as i64, oz + z as i64);
+            if water.is_river(cx, cz) || water.is_riverbank(cx, cz) {
+                palette.surface = palette.underwater;
+                palette.filler = palette.underwater;
+            }
+            // walk down from above the chunk, so blocks know how deep under the surface they are
+            let mut depth = None;
+            for y in (0..GenerationContext::HEIGHT).rev() {
+                depth =
+                    (context.density(x, y, z) > 0.0).then(|| depth.map_or(0, |depth| depth + 1));
+                let block = match depth {
+                    Some(0) if ((oy + y + 1) as f32) < sea_level => palette.underwater,
+                    Some(0) => palette.surface,
+                    Some(depth) if depth <= deposit => biome.sediment(),
+                    Some(1..=SUBSURFACE_DEPTH) => palette.filler,
+                    _ => continue,
+                };
+                if y < CHUNK_SIZE as i64 {
+                    blocks.push((BlockPos::new(x, y as u8, z), block));
+                }
+            }
+        }
+        context.chunk.set_blocks(blocks);
+    }
+}
+
+/// The stage choosing where the features of each column's biome stand on its surface.
+pub struct Decorate;
+
+impl GenerationStage for Decorate {
+    fn name(&self) -> &'static str {
+        "decorate"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let (ox, oy, oz) = context.origin();
+        let seed = context.noise.seed();
+        for (x, z) in iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE) {
+            let (biome, _) = context.column(x, z);
+            // templates are rolled with seeds of their own, so they do not line up with each other
+            let templates = context
+                .templates
+                .iter()
+                .enumerate()
+                .filter_map(|(index, template)| {
+                    let placement = template.placement.as_ref()?;
+                    placement.biomes.contains(&biome).then_some((
+                        Decoration::Template(index),
+                        placement.rarity,
+                        seed.wrapping_add(index as u32 + 1),
+                    ))
+                })
+                .collect_vec();
+            let candidates = biome
+                .decoration()
+                .map(|(decoration, rarity)| (decoration, rarity, seed))
+                .into_iter()
+                .chain(templates)
+                .collect_vec();
+            if candidates.is_empty() {
+                continue;
+            }
+            // features stand on the highest block of the column with open air above it
+            let solid = |y: i64| context.density(x, y, z) > 0.0;
+            let Some(top) = (0..CHUNK_SIZE as i64)
+                .rev()
+                .find(|&y| solid(y) && !solid(y + 1))
+            else {
+                continue;
+            };
+            // nothing grows under the sea
+            if ((oy + top + 1) as f32) < context.terrain.sea_level {
+                continue;
+            }
+            // at most one feature stands on a column, the first to roll it
+            let surface = BlockPos::new(x, top as u8, z);
+            let chosen = candidates
+                .into_iter()
+                .find_map(|(decoration, rarity, seed)| {
+                    let roll = generation::hash(seed, ox + x as i64, oz + z as i64);
+                    roll.is_multiple_of(rarity)
+                        .then_some((decoration, surface, roll / rarity))
+                });
+            context.decorations.extend(chosen);
+        }
+    }
+}
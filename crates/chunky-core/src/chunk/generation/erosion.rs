@@ -0,0 +1,380 @@
+use std::sync::Arc;
+
+use itertools::iproduct;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pipeline::{GenerationContext, GenerationStage},
+    Heightmap, RegionCache,
+};
+use crate::chunk::{BlockPos, BlockType, ChunkPos, CHUNK_SIZE};
+
+/// The size of an erosion region along one horizontal axis, measured in chunks.
+pub const REGION_CHUNKS: i64 = 8;
+
+/// The size of a single erosion cell along one horizontal axis, measured in blocks.
+pub const CELL_SIZE: i64 = 4;
+
+/// The number of cells along one axis of a region tile.
+pub const REGION_CELLS: usize = (REGION_CHUNKS * CHUNK_SIZE as i64 / CELL_SIZE) as usize;
+
+/// The number of cells each region is eroded beyond its edges. Neighbouring tiles overlap by twice
+/// this, and are blended across the overlap so their edges meet.
+pub const MARGIN_CELLS: usize = 8;
+
+/// The number of cells along one axis of a region tile, including its margins.
+const TILE_CELLS: usize = REGION_CELLS + 2 * MARGIN_CELLS;
+
+/// A position of an erosion region in region coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegionPos {
+    pub x: i64,
+    pub z: i64,
+}
+
+impl RegionPos {
+    /// Create a new region position.
+    pub fn new(x: i64, z: i64) -> Self {
+        Self { x, z }
+    }
+
+    /// Get the region containing the given chunk.
+    pub fn from_chunk(pos: ChunkPos) -> Self {
+        Self::new(
+            pos.x.div_euclid(REGION_CHUNKS),
+            pos.z.div_euclid(REGION_CHUNKS),
+        )
+    }
+
+    /// Get the region containing the given world block column.
+    pub fn from_world_block(x: i64, z: i64) -> Self {
+        let size = REGION_CHUNKS * CHUNK_SIZE as i64;
+        Self::new(x.div_euclid(size), z.div_euclid(size))
+    }
+
+    /// Return the world block coordinates of the region's minimum corner as `(x, z)`.
+    pub fn origin(&self) -> (i64, i64) {
+        let size = REGION_CHUNKS * CHUNK_SIZE as i64;
+        (self.x * size, self.z * size)
+    }
+
+    /// Convert a world block column into fractional cell coordinates within this region.
+    pub fn cell(&self, x: i64, z: i64) -> (f32, f32) {
+        let (ox, oz) = self.origin();
+        (
+            (x - ox) as f32 / CELL_SIZE as f32,
+            (z - oz) as f32 / CELL_SIZE as f32,
+        )
+    }
+}
+
+/// Settings for the erosion simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErosionSettings {
+    /// Whether erosion is applied during generation.
+    pub enabled: bool,
+    /// The number of thermal erosion iterations to run.
+    pub thermal_iterations: u32,
+    /// The largest height difference between neighbouring cells that is considered stable.
+    pub talus: f32,
+    /// The fraction of unstable material moved downhill per thermal iteration.
+    pub thermal_rate: f32,
+    /// The number of hydraulic erosion iterations to run.
+    pub hydraulic_iterations: u32,
+    /// The amount of water added to each cell per hydraulic iteration.
+    pub rain: f32,
+    /// The amount of sediment a unit of water can carry per unit of slope.
+    pub capacity: f32,
+    /// The fraction of spare capacity picked up from the terrain per iteration.
+    pub solubility: f32,
+    /// The fraction of excess sediment deposited per iteration.
+    pub deposition: f32,
+    /// The fraction of water that evaporates per iteration.
+    pub evaporation: f32,
+}
+
+impl Default for ErosionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            thermal_iterations: 16,
+            talus: CELL_SIZE as f32,
+            thermal_rate: 0.5,
+            hydraulic_iterations: 64,
+            rain: 0.01,
+            capacity: 1.0,
+            solubility: 0.1,
+            deposition: 0.3,
+            evaporation: 0.05,
+        }
+    }
+}
+
+/// An eroded region tile, covering the region and a margin of [`MARGIN_CELLS`] around it.
+#[derive(Debug, Clone)]
+pub struct ErodedTile {
+    /// The region this tile covers.
+    pub region: RegionPos,
+    /// The surface height of each cell after erosion, starting a margin before the region.
+    pub height: Heightmap,
+    /// The depth of deposited sediment in each cell, starting a margin before the region.
+    pub sediment: Heightmap,
+}
+
+impl ErodedTile {
+    /// Return the fractional cell coordinates of a world block column within the tile.
+    fn cell(&self, x: i64, z: i64) -> (f32, f32) {
+        let (cx, cz) = self.region.cell(x, z);
+        (cx + MARGIN_CELLS as f32, cz + MARGIN_CELLS as f32)
+    }
+
+    /// Sample the eroded surface height and sediment depth at the given world block column.
+    pub fn sample(&self, x: i64, z: i64) -> (f32, f32) {
+        let (cx, cz) = self.cell(x, z);
+        (self.height.sample(cx, cz), self.sediment.sample(cx, cz))
+    }
+
+    /// Return how much the tile counts towards the blended surface at the given world block
+    /// column. This falls from one inside the region to zero at the outer edge of its margin, where
+    /// the tile's erosion is least like that of its neighbours.
+    pub fn weight(&self, x: i64, z: i64) -> f32 {
+        let (cx, cz) = self.cell(x, z);
+        let last = (TILE_CELLS - 1) as f32;
+        let fade = |c: f32| (c.min(last - c) / MARGIN_CELLS as f32).clamp(0.0, 1.0);
+        fade(cx) * fade(cz)
+    }
+}
+
+/// The eroded tiles overlapping an area, blended together so the surface is continuous across
+/// region edges.
+#[derive(Debug, Clone)]
+pub struct ErodedTiles {
+    tiles: Vec<Arc<ErodedTile>>,
+}
+
+impl ErodedTiles {
+    /// Return the regions whose tiles overlap the given box of world block columns (inclusive).
+    pub fn regions(min: (i64, i64), max: (i64, i64)) -> impl Iterator<Item = RegionPos> {
+        let margin = MARGIN_CELLS as i64 * CELL_SIZE;
+        let lo = RegionPos::from_world_block(min.0 - margin, min.1 - margin);
+        let hi = RegionPos::from_world_block(max.0 + margin, max.1 + margin);
+        iproduct!(lo.x..=hi.x, lo.z..=hi.z).map(|(x, z)| RegionPos::new(x, z))
+    }
+
+    /// Blend the given tiles, which should be those of [`ErodedTiles::regions`].
+    pub fn new(tiles: Vec<Arc<ErodedTile>>) -> Self {
+        Self { tiles }
+    }
+
+    /// Sample the blended surface height and sediment depth at the given world block column.
+    pub fn sample(&self, x: i64, z: i64) -> (f32, f32) {
+        let (mut height, mut sediment, mut total) = (0.0, 0.0, 0.0);
+        for tile in &self.tiles {
+            let weight = tile.weight(x, z);
+            if weight <= 0.0 {
+                continue;
+            }
+            let (h, s) = tile.sample(x, z);
+            height += h * weight;
+            sediment += s * weight;
+            total += weight;
+        }
+        match total > 0.0 {
+            true => (height / total, sediment / total),
+            false => (0.0, 0.0),
+        }
+    }
+
+    /// Return the blended surface height of each cell of a region.
+    pub fn region_heights(&self, region: RegionPos) -> Heightmap {
+        region_heights(region, |x, z| self.sample(x, z).0)
+    }
+}
+
+/// Sample the surface height of each cell of a region from the given function of world block
+/// coordinates.
+pub fn region_heights(region: RegionPos, base: impl Fn(i64, i64) -> f32) -> Heightmap {
+    let (ox, oz) = region.origin();
+    Heightmap::from_fn(REGION_CELLS, REGION_CELLS, |x, z| {
+        base(ox + x as i64 * CELL_SIZE, oz + z as i64 * CELL_SIZE)
+    })
+}
+
+/// Erode a region and the margin around it, sampling its base surface height from the given
+/// function of world block coordinates.
+pub fn erode_region(
+    region: RegionPos,
+    settings: &ErosionSettings,
+    base: impl Fn(i64, i64) -> f32,
+) -> ErodedTile {
+    let (ox, oz) = region.origin();
+    let margin = MARGIN_CELLS as i64;
+    let mut height = Heightmap::from_fn(TILE_CELLS, TILE_CELLS, |x, z| {
+        base(
+            ox + (x as i64 - margin) * CELL_SIZE,
+            oz + (z as i64 - margin) * CELL_SIZE,
+        )
+    });
+    let mut sediment = Heightmap::zeros(TILE_CELLS, TILE_CELLS);
+
+    erode_hydraulic(&mut height, &mut sediment, settings);
+    erode_thermal(&mut height, &mut sediment, settings);
+
+    ErodedTile {
+        region,
+        height,
+        sediment,
+    }
+}
+
+/// Run thermal erosion, moving material from steep slopes to their lowest neighbour until the
+/// terrain settles at the talus angle.
+pub fn erode_thermal(height: &mut Heightmap, sediment: &mut Heightmap, settings: &ErosionSettings) {
+    let (width, depth) = height.dim();
+    for _ in 0..settings.thermal_iterations {
+        for (x, z) in iproduct!(0..width, 0..depth) {
+            let here = height.get(x, z);
+            let Some((nx, nz)) = height.lowest_neighbour(x, z) else {
+                continue;
+            };
+            let diff = here - height.get(nx, nz);
+            if diff <= settings.talus {
+                continue;
+            }
+            let moved = (diff - settings.talus) * settings.thermal_rate * 0.5;
+            *height.get_mut(x, z) -= moved;
+            *height.get_mut(nx, nz) += moved;
+            *sediment.get_mut(nx, nz) += moved;
+            let loose = sediment.get_mut(x, z);
+            *loose = (*loose - moved).max(0.0);
+        }
+    }
+}
+
+/// Run hydraulic erosion, carving channels where water flows downhill and depositing the
+/// carried sediment where it slows.
+pub fn erode_hydraulic(
+    height: &mut Heightmap,
+    sediment: &mut Heightmap,
+    settings: &ErosionSettings,
+) {
+    let (width, depth) = height.dim();
+    let mut water = Heightmap::zeros(width, depth);
+    let mut carried = Heightmap::zeros(width, depth);
+
+    for _ in 0..settings.hydraulic_iterations {
+        for (x, z) in iproduct!(0..width, 0..depth) {
+            *water.get_mut(x, z) += settings.rain;
+
+            let Some((nx, nz)) = height.lowest_neighbour(x, z) else {
+                // nowhere to flow, so drop everything here
+                let amount = carried.get(x, z);
+                deposit(height, sediment, &mut carried, x, z, amount);
+                continue;
+            };
+            let slope = (height.get(x, z) - height.get(nx, nz)).max(0.0);
+            let capacity = slope * water.get(x, z) * settings.capacity;
+            let load = carried.get(x, z);
+
+            if load > capacity {
+                let amount = (load - capacity) * settings.deposition;
+                deposit(height, sediment, &mut carried, x, z, amount);
+            } else {
+                // never dig below the neighbour, or water would pool in the channel
+                let amount = ((capacity - load) * settings.solubility).min(slope * 0.5);
+                *height.get_mut(x, z) -= amount;
+                *carried.get_mut(x, z) += amount;
+                let loose = sediment.get_mut(x, z);
+                *loose = (*loose - amount).max(0.0);
+            }
+
+            // move the water and its load downhill
+            let flow = water.get(x, z);
+            let load = carried.get(x, z);
+            *water.get_mut(x, z) = 0.0;
+            *carried.get_mut(x, z) = 0.0;
+            *water.get_mut(nx, nz) += flow;
+            *carried.get_mut(nx, nz) += load;
+        }
+
+        for (x, z) in iproduct!(0..width, 0..depth) {
+            *water.get_mut(x, z) *= 1.0 - settings.evaporation;
+        }
+    }
+
+    // settle whatever is still in suspension
+    for (x, z) in iproduct!(0..width, 0..depth) {
+        let amount = carried.get(x, z);
+        deposit(height, sediment, &mut carried, x, z, amount);
+    }
+}
+
+/// Move sediment out of suspension and onto the terrain.
+fn deposit(
+    height: &mut Heightmap,
+    sediment: &mut Heightmap,
+    carried: &mut Heightmap,
+    x: usize,
+    z: usize,
+    amount: f32,
+) {
+    *height.get_mut(x, z) += amount;
+    *sediment.get_mut(x, z) += amount;
+    *carried.get_mut(x, z) -= amount;
+}
+
+/// A cache of eroded region tiles, keyed by world seed and region position.
+pub type ErosionCache = RegionCache<ErodedTile>;
+
+impl ErosionCache {
+    /// Get the cached tile for the given seed and region, eroding it if it is missing. This
+    /// should be called from an async task, as eroding a region is expensive.
+    pub fn get_or_erode(
+        &self,
+        seed: u32,
+        region: RegionPos,
+        settings: &ErosionSettings,
+        base: impl Fn(i64, i64) -> f32,
+    ) -> Arc<ErodedTile> {
+        self.get_or_insert_with(seed, region, || erode_region(region, settings, base))
+    }
+}
+
+/// The stage reshaping the surface of each column to its height on the eroded tiles around it,
+/// and recording the sediment deposited on it for [`Surface`](super::biome::Surface) to lay down,
+/// when erosion is enabled.
+pub struct Erode;
+
+impl GenerationStage for Erode {
+    fn name(&self) -> &'static str {
+        "erosion"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let Some(tiles) = context.eroded_tiles() else {
+            return;
+        };
+        let (ox, oy, oz) = context.origin();
+        let mut blocks = Vec::new();
+        for (x, z) in iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE) {
+            let (biome, _) = context.column(x, z);
+            let (height, sediment) = tiles.sample(ox + x as i64, oz + z as i64);
+            context.set_column(x, z, biome, height);
+            context.set_sediment(x, z, sediment);
+            for y in 0..GenerationContext::HEIGHT {
+                let was_solid = context.density(x, y, z) > 0.0;
+                let density = context.terrain.base_density(height, oy + y);
+                context.set_density(x, y, z, density);
+                let solid = density > 0.0;
+                if solid != was_solid && y < CHUNK_SIZE as i64 {
+                    let block = match solid {
+                        true => BlockType::STONE,
+                        false => BlockType::EMPTY,
+                    };
+                    blocks.push((BlockPos::new(x, y as u8, z), block));
+                }
+            }
+        }
+        context.chunk.set_blocks(blocks);
+    }
+}
@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use itertools::iproduct;
+
+use super::pipeline::{GenerationContext, GenerationStage};
+use crate::chunk::{BlockPos, BlockType, CHUNK_SIZE};
+
+/// A generator for flat worlds, stacking layers of blocks upwards from world height zero and
+/// leaving everything else empty. Useful for debugging meshing, physics, and editing without the
+/// variance of noise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlatGenerator {
+    /// The block of each layer, from the bottom up.
+    layers: Vec<BlockType>,
+}
+
+impl Default for FlatGenerator {
+    /// Three layers of stone, two of dirt, and grass on top.
+    fn default() -> Self {
+        use BlockType as B;
+        Self::new(vec![
+            B::STONE,
+            B::STONE,
+            B::STONE,
+            B::DIRT,
+            B::DIRT,
+            B::GRASS,
+        ])
+    }
+}
+
+impl FromStr for FlatGenerator {
+    type Err = anyhow::Error;
+
+    /// Parse layers from a comma-separated list of blocks from the bottom up, each optionally
+    /// repeated with a count, such as `stone*3,dirt*2,grass`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut layers = Vec::new();
+        for layer in s.split(',') {
+            let (block, count) = match layer.split_once('*') {
+                Some((block, count)) => {
+                    let count = count
+                        .trim()
+                        .parse()
+                        .with_context(|| format!("invalid layer count {:?}", count))?;
+                    (block, count)
+                }
+                None => (layer, 1),
+            };
+            let block: BlockType = block.parse()?;
+            layers.extend(std::iter::repeat_n(block, count));
+        }
+        Ok(Self::new(layers))
+    }
+}
+
+impl FlatGenerator {
+    /// Create a generator with the given layers, from the bottom up.
+    pub fn new(layers: Vec<BlockType>) -> Self {
+        Self { layers }
+    }
+
+    /// Return the block of each layer, from the bottom up.
+    pub fn layers(&self) -> &[BlockType] {
+        &self.layers
+    }
+
+    /// Return the block at the given world block height.
+    fn layer(&self, y: i64) -> BlockType {
+        usize::try_from(y)
+            .ok()
+            .and_then(|y| self.layers.get(y))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl GenerationStage for FlatGenerator {
+    fn name(&self) -> &'static str {
+        "flat"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let (_, oy, _) = context.origin();
+        let mut blocks = Vec::new();
+        for (x, y, z) in iproduct!(0..CHUNK_SIZE, 0..GenerationContext::HEIGHT, 0..CHUNK_SIZE) {
+            let block = self.layer(oy + y);
+            context.set_density(x, y, z, if block.is_opaque() { 1.0 } else { -1.0 });
+            if block != BlockType::EMPTY && y < CHUNK_SIZE as i64 {
+                blocks.push((BlockPos::new(x, y as u8, z), block));
+            }
+        }
+        context.chunk.set_blocks(blocks);
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashSet},
+};
+
+use itertools::iproduct;
+
+use super::{
+    erosion::{RegionPos, CELL_SIZE},
+    hash,
+    pipeline::{GenerationContext, GenerationStage},
+    Heightmap, RegionCache,
+};
+use crate::chunk::{BlockPos, BlockType, CHUNK_SIZE};
+
+/// The minimum surface height at which rivers can spring, measured in blocks.
+pub const RIVER_SOURCE_HEIGHT: f32 = 24.0;
+
+/// One in this many cells above the source height is the source of a river.
+pub const RIVER_SOURCE_RARITY: u64 = 97;
+
+/// The depth rivers carve into the terrain, measured in blocks.
+pub const RIVER_DEPTH: f32 = 2.0;
+
+/// The water features of a region tile.
+#[derive(Debug, Clone)]
+pub struct WaterTile {
+    /// The region this tile covers.
+    pub region: RegionPos,
+    /// The terrain height of each cell, after river beds have been carved.
+    pub bed: Heightmap,
+    /// The water surface height of each cell. Cells whose surface is at or below their bed are dry.
+    pub surface: Heightmap,
+    /// The cells that rivers flow through.
+    rivers: HashSet<(usize, usize)>,
+}
+
+impl WaterTile {
+    /// Check if the given cell is covered by water.
+    pub fn is_wet(&self, x: usize, z: usize) -> bool {
+        self.surface.get(x, z) > self.bed.get(x, z)
+    }
+
+    /// Check if a river flows through the given cell.
+    pub fn is_river(&self, x: usize, z: usize) -> bool {
+        self.rivers.contains(&(x, z))
+    }
+
+    /// Check if the given cell borders a river without being part of it.
+    pub fn is_riverbank(&self, x: usize, z: usize) -> bool {
+        !self.is_river(x, z)
+            && self
+                .bed
+                .neighbours(x, z)
+                .any(|(nx, nz)| self.is_river(nx, nz))
+    }
+
+    /// Return the cell nearest the given world block column.
+    pub fn cell_at(&self, x: i64, z: i64) -> (usize, usize) {
+        let (cx, cz) = self.region.cell(x, z);
+        let (width, depth) = self.bed.dim();
+        (
+            (cx.round().max(0.0) as usize).min(width - 1),
+            (cz.round().max(0.0) as usize).min(depth - 1),
+        )
+    }
+
+    /// Sample the bed and water surface height at the given world block column.
+    pub fn sample(&self, x: i64, z: i64) -> (f32, f32) {
+        let (cx, cz) = self.region.cell(x, z);
+        (self.bed.sample(cx, cz), self.surface.sample(cx, cz))
+    }
+}
+
+/// A cache of the water features of region tiles, keyed by world seed and region position.
+pub type WaterCache = RegionCache<WaterTile>;
+
+/// Place rivers and lakes on a region tile's terrain height, with the sea surface at the given
+/// height, measured in blocks.
+pub fn place_water(seed: u32, region: RegionPos, height: &Heightmap, sea_level: f32) -> WaterTile {
+    let mut bed = height.clone();
+    let rivers = trace_rivers(seed, region, height, sea_level);
+    for &(x, z) in &rivers {
+        *bed.get_mut(x, z) -= RIVER_DEPTH;
+    }
+
+    // rivers keep the water level of the terrain they were carved into, lakes fill depressions,
+    // and everything else floods up to sea level
+    let mut surface = fill_depressions(&bed);
+    for (x, z) in iproduct!(0..surface.dim().0, 0..surface.dim().1) {
+        let level = surface.get_mut(x, z);
+        if rivers.contains(&(x, z)) {
+            *level = level.max(height.get(x, z) - 1.0);
+        }
+        *level = level.max(sea_level);
+    }
+
+    WaterTile {
+        region,
+        bed,
+        surface,
+        rivers,
+    }
+}
+
+/// A height ordered by [`f32::total_cmp`], so it can be queued in a heap.
+#[derive(Debug, Clone, Copy)]
+struct FloatOrd(f32);
+
+impl PartialEq for FloatOrd {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FloatOrd {}
+
+impl PartialOrd for FloatOrd {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FloatOrd {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Compute the water level of each cell if all depressions in the terrain were filled to their
+/// spill point, using the priority-flood algorithm. Water is assumed to drain off the edges.
+pub fn fill_depressions(height: &Heightmap) -> Heightmap {
+    let (width, depth) = height.dim();
+    let mut filled = height.clone();
+    let mut visited = HashSet::with_capacity(width * depth);
+    let mut queue = BinaryHeap::new();
+
+    // seed the flood from the edges of the tile
+    for (x, z) in iproduct!(0..width, 0..depth) {
+        if x == 0 || z == 0 || x == width - 1 || z == depth - 1 {
+            visited.insert((x, z));
+            queue.push(Reverse((FloatOrd(height.get(x, z)), x, z)));
+        }
+    }
+
+    while let Some(Reverse((FloatOrd(level), x, z))) = queue.pop() {
+        for (nx, nz) in height.neighbours(x, z) {
+            if !visited.insert((nx, nz)) {
+                continue;
+            }
+            let neighbour = filled.get_mut(nx, nz);
+            *neighbour = neighbour.max(level);
+            queue.push(Reverse((FloatOrd(*neighbour), nx, nz)));
+        }
+    }
+
+    filled
+}
+
+/// Trace rivers from high terrain down the steepest gradient until they reach the given sea level,
+/// a lake, or the edge of the tile.
+pub fn trace_rivers(
+    seed: u32,
+    region: RegionPos,
+    height: &Heightmap,
+    sea_level: f32,
+) -> HashSet<(usize, usize)> {
+    let (width, depth) = height.dim();
+    let (ox, oz) = region.origin();
+    let mut rivers = HashSet::new();
+
+    for (x, z) in iproduct!(0..width, 0..depth) {
+        // pick sources from world coordinates so they do not depend on the tile layout
+        let wx = ox / CELL_SIZE + x as i64;
+        let wz = oz / CELL_SIZE + z as i64;
+        if height.get(x, z) < RIVER_SOURCE_HEIGHT
+            || !hash(seed, wx, wz).is_multiple_of(RIVER_SOURCE_RARITY)
+        {
+            continue;
+        }
+
+        let (mut x, mut z) = (x, z);
+        while height.get(x, z) > sea_level && rivers.insert((x, z)) {
+            match height.lowest_neighbour(x, z) {
+                Some(next) => (x, z) = next,
+                None => break,
+            }
+        }
+    }
+
+    rivers
+}
+
+/// Fill the open space of the chunk's wet columns with water, from the water surface down to the
+/// first solid block, so caves under lakes and rivers stay dry.
+pub fn fill_water(context: &mut GenerationContext, tile: &WaterTile) {
+    let (ox, oy, oz) = context.origin();
+    let mut blocks = Vec::new();
+    for (x, z) in iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE) {
+        let (wx, wz) = (ox + x as i64, oz + z as i64);
+        let (cx, cz) = tile.cell_at(wx, wz);
+        if !tile.is_wet(cx, cz) {
+            continue;
+        }
+        let (_, surface) = tile.sample(wx, wz);
+        // walk down from above the chunk, so water stops at the ground even in the chunk below it
+        for y in (0..GenerationContext::HEIGHT).rev() {
+            if (oy + y) as f32 >= surface {
+                continue;
+            }
+            if context.density(x, y, z) > 0.0 {
+                break;
+            }
+            if y < CHUNK_SIZE as i64 {
+                blocks.push((BlockPos::new(x, y as u8, z), BlockType::WATER));
+            }
+        }
+    }
+    context.chunk.set_blocks(blocks);
+}
+
+/// The stage carving river beds into the terrain and filling rivers and lakes with water, from the
+/// water features of the chunk's region.
+pub struct Hydrology;
+
+impl GenerationStage for Hydrology {
+    fn name(&self) -> &'static str {
+        "hydrology"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let tile = context.water_tile();
+        let (ox, oy, oz) = context.origin();
+        let mut blocks = Vec::new();
+        for (x, z) in iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE) {
+            let (cx, cz) = tile.cell_at(ox + x as i64, oz + z as i64);
+            if !tile.is_river(cx, cz) {
+                continue;
+            }
+            // sink the surface of the column to the river bed, leaving the terrain below it be
+            let (biome, height) = context.column(x, z);
+            let bed = height - RIVER_DEPTH;
+            for y in 0..GenerationContext::HEIGHT {
+                let density = context.density(x, y, z);
+                let carved = context.terrain.base_density(bed, oy + y);
+                if carved >= density {
+                    continue;
+                }
+                context.set_density(x, y, z, carved);
+                if density > 0.0 && carved <= 0.0 && y < CHUNK_SIZE as i64 {
+                    blocks.push((BlockPos::new(x, y as u8, z), BlockType::EMPTY));
+                }
+            }
+            context.set_column(x, z, biome, bed);
+        }
+        context.chunk.set_blocks(blocks);
+        fill_water(context, &tile);
+    }
+}
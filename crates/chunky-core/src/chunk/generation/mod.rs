@@ -0,0 +1,216 @@
+pub mod biome;
+pub mod erosion;
+pub mod flat;
+pub mod hydrology;
+pub mod pipeline;
+pub mod preset;
+pub mod preview;
+pub mod structure;
+pub mod template;
+pub mod terrain;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use erosion::RegionPos;
+use ndarray::Array2;
+
+/// A 2D grid of values sampled over the horizontal plane, indexed by `[x, z]`.
+#[derive(Debug, Clone)]
+pub struct Heightmap {
+    values: Array2<f32>,
+}
+
+impl Heightmap {
+    /// Create a heightmap of the given size, filled with zeroes.
+    pub fn zeros(width: usize, depth: usize) -> Self {
+        Self {
+            values: Array2::zeros((width, depth)),
+        }
+    }
+
+    /// Create a heightmap of the given size by sampling a function at each cell.
+    pub fn from_fn(width: usize, depth: usize, f: impl Fn(usize, usize) -> f32) -> Self {
+        Self {
+            values: Array2::from_shape_fn((width, depth), |(x, z)| f(x, z)),
+        }
+    }
+
+    /// Return the size of the heightmap as `(width, depth)`.
+    pub fn dim(&self) -> (usize, usize) {
+        self.values.dim()
+    }
+
+    /// Get the value at the given cell.
+    pub fn get(&self, x: usize, z: usize) -> f32 {
+        self.values[[x, z]]
+    }
+
+    /// Get a mutable reference to the value at the given cell.
+    pub fn get_mut(&mut self, x: usize, z: usize) -> &mut f32 {
+        &mut self.values[[x, z]]
+    }
+
+    /// Sample the heightmap at a fractional cell position using bilinear interpolation. Positions
+    /// outside the heightmap are clamped to its edges.
+    pub fn sample(&self, x: f32, z: f32) -> f32 {
+        let (width, depth) = self.dim();
+        let x = x.clamp(0.0, (width - 1) as f32);
+        let z = z.clamp(0.0, (depth - 1) as f32);
+
+        let (x0, z0) = (x.floor() as usize, z.floor() as usize);
+        let (x1, z1) = ((x0 + 1).min(width - 1), (z0 + 1).min(depth - 1));
+        let (tx, tz) = (x - x0 as f32, z - z0 as f32);
+
+        let a = self.get(x0, z0) * (1.0 - tx) + self.get(x1, z0) * tx;
+        let b = self.get(x0, z1) * (1.0 - tx) + self.get(x1, z1) * tx;
+        a * (1.0 - tz) + b * tz
+    }
+
+    /// Return an iterator over the cells orthogonally adjacent to the given cell.
+    pub fn neighbours(&self, x: usize, z: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (width, depth) = self.dim();
+        [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter().filter_map(
+            move |(dx, dz): (isize, isize)| {
+                let nx = x.checked_add_signed(dx).filter(|&nx| nx < width)?;
+                let nz = z.checked_add_signed(dz).filter(|&nz| nz < depth)?;
+                Some((nx, nz))
+            },
+        )
+    }
+
+    /// Find the lowest neighbour of the given cell that is lower than the cell itself.
+    pub fn lowest_neighbour(&self, x: usize, z: usize) -> Option<(usize, usize)> {
+        let here = self.get(x, z);
+        self.neighbours(x, z)
+            .filter(|&(nx, nz)| self.get(nx, nz) < here)
+            .min_by(|&(ax, az), &(bx, bz)| self.get(ax, az).total_cmp(&self.get(bx, bz)))
+    }
+}
+
+/// A key into a region cache, made of a world seed and region position.
+type TileKey = (u32, RegionPos);
+
+/// The most tiles a region cache holds. Past this, the least recently used tile is dropped, and
+/// computed again if its region is needed again.
+pub const REGION_CACHE_TILES: usize = 64;
+
+/// The tiles in a region cache, each with the time it was last used.
+struct Tiles<T> {
+    /// The tiles, and the value of `clock` when each was last used.
+    tiles: HashMap<TileKey, (Arc<T>, u64)>,
+    /// A counter bumped on every use of a tile.
+    clock: u64,
+}
+
+impl<T> Default for Tiles<T> {
+    fn default() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            clock: 0,
+        }
+    }
+}
+
+/// A cache of tiles computed over whole regions, keyed by world seed and region position. Cloning
+/// the cache yields a handle to the same underlying storage, so it can be moved into async tasks.
+/// At most [`REGION_CACHE_TILES`] tiles are kept.
+pub struct RegionCache<T> {
+    tiles: Arc<Mutex<Tiles<T>>>,
+}
+
+impl<T> Default for RegionCache<T> {
+    fn default() -> Self {
+        Self {
+            tiles: Arc::default(),
+        }
+    }
+}
+
+impl<T> Clone for RegionCache<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tiles: self.tiles.clone(),
+        }
+    }
+}
+
+impl<T> RegionCache<T> {
+    /// Get the cached tile for the given seed and region, if it has been computed.
+    pub fn get(&self, seed: u32, region: RegionPos) -> Option<Arc<T>> {
+        let mut tiles = self.tiles.lock().unwrap();
+        tiles.clock += 1;
+        let clock = tiles.clock;
+        let (tile, used) = tiles.tiles.get_mut(&(seed, region))?;
+        *used = clock;
+        Some(tile.clone())
+    }
+
+    /// Get the cached tile for the given seed and region, computing it if it is missing.
+    pub fn get_or_insert_with(
+        &self,
+        seed: u32,
+        region: RegionPos,
+        f: impl FnOnce() -> T,
+    ) -> Arc<T> {
+        if let Some(tile) = self.get(seed, region) {
+            return tile;
+        }
+        // the lock is not held while computing, so two tasks may race on the same region - tiles
+        // are deterministic, so keep whichever finishes first
+        let tile = Arc::new(f());
+        let mut tiles = self.tiles.lock().unwrap();
+        tiles.clock += 1;
+        let clock = tiles.clock;
+        let tile = tiles
+            .tiles
+            .entry((seed, region))
+            .or_insert((tile, clock))
+            .0
+            .clone();
+        if tiles.tiles.len() > REGION_CACHE_TILES {
+            let oldest = tiles
+                .tiles
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(&key, _)| key);
+            if let Some(oldest) = oldest {
+                tiles.tiles.remove(&oldest);
+            }
+        }
+        tile
+    }
+
+    /// Remove all cached tiles.
+    pub fn clear(&self) {
+        self.tiles.lock().unwrap().tiles.clear();
+    }
+}
+
+/// Hash a seed and a cell position into a well-distributed integer.
+pub(crate) fn hash(seed: u32, x: i64, z: i64) -> u64 {
+    let h = (seed as u64)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        .wrapping_add(x as u64)
+        .wrapping_mul(0xBF58_476D_1CE4_E5B9)
+        .wrapping_add(z as u64);
+    finalise(h)
+}
+
+/// Hash a seed and any number of inputs into a well-distributed integer, for deterministic rolls
+/// such as random ticks and particle bursts that need no random number generator.
+pub fn mix(seed: u64, inputs: &[u64]) -> u64 {
+    let h = inputs.iter().fold(seed, |h, &input| {
+        h.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(input)
+    });
+    finalise(h)
+}
+
+/// The splitmix64 finaliser, spreading every bit of the combined inputs across the result.
+fn finalise(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
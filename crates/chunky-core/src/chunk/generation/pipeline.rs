@@ -0,0 +1,398 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use itertools::iproduct;
+use ndarray::Array3;
+use noise::{OpenSimplex, Seedable};
+
+use super::{
+    biome::{Biome, Decorate, Decoration, Surface, SUBSURFACE_DEPTH},
+    erosion::{self, Erode, ErodedTiles, ErosionCache, ErosionSettings, RegionPos},
+    hydrology::{self, Hydrology, WaterCache, WaterTile},
+    structure::{SpilledBlock, Structures},
+    template::StructureTemplate,
+    terrain::{BaseTerrain, Carve, Flood, TerrainSettings},
+};
+use crate::chunk::{density::DensityVolume, BlockPos, Chunk, CHUNK_SIZE, DENSITY_SHARPNESS};
+
+/// A step of chunk generation. Stages run in order over a shared [`GenerationContext`], each
+/// building on the work of those before it.
+pub trait GenerationStage: Send + Sync {
+    /// The name the stage is toggled and timed by.
+    fn name(&self) -> &'static str;
+
+    /// Run the stage over the chunk being generated.
+    fn run(&self, context: &mut GenerationContext);
+}
+
+/// The state shared by the stages generating a chunk.
+pub struct GenerationContext<'a> {
+    /// The noise terrain is generated from.
+    pub noise: &'a OpenSimplex,
+    /// The shape of the terrain.
+    pub terrain: TerrainSettings,
+    /// The structure templates that can be placed, indexed by [`Decoration::Template`].
+    pub templates: Vec<Arc<StructureTemplate>>,
+    /// How the terrain is eroded.
+    pub erosion: ErosionSettings,
+    /// The eroded tiles of the regions generated so far, shared between chunks.
+    eroded: &'a ErosionCache,
+    /// The water features of the regions generated so far, shared between chunks.
+    water: &'a WaterCache,
+    /// The chunk being generated.
+    pub chunk: &'a mut Chunk,
+    /// The biome and surface height of each column of the chunk.
+    columns: Vec<(Biome, f32)>,
+    /// The depth of sediment deposited on each column of the chunk by erosion, in blocks.
+    sediment: Vec<f32>,
+    /// The density of the terrain through the chunk and the few blocks above it, indexed by
+    /// `[x, y, z]`, which is positive inside the terrain.
+    density: Array3<f32>,
+    /// The features to stand on the surface, with the surface block and a random roll for each.
+    pub decorations: Vec<(Decoration, BlockPos, u64)>,
+    /// The blocks of features that fall in other chunks.
+    pub spilled: Vec<SpilledBlock>,
+}
+
+impl<'a> GenerationContext<'a> {
+    /// The number of blocks of density kept along the vertical axis, reaching above the chunk so
+    /// the blocks at its top know how deep under the surface they are.
+    pub const HEIGHT: i64 = CHUNK_SIZE as i64 + SUBSURFACE_DEPTH;
+
+    /// Start generating the given chunk, looking up the climate of each of its columns.
+    fn new(chunk: &'a mut Chunk, noise: &'a OpenSimplex, pipeline: &'a GenerationPipeline) -> Self {
+        let terrain = pipeline.terrain();
+        let (ox, _, oz) = origin(chunk);
+        let columns = iproduct!(0..CHUNK_SIZE as i64, 0..CHUNK_SIZE as i64)
+            .map(|(x, z)| {
+                let (wx, wz) = (ox + x, oz + z);
+                (
+                    Biome::at(noise, wx, wz),
+                    base_height(noise, &terrain, wx, wz),
+                )
+            })
+            .collect();
+        let size = CHUNK_SIZE as usize;
+        Self {
+            noise,
+            terrain,
+            templates: pipeline.templates(),
+            erosion: pipeline.erosion(),
+            eroded: &pipeline.eroded,
+            water: &pipeline.water,
+            chunk,
+            columns,
+            sediment: vec![0.0; size * size],
+            density: Array3::from_elem((size, Self::HEIGHT as usize, size), -1.0),
+            decorations: Vec::new(),
+            spilled: Vec::new(),
+        }
+    }
+
+    /// Return the world block coordinates of the chunk's lowest corner.
+    pub fn origin(&self) -> (i64, i64, i64) {
+        origin(self.chunk)
+    }
+
+    /// Return the biome and surface height of the given column of the chunk.
+    pub fn column(&self, x: u8, z: u8) -> (Biome, f32) {
+        self.columns[x as usize * CHUNK_SIZE as usize + z as usize]
+    }
+
+    /// Change the biome and surface height of the given column of the chunk.
+    pub fn set_column(&mut self, x: u8, z: u8, biome: Biome, height: f32) {
+        self.columns[x as usize * CHUNK_SIZE as usize + z as usize] = (biome, height);
+    }
+
+    /// Return the depth of sediment deposited on the given column of the chunk, in blocks. This is
+    /// zero unless the terrain has been eroded.
+    pub fn sediment(&self, x: u8, z: u8) -> f32 {
+        self.sediment[x as usize * CHUNK_SIZE as usize + z as usize]
+    }
+
+    /// Set the depth of sediment deposited on the given column of the chunk, in blocks.
+    pub fn set_sediment(&mut self, x: u8, z: u8, depth: f32) {
+        self.sediment[x as usize * CHUNK_SIZE as usize + z as usize] = depth;
+    }
+
+    /// Return the seed of the world being generated.
+    pub fn seed(&self) -> u32 {
+        self.noise.seed()
+    }
+
+    /// Return the region the chunk is in.
+    pub fn region(&self) -> RegionPos {
+        RegionPos::from_chunk(self.chunk.position)
+    }
+
+    /// Return the eroded tiles overlapping the given box of world block columns (inclusive),
+    /// eroding any whose region has not been eroded yet, or `None` if erosion is turned off.
+    fn eroded_tiles_over(&self, min: (i64, i64), max: (i64, i64)) -> Option<ErodedTiles> {
+        if !self.erosion.enabled {
+            return None;
+        }
+        let tiles = ErodedTiles::regions(min, max)
+            .map(|region| {
+                self.eroded
+                    .get_or_erode(self.seed(), region, &self.erosion, |x, z| {
+                        base_height(self.noise, &self.terrain, x, z)
+                    })
+            })
+            .collect();
+        Some(ErodedTiles::new(tiles))
+    }
+
+    /// Return the eroded tiles overlapping the chunk, eroding any whose region has not been eroded
+    /// yet, or `None` if erosion is turned off.
+    pub fn eroded_tiles(&self) -> Option<ErodedTiles> {
+        let (ox, _, oz) = self.origin();
+        let last = CHUNK_SIZE as i64 - 1;
+        self.eroded_tiles_over((ox, oz), (ox + last, oz + last))
+    }
+
+    /// Return the rivers and lakes of the chunk's region, placing them if no chunk of the region
+    /// has been generated yet. They are placed on the eroded terrain if erosion is turned on.
+    pub fn water_tile(&self) -> Arc<WaterTile> {
+        let (seed, region) = (self.seed(), self.region());
+        self.water.get_or_insert_with(seed, region, || {
+            let (ox, oz) = region.origin();
+            let last = (erosion::REGION_CELLS as i64 - 1) * erosion::CELL_SIZE;
+            let height = match self.eroded_tiles_over((ox, oz), (ox + last, oz + last)) {
+                Some(tiles) => tiles.region_heights(region),
+                None => erosion::region_heights(region, |x, z| {
+                    base_height(self.noise, &self.terrain, x, z)
+                }),
+            };
+            hydrology::place_water(seed, region, &height, self.terrain.sea_level)
+        })
+    }
+
+    /// Get the density at the given position, which may be up to [`SUBSURFACE_DEPTH`] blocks
+    /// above the chunk.
+    pub fn density(&self, x: u8, y: i64, z: u8) -> f32 {
+        self.density[[x as usize, y as usize, z as usize]]
+    }
+
+    /// Set the density at the given position, which may be up to [`SUBSURFACE_DEPTH`] blocks
+    /// above the chunk.
+    pub fn set_density(&mut self, x: u8, y: i64, z: u8, density: f32) {
+        self.density[[x as usize, y as usize, z as usize]] = density;
+    }
+
+    /// Hand the density through the chunk over to it, once every stage has run.
+    fn finish(self) -> Vec<SpilledBlock> {
+        let mut density = DensityVolume::filled(-1.0);
+        for pos in BlockPos::all() {
+            // saturate the density away from the surface, so it compresses into long runs
+            let value = self.density(pos.x, pos.y as i64, pos.z);
+            density.set(pos, value * DENSITY_SHARPNESS);
+        }
+        self.chunk.density = Some(density);
+        self.spilled
+    }
+}
+
+/// Return the height of the surface at the given world block column before erosion, with hills
+/// scaled to the biomes around it.
+fn base_height(noise: &OpenSimplex, terrain: &TerrainSettings, x: i64, z: i64) -> f32 {
+    let amplitude = Biome::blended_amplitude(noise, x, z);
+    terrain.surface_height(noise, x, z, amplitude)
+}
+
+/// Return the world block coordinates of the chunk's lowest corner.
+fn origin(chunk: &Chunk) -> (i64, i64, i64) {
+    let size = CHUNK_SIZE as i64;
+    let pos = chunk.position;
+    (pos.x * size, pos.y * size, pos.z * size)
+}
+
+/// How long a stage has spent generating chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTiming {
+    /// The name of the stage.
+    pub name: &'static str,
+    /// Whether the stage currently runs.
+    pub enabled: bool,
+    /// The number of chunks the stage has run over.
+    pub runs: u64,
+    /// The total time the stage has run for.
+    pub total: Duration,
+}
+
+impl StageTiming {
+    /// Return the average time the stage takes over a chunk.
+    pub fn average(&self) -> Duration {
+        self.total / self.runs.max(1) as u32
+    }
+}
+
+/// A stage in a pipeline, with its toggle and timings.
+struct Slot {
+    stage: Box<dyn GenerationStage>,
+    enabled: AtomicBool,
+    runs: AtomicU64,
+    nanos: AtomicU64,
+}
+
+/// An ordered list of generation stages, each of which can be turned off and is timed as it runs,
+/// along with the shape of the terrain they generate, how it is eroded, and the structure
+/// templates they place. The pipeline can be shared between threads generating chunks at once.
+pub struct GenerationPipeline {
+    slots: Vec<Slot>,
+    terrain: RwLock<TerrainSettings>,
+    erosion: RwLock<ErosionSettings>,
+    templates: RwLock<Vec<Arc<StructureTemplate>>>,
+    /// The eroded tiles of the regions generated so far.
+    eroded: ErosionCache,
+    /// The water features of the regions generated so far.
+    water: WaterCache,
+}
+
+impl Default for GenerationPipeline {
+    /// The full pipeline: base terrain, erosion, carving, surface blocks, rivers and lakes,
+    /// flooding, decoration, then structures.
+    fn default() -> Self {
+        Self::empty()
+            .with_stage(BaseTerrain)
+            .with_stage(Erode)
+            .with_stage(Carve)
+            .with_stage(Surface)
+            .with_stage(Hydrology)
+            .with_stage(Flood)
+            .with_stage(Decorate)
+            .with_stage(Structures)
+    }
+}
+
+impl GenerationPipeline {
+    /// Create a pipeline with no stages.
+    pub fn empty() -> Self {
+        Self {
+            slots: Vec::new(),
+            terrain: RwLock::default(),
+            erosion: RwLock::default(),
+            templates: RwLock::default(),
+            eroded: ErosionCache::default(),
+            water: WaterCache::default(),
+        }
+    }
+
+    /// Add a stage to the end of the pipeline.
+    pub fn with_stage<S: GenerationStage + 'static>(mut self, stage: S) -> Self {
+        self.slots.push(Slot {
+            stage: Box::new(stage),
+            enabled: AtomicBool::new(true),
+            runs: AtomicU64::new(0),
+            nanos: AtomicU64::new(0),
+        });
+        self
+    }
+
+    /// Swap the stage with the given name for another in the same place, such as one doing the same
+    /// work on other hardware. The pipeline is left as it is if it has no such stage.
+    pub fn replacing<S: GenerationStage + 'static>(mut self, name: &str, stage: S) -> Self {
+        if let Some(slot) = self.slots.iter_mut().find(|slot| slot.stage.name() == name) {
+            slot.stage = Box::new(stage);
+        }
+        self
+    }
+
+    /// Return the shape of the terrain the pipeline generates.
+    pub fn terrain(&self) -> TerrainSettings {
+        *self.terrain.read().unwrap()
+    }
+
+    /// Change the shape of the terrain the pipeline generates, for chunks generated from now on.
+    /// The cached region tiles are kept if the shape is unchanged.
+    pub fn set_terrain(&self, terrain: TerrainSettings) {
+        let mut current = self.terrain.write().unwrap();
+        if *current == terrain {
+            return;
+        }
+        *current = terrain;
+        // tiles made from the old terrain no longer fit
+        self.eroded.clear();
+        self.water.clear();
+    }
+
+    /// Return how the pipeline erodes the terrain.
+    pub fn erosion(&self) -> ErosionSettings {
+        self.erosion.read().unwrap().clone()
+    }
+
+    /// Change how the pipeline erodes the terrain, for chunks generated from now on. The cached
+    /// region tiles are kept if the settings are unchanged.
+    pub fn set_erosion(&self, erosion: ErosionSettings) {
+        let mut current = self.erosion.write().unwrap();
+        if *current == erosion {
+            return;
+        }
+        *current = erosion;
+        self.eroded.clear();
+        self.water.clear();
+    }
+
+    /// Return the structure templates the pipeline places.
+    pub fn templates(&self) -> Vec<Arc<StructureTemplate>> {
+        self.templates.read().unwrap().clone()
+    }
+
+    /// Change the structure templates the pipeline places, for chunks generated from now on.
+    pub fn set_templates(&self, templates: Vec<Arc<StructureTemplate>>) {
+        *self.templates.write().unwrap() = templates;
+    }
+
+    /// Turn the stage with the given name on or off, returning whether there is such a stage.
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> bool {
+        self.slots
+            .iter()
+            .filter(|slot| slot.stage.name() == name)
+            .map(|slot| slot.enabled.store(enabled, Ordering::Relaxed))
+            .count()
+            > 0
+    }
+
+    /// Return the names of the stages that are turned off.
+    pub fn disabled(&self) -> Vec<&'static str> {
+        self.slots
+            .iter()
+            .filter(|slot| !slot.enabled.load(Ordering::Relaxed))
+            .map(|slot| slot.stage.name())
+            .collect()
+    }
+
+    /// Return how long each stage has spent generating chunks, in order.
+    pub fn timings(&self) -> Vec<StageTiming> {
+        self.slots
+            .iter()
+            .map(|slot| StageTiming {
+                name: slot.stage.name(),
+                enabled: slot.enabled.load(Ordering::Relaxed),
+                runs: slot.runs.load(Ordering::Relaxed),
+                total: Duration::from_nanos(slot.nanos.load(Ordering::Relaxed)),
+            })
+            .collect()
+    }
+
+    /// Generate the chunk by running each enabled stage over it in turn, returning the blocks of
+    /// its features that fall in other chunks.
+    pub fn generate(&self, chunk: &mut Chunk, noise: &OpenSimplex) -> Vec<SpilledBlock> {
+        let mut context = GenerationContext::new(chunk, noise, self);
+        for slot in &self.slots {
+            if !slot.enabled.load(Ordering::Relaxed) {
+                continue;
+            }
+            let start = Instant::now();
+            slot.stage.run(&mut context);
+            slot.runs.fetch_add(1, Ordering::Relaxed);
+            slot.nanos
+                .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        }
+        context.finish()
+    }
+}
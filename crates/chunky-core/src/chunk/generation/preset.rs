@@ -0,0 +1,54 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::{flat::FlatGenerator, pipeline::GenerationPipeline};
+
+/// A built-in kind of world, chosen when the world is created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorldPreset {
+    /// Terrain generated from noise, through the full pipeline.
+    #[default]
+    Default,
+    /// Flat layers of stone, dirt, and grass.
+    Superflat,
+    /// Nothing at all, for building from scratch.
+    Void,
+}
+
+impl FromStr for WorldPreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "superflat" => Ok(Self::Superflat),
+            "void" => Ok(Self::Void),
+            _ => anyhow::bail!("unknown world preset {}", s),
+        }
+    }
+}
+
+impl WorldPreset {
+    /// Every preset, in the order they are offered.
+    pub const ALL: [Self; 3] = [Self::Default, Self::Superflat, Self::Void];
+
+    /// Return the name the preset is chosen by.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Superflat => "superflat",
+            Self::Void => "void",
+        }
+    }
+
+    /// Build the pipeline generating worlds of this preset.
+    pub fn pipeline(self) -> GenerationPipeline {
+        match self {
+            Self::Default => GenerationPipeline::default(),
+            Self::Superflat => GenerationPipeline::empty().with_stage(FlatGenerator::default()),
+            Self::Void => GenerationPipeline::empty(),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use itertools::iproduct;
+use noise::NoiseFn;
+
+use super::{biome::Biome, terrain::TerrainSettings};
+use crate::chunk::BlockType;
+
+/// The colour of open water in previews.
+const WATER: [f32; 3] = [0.15, 0.3, 0.65];
+
+/// The depth below sea level at which water in previews is drawn at its darkest, in blocks.
+const WATER_DEPTH: f32 = 32.0;
+
+/// A coarse top-down picture of a seed's terrain, baked from its surface heights and biomes alone
+/// without generating any chunks, so many seeds can be compared quickly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preview {
+    /// The width and depth of the preview, in pixels.
+    pub size: usize,
+    /// The colour of each pixel as RGBA bytes, in rows running along X, from low to high Z.
+    pub pixels: Vec<u8>,
+}
+
+impl Preview {
+    /// Bake a preview of the given size centred on the origin, with each pixel covering the given
+    /// number of blocks along each axis.
+    pub fn bake(
+        noise: &impl NoiseFn<f64, 2>,
+        terrain: &TerrainSettings,
+        size: usize,
+        blocks_per_pixel: i64,
+    ) -> Self {
+        let half = size as i64 / 2;
+        let pixels = iproduct!(0..size as i64, 0..size as i64)
+            .flat_map(|(z, x)| {
+                let (x, z) = ((x - half) * blocks_per_pixel, (z - half) * blocks_per_pixel);
+                let biome = Biome::at(noise, x, z);
+                let amplitude = Biome::blended_amplitude(noise, x, z);
+                let height = terrain.surface_height(noise, x, z, amplitude);
+                let [r, g, b] = pixel_colour(terrain, biome, height);
+                [r, g, b, 1.0].map(|channel| (channel.clamp(0.0, 1.0) * 255.0) as u8)
+            })
+            .collect();
+        Self { size, pixels }
+    }
+}
+
+/// Return the colour of a column of the given biome with its surface at the given height: water
+/// darkening with depth below sea level, and the biome's surface block shaded by height above it.
+fn pixel_colour(terrain: &TerrainSettings, biome: Biome, height: f32) -> [f32; 3] {
+    let depth = terrain.sea_level - height;
+    if depth > 0.0 {
+        let shade = 1.0 - 0.5 * (depth / WATER_DEPTH).min(1.0);
+        return WATER.map(|channel| channel * shade);
+    }
+    let relief = terrain.height_amplitude.max(1.0);
+    let shade = 0.7 + 0.3 * (-depth / relief).min(1.0);
+    block_colour(terrain.palettes.get(biome).surface).map(|channel| channel * shade)
+}
+
+/// Return the colour a block is drawn in previews.
+fn block_colour(block: BlockType) -> [f32; 3] {
+    let [r, g, b, _] = block.definition().color;
+    [r, g, b]
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use glam::IVec3;
+
+use super::pipeline::{GenerationContext, GenerationStage};
+use crate::chunk::{key::ChunkMap, BlockPos, BlockType, Chunk, ChunkPos};
+
+/// A block of a structure that reaches outside the chunk it was generated in, as the chunk it falls
+/// in, its position within that chunk, and the block.
+pub type SpilledBlock = (ChunkPos, BlockPos, BlockType);
+
+/// Blocks of structures that reach into chunks which have not been generated yet, held until those
+/// chunks are.
+#[derive(Debug, Default, Clone)]
+pub struct PendingBlocks {
+    blocks: ChunkMap<Vec<(BlockPos, BlockType)>>,
+}
+
+impl PendingBlocks {
+    /// Hold a block until the chunk it falls in is generated.
+    pub fn defer(&mut self, (chunk, pos, block): SpilledBlock) {
+        self.blocks
+            .entry(chunk.key())
+            .or_default()
+            .push((pos, block));
+    }
+
+    /// Take the blocks waiting for the given chunk.
+    pub fn take(&mut self, chunk: ChunkPos) -> Vec<(BlockPos, BlockType)> {
+        self.blocks.remove(&chunk.key()).unwrap_or_default()
+    }
+
+    /// Return the number of blocks waiting.
+    pub fn len(&self) -> usize {
+        self.blocks.values().map(Vec::len).sum()
+    }
+
+    /// Check if no blocks are waiting.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+/// Place the blocks of structures into a chunk. Structures only fill empty space, so they never cut
+/// into the terrain, except that stems push through leaves. The result does not depend on the
+/// order blocks are placed in, so overlapping features come out the same however the chunks around
+/// them are loaded.
+pub fn place<I: IntoIterator<Item = (BlockPos, BlockType)>>(chunk: &mut Chunk, blocks: I) {
+    let mut placed = HashMap::new();
+    for (pos, block) in blocks {
+        let current = placed
+            .get(&pos)
+            .copied()
+            .unwrap_or_else(|| *chunk.block_at(pos));
+        if current == BlockType::EMPTY || (current.has_tag("#leaves") && block.has_tag("#stem")) {
+            placed.insert(pos, block);
+        }
+    }
+    chunk.set_blocks(placed);
+}
+
+/// The stage building the features chosen for the chunk, placing the blocks that fall inside it
+/// and setting aside those that fall in other chunks.
+pub struct Structures;
+
+impl GenerationStage for Structures {
+    fn name(&self) -> &'static str {
+        "structures"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let position = context.chunk.position;
+        let origin = position.to_world().as_ivec3();
+        let (inside, spilled): (Vec<_>, Vec<_>) = context
+            .decorations
+            .drain(..)
+            .flat_map(|(decoration, surface, roll)| {
+                decoration.blocks(origin + IVec3::from(surface), roll, &context.templates)
+            })
+            .map(|(pos, block)| {
+                let chunk = ChunkPos::from_world_block(pos);
+                (chunk, BlockPos::from_world_block(pos), block)
+            })
+            .partition(|&(chunk, _, _)| chunk == position);
+        place(
+            context.chunk,
+            inside.into_iter().map(|(_, pos, block)| (pos, block)),
+        );
+        context.spilled.extend(spilled);
+    }
+}
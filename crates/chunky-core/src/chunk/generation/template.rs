@@ -0,0 +1,64 @@
+use anyhow::ensure;
+use glam::IVec3;
+use itertools::iproduct;
+use serde::{Deserialize, Serialize};
+
+use super::biome::Biome;
+use crate::chunk::BlockType;
+
+/// A structure designed outside the game, such as a house or a ruin: a box of blocks, and the block
+/// within it that is placed at the chosen position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructureTemplate {
+    /// The width, height, and depth of the box.
+    pub size: [u32; 3],
+    /// The block of the box placed at the chosen position. Generated structures are placed with
+    /// their anchor on the block above the surface.
+    pub anchor: [i32; 3],
+    /// The blocks of the box, running along X, then Z, then up Y. Empty blocks leave the world as
+    /// it is.
+    pub blocks: Vec<BlockType>,
+    /// Where the structure is scattered as terrain is generated. Only placed by hand if unset.
+    #[serde(default)]
+    pub placement: Option<TemplatePlacement>,
+}
+
+/// Where a structure is scattered as terrain is generated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplatePlacement {
+    /// The biomes the structure stands in.
+    pub biomes: Vec<Biome>,
+    /// The structure stands on one in however many surface columns of those biomes.
+    pub rarity: u64,
+}
+
+impl StructureTemplate {
+    /// Parse a template from TOML, checking its blocks fill its box.
+    pub fn from_toml(source: &str) -> anyhow::Result<Self> {
+        let template: Self = toml::from_str(source)?;
+        let [x, y, z] = template.size;
+        let volume = x as usize * y as usize * z as usize;
+        ensure!(
+            template.blocks.len() == volume,
+            "template of size {:?} has {} blocks, expected {}",
+            template.size,
+            template.blocks.len(),
+            volume
+        );
+        if let Some(placement) = &template.placement {
+            ensure!(placement.rarity > 0, "template rarity must be at least 1");
+        }
+        Ok(template)
+    }
+
+    /// Return the blocks of the structure with its anchor at the given world block coordinates,
+    /// leaving out its empty blocks.
+    pub fn blocks_at(&self, anchor: IVec3) -> impl Iterator<Item = (IVec3, BlockType)> + '_ {
+        let [width, height, depth] = self.size.map(|size| size as i32);
+        let origin = anchor - IVec3::from(self.anchor);
+        iproduct!(0..height, 0..depth, 0..width)
+            .zip(&self.blocks)
+            .filter(|(_, &block)| block != BlockType::EMPTY)
+            .map(move |((y, z, x), &block)| (origin + IVec3::new(x, y, z), block))
+    }
+}
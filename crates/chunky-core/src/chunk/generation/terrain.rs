@@ -0,0 +1,181 @@
+use itertools::iproduct;
+use noise::NoiseFn;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    biome::BiomePalettes,
+    pipeline::{GenerationContext, GenerationStage},
+};
+use crate::chunk::{BlockPos, BlockType, CHUNK_SIZE};
+
+/// An offset applied to the heightmap noise, so it does not line up with the overhang noise.
+const HEIGHT_OFFSET: f64 = 10_000.0;
+
+/// The shape of generated terrain. Missing fields take their defaults, so settings files only need
+/// the values they change.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainSettings {
+    /// The height the surface is centred on, in blocks.
+    pub base_height: f32,
+    /// The furthest the surface rises above or sinks below the base height, in blocks.
+    pub height_amplitude: f32,
+    /// The horizontal size of the largest hills and valleys, in blocks.
+    pub height_scale: f64,
+    /// The number of octaves of noise summed into the surface height, each half the size and half
+    /// the height of the last.
+    pub height_octaves: u32,
+    /// The depth below the surface at which terrain is fully solid, before overhangs are carved.
+    pub surface_falloff: f32,
+    /// How strongly the 3D noise pushes the terrain in and out of the surface to form overhangs,
+    /// as a fraction of the surface falloff.
+    pub overhang_strength: f32,
+    /// The size of overhangs, in blocks.
+    pub overhang_scale: f64,
+    /// The height below which empty space is filled with water, in blocks.
+    pub sea_level: f32,
+    /// The blocks each biome's terrain is covered in.
+    pub palettes: BiomePalettes,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            base_height: 16.0,
+            height_amplitude: 24.0,
+            height_scale: 256.0,
+            height_octaves: 5,
+            surface_falloff: 8.0,
+            overhang_strength: 0.6,
+            overhang_scale: 16.0,
+            sea_level: 8.0,
+            palettes: BiomePalettes::default(),
+        }
+    }
+}
+
+impl TerrainSettings {
+    /// Return the height of the surface at the given world block column, from fractal noise.
+    /// Hills are scaled by the given amplitude, which varies between biomes.
+    pub fn surface_height(
+        &self,
+        noise: &impl NoiseFn<f64, 2>,
+        x: i64,
+        z: i64,
+        amplitude: f32,
+    ) -> f32 {
+        let (mut sum, mut weight, mut total, mut scale) = (0.0, 1.0, 0.0, self.height_scale);
+        for _ in 0..self.height_octaves {
+            sum += weight * noise.get([x as f64 / scale + HEIGHT_OFFSET, z as f64 / scale]);
+            total += weight;
+            weight /= 2.0;
+            scale /= 2.0;
+        }
+        if total == 0.0 {
+            return self.base_height;
+        }
+        self.base_height + amplitude * self.height_amplitude * (sum / total) as f32
+    }
+
+    /// Return the density of the terrain at the given world block height, in a column whose
+    /// surface is at the given height, before overhangs are carved. Density is positive inside the
+    /// terrain and rises with depth.
+    pub fn base_density(&self, height: f32, y: i64) -> f32 {
+        (height - y as f32) / self.surface_falloff
+    }
+
+    /// Return how far 3D noise pushes the terrain in or out of the surface at the given world
+    /// block position to form overhangs, or `None` if the given density is too far from the
+    /// surface for the noise to flip it.
+    pub fn overhang(
+        &self,
+        noise: &impl NoiseFn<f64, 3>,
+        density: f32,
+        x: i64,
+        y: i64,
+        z: i64,
+    ) -> Option<f32> {
+        if density.abs() > self.overhang_strength {
+            return None;
+        }
+        let [x, y, z] = [x, y, z].map(|value| value as f64 / self.overhang_scale);
+        Some(self.overhang_strength * noise.get([x, y, z]) as f32)
+    }
+}
+
+/// The stage filling the chunk with stone below the surface height of each column.
+pub struct BaseTerrain;
+
+impl GenerationStage for BaseTerrain {
+    fn name(&self) -> &'static str {
+        "base terrain"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let (_, oy, _) = context.origin();
+        let mut blocks = Vec::new();
+        for (x, z) in iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE) {
+            let (_, height) = context.column(x, z);
+            for y in 0..GenerationContext::HEIGHT {
+                let density = context.terrain.base_density(height, oy + y);
+                context.set_density(x, y, z, density);
+                if density > 0.0 && y < CHUNK_SIZE as i64 {
+                    blocks.push((BlockPos::new(x, y as u8, z), BlockType::STONE));
+                }
+            }
+        }
+        context.chunk.set_blocks(blocks);
+    }
+}
+
+/// The stage carving overhangs into the terrain near the surface with 3D noise.
+pub struct Carve;
+
+impl GenerationStage for Carve {
+    fn name(&self) -> &'static str {
+        "carve"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let (ox, oy, oz) = context.origin();
+        let mut blocks = Vec::new();
+        for (x, y, z) in iproduct!(0..CHUNK_SIZE, 0..GenerationContext::HEIGHT, 0..CHUNK_SIZE) {
+            let density = context.density(x, y, z);
+            let (wx, wy, wz) = (ox + x as i64, oy + y, oz + z as i64);
+            let Some(offset) = context.terrain.overhang(context.noise, density, wx, wy, wz) else {
+                continue;
+            };
+            context.set_density(x, y, z, density + offset);
+            let solid = density + offset > 0.0;
+            if solid != (density > 0.0) && y < CHUNK_SIZE as i64 {
+                let block = if solid {
+                    BlockType::STONE
+                } else {
+                    BlockType::EMPTY
+                };
+                blocks.push((BlockPos::new(x, y as u8, z), block));
+            }
+        }
+        context.chunk.set_blocks(blocks);
+    }
+}
+
+/// The stage filling the empty space below sea level with water.
+pub struct Flood;
+
+impl GenerationStage for Flood {
+    fn name(&self) -> &'static str {
+        "flood"
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let (_, oy, _) = context.origin();
+        let sea_level = context.terrain.sea_level;
+        let water = BlockPos::all()
+            .filter(|&pos| ((oy + pos.y as i64) as f32) < sea_level)
+            .filter(|&pos| *context.chunk.block_at(pos) == BlockType::EMPTY)
+            .map(|pos| (pos, BlockType::WATER))
+            .collect::<Vec<_>>();
+        context.chunk.set_blocks(water);
+    }
+}
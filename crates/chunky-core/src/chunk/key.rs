@@ -0,0 +1,178 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{BuildHasherDefault, Hasher},
+};
+
+use super::ChunkPos;
+
+/// The number of bits of each chunk coordinate stored in a key.
+const AXIS_BITS: u32 = 21;
+
+/// The offset added to each coordinate so negative coordinates encode as unsigned values.
+const AXIS_OFFSET: i64 = 1 << (AXIS_BITS - 1);
+
+/// A mask selecting the bits of a coordinate stored in a key.
+const AXIS_MASK: u64 = (1 << AXIS_BITS) - 1;
+
+/// A chunk position packed into a single Morton-encoded integer, so that chunk maps hash one word
+/// instead of three. Nearby chunks have nearby keys. Coordinates are kept to 21 bits each, which
+/// covers about a million chunks in every direction from the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChunkKey(u64);
+
+impl ChunkKey {
+    /// The smallest chunk coordinate a key can hold.
+    pub const MIN_COORDINATE: i64 = -AXIS_OFFSET;
+
+    /// The largest chunk coordinate a key can hold.
+    pub const MAX_COORDINATE: i64 = AXIS_OFFSET - 1;
+
+    /// Return the key of a chunk position, or `None` if any of its coordinates is out of the range
+    /// a key can hold. Positions from outside the engine should be converted with this rather than
+    /// [`From`], which wraps them onto other chunks.
+    pub fn checked(pos: ChunkPos) -> Option<Self> {
+        Self::in_range(pos).then(|| Self::encode(pos))
+    }
+
+    /// Check if every coordinate of a chunk position fits in a key.
+    fn in_range(pos: ChunkPos) -> bool {
+        [pos.x, pos.y, pos.z]
+            .iter()
+            .all(|value| (Self::MIN_COORDINATE..=Self::MAX_COORDINATE).contains(value))
+    }
+
+    /// Pack a chunk position into a key, keeping the low 21 bits of each coordinate.
+    fn encode(pos: ChunkPos) -> Self {
+        let encode = |value: i64| Self::spread((value + AXIS_OFFSET) as u64);
+        Self(encode(pos.x) | (encode(pos.y) << 1) | (encode(pos.z) << 2))
+    }
+
+    /// Spread the low 21 bits of a value out so there are two zero bits between each.
+    fn spread(value: u64) -> u64 {
+        let mut x = value & AXIS_MASK;
+        x = (x | (x << 32)) & 0x001f_0000_0000_ffff;
+        x = (x | (x << 16)) & 0x001f_0000_ff00_00ff;
+        x = (x | (x << 8)) & 0x100f_00f0_0f00_f00f;
+        x = (x | (x << 4)) & 0x10c3_0c30_c30c_30c3;
+        x = (x | (x << 2)) & 0x1249_2492_4924_9249;
+        x
+    }
+
+    /// Gather every third bit of a value back into the low 21 bits.
+    fn compact(value: u64) -> u64 {
+        let mut x = value & 0x1249_2492_4924_9249;
+        x = (x | (x >> 2)) & 0x10c3_0c30_c30c_30c3;
+        x = (x | (x >> 4)) & 0x100f_00f0_0f00_f00f;
+        x = (x | (x >> 8)) & 0x001f_0000_ff00_00ff;
+        x = (x | (x >> 16)) & 0x001f_0000_0000_ffff;
+        x = (x | (x >> 32)) & AXIS_MASK;
+        x
+    }
+}
+
+impl From<ChunkPos> for ChunkKey {
+    fn from(pos: ChunkPos) -> Self {
+        debug_assert!(
+            Self::in_range(pos),
+            "chunk {:?} is out of the range of a chunk key",
+            pos
+        );
+        Self::encode(pos)
+    }
+}
+
+impl From<ChunkKey> for ChunkPos {
+    fn from(ChunkKey(key): ChunkKey) -> Self {
+        let decode = |value: u64| ChunkKey::compact(value) as i64 - AXIS_OFFSET;
+        ChunkPos::new(decode(key), decode(key >> 1), decode(key >> 2))
+    }
+}
+
+/// A hasher for [`ChunkKey`]s, which only need their single word mixed rather than a
+/// general-purpose hash.
+#[derive(Default)]
+pub struct ChunkKeyHasher(u64);
+
+impl Hasher for ChunkKeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        // a multiply-xorshift mix, so the structured Morton bits reach the high bits of the hash
+        let mixed = (self.0 ^ value).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        self.0 = mixed ^ (mixed >> 32);
+    }
+}
+
+/// A map keyed by chunk position.
+pub type ChunkMap<V> = HashMap<ChunkKey, V, BuildHasherDefault<ChunkKeyHasher>>;
+
+/// A set of chunk positions.
+pub type ChunkSet = HashSet<ChunkKey, BuildHasherDefault<ChunkKeyHasher>>;
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    /// Coordinates around the origin and at both ends of the range of a key.
+    const COORDINATES: [i64; 7] = [
+        ChunkKey::MIN_COORDINATE,
+        ChunkKey::MIN_COORDINATE + 1,
+        -1,
+        0,
+        1,
+        ChunkKey::MAX_COORDINATE - 1,
+        ChunkKey::MAX_COORDINATE,
+    ];
+
+    #[test]
+    fn round_trip() {
+        for (x, y, z) in itertools::iproduct!(COORDINATES, COORDINATES, COORDINATES) {
+            let pos = ChunkPos::new(x, y, z);
+            let key = ChunkKey::checked(pos).unwrap();
+            assert_eq!(key, ChunkKey::from(pos));
+            assert_eq!(ChunkPos::from(key), pos);
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        // along one axis, keys order the same way as coordinates, negatives included
+        for axis in 0..3 {
+            let keys = COORDINATES
+                .map(|value| {
+                    let mut coordinates = [0; 3];
+                    coordinates[axis] = value;
+                    ChunkPos::new(coordinates[0], coordinates[1], coordinates[2]).key()
+                })
+                .to_vec();
+            assert!(keys.iter().tuple_windows().all(|(a, b)| a < b));
+        }
+
+        // a Morton order visits each 2x2x2 block of chunks before moving to the next
+        let block = itertools::iproduct!(0..2, 0..2, 0..2)
+            .map(|(x, y, z)| ChunkPos::new(x, y, z).key())
+            .collect_vec();
+        let beyond = ChunkPos::new(2, 0, 0).key();
+        assert!(block.iter().all(|&key| key < beyond));
+    }
+
+    #[test]
+    fn checked_rejects_out_of_range() {
+        let outside = [ChunkKey::MIN_COORDINATE - 1, ChunkKey::MAX_COORDINATE + 1];
+        for value in outside {
+            assert_eq!(ChunkKey::checked(ChunkPos::new(value, 0, 0)), None);
+            assert_eq!(ChunkKey::checked(ChunkPos::new(0, value, 0)), None);
+            assert_eq!(ChunkKey::checked(ChunkPos::new(0, 0, value)), None);
+        }
+    }
+}
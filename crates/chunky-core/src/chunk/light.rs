@@ -0,0 +1,376 @@
+use std::collections::VecDeque;
+
+use glam::IVec3;
+use itertools::iproduct;
+
+use super::{
+    section::{SECTIONS, SECTION_HEIGHT},
+    BlockPos, BlockType, CHUNK_SIZE,
+};
+
+/// The brightest light level a block can have.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The number of blocks in a chunk.
+const VOLUME: usize = (CHUNK_SIZE as usize).pow(3);
+
+/// The light level of a block, packing sky light into the high four bits and block light into the
+/// low four bits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightLevel(u8);
+
+impl LightLevel {
+    /// Full sky light and no block light.
+    pub const SKY: Self = Self(MAX_LIGHT << 4);
+
+    /// Create a light level from sky and block light, each clamped to [`MAX_LIGHT`].
+    pub fn new(sky: u8, block: u8) -> Self {
+        Self((sky.min(MAX_LIGHT) << 4) | block.min(MAX_LIGHT))
+    }
+
+    /// Return the light reaching the block from the sky.
+    pub fn sky(self) -> u8 {
+        self.0 >> 4
+    }
+
+    /// Return the light reaching the block from light-emitting blocks.
+    pub fn block(self) -> u8 {
+        self.0 & MAX_LIGHT
+    }
+
+    /// Return the light level with the sky light replaced.
+    pub fn with_sky(self, sky: u8) -> Self {
+        Self::new(sky, self.block())
+    }
+
+    /// Return the light level with the block light replaced.
+    pub fn with_block(self, block: u8) -> Self {
+        Self::new(self.sky(), block)
+    }
+
+    /// Return the brightness of the block in `[0, 1]`, taking the brighter of the two sources.
+    pub fn brightness(self) -> f32 {
+        self.sky().max(self.block()) as f32 / MAX_LIGHT as f32
+    }
+}
+
+/// The light level of every block in a chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightVolume {
+    levels: Box<[LightLevel]>,
+}
+
+impl Default for LightVolume {
+    fn default() -> Self {
+        Self::filled(LightLevel::default())
+    }
+}
+
+impl LightVolume {
+    /// Create a volume with every block at the given light level.
+    pub fn filled(level: LightLevel) -> Self {
+        Self {
+            levels: vec![level; VOLUME].into_boxed_slice(),
+        }
+    }
+
+    /// Return the index of the given block.
+    fn index(pos: BlockPos) -> usize {
+        let size = CHUNK_SIZE as usize;
+        (pos.z as usize * size + pos.x as usize) * size + pos.y as usize
+    }
+
+    /// Get the light level at the given position.
+    pub fn get(&self, pos: BlockPos) -> LightLevel {
+        self.levels[Self::index(pos)]
+    }
+
+    /// Set the light level at the given position.
+    pub fn set(&mut self, pos: BlockPos, level: LightLevel) {
+        self.levels[Self::index(pos)] = level;
+    }
+
+    /// Set every block to the given light level.
+    pub fn fill(&mut self, level: LightLevel) {
+        self.levels.fill(level);
+    }
+}
+
+/// The offsets of the six blocks sharing a face with a block, which light spreads between.
+const NEIGHBOURS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// One of the two kinds of light a block holds, which spread separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightChannel {
+    /// Light from the sky, falling down from above.
+    Sky,
+    /// Light from light-emitting blocks.
+    Block,
+}
+
+impl LightChannel {
+    /// Both channels.
+    pub const ALL: [Self; 2] = [Self::Sky, Self::Block];
+
+    /// Return this channel's light in the given light level.
+    pub fn get(self, level: LightLevel) -> u8 {
+        match self {
+            Self::Sky => level.sky(),
+            Self::Block => level.block(),
+        }
+    }
+
+    /// Return the given light level with this channel's light replaced.
+    pub fn with(self, level: LightLevel, light: u8) -> LightLevel {
+        match self {
+            Self::Sky => level.with_sky(light),
+            Self::Block => level.with_block(light),
+        }
+    }
+
+    /// Return the light reaching a block from a neighbour holding the given light, given the
+    /// offset from the neighbour to the block. Light loses a level with each block it enters, and
+    /// the block's attenuation on top, so opaque blocks stop it outright.
+    pub fn spread(self, light: u8, offset: IVec3, block: BlockType) -> u8 {
+        let attenuation = block.light_attenuation();
+        // full sunlight shines straight down through clear blocks without fading
+        if self == Self::Sky && offset == IVec3::NEG_Y && light == MAX_LIGHT && attenuation == 0 {
+            return MAX_LIGHT;
+        }
+        light.saturating_sub(1 + attenuation)
+    }
+}
+
+/// The blocks and light of a world spread over many chunks, which light is propagated through
+/// across chunk borders.
+pub trait LightWorld {
+    /// Return the block at the given world block coordinates, or `None` if its chunk is not loaded.
+    fn block_at(&self, pos: IVec3) -> Option<BlockType>;
+
+    /// Return the light level at the given world block coordinates, or `None` if its chunk is not
+    /// loaded.
+    fn light_at(&self, pos: IVec3) -> Option<LightLevel>;
+
+    /// Set the light level at the given world block coordinates, if its chunk is loaded.
+    fn set_light(&mut self, pos: IVec3, level: LightLevel);
+
+    /// Check if the chunk section containing the given world block coordinates holds no blocks, or
+    /// return `None` if its chunk is not loaded.
+    fn is_empty_section(&self, pos: IVec3) -> Option<bool>;
+}
+
+/// Spread light in the given channel out from the given blocks, which must already hold the light
+/// they spread. Blocks already at least as bright are left alone.
+pub fn spread_light<W, I>(world: &mut W, channel: LightChannel, from: I)
+where
+    W: LightWorld,
+    I: IntoIterator<Item = IVec3>,
+{
+    let mut queue = from.into_iter().collect::<VecDeque<_>>();
+    while let Some(pos) = queue.pop_front() {
+        let Some(light) = world.light_at(pos).map(|level| channel.get(level)) else {
+            continue;
+        };
+        for offset in NEIGHBOURS {
+            let neighbour = pos + offset;
+            let (Some(block), Some(level)) = (world.block_at(neighbour), world.light_at(neighbour))
+            else {
+                continue;
+            };
+            let spread = channel.spread(light, offset, block);
+            if spread > channel.get(level) {
+                world.set_light(neighbour, channel.with(level, spread));
+                queue.push_back(neighbour);
+            }
+        }
+    }
+}
+
+/// Take away the light in the given channel the given blocks held, and all the light that spread
+/// from them, then fill back in from the brighter light around the darkened area.
+pub fn remove_light<W, I>(world: &mut W, channel: LightChannel, at: I)
+where
+    W: LightWorld,
+    I: IntoIterator<Item = IVec3>,
+{
+    let mut queue = VecDeque::new();
+    for pos in at {
+        let Some(level) = world.light_at(pos) else {
+            continue;
+        };
+        world.set_light(pos, channel.with(level, 0));
+        queue.push_back((pos, channel.get(level)));
+    }
+    let mut edges = Vec::new();
+    while let Some((pos, light)) = queue.pop_front() {
+        for offset in NEIGHBOURS {
+            let neighbour = pos + offset;
+            let (Some(block), Some(level)) = (world.block_at(neighbour), world.light_at(neighbour))
+            else {
+                continue;
+            };
+            match channel.get(level) {
+                0 => {}
+                // dimmer light, or sunlight falling straight down, may have come from the removed
+                // light, so it goes too
+                dimmer if dimmer < light || dimmer <= channel.spread(light, offset, block) => {
+                    world.set_light(neighbour, channel.with(level, 0));
+                    queue.push_back((neighbour, dimmer));
+                }
+                // brighter light comes from elsewhere, and spreads back into the gap
+                _ => edges.push(neighbour),
+            }
+        }
+    }
+    spread_light(world, channel, edges);
+}
+
+/// Relight the block at the given world block coordinates after it has been changed, taking away
+/// the light it held and spread, then lighting it again from its own emission and its neighbours.
+pub fn relight_block<W: LightWorld>(world: &mut W, pos: IVec3) {
+    relight_blocks(world, [pos]);
+}
+
+/// Relight the blocks at the given world block coordinates after they have been changed, such as by
+/// filling a region. The light is taken away from all of them at once before any is lit again, so a
+/// large edit is relit in one pass rather than block by block.
+pub fn relight_blocks<W, I>(world: &mut W, positions: I)
+where
+    W: LightWorld,
+    I: IntoIterator<Item = IVec3>,
+{
+    let positions = positions.into_iter().collect::<Vec<_>>();
+    for channel in LightChannel::ALL {
+        remove_light(world, channel, positions.iter().copied());
+    }
+    for &pos in &positions {
+        let (Some(block), Some(level)) = (world.block_at(pos), world.light_at(pos)) else {
+            continue;
+        };
+        let emission = block.light_emission();
+        if emission > level.block() {
+            world.set_light(pos, level.with_block(emission));
+        }
+    }
+    for channel in LightChannel::ALL {
+        let lit = positions
+            .iter()
+            .flat_map(|&pos| NEIGHBOURS.map(|offset| pos + offset))
+            .filter(|&neighbour| {
+                world
+                    .light_at(neighbour)
+                    .is_some_and(|level| channel.get(level) > 0)
+            })
+            .collect::<Vec<_>>();
+        let from = positions.iter().copied().chain(lit).collect::<Vec<_>>();
+        spread_light(world, channel, from);
+    }
+}
+
+/// Light the chunk with the given origin, in world block coordinates, once it has been loaded.
+/// Sunlight falls down each column from the chunk above, spreading sideways into overhangs and
+/// caves, and light spreads from the chunk's emitters and in from the lit blocks around it.
+/// Columns under chunks that are not loaded are taken to be open to the sky, and are darkened
+/// once the chunk above them loads.
+pub fn light_chunk<W: LightWorld>(world: &mut W, origin: IVec3) {
+    let size = CHUNK_SIZE as i32;
+    let mut sky_lit = Vec::new();
+    let mut block_lit = Vec::new();
+    // the lowest block in each column in full sunlight, or the chunk's size if none are
+    let mut sunlit = [[size; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+    let mut sky = [[MAX_LIGHT; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+    for (x, z) in iproduct!(0..size, 0..size) {
+        let above = origin + IVec3::new(x, size, z);
+        if let Some(level) = world.light_at(above) {
+            sky[x as usize][z as usize] = level.sky();
+        }
+    }
+    // sunlight falls a section at a time from the top, and through empty sections without
+    // looking up their blocks
+    for section in (0..SECTIONS as i32).rev() {
+        let bottom = section * SECTION_HEIGHT as i32;
+        let Some(empty) = world.is_empty_section(origin + IVec3::Y * bottom) else {
+            return;
+        };
+        for (x, z) in iproduct!(0..size, 0..size) {
+            let column = origin + IVec3::new(x, 0, z);
+            let sky = &mut sky[x as usize][z as usize];
+            for y in (bottom..bottom + SECTION_HEIGHT as i32).rev() {
+                let pos = column + IVec3::Y * y;
+                let block = match empty {
+                    true => BlockType::EMPTY,
+                    false => world.block_at(pos).unwrap_or_default(),
+                };
+                *sky = LightChannel::Sky.spread(*sky, IVec3::NEG_Y, block);
+                let emission = block.light_emission();
+                world.set_light(pos, LightLevel::new(*sky, emission));
+                if *sky == MAX_LIGHT {
+                    sunlit[x as usize][z as usize] = y;
+                } else if *sky > 0 {
+                    sky_lit.push(pos);
+                }
+                if emission > 0 {
+                    block_lit.push(pos);
+                }
+            }
+        }
+    }
+    // full sunlight only spreads sideways where the column beside it is shaded, which saves
+    // spreading from every block of chunks open to the sky
+    for (x, z) in iproduct!(0..size, 0..size) {
+        let edge = x == 0 || z == 0 || x == size - 1 || z == size - 1;
+        let shade = |y| {
+            edge || [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .iter()
+                .any(|(dx, dz)| sunlit[(x + dx) as usize][(z + dz) as usize] > y)
+        };
+        let lit = (sunlit[x as usize][z as usize]..size).filter(|&y| shade(y));
+        sky_lit.extend(lit.map(|y| origin + IVec3::new(x, y, z)));
+    }
+    let border = NEIGHBOURS.into_iter().flat_map(|normal| {
+        // the layer of the chunk against each face, pushed out one block through it
+        let layer = normal.max(IVec3::ZERO) * (size - 1) + normal;
+        iproduct!(0..size, 0..size, 0..size)
+            .map(IVec3::from)
+            .filter(move |block| (*block * normal.abs()).cmpeq(IVec3::ZERO).all())
+            .map(move |block| origin + block + layer)
+    });
+    for pos in border {
+        if let Some(level) = world.light_at(pos) {
+            if level.sky() > 0 {
+                sky_lit.push(pos);
+            }
+            if level.block() > 0 {
+                block_lit.push(pos);
+            }
+        }
+    }
+    spread_light(world, LightChannel::Sky, sky_lit);
+    spread_light(world, LightChannel::Block, block_lit);
+
+    // the chunk below took this one to be open to the sky, so darken it where it is not
+    let shaded = iproduct!(0..size, 0..size)
+        .map(|(x, z)| origin + IVec3::new(x, 0, z))
+        .filter(|&bottom| {
+            let below = bottom + IVec3::NEG_Y;
+            let (Some(bottom), Some(block), Some(level)) = (
+                world.light_at(bottom),
+                world.block_at(below),
+                world.light_at(below),
+            ) else {
+                return false;
+            };
+            level.sky() == MAX_LIGHT
+                && LightChannel::Sky.spread(bottom.sky(), IVec3::NEG_Y, block) < MAX_LIGHT
+        })
+        .map(|bottom| bottom + IVec3::NEG_Y)
+        .collect::<Vec<_>>();
+    remove_light(world, LightChannel::Sky, shaded);
+}
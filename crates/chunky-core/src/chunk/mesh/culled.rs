@@ -0,0 +1,95 @@
+use glam::{IVec3, Vec3};
+
+use crate::chunk::{
+    micro::Microblocks,
+    registry::{BlockDefinition, Transparency},
+    state::BlockState,
+    BlockPos, CHUNK_SIZE,
+};
+
+use super::{triangulize, ChunkMeshBuilder, ChunkNeighbours, Face, MeshData, Quad};
+
+/// A mesh builder that culls invisible faces, tinting each face the colour of its block. Blocks
+/// are built in their own frame and turned into the world by their state.
+pub struct CulledMeshBuilder {}
+
+impl CulledMeshBuilder {
+    /// Add the visible faces of a carved block's sub-voxels. Faces are culled against the block's
+    /// other sub-voxels, and against full cubes on the block's boundary. The sub-voxels turn with
+    /// the block's state.
+    fn push_microblocks(
+        quads: &mut Vec<Quad>,
+        neighbours: &ChunkNeighbours,
+        pos: BlockPos,
+        microblocks: &Microblocks,
+        definition: &BlockDefinition,
+        state: BlockState,
+    ) {
+        let size = microblocks.resolution().size();
+        let scale = 1.0 / size as f32;
+        let origin = IVec3::from(pos);
+        let centre = origin.as_vec3() + Vec3::splat(0.5);
+        for cell in microblocks.cells() {
+            let cell_pos = BlockPos::new(cell.x as u8, cell.y as u8, cell.z as u8);
+            for (face, side) in Quad::faces(cell_pos).into_iter().zip(Face::ALL) {
+                let dir = face.normal();
+                let next = cell + dir.as_ivec3();
+                if microblocks.is_filled(next) {
+                    continue;
+                }
+                let inside = next.min_element() >= 0 && next.max_element() < size;
+                let neighbour = origin + state.rotate(dir).as_ivec3();
+                if !inside && neighbours.is_full_cube(neighbour) {
+                    continue;
+                }
+                quads.push(
+                    face.transformed(scale, origin.as_vec3())
+                        .rotated(state, centre)
+                        .with_light(neighbours.light_at(neighbour))
+                        .with_tint(definition.face_color(side)),
+                );
+            }
+        }
+    }
+
+    /// Return the visible faces of the chunk's blocks, before they are triangulized.
+    pub fn quads(neighbours: &ChunkNeighbours) -> Vec<Quad> {
+        let mut quads = Vec::with_capacity(CHUNK_SIZE as usize * CHUNK_SIZE as usize * 6);
+        for (pos, block) in neighbours.chunk.blocks() {
+            let definition = block.definition();
+            if definition.transparency == Transparency::Translucent {
+                continue;
+            }
+            let state = neighbours.chunk.state_at(pos);
+            if let Some(microblocks) = neighbours.chunk.microblocks_at(pos) {
+                Self::push_microblocks(&mut quads, neighbours, pos, microblocks, definition, state);
+                continue;
+            }
+            let centre = IVec3::from(pos).as_vec3() + Vec3::splat(0.5);
+            for (face, side) in Quad::faces(pos).into_iter().zip(Face::ALL) {
+                let face = face.rotated(state, centre);
+                let dir = face.normal();
+                let neighbour = IVec3::from(pos) + dir.as_ivec3();
+                // cut out blocks only hide the faces between blocks of their own kind
+                let hidden = neighbours.is_full_cube(neighbour)
+                    || (definition.transparency == Transparency::Cutout
+                        && *neighbours.block_at(neighbour) == block
+                        && neighbours.microblocks_at(neighbour).is_none());
+                if !hidden {
+                    // faces are lit by the block in front of them
+                    quads.push(
+                        face.with_light(neighbours.light_at(neighbour))
+                            .with_tint(definition.face_color(side)),
+                    );
+                }
+            }
+        }
+        quads
+    }
+}
+
+impl ChunkMeshBuilder for CulledMeshBuilder {
+    fn build(neighbours: ChunkNeighbours) -> MeshData {
+        triangulize(Self::quads(&neighbours))
+    }
+}
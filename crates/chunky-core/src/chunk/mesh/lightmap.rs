@@ -0,0 +1,92 @@
+use glam::{IVec3, Vec3};
+
+use super::{ChunkNeighbours, Quad};
+use crate::chunk::light::LightLevel;
+
+/// How much a corner is darkened by the number of full cubes around it, for ambient occlusion.
+const OCCLUSION: [f32; 4] = [1.0, 0.8, 0.65, 0.5];
+
+/// The light falling on a chunk's faces, baked into a texture sampled across each face instead of
+/// being stored in its vertices. Each quad gets a tile of two by two texels holding the light at
+/// its corners, darkened by the blocks around each corner, so the light blends smoothly across the
+/// face when the texture is sampled with linear filtering.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Lightmap {
+    /// The width of the texture, in texels.
+    pub width: u32,
+    /// The height of the texture, in texels.
+    pub height: u32,
+    /// The brightness of each texel, from dark at 0 to full light at 255, in rows.
+    pub texels: Vec<u8>,
+}
+
+/// Bake the light falling on the given quads into a lightmap, returning it along with the
+/// lightmap coordinates of each of their vertices. The quads are left in full light, so the
+/// vertices only carry their tint.
+pub fn bake(quads: &mut [Quad], neighbours: &ChunkNeighbours) -> (Lightmap, Vec<[f32; 2]>) {
+    if quads.is_empty() {
+        return (Lightmap::default(), Vec::new());
+    }
+    // lay the tiles out in a roughly square grid
+    let columns = (quads.len() as f32).sqrt().ceil() as u32;
+    let rows = (quads.len() as u32).div_ceil(columns);
+    let (width, height) = (columns * 2, rows * 2);
+    let mut texels = vec![0; (width * height) as usize];
+    let mut uvs = Vec::with_capacity(quads.len() * 4);
+
+    for (index, quad) in quads.iter_mut().enumerate() {
+        let tile = (index as u32 % columns * 2, index as u32 / columns * 2);
+        let normal = quad.normal();
+        // the corners of a quad run up its first side, then across and back down
+        for (vertex, (dx, dy)) in quad.vertices.iter().zip([(0, 0), (0, 1), (1, 1), (1, 0)]) {
+            let (x, y) = (tile.0 + dx, tile.1 + dy);
+            let brightness = corner_light(neighbours, *vertex, normal);
+            texels[(y * width + x) as usize] = (brightness * 255.0).round() as u8;
+            // sample the centre of the texel, so nothing bleeds in from the neighbouring tiles
+            uvs.push([
+                (x as f32 + 0.5) / width as f32,
+                (y as f32 + 0.5) / height as f32,
+            ]);
+        }
+        quad.light = LightLevel::SKY;
+    }
+    let lightmap = Lightmap {
+        width,
+        height,
+        texels,
+    };
+    (lightmap, uvs)
+}
+
+/// Return the brightness at a corner of a face: the average light of the open blocks in front of
+/// the face that touch the corner, darkened by the full cubes among them.
+fn corner_light(neighbours: &ChunkNeighbours, corner: Vec3, normal: Vec3) -> f32 {
+    let (across, along) = tangents(normal);
+    let (mut total, mut open) = (0.0, 0);
+    let mut occluded = 0;
+    for (a, b) in [(-0.5, -0.5), (-0.5, 0.5), (0.5, -0.5), (0.5, 0.5)] {
+        let pos = (corner + normal * 0.5 + across * a + along * b)
+            .floor()
+            .as_ivec3();
+        if neighbours.is_full_cube(pos) {
+            occluded += 1;
+        } else {
+            total += neighbours.light_at(pos).brightness();
+            open += 1;
+        }
+    }
+    if open == 0 {
+        return 0.0;
+    }
+    total / open as f32 * OCCLUSION[occluded.min(3)]
+}
+
+/// Return the two axes lying in the plane of a face with the given normal.
+fn tangents(normal: Vec3) -> (Vec3, Vec3) {
+    let axis = normal.abs().round().as_ivec3();
+    match axis {
+        IVec3::X => (Vec3::Y, Vec3::Z),
+        IVec3::Y => (Vec3::X, Vec3::Z),
+        _ => (Vec3::X, Vec3::Y),
+    }
+}
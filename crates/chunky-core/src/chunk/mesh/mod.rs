@@ -0,0 +1,520 @@
+pub mod culled;
+pub mod lightmap;
+pub mod smooth;
+pub mod stupid;
+
+use std::str::FromStr;
+
+use culled::CulledMeshBuilder;
+use glam::{IVec3, Vec3};
+use itertools::iproduct;
+use serde::{Deserialize, Serialize};
+use smooth::SurfaceNetsMeshBuilder;
+
+use super::{
+    fluid, light::LightLevel, micro::Microblocks, registry::Transparency, state::BlockState,
+    BlockPos, BlockType, Chunk, ChunkPos, CHUNK_SIZE,
+};
+
+/// Chunk size minus one.
+const CHUNK_SIZE_MINUS_ONE: u8 = CHUNK_SIZE - 1;
+
+/// Chunk size as an `i32`, i.e. the first position past the end of the chunk.
+const CHUNK_SIZE_I32: i32 = CHUNK_SIZE as i32;
+
+/// Chunk size plus one.
+const CHUNK_SIZE_PLUS_ONE: i32 = CHUNK_SIZE as i32 + 1;
+
+/// How chunks are turned into meshes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MeshingMode {
+    /// A cube for every block, culling hidden faces.
+    #[default]
+    Blocky,
+    /// A smooth surface through the chunk's density field.
+    Smooth,
+}
+
+impl FromStr for MeshingMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blocky" => Ok(Self::Blocky),
+            "smooth" => Ok(Self::Smooth),
+            _ => anyhow::bail!("unknown meshing mode {}", s),
+        }
+    }
+}
+
+/// How the light falling on a chunk is carried to the renderer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightingMode {
+    /// Each face is shaded by the light in front of it, stored in its vertex colours.
+    #[default]
+    Vertex,
+    /// The light at each corner of every face is baked into a small per-chunk lightmap, blending
+    /// smoothly across faces and darkening corners, without adding vertices. Smooth meshes are
+    /// already lit smoothly, so they keep vertex light.
+    Lightmap,
+}
+
+impl FromStr for LightingMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vertex" => Ok(Self::Vertex),
+            "lightmap" => Ok(Self::Lightmap),
+            _ => anyhow::bail!("unknown lighting mode {}", s),
+        }
+    }
+}
+
+/// The geometry of a chunk's mesh, as an indexed triangle list in the chunk's local coordinates.
+/// This is independent of any renderer, so it can be uploaded by whichever engine displays it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MeshData {
+    /// The position of each vertex.
+    pub positions: Vec<[f32; 3]>,
+    /// The normal of each vertex.
+    pub normals: Vec<[f32; 3]>,
+    /// The colour of each vertex, as linear RGBA.
+    pub colors: Vec<[f32; 4]>,
+    /// The vertices of each triangle, three to a triangle, wound counter-clockwise.
+    pub indices: Vec<u32>,
+    /// The coordinates of each vertex in the lightmap, or empty if the mesh has none.
+    pub lightmap_uvs: Vec<[f32; 2]>,
+    /// The light baked into the mesh's faces, if it is lit by a lightmap rather than its vertex
+    /// colours.
+    pub lightmap: Option<lightmap::Lightmap>,
+}
+
+/// A mesh builder for chunks. Builders may read either the blocks of the chunk or its density
+/// field.
+pub trait ChunkMeshBuilder {
+    /// Builds a mesh for a chunk.
+    fn build(data: ChunkNeighbours) -> MeshData;
+}
+
+/// A struct that stores neighbours of a chunk.
+pub struct ChunkNeighbours<'a> {
+    pub chunk: &'a Chunk,
+    pub north: &'a Chunk,
+    pub east: &'a Chunk,
+    pub south: &'a Chunk,
+    pub west: &'a Chunk,
+    pub up: &'a Chunk,
+    pub down: &'a Chunk,
+}
+
+impl<'a> ChunkNeighbours<'a> {
+    /// Returns the chunk containing the given position, and the position within that chunk.
+    /// Positions outside the chunk along more than one axis would need a diagonal neighbour, so
+    /// they are clamped into the nearest face neighbour instead.
+    fn locate(&self, pos: IVec3) -> (&Chunk, BlockPos) {
+        let mut pos = pos.clamp(IVec3::splat(-1), IVec3::splat(CHUNK_SIZE_I32));
+        let outside = |value: i32| !(0..CHUNK_SIZE_I32).contains(&value);
+        for axis in 1..3 {
+            if (0..axis).any(|earlier| outside(pos[earlier])) {
+                pos[axis] = pos[axis].clamp(0, CHUNK_SIZE_I32 - 1);
+            }
+        }
+        let IVec3 { x, y, z } = pos;
+        match (x, y, z) {
+            (-1, _, _) => (self.west, (CHUNK_SIZE_MINUS_ONE, y as u8, z as u8).into()),
+            (CHUNK_SIZE_I32, _, _) => (self.east, (0, y as u8, z as u8).into()),
+            (_, -1, _) => (self.down, (x as u8, CHUNK_SIZE_MINUS_ONE, z as u8).into()),
+            (_, CHUNK_SIZE_I32, _) => (self.up, (x as u8, 0, z as u8).into()),
+            (_, _, -1) => (self.north, (x as u8, y as u8, CHUNK_SIZE_MINUS_ONE).into()),
+            (_, _, CHUNK_SIZE_I32) => (self.south, (x as u8, y as u8, 0).into()),
+            _ => (self.chunk, (x as u8, y as u8, z as u8).into()),
+        }
+    }
+
+    /// Returns the block at the given position, with neighbours taken into account.
+    pub fn block_at(&self, pos: IVec3) -> &BlockType {
+        let (chunk, pos) = self.locate(pos);
+        chunk.block_at(pos)
+    }
+
+    /// Returns the shape of the carved block at the given position, with neighbours taken into
+    /// account.
+    pub fn microblocks_at(&self, pos: IVec3) -> Option<&Microblocks> {
+        let (chunk, pos) = self.locate(pos);
+        chunk.microblocks_at(pos)
+    }
+
+    /// Returns whether the block at the given position is an opaque, uncarved cube, which hides the
+    /// faces of the blocks against it.
+    pub fn is_full_cube(&self, pos: IVec3) -> bool {
+        self.block_at(pos).transparency() == Transparency::Opaque
+            && self.microblocks_at(pos).is_none()
+    }
+
+    /// Returns the light level at the given position, with neighbours taken into account.
+    pub fn light_at(&self, pos: IVec3) -> LightLevel {
+        let (chunk, pos) = self.locate(pos);
+        chunk.light_at(pos)
+    }
+
+    /// Returns the density at the given position, with neighbours taken into account.
+    pub fn density_at(&self, pos: IVec3) -> f32 {
+        let (chunk, pos) = self.locate(pos);
+        chunk.density_at(pos)
+    }
+
+    /// Return an iterator over all blocks in the chunk, ordered by their position.
+    pub fn blocks(&self) -> impl Iterator<Item = (IVec3, BlockType)> + '_ {
+        iproduct!(
+            -1..CHUNK_SIZE_PLUS_ONE,
+            -1..CHUNK_SIZE_PLUS_ONE,
+            -1..CHUNK_SIZE_PLUS_ONE
+        )
+        .map(move |pos| {
+            let block = self.block_at(pos.into());
+            (pos.into(), *block)
+        })
+    }
+}
+
+/// A struct that stores the vertices and indices of a mesh.
+pub struct Quad {
+    /// The vertices of the quad.
+    pub vertices: [Vec3; 4],
+    /// The light level falling on the quad.
+    pub light: LightLevel,
+    /// The colour the quad is tinted, as linear RGBA.
+    pub tint: [f32; 4],
+}
+
+/// A face of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Face {
+    North,
+    East,
+    South,
+    West,
+    Up,
+    Down,
+}
+
+impl Face {
+    /// Every face, in the order [`Quad::faces`] returns them.
+    pub const ALL: [Face; 6] = [
+        Face::North,
+        Face::East,
+        Face::South,
+        Face::West,
+        Face::Up,
+        Face::Down,
+    ];
+}
+
+impl From<Face> for Vec3 {
+    fn from(face: Face) -> Self {
+        match face {
+            Face::North => Vec3::Z,
+            Face::East => Vec3::X,
+            Face::South => Vec3::NEG_Z,
+            Face::West => Vec3::NEG_X,
+            Face::Up => Vec3::Y,
+            Face::Down => Vec3::NEG_Y,
+        }
+    }
+}
+
+impl Quad {
+    /// Returns a quad for the north face of the block at the given position.
+    #[inline]
+    pub fn north(pos: BlockPos) -> Quad {
+        Quad::square(
+            IVec3::new(pos.x as i32, pos.y as i32, pos.z as i32),
+            Vec3::NEG_Z,
+        )
+    }
+
+    /// Returns a quad for the east face of the block at the given position.
+    #[inline]
+    pub fn east(pos: BlockPos) -> Quad {
+        Quad::square(
+            IVec3::new(pos.x as i32 + 1, pos.y as i32, pos.z as i32),
+            Vec3::X,
+        )
+    }
+
+    /// Returns a quad for the south face of the block at the given position.
+    #[inline]
+    pub fn south(pos: BlockPos) -> Quad {
+        Quad::square(
+            IVec3::new(pos.x as i32 + 1, pos.y as i32, pos.z as i32 + 1),
+            Vec3::Z,
+        )
+    }
+
+    /// Returns a quad for the west face of the block at the given position.
+    #[inline]
+    pub fn west(pos: BlockPos) -> Quad {
+        Quad::square(
+            IVec3::new(pos.x as i32, pos.y as i32, pos.z as i32 + 1),
+            Vec3::NEG_X,
+        )
+    }
+
+    /// Returns a quad for the up face of the block at the given position.
+    #[inline]
+    pub fn up(pos: BlockPos) -> Quad {
+        Quad::square(
+            IVec3::new(pos.x as i32, pos.y as i32 + 1, pos.z as i32 + 1),
+            Vec3::Y,
+        )
+    }
+
+    /// Returns a quad for the down face of the block at the given position.
+    #[inline]
+    pub fn down(pos: BlockPos) -> Quad {
+        Quad::square(
+            IVec3::new(pos.x as i32, pos.y as i32, pos.z as i32),
+            Vec3::NEG_Y,
+        )
+    }
+
+    /// Create a list of quads for the given block position.
+    #[inline]
+    pub fn faces(pos: BlockPos) -> [Quad; 6] {
+        [
+            Quad::north(pos),
+            Quad::east(pos),
+            Quad::south(pos),
+            Quad::west(pos),
+            Quad::up(pos),
+            Quad::down(pos),
+        ]
+    }
+
+    #[inline]
+    pub fn square(pos: IVec3, direction: Vec3) -> Quad {
+        Quad::new(pos, direction, 1, 1)
+    }
+
+    /// Creates a new quad from a rectangle, facing along the given unit axis. The quad's normal will
+    /// be in the right-hand normal direction.
+    pub fn new(pos: IVec3, direction: Vec3, width: u32, height: u32) -> Quad {
+        let normal = direction;
+
+        // handle up and down directions separately - cross
+        let up = if direction == Vec3::Y || direction == Vec3::NEG_Y {
+            Vec3::X
+        } else {
+            Vec3::Y
+        };
+        let right = normal.cross(up);
+
+        // counter-clockwise when looking at the normal
+        let a = pos.as_vec3();
+        let b = pos.as_vec3() + up * height as f32;
+        let c = pos.as_vec3() + up * height as f32 + right * width as f32;
+        let d = pos.as_vec3() + right * width as f32;
+
+        Quad {
+            vertices: [a, b, c, d],
+            light: LightLevel::SKY,
+            tint: [1.0; 4],
+        }
+    }
+
+    /// Scale the quad about the origin, then move it by the given offset.
+    #[inline]
+    pub fn transformed(mut self, scale: f32, offset: Vec3) -> Quad {
+        for vertex in &mut self.vertices {
+            *vertex = *vertex * scale + offset;
+        }
+        self
+    }
+
+    /// Turn the quad about the given centre from a block's own frame into the world, by the
+    /// block's state.
+    #[inline]
+    pub fn rotated(mut self, state: BlockState, centre: Vec3) -> Quad {
+        if state.facing() != Face::Up {
+            for vertex in &mut self.vertices {
+                *vertex = centre + state.rotate(*vertex - centre);
+            }
+        }
+        self
+    }
+
+    /// Set the light level falling on the quad.
+    #[inline]
+    pub fn with_light(mut self, light: LightLevel) -> Quad {
+        self.light = light;
+        self
+    }
+
+    /// Set the colour the quad is tinted.
+    #[inline]
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Quad {
+        self.tint = tint;
+        self
+    }
+
+    /// Calculates the normal of the quad.
+    #[inline]
+    pub fn normal(&self) -> Vec3 {
+        let a = self.vertices[0];
+        let b = self.vertices[1];
+        let c = self.vertices[2];
+
+        let ab = b - a;
+        let ac = c - a;
+
+        ab.cross(ac).normalize()
+    }
+}
+
+/// Triangulizes a list of quads.
+pub fn triangulize(quads: Vec<Quad>) -> MeshData {
+    // mesh properties
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+
+    for quad in quads {
+        // append vertices
+        let start = vertices.len() as u32;
+        for vertex in &quad.vertices {
+            vertices.push(vertex.to_array());
+        }
+        indices.extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+        // push normal for each vertex
+        let normal = quad.normal();
+        let brightness = quad.light.brightness();
+        let [r, g, b, a] = quad.tint;
+        for _ in 0..4 {
+            normals.push(normal.to_array());
+            colors.push([r * brightness, g * brightness, b * brightness, a]);
+        }
+    }
+
+    MeshData {
+        positions: vertices,
+        normals,
+        colors,
+        indices,
+        ..Default::default()
+    }
+}
+
+impl MeshData {
+    /// Add the geometry of another mesh to this one.
+    pub fn append(&mut self, other: MeshData) {
+        let start = self.positions.len() as u32;
+        self.positions.extend(other.positions);
+        self.normals.extend(other.normals);
+        self.colors.extend(other.colors);
+        self.lightmap_uvs.extend(other.lightmap_uvs);
+        self.indices
+            .extend(other.indices.into_iter().map(|index| index + start));
+    }
+}
+
+/// Return the colour a translucent block is tinted, or `None` if it is not drawn.
+fn liquid_tint(block: BlockType) -> Option<[f32; 4]> {
+    let definition = block.definition();
+    (definition.transparency == Transparency::Translucent && definition.color[3] > 0.0)
+        .then_some(definition.color)
+}
+
+/// Return the faces of the see-through blocks in the chunk that border empty space, so only the
+/// surface of a body of water is drawn, whatever the meshing mode. Flowing water is drawn lower
+/// the further it has spread, unless more water sits on top of it.
+fn liquid_quads(neighbours: &ChunkNeighbours) -> Vec<Quad> {
+    let mut quads = Vec::new();
+    for (pos, block) in neighbours.chunk.blocks() {
+        let Some(tint) = liquid_tint(block) else {
+            continue;
+        };
+        let above = IVec3::from(pos) + IVec3::Y;
+        let height = if *neighbours.block_at(above) == block {
+            1.0
+        } else {
+            fluid::surface_height(fluid::level(neighbours.chunk.state_at(pos)))
+        };
+        for mut face in Quad::faces(pos) {
+            let neighbour = IVec3::from(pos) + face.normal().as_ivec3();
+            // a lowered surface shows beneath whatever sits on top of it
+            let lowered = neighbour == above && height < 1.0;
+            if *neighbours.block_at(neighbour) != BlockType::EMPTY && !lowered {
+                continue;
+            }
+            let bottom = pos.y as f32;
+            for vertex in &mut face.vertices {
+                vertex.y = bottom + (vertex.y - bottom) * height;
+            }
+            quads.push(
+                face.with_light(neighbours.light_at(neighbour))
+                    .with_tint(tint),
+            );
+        }
+    }
+    quads
+}
+
+pub fn build(data: ChunkNeighbours, mode: MeshingMode, lighting: LightingMode) -> MeshData {
+    let mut liquids = liquid_quads(&data);
+    match (mode, lighting) {
+        (MeshingMode::Blocky, LightingMode::Lightmap) => {
+            let mut quads = CulledMeshBuilder::quads(&data);
+            quads.append(&mut liquids);
+            let (lightmap, uvs) = lightmap::bake(&mut quads, &data);
+            MeshData {
+                lightmap_uvs: uvs,
+                lightmap: Some(lightmap),
+                ..triangulize(quads)
+            }
+        }
+        (MeshingMode::Blocky, LightingMode::Vertex) => {
+            let mut quads = CulledMeshBuilder::quads(&data);
+            quads.append(&mut liquids);
+            triangulize(quads)
+        }
+        (MeshingMode::Smooth, _) => {
+            let mut mesh = SurfaceNetsMeshBuilder::build(data);
+            mesh.append(triangulize(liquids));
+            mesh
+        }
+    }
+}
+
+/// Build the mesh of a chunk, given its neighbours in north, east, south, west, up, down order.
+pub fn build_with_neighbours(
+    chunk: &Chunk,
+    [north, east, south, west, up, down]: &[Chunk; 6],
+    mode: MeshingMode,
+    lighting: LightingMode,
+) -> MeshData {
+    build(
+        ChunkNeighbours {
+            chunk,
+            north,
+            east,
+            south,
+            west,
+            up,
+            down,
+        },
+        mode,
+        lighting,
+    )
+}
+
+/// Build the mesh of a chunk against solid neighbours, for chunks whose neighbours may not be
+/// loaded yet.
+pub fn build_isolated(chunk: &Chunk, mode: MeshingMode, lighting: LightingMode) -> MeshData {
+    let neighbours = ChunkPos::FACE_NEIGHBOURS
+        .map(|dir| Chunk::empty(chunk.position + dir).filled(BlockType::STONE));
+    build_with_neighbours(chunk, &neighbours, mode, lighting)
+}
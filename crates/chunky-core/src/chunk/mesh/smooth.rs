@@ -0,0 +1,160 @@
+use glam::{IVec3, Vec3};
+use itertools::iproduct;
+
+use super::{ChunkMeshBuilder, ChunkNeighbours, MeshData, CHUNK_SIZE_I32};
+
+/// The number of density samples along each axis, covering the chunk and the blocks either side.
+const SAMPLES: i32 = CHUNK_SIZE_I32 + 2;
+
+/// The number of cells along each axis. Cells span the centres of eight neighbouring blocks, and
+/// start one block before the chunk so its surface joins up with its neighbours'.
+const CELLS: i32 = CHUNK_SIZE_I32 + 1;
+
+/// The offsets of the corners of a cell from its lowest corner.
+const CORNERS: [IVec3; 8] = [
+    IVec3::new(0, 0, 0),
+    IVec3::new(1, 0, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(1, 1, 0),
+    IVec3::new(0, 0, 1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(0, 1, 1),
+    IVec3::new(1, 1, 1),
+];
+
+/// The pairs of corners joined by the edges of a cell.
+const EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// A mesh builder that places a smooth surface through the chunk's density field using surface
+/// nets, with normals taken from the density gradient so the surface is shaded smoothly.
+pub struct SurfaceNetsMeshBuilder;
+
+impl SurfaceNetsMeshBuilder {
+    /// Return the index of a density sample.
+    fn sample_index(pos: IVec3) -> usize {
+        let pos = pos + IVec3::ONE;
+        ((pos.z * SAMPLES + pos.y) * SAMPLES + pos.x) as usize
+    }
+
+    /// Return the index of a cell, given its lowest corner.
+    fn cell_index(cell: IVec3) -> usize {
+        let cell = cell + IVec3::ONE;
+        ((cell.z * CELLS + cell.y) * CELLS + cell.x) as usize
+    }
+}
+
+impl ChunkMeshBuilder for SurfaceNetsMeshBuilder {
+    fn build(neighbours: ChunkNeighbours) -> MeshData {
+        let range = -1..CHUNK_SIZE_I32 + 1;
+        let mut densities = vec![0.0; (SAMPLES * SAMPLES * SAMPLES) as usize];
+        for (z, y, x) in iproduct!(range.clone(), range.clone(), range) {
+            let pos = IVec3::new(x, y, z);
+            densities[Self::sample_index(pos)] = neighbours.density_at(pos);
+        }
+        let density = |pos: IVec3| densities[Self::sample_index(pos)];
+
+        // place a vertex in every cell the surface passes through
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut colors = Vec::new();
+        let mut vertices = vec![None; (CELLS * CELLS * CELLS) as usize];
+        for (z, y, x) in iproduct!(-1..CHUNK_SIZE_I32, -1..CHUNK_SIZE_I32, -1..CHUNK_SIZE_I32) {
+            let cell = IVec3::new(x, y, z);
+            let corners = CORNERS.map(|offset| density(cell + offset));
+            let inside = corners.iter().filter(|&&value| value > 0.0).count();
+            if inside == 0 || inside == corners.len() {
+                continue;
+            }
+
+            // average the points where the surface crosses the cell's edges
+            let mut sum = Vec3::ZERO;
+            let mut crossings = 0;
+            for (a, b) in EDGES {
+                let (da, db) = (corners[a], corners[b]);
+                if (da > 0.0) != (db > 0.0) {
+                    let t = da / (da - db);
+                    sum += CORNERS[a].as_vec3().lerp(CORNERS[b].as_vec3(), t);
+                    crossings += 1;
+                }
+            }
+
+            // the density increases into the terrain, so the surface faces down its gradient
+            let gradient = Vec3::new(
+                corners[1] + corners[3] + corners[5] + corners[7]
+                    - corners[0]
+                    - corners[2]
+                    - corners[4]
+                    - corners[6],
+                corners[2] + corners[3] + corners[6] + corners[7]
+                    - corners[0]
+                    - corners[1]
+                    - corners[4]
+                    - corners[5],
+                corners[4] + corners[5] + corners[6] + corners[7]
+                    - corners[0]
+                    - corners[1]
+                    - corners[2]
+                    - corners[3],
+            );
+            let brightness = CORNERS
+                .iter()
+                .zip(corners)
+                .filter(|(_, value)| *value <= 0.0)
+                .map(|(offset, _)| neighbours.light_at(cell + *offset).brightness())
+                .fold(0.0, f32::max);
+
+            vertices[Self::cell_index(cell)] = Some(positions.len() as u32);
+            // densities are sampled at the centres of blocks
+            positions.push((cell.as_vec3() + sum / crossings as f32 + 0.5).to_array());
+            normals.push((-gradient.normalize_or_zero()).to_array());
+            colors.push([brightness, brightness, brightness, 1.0]);
+        }
+
+        // join the vertices around every edge the surface crosses, for edges starting in the chunk
+        let mut indices = Vec::new();
+        for (z, y, x) in iproduct!(0..CHUNK_SIZE_I32, 0..CHUNK_SIZE_I32, 0..CHUNK_SIZE_I32) {
+            let pos = IVec3::new(x, y, z);
+            let inside = density(pos) > 0.0;
+            for axis in 0..3 {
+                if inside == (density(pos + IVec3::AXES[axis]) > 0.0) {
+                    continue;
+                }
+                let (u, v) = (IVec3::AXES[(axis + 1) % 3], IVec3::AXES[(axis + 2) % 3]);
+                let Some(mut quad) = [pos - u - v, pos - v, pos, pos - u]
+                    .map(|cell| vertices[Self::cell_index(cell)])
+                    .into_iter()
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    continue;
+                };
+                // the cells wind counter-clockwise around the axis, so flip quads facing back
+                // along it
+                if !inside {
+                    quad.reverse();
+                }
+                indices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+            }
+        }
+
+        MeshData {
+            positions,
+            normals,
+            colors,
+            indices,
+            ..Default::default()
+        }
+    }
+}
@@ -1,12 +1,11 @@
-use bevy::prelude::Mesh;
 use itertools::Itertools;
 
-use super::{triangulize, ChunkMeshBuilder, ChunkNeighbours, Quad};
+use super::{triangulize, ChunkMeshBuilder, ChunkNeighbours, MeshData, Quad};
 
 pub struct StupidMeshBuilder;
 
 impl ChunkMeshBuilder for StupidMeshBuilder {
-    fn build(neighbours: ChunkNeighbours) -> Mesh {
+    fn build(neighbours: ChunkNeighbours) -> MeshData {
         // just collect all faces and triangulize them
         let quads = neighbours
             .chunk
@@ -0,0 +1,97 @@
+use glam::{IVec3, Vec3};
+use itertools::iproduct;
+use serde::{Deserialize, Serialize};
+
+/// The number of sub-voxels along each axis of a carved block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MicroResolution {
+    /// 2×2×2 sub-voxels.
+    Two,
+    /// 4×4×4 sub-voxels.
+    #[default]
+    Four,
+}
+
+impl MicroResolution {
+    /// Return the number of sub-voxels along each axis.
+    pub fn size(self) -> i32 {
+        match self {
+            Self::Two => 2,
+            Self::Four => 4,
+        }
+    }
+}
+
+/// The sub-voxels left in a carved block, which are all made of the block's own type. Blocks
+/// carved into microblocks keep their type, and store their shape in their block data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Microblocks {
+    /// The number of sub-voxels along each axis.
+    resolution: MicroResolution,
+    /// A bit for each sub-voxel, set if it is filled, indexed in y, z, x order.
+    cells: u64,
+}
+
+impl Microblocks {
+    /// Create a completely filled block of sub-voxels.
+    pub fn full(resolution: MicroResolution) -> Self {
+        let count = resolution.size().pow(3) as u32;
+        Self {
+            resolution,
+            cells: u64::MAX >> (64 - count),
+        }
+    }
+
+    /// Return the number of sub-voxels along each axis.
+    pub fn resolution(&self) -> MicroResolution {
+        self.resolution
+    }
+
+    /// Check if every sub-voxel has been removed.
+    pub fn is_empty(&self) -> bool {
+        self.cells == 0
+    }
+
+    /// Check if the sub-voxel at the given position is filled. Positions outside the block are
+    /// never filled.
+    pub fn is_filled(&self, cell: IVec3) -> bool {
+        self.index(cell)
+            .is_some_and(|index| self.cells & (1 << index) != 0)
+    }
+
+    /// Remove the sub-voxel at the given position, returning whether it was filled.
+    pub fn remove(&mut self, cell: IVec3) -> bool {
+        let filled = self.is_filled(cell);
+        if let Some(index) = self.index(cell) {
+            self.cells &= !(1 << index);
+        }
+        filled
+    }
+
+    /// Return the filled sub-voxel nearest to a point within the block, where the block spans 0 to 1
+    /// on each axis.
+    pub fn nearest(&self, point: Vec3) -> Option<IVec3> {
+        let size = self.resolution.size() as f32;
+        self.cells().min_by(|a, b| {
+            let centre = |cell: &IVec3| (cell.as_vec3() + 0.5) / size;
+            centre(a)
+                .distance_squared(point)
+                .total_cmp(&centre(b).distance_squared(point))
+        })
+    }
+
+    /// Return an iterator over the positions of the filled sub-voxels.
+    pub fn cells(&self) -> impl Iterator<Item = IVec3> + '_ {
+        let size = self.resolution.size();
+        iproduct!(0..size, 0..size, 0..size)
+            .map(|(y, z, x)| IVec3::new(x, y, z))
+            .filter(|&cell| self.is_filled(cell))
+    }
+
+    /// Return the bit index of a sub-voxel, or `None` if it lies outside the block.
+    fn index(&self, cell: IVec3) -> Option<u32> {
+        let size = self.resolution.size();
+        (cell.min_element() >= 0 && cell.max_element() < size)
+            .then(|| ((cell.y * size + cell.z) * size + cell.x) as u32)
+    }
+}
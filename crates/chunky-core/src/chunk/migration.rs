@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+use bincode::Options;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    density::DensityVolume, entity::EntityRecord, mesh::Face, scheduled::ScheduledChange,
+    serialize::ChunkRepr, BlockData, BlockPos, BlockType, ChunkPos, ItemStack,
+};
+
+/// The version of the layout chunks are currently saved in. Bump this, and add a migration from
+/// the previous version to [`MIGRATIONS`], whenever the serialized layout of a chunk changes.
+pub const FORMAT_VERSION: u32 = 4;
+
+/// A function upgrading a serialized chunk from one format version to the next.
+type Migration = fn(&[u8]) -> anyhow::Result<Vec<u8>>;
+
+/// The migrations between format versions, where the migration at index `n` upgrades chunks from
+/// version `n` to version `n + 1`.
+const MIGRATIONS: [Migration; FORMAT_VERSION as usize] =
+    [from_unversioned, from_v1, from_v2, from_v3];
+
+/// Upgrade a serialized chunk from the given format version to [`FORMAT_VERSION`].
+pub fn upgrade(raw: Vec<u8>, version: u32) -> anyhow::Result<Vec<u8>> {
+    if version > FORMAT_VERSION {
+        bail!(
+            "chunk was saved in format version {}, but only versions up to {} are supported",
+            version,
+            FORMAT_VERSION
+        );
+    }
+    MIGRATIONS[version as usize..]
+        .iter()
+        .try_fold(raw, |raw, migration| migration(&raw))
+}
+
+/// Deserialize a value that must span the whole of the given bytes, so that layouts which are
+/// prefixes of one another can be told apart.
+fn deserialize_exact<T: DeserializeOwned>(raw: &[u8]) -> Option<T> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(raw)
+        .ok()
+}
+
+/// Block data as saved before blocks could be carved into microblocks.
+#[derive(Deserialize)]
+struct BlockDataV0 {
+    orientation: Option<Face>,
+    inventory: Vec<ItemStack>,
+    state: BTreeMap<String, String>,
+}
+
+/// The chunk layout saved before microblocks were added.
+#[derive(Deserialize)]
+struct UnversionedRepr {
+    position: ChunkPos,
+    runs: Vec<(BlockType, u16)>,
+    block_data: Vec<(BlockPos, BlockDataV0)>,
+}
+
+/// The chunk layout saved once microblocks were added, before block changes could be scheduled.
+#[derive(Deserialize)]
+struct MicroblocksRepr {
+    position: ChunkPos,
+    runs: Vec<(BlockType, u16)>,
+    block_data: Vec<(BlockPos, BlockData)>,
+}
+
+/// The chunk layout saved once block changes could be scheduled, before chunks had density
+/// fields.
+#[derive(Deserialize)]
+struct ScheduledRepr {
+    position: ChunkPos,
+    runs: Vec<(BlockType, u16)>,
+    block_data: Vec<(BlockPos, BlockData)>,
+    scheduled: Vec<ScheduledChange>,
+}
+
+/// The chunk layout of format version 1, which stored a density for every block.
+#[derive(Serialize, Deserialize)]
+struct DensityRepr {
+    position: ChunkPos,
+    runs: Vec<(BlockType, u16)>,
+    block_data: Vec<(BlockPos, BlockData)>,
+    scheduled: Vec<ScheduledChange>,
+    density: Option<Vec<i8>>,
+}
+
+/// The chunk layout of format version 2, before chunks kept the entities standing in them.
+#[derive(Serialize, Deserialize)]
+struct RunsRepr {
+    position: ChunkPos,
+    runs: Vec<(BlockType, u16)>,
+    block_data: Vec<(BlockPos, BlockData)>,
+    scheduled: Vec<ScheduledChange>,
+    density: Option<Vec<(i8, u16)>>,
+}
+
+/// The chunk layout of format version 3, before blocks had states.
+#[derive(Serialize, Deserialize)]
+struct EntitiesRepr {
+    position: ChunkPos,
+    runs: Vec<(BlockType, u16)>,
+    block_data: Vec<(BlockPos, BlockData)>,
+    scheduled: Vec<ScheduledChange>,
+    density: Option<Vec<(i8, u16)>>,
+    entities: Vec<EntityRecord>,
+}
+
+impl From<UnversionedRepr> for MicroblocksRepr {
+    fn from(repr: UnversionedRepr) -> Self {
+        let block_data = repr
+            .block_data
+            .into_iter()
+            .map(|(pos, data)| {
+                let data = BlockData {
+                    orientation: data.orientation,
+                    inventory: data.inventory,
+                    state: data.state,
+                    microblocks: None,
+                };
+                (pos, data)
+            })
+            .collect();
+        Self {
+            position: repr.position,
+            runs: repr.runs,
+            block_data,
+        }
+    }
+}
+
+impl From<MicroblocksRepr> for ScheduledRepr {
+    fn from(repr: MicroblocksRepr) -> Self {
+        Self {
+            position: repr.position,
+            runs: repr.runs,
+            block_data: repr.block_data,
+            scheduled: Vec::new(),
+        }
+    }
+}
+
+impl From<ScheduledRepr> for DensityRepr {
+    fn from(repr: ScheduledRepr) -> Self {
+        Self {
+            position: repr.position,
+            runs: repr.runs,
+            block_data: repr.block_data,
+            scheduled: repr.scheduled,
+            density: None,
+        }
+    }
+}
+
+impl From<DensityRepr> for RunsRepr {
+    fn from(repr: DensityRepr) -> Self {
+        Self {
+            position: repr.position,
+            runs: repr.runs,
+            block_data: repr.block_data,
+            scheduled: repr.scheduled,
+            density: repr
+                .density
+                .and_then(DensityVolume::from_quantized)
+                .map(|density| density.runs()),
+        }
+    }
+}
+
+impl From<RunsRepr> for EntitiesRepr {
+    fn from(repr: RunsRepr) -> Self {
+        Self {
+            position: repr.position,
+            runs: repr.runs,
+            block_data: repr.block_data,
+            scheduled: repr.scheduled,
+            density: repr.density,
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl From<EntitiesRepr> for ChunkRepr {
+    fn from(repr: EntitiesRepr) -> Self {
+        Self {
+            position: repr.position,
+            runs: repr.runs,
+            block_data: repr.block_data,
+            scheduled: repr.scheduled,
+            density: repr.density,
+            entities: repr.entities,
+            states: Vec::new(),
+        }
+    }
+}
+
+/// Upgrade a chunk saved before worlds were versioned. Unversioned worlds may hold any of the
+/// layouts used until then, so each is tried from newest to oldest.
+fn from_unversioned(raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if deserialize_exact::<DensityRepr>(raw).is_some() {
+        return Ok(raw.to_vec());
+    }
+    let repr = deserialize_exact::<ScheduledRepr>(raw)
+        .or_else(|| deserialize_exact::<MicroblocksRepr>(raw).map(ScheduledRepr::from))
+        .or_else(|| {
+            deserialize_exact::<UnversionedRepr>(raw)
+                .map(MicroblocksRepr::from)
+                .map(ScheduledRepr::from)
+        })
+        .context("chunk does not match any unversioned layout")?;
+    Ok(bincode::serialize(&DensityRepr::from(repr))?)
+}
+
+/// Upgrade a chunk from format version 1, run-length encoding its densities.
+fn from_v1(raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let repr = bincode::deserialize::<DensityRepr>(raw)?;
+    Ok(bincode::serialize(&RunsRepr::from(repr))?)
+}
+
+/// Upgrade a chunk from format version 2, which kept no entities.
+fn from_v2(raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let repr = bincode::deserialize::<RunsRepr>(raw)?;
+    Ok(bincode::serialize(&EntitiesRepr::from(repr))?)
+}
+
+/// Upgrade a chunk from format version 3, whose blocks had no states.
+fn from_v3(raw: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let repr = bincode::deserialize::<EntitiesRepr>(raw)?;
+    Ok(bincode::serialize(&ChunkRepr::from(repr))?)
+}
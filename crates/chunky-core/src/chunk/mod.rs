@@ -0,0 +1,924 @@
+mod access;
+pub mod delta;
+pub mod density;
+pub mod entity;
+pub mod fluid;
+pub mod generation;
+pub mod key;
+pub mod light;
+pub mod mesh;
+pub mod micro;
+pub mod migration;
+pub mod occupancy;
+pub mod registry;
+pub mod scheduled;
+pub mod section;
+mod serialize;
+pub mod state;
+pub mod surface;
+
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::Debug,
+    ops::{Add, Sub},
+    str::FromStr,
+};
+
+use access::AccessStamp;
+use density::DensityVolume;
+use entity::EntityRecord;
+use glam::{IVec3, Vec3};
+use itertools::iproduct;
+use key::ChunkKey;
+use light::{LightLevel, LightVolume};
+use mesh::Face;
+use micro::{MicroResolution, Microblocks};
+use occupancy::Occupancy;
+use registry::{BlockDefinition, Transparency};
+use scheduled::ScheduledChange;
+use section::{Section, SECTIONS};
+use serde::{Deserialize, Serialize};
+use state::BlockState;
+use surface::SurfaceMap;
+
+/// The size of a chunk along one axis, measured in blocks.
+pub const CHUNK_SIZE: u8 = 32;
+
+/// How steeply stored density rises through the surface. Generated densities further than the
+/// inverse of this from zero saturate to fully inside or outside the terrain.
+const DENSITY_SHARPNESS: f32 = 4.0;
+
+/// A position of a chunk in the world in chunk coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChunkPos {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+}
+
+impl From<(i64, i64, i64)> for ChunkPos {
+    fn from((x, y, z): (i64, i64, i64)) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl Add for ChunkPos {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for ChunkPos {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl ChunkPos {
+    pub const NORTH: Self = Self { x: 0, y: 0, z: -1 };
+    pub const EAST: Self = Self { x: 1, y: 0, z: 0 };
+    pub const SOUTH: Self = Self { x: 0, y: 0, z: 1 };
+    pub const WEST: Self = Self { x: -1, y: 0, z: 0 };
+    pub const UP: Self = Self { x: 0, y: 1, z: 0 };
+    pub const DOWN: Self = Self { x: 0, y: -1, z: 0 };
+
+    /// The offsets of the six chunks sharing a face with a chunk, in north, east, south, west, up,
+    /// down order.
+    pub const FACE_NEIGHBOURS: [Self; 6] = [
+        Self::NORTH,
+        Self::EAST,
+        Self::SOUTH,
+        Self::WEST,
+        Self::UP,
+        Self::DOWN,
+    ];
+
+    /// Create a new chunk position.
+    pub fn new(x: i64, y: i64, z: i64) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Create a new chunk position from a world position.
+    pub fn from_world(pos: Vec3) -> Self {
+        Self::from_world_block(pos.floor().as_ivec3())
+    }
+
+    /// Create a new chunk position from the world coordinates of a block within it.
+    pub fn from_world_block(pos: IVec3) -> Self {
+        Self {
+            x: (pos.x as i64).div_euclid(CHUNK_SIZE as i64),
+            y: (pos.y as i64).div_euclid(CHUNK_SIZE as i64),
+            z: (pos.z as i64).div_euclid(CHUNK_SIZE as i64),
+        }
+    }
+
+    pub fn to_world(self) -> Vec3 {
+        Vec3::new(
+            self.x as f32 * CHUNK_SIZE as f32,
+            self.y as f32 * CHUNK_SIZE as f32,
+            self.z as f32 * CHUNK_SIZE as f32,
+        )
+    }
+
+    /// Return an iterator over the neighboring chunk positions.
+    pub fn neighbors(&self, radius: i64) -> impl Iterator<Item = ChunkPos> + '_ {
+        iproduct!(-radius..=radius, -radius..=radius, -radius..=radius)
+            .filter(|&(dx, dy, dz)| dx != 0 || dy != 0 || dz != 0)
+            .map(move |(dx, dy, dz)| ChunkPos::new(self.x + dx, self.y + dy, self.z + dz))
+    }
+
+    /// Return the largest component
+    pub fn max(&self) -> i64 {
+        self.x.max(self.y.max(self.z))
+    }
+
+    /// Return the key used to look the chunk up in chunk maps.
+    pub fn key(self) -> ChunkKey {
+        self.into()
+    }
+
+    /// Return the key used to look the chunk up in chunk maps, or `None` if the position is too
+    /// far from the origin to have one. See [`ChunkKey::checked`].
+    pub fn checked_key(self) -> Option<ChunkKey> {
+        ChunkKey::checked(self)
+    }
+
+    /// Return the Chebyshev distance to another chunk position, i.e. the largest difference along
+    /// any axis.
+    pub fn distance(self, other: ChunkPos) -> i64 {
+        let diff = self - other;
+        diff.x.abs().max(diff.y.abs()).max(diff.z.abs())
+    }
+
+    /// Return the squared Euclidean distance to another chunk position, which orders chunks in
+    /// rings around a centre rather than in cubic shells.
+    pub fn distance_squared(self, other: ChunkPos) -> i64 {
+        let diff = self - other;
+        diff.x * diff.x + diff.y * diff.y + diff.z * diff.z
+    }
+}
+
+/// A position of a block within a chunk in block coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockPos {
+    pub x: u8,
+    pub y: u8,
+    pub z: u8,
+}
+
+impl PartialOrd for BlockPos {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BlockPos {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // enforce zxy order
+        self.z
+            .cmp(&other.z)
+            .then_with(|| self.x.cmp(&other.x))
+            .then_with(|| self.y.cmp(&other.y))
+    }
+}
+
+impl Add for BlockPos {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for BlockPos {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl BlockPos {
+    /// Create a new block position.
+    pub fn new(x: u8, y: u8, z: u8) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Return an iterator over all block positions in a chunk.
+    pub fn all() -> impl Iterator<Item = BlockPos> {
+        iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE, 0..CHUNK_SIZE).map(|pos| pos.into())
+    }
+
+    /// Create a new block position from a world position, relative to the chunk containing it.
+    pub fn from_world(pos: Vec3) -> Self {
+        Self::from_world_block(pos.floor().as_ivec3())
+    }
+
+    /// Create a new block position from the world coordinates of a block, relative to the chunk
+    /// containing it.
+    pub fn from_world_block(pos: IVec3) -> Self {
+        Self {
+            x: pos.x.rem_euclid(CHUNK_SIZE as i32) as u8,
+            y: pos.y.rem_euclid(CHUNK_SIZE as i32) as u8,
+            z: pos.z.rem_euclid(CHUNK_SIZE as i32) as u8,
+        }
+    }
+
+    pub fn world_pos(&self, chunk_pos: ChunkPos) -> Vec3 {
+        Vec3::new(
+            (chunk_pos.x * CHUNK_SIZE as i64 + self.x as i64) as f32,
+            (chunk_pos.y * CHUNK_SIZE as i64 + self.y as i64) as f32,
+            (chunk_pos.z * CHUNK_SIZE as i64 + self.z as i64) as f32,
+        )
+    }
+}
+
+impl From<(u8, u8, u8)> for BlockPos {
+    fn from((x, y, z): (u8, u8, u8)) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl From<BlockPos> for (u8, u8, u8) {
+    fn from(pos: BlockPos) -> (u8, u8, u8) {
+        (pos.x, pos.y, pos.z)
+    }
+}
+
+impl From<BlockPos> for IVec3 {
+    fn from(pos: BlockPos) -> IVec3 {
+        IVec3::new(pos.x as i32, pos.y as i32, pos.z as i32)
+    }
+}
+
+/// The data of a chunk.
+#[derive(Clone)]
+pub struct Chunk {
+    /// The position of the chunk in the world.
+    pub position: ChunkPos,
+    /// The block data of the chunk, split into vertical sections.
+    sections: [Section; SECTIONS],
+    /// Extra data for the few blocks that need it.
+    block_data: BTreeMap<BlockPos, BlockData>,
+    /// A coarse summary of where the chunk's solid blocks are.
+    occupancy: Occupancy,
+    /// The light level of every block in the chunk.
+    light: LightVolume,
+    /// The height of the topmost opaque block in each column.
+    surface: SurfaceMap,
+    /// The density of every block for smooth meshing, or `None` if it only follows the blocks.
+    density: Option<DensityVolume>,
+    /// The block changes queued in the chunk, ordered by the tick they happen at.
+    scheduled: Vec<ScheduledChange>,
+    /// The entities standing in the chunk, as of when they were last recorded.
+    entities: Vec<EntityRecord>,
+    /// A counter incremented every time the chunk is modified.
+    revision: u64,
+    /// A counter incremented every time anything saved with the chunk changes, including changes
+    /// that leave its mesh as it is.
+    changes: u64,
+    /// The value of `changes` the chunk was last saved at.
+    saved: u64,
+    /// The frame the chunk was last accessed on, for evicting chunks over the memory budget.
+    last_access: AccessStamp,
+}
+
+impl Debug for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Chunk")
+            .field("position", &self.position)
+            .field(
+                "blocks",
+                &self.sections.iter().map(Section::len).sum::<usize>(),
+            )
+            .field("block_data", &self.block_data.len())
+            .field("revision", &self.revision)
+            .field("dirty", &self.is_dirty())
+            .finish()
+    }
+}
+
+impl Chunk {
+    /// Create an empty chunk.
+    pub fn empty(position: ChunkPos) -> Self {
+        Self {
+            position,
+            sections: Default::default(),
+            block_data: BTreeMap::new(),
+            occupancy: Occupancy::default(),
+            light: LightVolume::filled(LightLevel::SKY),
+            surface: SurfaceMap::default(),
+            density: None,
+            scheduled: Vec::new(),
+            entities: Vec::new(),
+            revision: 0,
+            changes: 0,
+            saved: 0,
+            last_access: AccessStamp::default(),
+        }
+    }
+
+    /// Create a chunk filled with a block.
+    pub fn filled(mut self, block: BlockType) -> Self {
+        self.fill(block);
+        self
+    }
+
+    /// Return an estimate of the memory used by the chunk, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        let blocks = self.sections.iter().map(Section::len).sum::<usize>();
+        let states = self.states().count();
+        size_of::<Self>()
+            + blocks * (size_of::<BlockPos>() + size_of::<BlockType>())
+            + states * (size_of::<BlockPos>() + size_of::<BlockState>())
+            + self.block_data.len() * (size_of::<BlockPos>() + size_of::<BlockData>())
+            + (CHUNK_SIZE as usize).pow(3) * size_of::<LightLevel>()
+            + self
+                .density
+                .as_ref()
+                .map_or(0, |_| (CHUNK_SIZE as usize).pow(3))
+            + self
+                .entities
+                .iter()
+                .map(|entity| size_of::<EntityRecord>() + entity.data.len())
+                .sum::<usize>()
+    }
+
+    /// Get the block at the given position.
+    pub fn block_at<I: Into<BlockPos>>(&self, pos: I) -> &BlockType {
+        let pos = pos.into();
+        self.sections[Section::index_of(pos)].block_at(pos)
+    }
+
+    /// Get the state of the block at the given position.
+    pub fn state_at<I: Into<BlockPos>>(&self, pos: I) -> BlockState {
+        let pos = pos.into();
+        self.sections[Section::index_of(pos)].state_at(pos)
+    }
+
+    /// Set the state of the block at the given position, such as the direction it faces. Empty
+    /// space has no state, so this does nothing if the block is empty.
+    pub fn set_block_state<I: Into<BlockPos>>(&mut self, pos: I, state: BlockState) {
+        let pos = pos.into();
+        if *self.block_at(pos) == BlockType::EMPTY || self.state_at(pos) == state {
+            return;
+        }
+        self.touch();
+        self.sections[Section::index_of(pos)].set_state(pos, state, self.revision);
+    }
+
+    /// Return an iterator over the blocks whose state is not the default, ordered by their section
+    /// and then by their position.
+    pub fn states(&self) -> impl Iterator<Item = (BlockPos, BlockState)> + '_ {
+        self.sections.iter().flat_map(Section::states)
+    }
+
+    /// Get the density at the given position, from -1 outside the terrain to 1 inside it. Chunks
+    /// without a density field are fully inside their opaque blocks and outside everything else.
+    pub fn density_at<I: Into<BlockPos>>(&self, pos: I) -> f32 {
+        let pos = pos.into();
+        match &self.density {
+            Some(density) => density.get(pos),
+            None if self.block_at(pos).is_opaque() => 1.0,
+            None => -1.0,
+        }
+    }
+
+    /// Return the density field of the chunk, if it has one.
+    pub fn density(&self) -> Option<&DensityVolume> {
+        self.density.as_ref()
+    }
+
+    /// Discard the density field of the chunk, so its density only follows the blocks.
+    pub fn clear_density(&mut self) {
+        self.density = None;
+    }
+
+    /// Add to the density of the given blocks, turning them solid or empty as their density
+    /// crosses the surface. Chunks without a density field get one that follows their blocks.
+    pub fn sculpt<I: IntoIterator<Item = (BlockPos, f32)>>(&mut self, edits: I) {
+        let mut edits = edits.into_iter().peekable();
+        if edits.peek().is_none() {
+            return;
+        }
+        self.touch();
+        // take the field out, so placing blocks does not snap it to the blocks
+        let mut density = self.density.take().unwrap_or_else(|| {
+            let mut density = DensityVolume::filled(-1.0);
+            for (pos, block) in self.blocks() {
+                if block.is_opaque() {
+                    density.set(pos, 1.0);
+                }
+            }
+            density
+        });
+        for (pos, amount) in edits {
+            let value = density.get(pos) + amount;
+            let inside = value > 0.0;
+            if inside != self.block_at(pos).is_opaque() {
+                let block = match inside {
+                    true => BlockType::STONE,
+                    false => BlockType::EMPTY,
+                };
+                self.put_block(pos, block);
+            }
+            density.set(pos, value);
+        }
+        self.density = Some(density);
+    }
+
+    /// Get the light level at the given position.
+    pub fn light_at<I: Into<BlockPos>>(&self, pos: I) -> LightLevel {
+        self.light.get(pos.into())
+    }
+
+    /// Set the light level at the given position. Light is derived from the blocks, so this does
+    /// not change the revision of the chunk.
+    pub fn set_light<I: Into<BlockPos>>(&mut self, pos: I, level: LightLevel) {
+        self.light.set(pos.into(), level);
+    }
+
+    /// Return the light levels of the chunk.
+    pub fn light(&self) -> &LightVolume {
+        &self.light
+    }
+
+    /// Return the vertical sections of the chunk, from bottom to top.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Return the indices of the sections modified after the given revision of the chunk.
+    pub fn sections_modified_since(&self, revision: u64) -> impl Iterator<Item = usize> + '_ {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(move |(_, section)| section.revision() > revision)
+            .map(|(index, _)| index)
+    }
+
+    /// Return the revision of the chunk. This is incremented every time the chunk is modified, so
+    /// comparing it against a previously seen revision tells whether the chunk has changed since.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Check if the chunk has been modified since it was last marked clean.
+    pub fn is_dirty(&self) -> bool {
+        self.changes != self.saved
+    }
+
+    /// Mark the chunk as clean, e.g. after it has been saved.
+    pub fn mark_clean(&mut self) {
+        self.saved = self.changes;
+    }
+
+    /// Return a counter incremented every time anything saved with the chunk changes. Unlike the
+    /// revision, this also counts changes that leave the mesh as it is, such as to its entities.
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    /// Mark the chunk as saved as it was when [`Chunk::changes`] returned the given value, e.g.
+    /// once a write of a copy taken then has finished. The chunk stays dirty if it has been
+    /// modified since.
+    pub fn mark_saved(&mut self, changes: u64) {
+        self.saved = self.saved.max(changes);
+    }
+
+    /// Return the coarse summary of where the chunk's solid blocks are, for skipping empty regions
+    /// in collision and physics queries.
+    pub fn occupancy(&self) -> &Occupancy {
+        &self.occupancy
+    }
+
+    /// Return the height of the topmost opaque block in each column.
+    pub fn surface(&self) -> &SurfaceMap {
+        &self.surface
+    }
+
+    /// Return the height of the topmost opaque block in the given column, if there is one.
+    pub fn surface_at(&self, x: u8, z: u8) -> Option<u8> {
+        self.surface.get(x, z)
+    }
+
+    /// Check if the given position is above every opaque block in its column.
+    pub fn is_above_surface<I: Into<BlockPos>>(&self, pos: I) -> bool {
+        self.surface.is_above(pos.into())
+    }
+
+    /// Mark the chunk as replacing a loaded chunk at the given revision. Its revision follows on
+    /// from the old one, so meshes of the old chunk are not mistaken for newer ones, and it is
+    /// dirty so any saved copy is overwritten.
+    pub fn supersede(&mut self, revision: u64) {
+        self.revision = self.revision.max(revision) + 1;
+        self.changes += 1;
+    }
+
+    /// Record an access to the chunk on the given frame, for evicting the least recently used
+    /// chunks.
+    pub fn record_access(&self, frame: u64) {
+        self.last_access.touch(frame);
+    }
+
+    /// Return the frame the chunk was last accessed on.
+    pub fn last_access(&self) -> u64 {
+        self.last_access.get()
+    }
+
+    /// Record a modification to the chunk.
+    fn touch(&mut self) {
+        self.revision += 1;
+        self.changes += 1;
+    }
+
+    /// Get the extra data of the block at the given position, if it has any.
+    pub fn block_data_at<I: Into<BlockPos>>(&self, pos: I) -> Option<&BlockData> {
+        self.block_data.get(&pos.into())
+    }
+
+    /// Get a mutable reference to the extra data of the block at the given position, if it has any.
+    /// The chunk is conservatively treated as modified if the block has data.
+    pub fn block_data_at_mut<I: Into<BlockPos>>(&mut self, pos: I) -> Option<&mut BlockData> {
+        let pos = pos.into();
+        if self.block_data.contains_key(&pos) {
+            self.touch();
+        }
+        self.block_data.get_mut(&pos)
+    }
+
+    /// Attach extra data to the block at the given position, replacing any existing data.
+    pub fn set_block_data<I: Into<BlockPos>>(&mut self, pos: I, data: BlockData) {
+        self.block_data.insert(pos.into(), data);
+        self.touch();
+    }
+
+    /// Remove the extra data of the block at the given position, returning it if present.
+    pub fn remove_block_data<I: Into<BlockPos>>(&mut self, pos: I) -> Option<BlockData> {
+        let data = self.block_data.remove(&pos.into());
+        if data.is_some() {
+            self.touch();
+        }
+        data
+    }
+
+    /// Get the shape of the block at the given position, if it has been carved into microblocks.
+    pub fn microblocks_at<I: Into<BlockPos>>(&self, pos: I) -> Option<&Microblocks> {
+        self.block_data_at(pos)?.microblocks.as_ref()
+    }
+
+    /// Remove the sub-voxel of the block at the given position nearest to a point within it,
+    /// carving the block into microblocks of the given resolution if it has not been carved yet.
+    /// The block is removed once its last sub-voxel is. Returns whether anything was removed.
+    pub fn carve<I: Into<BlockPos>>(
+        &mut self,
+        pos: I,
+        point: Vec3,
+        resolution: MicroResolution,
+    ) -> bool {
+        let pos = pos.into();
+        if !self.block_at(pos).is_carvable() {
+            return false;
+        }
+        let mut microblocks = self
+            .microblocks_at(pos)
+            .copied()
+            .unwrap_or_else(|| Microblocks::full(resolution));
+        let Some(cell) = microblocks.nearest(point) else {
+            return false;
+        };
+        microblocks.remove(cell);
+        if microblocks.is_empty() {
+            self.set_block(pos, BlockType::EMPTY);
+        } else {
+            let mut data = self.block_data_at(pos).cloned().unwrap_or_default();
+            data.microblocks = Some(microblocks);
+            self.set_block_data(pos, data);
+        }
+        true
+    }
+
+    /// Queue a block change in the chunk.
+    pub fn schedule(&mut self, change: ScheduledChange) {
+        let index = self
+            .scheduled
+            .partition_point(|queued| queued.tick <= change.tick);
+        self.scheduled.insert(index, change);
+        self.touch();
+    }
+
+    /// Return the block changes queued in the chunk, ordered by the tick they happen at.
+    pub fn scheduled(&self) -> &[ScheduledChange] {
+        &self.scheduled
+    }
+
+    /// Return the tick of the next queued block change, if there is one.
+    pub fn next_scheduled_tick(&self) -> Option<u64> {
+        self.scheduled.first().map(|change| change.tick)
+    }
+
+    /// Remove and return the queued block changes due by the given tick.
+    pub fn take_scheduled(&mut self, tick: u64) -> Vec<ScheduledChange> {
+        let due = self.scheduled.partition_point(|change| change.tick <= tick);
+        self.scheduled.drain(..due).collect()
+    }
+
+    /// Return the entities recorded as standing in the chunk.
+    pub fn entities(&self) -> &[EntityRecord] {
+        &self.entities
+    }
+
+    /// Record the entities standing in the chunk, marking it dirty if they changed. The mesh is
+    /// unaffected, so the revision is left as it is.
+    pub fn set_entities(&mut self, entities: Vec<EntityRecord>) {
+        if entities != self.entities {
+            self.entities = entities;
+            self.changes += 1;
+        }
+    }
+
+    /// Return an iterator over all blocks with extra data, ordered by their position.
+    pub fn block_data(&self) -> impl Iterator<Item = (&BlockPos, &BlockData)> {
+        self.block_data.iter()
+    }
+
+    /// Return an iterator over all non-empty blocks in the chunk, ordered by their section and then
+    /// by their position. Empty sections are skipped entirely.
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockPos, BlockType)> + '_ {
+        self.sections.iter().flat_map(Section::blocks)
+    }
+
+    /// Return the distinct block types in the chunk, including empty space if any block is empty.
+    pub fn palette(&self) -> Vec<BlockType> {
+        let mut palette = Vec::new();
+        let len = self.sections.iter().map(Section::len).sum::<usize>();
+        if len < CHUNK_SIZE as usize * CHUNK_SIZE as usize * CHUNK_SIZE as usize {
+            palette.push(BlockType::EMPTY);
+        }
+        for (_, block) in self.blocks() {
+            if !palette.contains(&block) {
+                palette.push(block);
+            }
+        }
+        palette
+    }
+
+    /// Set the block at the given position. Any extra data and state belonging to the previous
+    /// block is discarded if the block type changes.
+    pub fn set_block<Pos: Into<BlockPos>>(&mut self, pos: Pos, block: BlockType) {
+        self.touch();
+        self.put_block(pos.into(), block);
+    }
+
+    /// Set many blocks at once, recording a single modification to the chunk.
+    pub fn set_blocks<I: IntoIterator<Item = (BlockPos, BlockType)>>(&mut self, blocks: I) {
+        let mut blocks = blocks.into_iter().peekable();
+        if blocks.peek().is_none() {
+            return;
+        }
+        self.touch();
+        for (pos, block) in blocks {
+            self.put_block(pos, block);
+        }
+    }
+
+    /// Set the block at the given position as part of the current revision.
+    fn put_block(&mut self, pos: BlockPos, block: BlockType) {
+        let previous = self.sections[Section::index_of(pos)].set_block(pos, block, self.revision);
+        if previous != block {
+            self.block_data.remove(&pos);
+        }
+        match (previous.is_solid(), block.is_solid()) {
+            (false, true) => self.occupancy.add(pos),
+            (true, false) => self.occupancy.remove(pos),
+            _ => {}
+        }
+        if let Some(density) = &mut self.density {
+            // keep the sign of the density in step with the block, so edits show up in smooth
+            // meshes
+            if (density.get(pos) > 0.0) != block.is_opaque() {
+                density.set(pos, if block.is_opaque() { 1.0 } else { -1.0 });
+            }
+        }
+        if block.is_opaque() {
+            self.surface.raise(pos);
+        } else if self.surface.get(pos.x, pos.z) == Some(pos.y) {
+            // the top of the column was removed, so find the next block down
+            let height = (0..pos.y)
+                .rev()
+                .find(|&y| self.block_at((pos.x, y, pos.z)).is_opaque());
+            self.surface.set(pos.x, pos.z, height);
+        }
+    }
+
+    /// Fill the chunk with a block.
+    fn fill(&mut self, block: BlockType) {
+        self.touch();
+        self.block_data.clear();
+        self.density = None;
+        for section in &mut self.sections {
+            section.clear(self.revision);
+        }
+        if block != BlockType::EMPTY {
+            for pos in BlockPos::all() {
+                self.sections[Section::index_of(pos)].set_block(pos, block, self.revision);
+            }
+        }
+        self.occupancy = match block.is_solid() {
+            true => Occupancy::full(),
+            false => Occupancy::default(),
+        };
+        self.surface = SurfaceMap::filled(block.is_opaque().then_some(CHUNK_SIZE - 1));
+    }
+}
+
+/// The type of a block in the world: its number in the
+/// [`BlockRegistry`](registry::BlockRegistry), which defines its properties. Blocks are saved as
+/// their number in binary formats, and by name in readable ones.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockType(u16);
+
+impl BlockType {
+    pub const EMPTY: Self = Self(0);
+    pub const STONE: Self = Self(1);
+    pub const WATER: Self = Self(2);
+    pub const GRASS: Self = Self(3);
+    pub const DIRT: Self = Self(4);
+    pub const SAND: Self = Self(5);
+    pub const SNOW: Self = Self(6);
+    pub const WOOD: Self = Self(7);
+    pub const LEAVES: Self = Self(8);
+    pub const CACTUS: Self = Self(9);
+    pub const GRAVEL: Self = Self(10);
+    pub const GLASS: Self = Self(11);
+    pub const LAMP: Self = Self(12);
+
+    /// Every built-in block type, in the order they are numbered.
+    pub const ALL: [Self; 13] = [
+        Self::EMPTY,
+        Self::STONE,
+        Self::WATER,
+        Self::GRASS,
+        Self::DIRT,
+        Self::SAND,
+        Self::SNOW,
+        Self::WOOD,
+        Self::LEAVES,
+        Self::CACTUS,
+        Self::GRAVEL,
+        Self::GLASS,
+        Self::LAMP,
+    ];
+
+    /// Return the block with the given number, whether or not it is registered.
+    pub const fn from_id(id: u16) -> Self {
+        Self(id)
+    }
+
+    /// Return the number of this block.
+    pub fn id(self) -> u16 {
+        self.0
+    }
+
+    /// Return the definition of this block in the installed registry.
+    pub fn definition(&self) -> &'static BlockDefinition {
+        registry::registry().definition(*self)
+    }
+
+    /// Return the name of this block, such as `stone`.
+    pub fn name(&self) -> &'static str {
+        &self.definition().name
+    }
+
+    /// Return how much of what lies behind this block shows through it.
+    pub fn transparency(&self) -> Transparency {
+        self.definition().transparency
+    }
+
+    /// Check if this block fills its space, i.e. whether it is opaque or cut out rather than
+    /// translucent. Blocks that fill their space make up the terrain's density field.
+    pub fn is_opaque(&self) -> bool {
+        self.transparency() != Transparency::Translucent
+    }
+
+    /// Check if this block is solid, i.e. whether it blocks movement.
+    pub fn is_solid(&self) -> bool {
+        self.definition().solid
+    }
+
+    /// Check if this block can be carved into microblocks.
+    pub fn is_carvable(&self) -> bool {
+        self.definition().carvable
+    }
+
+    /// Return how many levels light loses passing through this block, on top of the one it loses
+    /// per block travelled. Leaves dapple light and water dims it, glass lets it through, and other
+    /// opaque blocks stop it.
+    pub fn light_attenuation(&self) -> u8 {
+        self.definition().light_attenuation
+    }
+
+    /// Return the level of light this block gives off.
+    pub fn light_emission(&self) -> u8 {
+        self.definition().emission
+    }
+
+    /// Return how long this block takes to break, in seconds.
+    pub fn hardness(&self) -> f32 {
+        self.definition().hardness
+    }
+
+    /// Check if this block belongs to the given category, such as `#stone`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.definition().has_tag(tag)
+    }
+}
+
+impl Debug for BlockType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match registry::registry().get(*self) {
+            Some(definition) => f.write_str(&definition.name),
+            None => write!(f, "BlockType({})", self.0),
+        }
+    }
+}
+
+impl FromStr for BlockType {
+    type Err = anyhow::Error;
+
+    /// Parse a block from its name in lowercase, such as `stone`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        registry::registry()
+            .find(s)
+            .ok_or_else(|| anyhow::anyhow!("unknown block {:?}", s))
+    }
+}
+
+impl Serialize for BlockType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(self.name())
+        } else {
+            // the same as the variant index blocks were saved as when they were an enum
+            serializer.serialize_u32(self.0 as u32)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BlockVisitor;
+
+        impl serde::de::Visitor<'_> for BlockVisitor {
+            type Value = BlockType;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a block name or number")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<BlockType, E> {
+                name.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, id: u64) -> Result<BlockType, E> {
+                u16::try_from(id)
+                    .map(BlockType)
+                    .map_err(|_| E::custom(format!("invalid block number {}", id)))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BlockVisitor)
+        } else {
+            deserializer.deserialize_u32(BlockVisitor)
+        }
+    }
+}
+
+/// A stack of items held in a block's inventory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    /// The block this stack contains.
+    pub block: BlockType,
+    /// The number of items in the stack.
+    pub count: u32,
+}
+
+/// Extra data attached to a block, for blocks that need more than their type - such as chests,
+/// signs, or machines.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockData {
+    /// The direction the block is facing, if it can be oriented.
+    pub orientation: Option<Face>,
+    /// The items stored in the block.
+    pub inventory: Vec<ItemStack>,
+    /// Arbitrary block-specific state.
+    pub state: BTreeMap<String, String>,
+    /// The sub-voxels left in the block, if it has been carved.
+    pub microblocks: Option<Microblocks>,
+}
@@ -0,0 +1,107 @@
+use itertools::iproduct;
+
+use super::{BlockPos, CHUNK_SIZE};
+
+/// The number of occupancy cells along one axis of a chunk.
+pub const OCCUPANCY_CELLS: u8 = 4;
+
+/// The size of an occupancy cell along one axis, measured in blocks.
+pub const OCCUPANCY_CELL_SIZE: u8 = CHUNK_SIZE / OCCUPANCY_CELLS;
+
+/// The total number of occupancy cells in a chunk.
+const CELL_COUNT: usize = (OCCUPANCY_CELLS as usize).pow(3);
+
+/// A coarse summary of which regions of a chunk contain solid blocks. The chunk is split into
+/// 4x4x4 cells, and each cell tracks how many solid blocks it contains, so queries can skip empty
+/// regions before looking at individual blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occupancy {
+    /// The number of solid blocks in each cell.
+    counts: [u16; CELL_COUNT],
+}
+
+impl Default for Occupancy {
+    fn default() -> Self {
+        Self {
+            counts: [0; CELL_COUNT],
+        }
+    }
+}
+
+impl Occupancy {
+    /// Create a summary of a chunk completely filled with solid blocks.
+    pub fn full() -> Self {
+        let volume =
+            OCCUPANCY_CELL_SIZE as u16 * OCCUPANCY_CELL_SIZE as u16 * OCCUPANCY_CELL_SIZE as u16;
+        Self {
+            counts: [volume; CELL_COUNT],
+        }
+    }
+
+    /// Return the index of the cell containing the given block.
+    fn index(pos: BlockPos) -> usize {
+        Self::cell_index(
+            pos.x / OCCUPANCY_CELL_SIZE,
+            pos.y / OCCUPANCY_CELL_SIZE,
+            pos.z / OCCUPANCY_CELL_SIZE,
+        )
+    }
+
+    /// Return the index of the given cell.
+    fn cell_index(x: u8, y: u8, z: u8) -> usize {
+        let cells = OCCUPANCY_CELLS as usize;
+        (z as usize * cells + x as usize) * cells + y as usize
+    }
+
+    /// Record that a solid block was added at the given position.
+    pub fn add(&mut self, pos: BlockPos) {
+        self.counts[Self::index(pos)] += 1;
+    }
+
+    /// Record that a solid block was removed from the given position.
+    pub fn remove(&mut self, pos: BlockPos) {
+        self.counts[Self::index(pos)] -= 1;
+    }
+
+    /// Return a bitmask with one bit set for every occupied cell, indexed in zxy order.
+    pub fn mask(&self) -> u64 {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .fold(0, |mask, (index, _)| mask | 1 << index)
+    }
+
+    /// Check if the chunk contains no solid blocks at all.
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&count| count == 0)
+    }
+
+    /// Check if the given cell contains any solid blocks.
+    pub fn is_cell_occupied(&self, x: u8, y: u8, z: u8) -> bool {
+        self.counts[Self::cell_index(x, y, z)] > 0
+    }
+
+    /// Check if the block at the given position may be solid. A `false` result guarantees that it
+    /// is not.
+    pub fn may_contain(&self, pos: BlockPos) -> bool {
+        self.counts[Self::index(pos)] > 0
+    }
+
+    /// Check if any cell overlapping the box between the two block positions (inclusive) contains
+    /// solid blocks.
+    pub fn any_in(&self, min: BlockPos, max: BlockPos) -> bool {
+        let (min_x, min_y, min_z) = (
+            min.x / OCCUPANCY_CELL_SIZE,
+            min.y / OCCUPANCY_CELL_SIZE,
+            min.z / OCCUPANCY_CELL_SIZE,
+        );
+        let (max_x, max_y, max_z) = (
+            max.x / OCCUPANCY_CELL_SIZE,
+            max.y / OCCUPANCY_CELL_SIZE,
+            max.z / OCCUPANCY_CELL_SIZE,
+        );
+        iproduct!(min_x..=max_x, min_y..=max_y, min_z..=max_z)
+            .any(|(x, y, z)| self.is_cell_occupied(x, y, z))
+    }
+}
@@ -0,0 +1,368 @@
+use std::sync::{LazyLock, OnceLock};
+
+use anyhow::bail;
+
+use super::{light::MAX_LIGHT, mesh::Face, BlockType};
+
+/// The registry blocks are looked up in once one has been installed.
+static INSTALLED: OnceLock<BlockRegistry> = OnceLock::new();
+
+/// The registry of built-in blocks, used until one is installed.
+static BUILT_IN: LazyLock<BlockRegistry> = LazyLock::new(BlockRegistry::default);
+
+/// The definition of blocks whose number is not registered, such as those saved by a plugin that
+/// is not loaded. They are drawn in a loud magenta so they stand out.
+static MISSING: LazyLock<BlockDefinition> =
+    LazyLock::new(|| BlockDefinition::new("missing", [1.0, 0.0, 1.0]));
+
+/// How much of what lies behind a block shows through it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transparency {
+    /// Nothing shows through the block, so the faces of blocks against it are hidden.
+    Opaque,
+    /// The block is drawn as a cube with holes in it, such as leaves, so the faces of other blocks
+    /// against it still show. It fills its space like an opaque block.
+    Cutout,
+    /// The block is see-through, such as water or glass, and only drawn where it borders empty
+    /// space.
+    Translucent,
+}
+
+/// The sounds a block makes, as paths of audio files relative to the assets directory. Blocks
+/// without a sound for something make none.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BlockSounds {
+    /// The sound played when the block is broken.
+    pub break_sound: Option<String>,
+    /// The sound played when the block is placed.
+    pub place_sound: Option<String>,
+    /// The sound of a footstep on the block.
+    pub step_sound: Option<String>,
+}
+
+impl BlockSounds {
+    /// The sounds shared by blocks of the given material, such as `stone`, found at
+    /// `sounds/<material>/break.ogg`, and likewise for `place` and `step`.
+    pub fn material(material: &str) -> Self {
+        let sound = |name| Some(format!("sounds/{}/{}.ogg", material, name));
+        Self {
+            break_sound: sound("break"),
+            place_sound: sound("place"),
+            step_sound: sound("step"),
+        }
+    }
+
+    /// Return an iterator over the paths of the sounds the block makes.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        [&self.break_sound, &self.place_sound, &self.step_sound]
+            .into_iter()
+            .filter_map(Option::as_deref)
+    }
+}
+
+/// The properties of a kind of block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockDefinition {
+    /// The name the block is known by, in lowercase, such as `stone`.
+    pub name: String,
+    /// How much of what lies behind the block shows through it.
+    pub transparency: Transparency,
+    /// Whether the block blocks movement.
+    pub solid: bool,
+    /// Whether the block can be carved into microblocks.
+    pub carvable: bool,
+    /// Whether the block falls when there is nothing solid beneath it, such as sand.
+    pub falls: bool,
+    /// How many levels light loses passing through the block, on top of the one it loses per block
+    /// travelled.
+    pub light_attenuation: u8,
+    /// The level of light the block gives off, up to [`MAX_LIGHT`].
+    pub emission: u8,
+    /// How long the block takes to break, in seconds. Blocks that cannot be broken, such as empty
+    /// space, take forever.
+    pub hardness: f32,
+    /// The path of the block's texture, for renderers that draw textured blocks.
+    pub texture: Option<String>,
+    /// The sounds the block makes when it is broken, placed, and walked on.
+    pub sounds: BlockSounds,
+    /// The categories the block belongs to, such as `stone` or `replaceable`, which world
+    /// generation and gameplay rules match blocks by. Written `#stone` in text, without the `#`
+    /// here.
+    pub tags: Vec<String>,
+    /// The colour the block is drawn in, as linear RGBA. Translucent blocks with no opacity are not
+    /// drawn at all.
+    pub color: [f32; 4],
+    /// The colour of the block's top and bottom, if they differ from its sides, such as the rings
+    /// at the ends of a log. They turn with the block's state.
+    pub top_color: Option<[f32; 4]>,
+}
+
+impl BlockDefinition {
+    /// Define an opaque, solid block with the given name, drawn in the given colour.
+    pub fn new(name: impl Into<String>, [r, g, b]: [f32; 3]) -> Self {
+        Self {
+            name: name.into(),
+            transparency: Transparency::Opaque,
+            solid: true,
+            carvable: false,
+            falls: false,
+            light_attenuation: MAX_LIGHT,
+            emission: 0,
+            hardness: 1.0,
+            texture: None,
+            sounds: BlockSounds::default(),
+            tags: Vec::new(),
+            color: [r, g, b, 1.0],
+            top_color: None,
+        }
+    }
+
+    /// Make the block translucent, drawn with the given opacity and dimming light passing through
+    /// it by the given number of levels.
+    pub fn see_through(mut self, opacity: f32, light_attenuation: u8) -> Self {
+        self.transparency = Transparency::Translucent;
+        self.light_attenuation = light_attenuation;
+        self.color[3] = opacity;
+        self
+    }
+
+    /// Cut holes in the block, dimming light passing through it by the given number of levels.
+    pub fn cutout(mut self, light_attenuation: u8) -> Self {
+        self.transparency = Transparency::Cutout;
+        self.light_attenuation = light_attenuation;
+        self
+    }
+
+    /// Let things move through the block.
+    pub fn passable(mut self) -> Self {
+        self.solid = false;
+        self
+    }
+
+    /// Make the block give off the given level of light.
+    pub fn with_emission(mut self, emission: u8) -> Self {
+        self.emission = emission.min(MAX_LIGHT);
+        self
+    }
+
+    /// Set how long the block takes to break, in seconds.
+    pub fn with_hardness(mut self, hardness: f32) -> Self {
+        self.hardness = hardness;
+        self
+    }
+
+    /// Let the block be carved into microblocks.
+    pub fn carvable(mut self) -> Self {
+        self.carvable = true;
+        self
+    }
+
+    /// Make the block fall when there is nothing solid beneath it.
+    pub fn falling(mut self) -> Self {
+        self.falls = true;
+        self
+    }
+
+    /// Draw the block's top and bottom in a different colour to its sides.
+    pub fn with_top_color(mut self, [r, g, b]: [f32; 3]) -> Self {
+        self.top_color = Some([r, g, b, self.color[3]]);
+        self
+    }
+
+    /// Return the colour the given face of the block is drawn in, in the block's own frame.
+    pub fn face_color(&self, face: Face) -> [f32; 4] {
+        match (face, self.top_color) {
+            (Face::Up | Face::Down, Some(color)) => color,
+            _ => self.color,
+        }
+    }
+
+    /// Add the block to the given categories, such as `stone`, with or without a leading `#`.
+    pub fn with_tags<'a>(mut self, tags: impl IntoIterator<Item = &'a str>) -> Self {
+        self.tags
+            .extend(tags.into_iter().map(|tag| normalize_tag(tag).to_owned()));
+        self
+    }
+
+    /// Check if the block belongs to the given category, with or without a leading `#`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        self.tags.iter().any(|own| own.eq_ignore_ascii_case(tag))
+    }
+
+    /// Set the sounds the block makes.
+    pub fn with_sounds(mut self, sounds: BlockSounds) -> Self {
+        self.sounds = sounds;
+        self
+    }
+
+    /// Set the path of the block's texture.
+    pub fn with_texture(mut self, texture: impl Into<String>) -> Self {
+        self.texture = Some(texture.into());
+        self
+    }
+}
+
+/// Return the given tag without its leading `#`, if it has one.
+fn normalize_tag(tag: &str) -> &str {
+    let tag = tag.trim();
+    tag.strip_prefix('#').unwrap_or(tag)
+}
+
+/// The blocks of the world, numbered in the order they are registered. The built-in blocks come
+/// first, in the order of [`BlockType::ALL`], and blocks registered after them must be registered
+/// in the same order every time a world is loaded, since chunks are saved with block numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockRegistry {
+    definitions: Vec<BlockDefinition>,
+}
+
+impl Default for BlockRegistry {
+    /// A registry of the built-in blocks.
+    fn default() -> Self {
+        let definitions = vec![
+            BlockDefinition::new("empty", [0.0; 3])
+                .see_through(0.0, 0)
+                .passable()
+                .with_hardness(f32::INFINITY)
+                .with_tags(["transparent", "replaceable"]),
+            BlockDefinition::new("stone", [0.5, 0.5, 0.5])
+                .carvable()
+                .with_hardness(1.5)
+                .with_sounds(BlockSounds::material("stone"))
+                .with_tags(["stone"]),
+            BlockDefinition::new("water", [0.2, 0.4, 0.8])
+                .see_through(0.6, 2)
+                .passable()
+                .with_hardness(f32::INFINITY)
+                .with_tags(["transparent", "replaceable", "liquid"]),
+            BlockDefinition::new("grass", [0.3, 0.6, 0.2])
+                .with_hardness(0.6)
+                .with_sounds(BlockSounds::material("grass"))
+                .with_tags(["soil"]),
+            BlockDefinition::new("dirt", [0.45, 0.3, 0.15])
+                .with_hardness(0.5)
+                .with_sounds(BlockSounds::material("dirt"))
+                .with_tags(["soil"]),
+            BlockDefinition::new("sand", [0.85, 0.8, 0.55])
+                .falling()
+                .with_hardness(0.5)
+                .with_sounds(BlockSounds::material("sand"))
+                .with_tags(["soil"]),
+            BlockDefinition::new("snow", [0.95, 0.95, 0.98])
+                .with_hardness(0.2)
+                .with_sounds(BlockSounds::material("snow")),
+            BlockDefinition::new("wood", [0.4, 0.25, 0.1])
+                .with_top_color([0.6, 0.45, 0.25])
+                .with_hardness(2.0)
+                .with_sounds(BlockSounds::material("wood"))
+                .with_tags(["log", "stem"]),
+            // leaves dapple the light passing through them
+            BlockDefinition::new("leaves", [0.15, 0.45, 0.1])
+                .cutout(1)
+                .with_hardness(0.2)
+                .with_sounds(BlockSounds::material("grass"))
+                .with_tags(["leaves", "transparent"]),
+            BlockDefinition::new("cactus", [0.2, 0.55, 0.25])
+                .with_hardness(0.4)
+                .with_sounds(BlockSounds::material("wood"))
+                .with_tags(["stem"]),
+            BlockDefinition::new("gravel", [0.55, 0.52, 0.5])
+                .falling()
+                .with_hardness(0.6)
+                .with_sounds(BlockSounds::material("gravel")),
+            // glass is solid, but only its edges with open air are drawn, faintly
+            BlockDefinition::new("glass", [0.85, 0.92, 0.95])
+                .see_through(0.25, 0)
+                .with_hardness(0.3)
+                .with_sounds(BlockSounds::material("glass"))
+                .with_tags(["transparent"]),
+            BlockDefinition::new("lamp", [1.0, 0.85, 0.5])
+                .with_emission(14)
+                .with_hardness(0.3)
+                .with_sounds(BlockSounds::material("glass")),
+        ];
+        debug_assert_eq!(definitions.len(), BlockType::ALL.len());
+        Self { definitions }
+    }
+}
+
+impl BlockRegistry {
+    /// Register a block, returning the number it was given.
+    pub fn register(&mut self, definition: BlockDefinition) -> anyhow::Result<BlockType> {
+        if definition.name.is_empty() || definition.name != definition.name.to_lowercase() {
+            bail!("block name {:?} must be lowercase", definition.name);
+        }
+        if let Some(tag) = definition
+            .tags
+            .iter()
+            .find(|tag| tag.is_empty() || **tag != tag.to_lowercase())
+        {
+            bail!(
+                "tag {:?} of block {:?} must be lowercase",
+                tag,
+                definition.name
+            );
+        }
+        if self.find(&definition.name).is_some() {
+            bail!("block {:?} is already registered", definition.name);
+        }
+        let Ok(id) = u16::try_from(self.definitions.len()) else {
+            bail!("too many blocks registered");
+        };
+        self.definitions.push(definition);
+        Ok(BlockType::from_id(id))
+    }
+
+    /// Get the definition of the given block, if it is registered.
+    pub fn get(&self, block: BlockType) -> Option<&BlockDefinition> {
+        self.definitions.get(block.id() as usize)
+    }
+
+    /// Get the definition of the given block, or that of a missing block if it is not registered.
+    pub fn definition(&self, block: BlockType) -> &BlockDefinition {
+        self.get(block).unwrap_or(&MISSING)
+    }
+
+    /// Find the block with the given name, ignoring case.
+    pub fn find(&self, name: &str) -> Option<BlockType> {
+        let name = name.trim();
+        self.definitions
+            .iter()
+            .position(|definition| definition.name.eq_ignore_ascii_case(name))
+            .map(|id| BlockType::from_id(id as u16))
+    }
+
+    /// Return an iterator over the blocks in the given category, such as `#ore`, in the order they
+    /// are numbered.
+    pub fn blocks_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = BlockType> + 'a {
+        self.iter()
+            .filter(move |(_, definition)| definition.has_tag(tag))
+            .map(|(block, _)| block)
+    }
+
+    /// Return an iterator over every registered block, in the order they are numbered.
+    pub fn iter(&self) -> impl Iterator<Item = (BlockType, &BlockDefinition)> {
+        self.definitions
+            .iter()
+            .enumerate()
+            .map(|(id, definition)| (BlockType::from_id(id as u16), definition))
+    }
+
+    /// Make this the registry blocks are looked up in, for the rest of the process. Installing the
+    /// same registry again does nothing, but a different one cannot replace it.
+    pub fn install(self) -> anyhow::Result<()> {
+        if let Err(rejected) = INSTALLED.set(self) {
+            if INSTALLED.get() != Some(&rejected) {
+                bail!("a different block registry is already installed");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Return the registry blocks are looked up in: the installed one, or the built-in blocks if none
+/// has been installed.
+pub fn registry() -> &'static BlockRegistry {
+    INSTALLED.get().unwrap_or(&BUILT_IN)
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use super::{BlockPos, BlockType};
+
+/// A block change queued to happen at a later tick, such as a crop growing or a fuse burning down.
+/// Changes are kept in the chunk they affect, so they are saved and reloaded along with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledChange {
+    /// The tick the change happens at.
+    pub tick: u64,
+    /// The position of the block within its chunk.
+    pub pos: BlockPos,
+    /// The block to place.
+    pub block: BlockType,
+}
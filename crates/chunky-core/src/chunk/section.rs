@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use super::{state::BlockState, BlockPos, BlockType, CHUNK_SIZE};
+
+/// The height of a chunk section, measured in blocks.
+pub const SECTION_HEIGHT: u8 = 8;
+
+/// The number of sections stacked vertically in a chunk.
+pub const SECTIONS: usize = (CHUNK_SIZE / SECTION_HEIGHT) as usize;
+
+/// A horizontal slice of a chunk, `SECTION_HEIGHT` blocks tall. Sections are the unit of work for
+/// remeshing and lighting, so that empty sections can be skipped entirely.
+#[derive(Debug, Default, Clone)]
+pub struct Section {
+    /// The non-empty blocks in the section, keyed by their position within the chunk.
+    blocks: BTreeMap<BlockPos, BlockType>,
+    /// The states of the blocks in the section whose state is not the default.
+    states: BTreeMap<BlockPos, BlockState>,
+    /// The revision of the chunk at which this section was last modified.
+    revision: u64,
+}
+
+impl Section {
+    /// Return the index of the section containing the given block.
+    pub fn index_of(pos: BlockPos) -> usize {
+        (pos.y / SECTION_HEIGHT) as usize
+    }
+
+    /// Check if the section contains no blocks.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Return the number of non-empty blocks in the section.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Return the revision of the chunk at which this section was last modified.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Get the block at the given position.
+    pub fn block_at(&self, pos: BlockPos) -> &BlockType {
+        self.blocks.get(&pos).unwrap_or(&BlockType::EMPTY)
+    }
+
+    /// Get the state of the block at the given position.
+    pub fn state_at(&self, pos: BlockPos) -> BlockState {
+        self.states.get(&pos).copied().unwrap_or_default()
+    }
+
+    /// Return an iterator over the blocks in the section whose state is not the default, ordered
+    /// by their position.
+    pub fn states(&self) -> impl Iterator<Item = (BlockPos, BlockState)> + '_ {
+        self.states.iter().map(|(&pos, &state)| (pos, state))
+    }
+
+    /// Return an iterator over all non-empty blocks in the section, ordered by their position.
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockPos, BlockType)> + '_ {
+        self.blocks.iter().map(|(&pos, &block)| (pos, block))
+    }
+
+    /// Set the block at the given position, returning the block it replaced. The state of the
+    /// previous block is discarded if the block type changes.
+    pub(super) fn set_block(
+        &mut self,
+        pos: BlockPos,
+        block: BlockType,
+        revision: u64,
+    ) -> BlockType {
+        self.revision = revision;
+        let previous = match block {
+            BlockType::EMPTY => self.blocks.remove(&pos),
+            _ => self.blocks.insert(pos, block),
+        }
+        .unwrap_or_default();
+        if previous != block {
+            self.states.remove(&pos);
+        }
+        previous
+    }
+
+    /// Set the state of the block at the given position. Empty space has no state, so this does
+    /// nothing if the block is empty.
+    pub(super) fn set_state(&mut self, pos: BlockPos, state: BlockState, revision: u64) {
+        if !self.blocks.contains_key(&pos) {
+            return;
+        }
+        self.revision = revision;
+        match state {
+            BlockState::DEFAULT => self.states.remove(&pos),
+            _ => self.states.insert(pos, state),
+        };
+    }
+
+    /// Remove all blocks from the section.
+    pub(super) fn clear(&mut self, revision: u64) {
+        self.revision = revision;
+        self.blocks.clear();
+        self.states.clear();
+    }
+}
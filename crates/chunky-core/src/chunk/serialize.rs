@@ -0,0 +1,85 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{
+    density::DensityVolume, entity::EntityRecord, scheduled::ScheduledChange, section::Section,
+    state::BlockState, BlockData, BlockPos, BlockType, Chunk, ChunkPos,
+};
+
+/// The serialized layout of a chunk. Blocks are run-length encoded in [`BlockPos::all`] order,
+/// since chunks are mostly long runs of air or stone, and so are densities, which saturate away
+/// from the surface. Derived data such as occupancy and light is rebuilt on load rather than
+/// stored.
+#[derive(Serialize, Deserialize)]
+pub(super) struct ChunkRepr {
+    pub(super) position: ChunkPos,
+    pub(super) runs: Vec<(BlockType, u16)>,
+    pub(super) block_data: Vec<(BlockPos, BlockData)>,
+    pub(super) scheduled: Vec<ScheduledChange>,
+    pub(super) density: Option<Vec<(i8, u16)>>,
+    pub(super) entities: Vec<EntityRecord>,
+    pub(super) states: Vec<(BlockPos, BlockState)>,
+}
+
+impl From<&Chunk> for ChunkRepr {
+    fn from(chunk: &Chunk) -> Self {
+        let mut runs: Vec<(BlockType, u16)> = Vec::new();
+        for pos in BlockPos::all() {
+            let block = *chunk.block_at(pos);
+            match runs.last_mut() {
+                Some((last, length)) if *last == block => *length += 1,
+                _ => runs.push((block, 1)),
+            }
+        }
+        Self {
+            position: chunk.position,
+            runs,
+            block_data: chunk
+                .block_data()
+                .map(|(&pos, data)| (pos, data.clone()))
+                .collect(),
+            scheduled: chunk.scheduled.clone(),
+            density: chunk.density.as_ref().map(DensityVolume::runs),
+            entities: chunk.entities.clone(),
+            states: chunk.states().collect(),
+        }
+    }
+}
+
+impl From<ChunkRepr> for Chunk {
+    fn from(repr: ChunkRepr) -> Self {
+        let mut chunk = Chunk::empty(repr.position);
+        let blocks = repr
+            .runs
+            .into_iter()
+            .flat_map(|(block, length)| std::iter::repeat_n(block, length as usize));
+        chunk.set_blocks(
+            BlockPos::all()
+                .zip(blocks)
+                .filter(|(_, block)| *block != BlockType::EMPTY),
+        );
+        chunk.block_data.extend(repr.block_data);
+        chunk.scheduled = repr.scheduled;
+        chunk.density = repr
+            .density
+            .and_then(|runs| DensityVolume::from_runs(&runs));
+        chunk.entities = repr.entities;
+        for (pos, state) in repr.states {
+            chunk.sections[Section::index_of(pos)].set_state(pos, state, chunk.revision);
+        }
+        // the chunk matches what was saved
+        chunk.mark_clean();
+        chunk
+    }
+}
+
+impl Serialize for Chunk {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ChunkRepr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Chunk {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ChunkRepr::deserialize(deserializer).map(Chunk::from)
+    }
+}
@@ -0,0 +1,90 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use super::mesh::Face;
+
+/// The bits of a block's state holding the direction its top faces.
+const FACING_BITS: u8 = 3;
+
+/// The directions a block's top can face, in the order they are numbered in its state, so blocks
+/// facing up have a state of zero.
+const FACINGS: [Face; 6] = [
+    Face::Up,
+    Face::Down,
+    Face::North,
+    Face::East,
+    Face::South,
+    Face::West,
+];
+
+/// A few bits stored alongside a block's type, for what varies between blocks of the same type,
+/// such as the axis of a log or the way a stair faces. The low three bits hold the direction the
+/// top of the block faces, and the rest are free for blocks to use as they need.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockState(u8);
+
+impl BlockState {
+    /// The state of a block facing up, with no other bits set.
+    pub const DEFAULT: Self = Self(0);
+
+    /// Create a state from its raw bits.
+    pub fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    /// Return the raw bits of the state.
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Return the direction the top of the block faces. States with an unknown direction face up.
+    pub fn facing(self) -> Face {
+        FACINGS
+            .get((self.0 & ((1 << FACING_BITS) - 1)) as usize)
+            .copied()
+            .unwrap_or(Face::Up)
+    }
+
+    /// Return the state with the top of the block facing the given direction.
+    pub fn with_facing(self, face: Face) -> Self {
+        let facing = FACINGS.iter().position(|&f| f == face).unwrap_or(0) as u8;
+        Self((self.0 >> FACING_BITS << FACING_BITS) | facing)
+    }
+
+    /// Return the bits left for the block's own use.
+    pub fn extra(self) -> u8 {
+        self.0 >> FACING_BITS
+    }
+
+    /// Return the state with the bits left for the block's own use replaced. Only the low five bits
+    /// of `extra` fit.
+    pub fn with_extra(self, extra: u8) -> Self {
+        Self((extra << FACING_BITS) | (self.0 & ((1 << FACING_BITS) - 1)))
+    }
+
+    /// Rotate a direction from the block's own frame, in which its top faces up, into the world.
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let Vec3 { x, y, z } = v;
+        match self.facing() {
+            Face::Up => v,
+            Face::Down => Vec3::new(x, -y, -z),
+            Face::North => Vec3::new(x, -z, y),
+            Face::South => Vec3::new(x, z, -y),
+            Face::East => Vec3::new(y, -x, z),
+            Face::West => Vec3::new(-y, x, z),
+        }
+    }
+
+    /// Rotate a direction from the world into the block's own frame, undoing [`Self::rotate`].
+    pub fn unrotate(self, v: Vec3) -> Vec3 {
+        let Vec3 { x, y, z } = v;
+        match self.facing() {
+            Face::Up => v,
+            Face::Down => Vec3::new(x, -y, -z),
+            Face::North => Vec3::new(x, z, -y),
+            Face::South => Vec3::new(x, -z, y),
+            Face::East => Vec3::new(-y, x, z),
+            Face::West => Vec3::new(y, -x, z),
+        }
+    }
+}
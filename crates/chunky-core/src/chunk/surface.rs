@@ -0,0 +1,61 @@
+use super::{BlockPos, CHUNK_SIZE};
+
+/// The number of columns in a chunk.
+const COLUMNS: usize = (CHUNK_SIZE as usize).pow(2);
+
+/// The height of the topmost opaque block in each column of a chunk, so surface queries do not
+/// need to scan the column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SurfaceMap {
+    /// One more than the height of the topmost opaque block in each column, or zero if the column
+    /// has no opaque blocks.
+    heights: [u8; COLUMNS],
+}
+
+impl Default for SurfaceMap {
+    fn default() -> Self {
+        Self {
+            heights: [0; COLUMNS],
+        }
+    }
+}
+
+impl SurfaceMap {
+    /// Create a map where every column has its topmost opaque block at the given height.
+    pub fn filled(height: Option<u8>) -> Self {
+        Self {
+            heights: [Self::encode(height); COLUMNS],
+        }
+    }
+
+    /// Return the index of the given column.
+    fn index(x: u8, z: u8) -> usize {
+        z as usize * CHUNK_SIZE as usize + x as usize
+    }
+
+    /// Encode a height, reserving zero for columns with no opaque blocks.
+    fn encode(height: Option<u8>) -> u8 {
+        height.map_or(0, |height| height + 1)
+    }
+
+    /// Return the height of the topmost opaque block in the given column, if there is one.
+    pub fn get(&self, x: u8, z: u8) -> Option<u8> {
+        self.heights[Self::index(x, z)].checked_sub(1)
+    }
+
+    /// Set the height of the topmost opaque block in the given column.
+    pub fn set(&mut self, x: u8, z: u8, height: Option<u8>) {
+        self.heights[Self::index(x, z)] = Self::encode(height);
+    }
+
+    /// Record that an opaque block was placed at the given position.
+    pub fn raise(&mut self, pos: BlockPos) {
+        let height = &mut self.heights[Self::index(pos.x, pos.z)];
+        *height = (*height).max(pos.y + 1);
+    }
+
+    /// Check if the given position is above the topmost opaque block in its column.
+    pub fn is_above(&self, pos: BlockPos) -> bool {
+        self.get(pos.x, pos.z).is_none_or(|height| pos.y > height)
+    }
+}
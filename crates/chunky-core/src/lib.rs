@@ -0,0 +1,4 @@
+pub mod chunk;
+#[cfg(feature = "python")]
+mod python;
+pub mod storage;
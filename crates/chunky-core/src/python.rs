@@ -0,0 +1,190 @@
+//! Python bindings to the generation pipeline and chunk queries, so terrain experiments can be
+//! scripted against the same code the game generates worlds with. Build the `chunky` module with
+//! `maturin develop` from this crate.
+
+use noise::OpenSimplex;
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::chunk::{
+    generation::pipeline::GenerationPipeline, registry::registry, BlockPos, BlockType, Chunk,
+    ChunkPos, CHUNK_SIZE,
+};
+
+/// Return the number of a block in the block registry.
+fn block_id(block: BlockType) -> u32 {
+    block.id() as u32
+}
+
+/// Check that a coordinate lies within a chunk.
+fn check_coordinate(value: u8) -> PyResult<u8> {
+    match value < CHUNK_SIZE {
+        true => Ok(value),
+        false => Err(PyValueError::new_err(format!(
+            "coordinate {} is outside the chunk",
+            value
+        ))),
+    }
+}
+
+/// Generates chunks from a seed through the stages of the generation pipeline.
+#[pyclass(name = "Generator")]
+struct PyGenerator {
+    noise: OpenSimplex,
+    pipeline: GenerationPipeline,
+}
+
+#[pymethods]
+impl PyGenerator {
+    #[new]
+    fn new(seed: u32) -> Self {
+        Self {
+            noise: OpenSimplex::new(seed),
+            pipeline: GenerationPipeline::default(),
+        }
+    }
+
+    /// The names of the stages, in the order they run.
+    fn stages(&self) -> Vec<&'static str> {
+        self.pipeline
+            .timings()
+            .into_iter()
+            .map(|timing| timing.name)
+            .collect()
+    }
+
+    /// Turn the named stage on or off.
+    fn set_enabled(&self, name: &str, enabled: bool) -> PyResult<()> {
+        match self.pipeline.set_enabled(name, enabled) {
+            true => Ok(()),
+            false => Err(PyValueError::new_err(format!("no stage named {:?}", name))),
+        }
+    }
+
+    /// The name, whether it is enabled, the number of runs, and the average time in seconds of
+    /// each stage.
+    fn timings(&self) -> Vec<(&'static str, bool, u64, f64)> {
+        self.pipeline
+            .timings()
+            .into_iter()
+            .map(|timing| {
+                let average = timing.average().as_secs_f64();
+                (timing.name, timing.enabled, timing.runs, average)
+            })
+            .collect()
+    }
+
+    /// Generate the chunk at the given chunk coordinates. Blocks of features that reach into
+    /// other chunks are left out.
+    fn generate(&self, x: i64, y: i64, z: i64) -> PyResult<PyChunk> {
+        let pos = ChunkPos::new(x, y, z);
+        if pos.checked_key().is_none() {
+            return Err(PyValueError::new_err(format!(
+                "chunk {:?} is out of the range of a chunk key",
+                (x, y, z)
+            )));
+        }
+        let mut chunk = Chunk::empty(pos);
+        self.pipeline.generate(&mut chunk, &self.noise);
+        Ok(PyChunk(chunk))
+    }
+}
+
+/// A generated chunk. Blocks are numbered by their position in `block_names()`.
+#[pyclass(name = "Chunk")]
+struct PyChunk(Chunk);
+
+#[pymethods]
+impl PyChunk {
+    /// The chunk coordinates of the chunk.
+    #[getter]
+    fn position(&self) -> (i64, i64, i64) {
+        let pos = self.0.position;
+        (pos.x, pos.y, pos.z)
+    }
+
+    /// The block at the given position within the chunk.
+    fn block(&self, x: u8, y: u8, z: u8) -> PyResult<u32> {
+        let pos = BlockPos::new(
+            check_coordinate(x)?,
+            check_coordinate(y)?,
+            check_coordinate(z)?,
+        );
+        Ok(block_id(*self.0.block_at(pos)))
+    }
+
+    /// Every block in the chunk, indexed by `[x][y][z]`.
+    fn blocks(&self) -> Vec<Vec<Vec<u32>>> {
+        (0..CHUNK_SIZE)
+            .map(|x| {
+                (0..CHUNK_SIZE)
+                    .map(|y| {
+                        (0..CHUNK_SIZE)
+                            .map(|z| block_id(*self.0.block_at((x, y, z))))
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// A slice of blocks through the chunk at the given index along the given axis, one of "x",
+    /// "y", or "z". Rows and columns follow the remaining two axes in order.
+    fn slice(&self, axis: &str, index: u8) -> PyResult<Vec<Vec<u32>>> {
+        let index = check_coordinate(index)?;
+        let pos: fn(u8, u8, u8) -> BlockPos = match axis {
+            "x" => |index, a, b| BlockPos::new(index, a, b),
+            "y" => |index, a, b| BlockPos::new(a, index, b),
+            "z" => |index, a, b| BlockPos::new(a, b, index),
+            _ => return Err(PyValueError::new_err(format!("no axis named {:?}", axis))),
+        };
+        Ok((0..CHUNK_SIZE)
+            .map(|a| {
+                (0..CHUNK_SIZE)
+                    .map(|b| block_id(*self.0.block_at(pos(index, a, b))))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// The height of the highest opaque block in each column, indexed by `[x][z]`, or `None` for
+    /// empty columns.
+    fn surface(&self) -> Vec<Vec<Option<u8>>> {
+        (0..CHUNK_SIZE)
+            .map(|x| (0..CHUNK_SIZE).map(|z| self.0.surface_at(x, z)).collect())
+            .collect()
+    }
+
+    /// The density at the given position within the chunk, which is positive inside the terrain.
+    fn density(&self, x: u8, y: u8, z: u8) -> PyResult<f32> {
+        let pos = BlockPos::new(
+            check_coordinate(x)?,
+            check_coordinate(y)?,
+            check_coordinate(z)?,
+        );
+        Ok(self.0.density_at(pos))
+    }
+
+    /// The blocks used in the chunk.
+    fn palette(&self) -> Vec<u32> {
+        self.0.palette().into_iter().map(block_id).collect()
+    }
+}
+
+/// The names of the blocks, in the order they are numbered.
+#[pyfunction]
+fn block_names() -> Vec<String> {
+    registry()
+        .iter()
+        .map(|(_, definition)| definition.name.clone())
+        .collect()
+}
+
+/// The `chunky` Python module.
+#[pymodule]
+fn chunky(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("CHUNK_SIZE", CHUNK_SIZE)?;
+    module.add_class::<PyGenerator>()?;
+    module.add_class::<PyChunk>()?;
+    module.add_function(wrap_pyfunction!(block_names, module)?)?;
+    Ok(())
+}
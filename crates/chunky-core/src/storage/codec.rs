@@ -0,0 +1,181 @@
+use std::{io::Read, str::FromStr, sync::Arc};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::{
+    migration::{self, FORMAT_VERSION},
+    Chunk,
+};
+
+/// A payload of uncompressed bincode.
+pub(super) const RAW: u8 = 0;
+
+/// A payload compressed with zstd.
+const ZSTD: u8 = 1;
+
+/// A payload compressed with zstd using the world's dictionary.
+const ZSTD_DICTIONARY: u8 = 2;
+
+/// A payload compressed with lz4, prefixed with its uncompressed size.
+const LZ4: u8 = 3;
+
+/// The zstd compression level used for chunks.
+const LEVEL: i32 = 3;
+
+/// The compression applied to chunk payloads.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// No compression.
+    None,
+    /// zstd, which compresses best and can use a trained dictionary.
+    #[default]
+    Zstd,
+    /// lz4, which compresses less but is much faster.
+    Lz4,
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "zstd" => Ok(Self::Zstd),
+            "lz4" => Ok(Self::Lz4),
+            _ => bail!("unknown compression {}", s),
+        }
+    }
+}
+
+/// Encodes chunks into the payloads saved by chunk stores. The first byte of each payload records
+/// how it was encoded, so payloads written with a different compression, or before a dictionary
+/// was trained, can still be read.
+#[derive(Debug, Clone)]
+pub struct ChunkCodec {
+    /// The compression applied to new payloads.
+    compression: Compression,
+    /// The world's compression dictionary, if one has been trained.
+    dictionary: Option<Arc<[u8]>>,
+    /// Another dictionary payloads may have been compressed with, such as the one a recompression
+    /// of the world was switching to when it was interrupted. Only used for decoding.
+    fallback: Option<Arc<[u8]>>,
+    /// The format version of the chunks being decoded.
+    version: u32,
+}
+
+impl Default for ChunkCodec {
+    fn default() -> Self {
+        Self::new(Compression::default(), None)
+    }
+}
+
+impl ChunkCodec {
+    /// Create a codec with the given compression. The dictionary is only used by zstd.
+    pub fn new(compression: Compression, dictionary: Option<Vec<u8>>) -> Self {
+        Self {
+            compression,
+            dictionary: dictionary.map(Arc::from),
+            fallback: None,
+            version: FORMAT_VERSION,
+        }
+    }
+
+    /// Also decode payloads compressed with the given dictionary, for worlds whose chunks are
+    /// partway through being recompressed from one dictionary to another.
+    pub fn with_fallback_dictionary(mut self, dictionary: Option<Vec<u8>>) -> Self {
+        self.fallback = dictionary.map(Arc::from);
+        self
+    }
+
+    /// Decode chunks saved in the given format version, upgrading them to the current version.
+    /// Chunks are always encoded in the current version.
+    pub fn reading_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Encode a chunk into a payload.
+    pub fn encode(&self, chunk: &Chunk) -> anyhow::Result<Vec<u8>> {
+        let raw = bincode::serialize(chunk)?;
+        let (tag, compressed) = match (self.compression, &self.dictionary) {
+            (Compression::None, _) => (RAW, raw),
+            (Compression::Lz4, _) => (LZ4, lz4_flex::compress_prepend_size(&raw)),
+            (Compression::Zstd, Some(dictionary)) => (
+                ZSTD_DICTIONARY,
+                zstd::bulk::Compressor::with_dictionary(LEVEL, dictionary)?.compress(&raw)?,
+            ),
+            (Compression::Zstd, None) => (ZSTD, zstd::bulk::compress(&raw, LEVEL)?),
+        };
+        let mut payload = Vec::with_capacity(compressed.len() + 1);
+        payload.push(tag);
+        payload.extend(compressed);
+        Ok(payload)
+    }
+
+    /// Decode a chunk from a payload.
+    pub fn decode(&self, payload: &[u8]) -> anyhow::Result<Chunk> {
+        let raw = match self.version {
+            FORMAT_VERSION => self.decompress(payload)?,
+            _ => self.upgrade(payload)?,
+        };
+        Ok(bincode::deserialize(&raw)?)
+    }
+
+    /// Decompress a payload, and upgrade the chunk inside from an older format version.
+    fn upgrade(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        // unversioned worlds may predate compression, when payloads were plain bincode
+        let candidates = [
+            self.decompress(payload).ok(),
+            (self.version == 0).then(|| payload.to_vec()),
+        ];
+        let mut result = Err(anyhow::anyhow!("failed to decompress chunk payload"));
+        for raw in candidates.into_iter().flatten() {
+            result = migration::upgrade(raw, self.version);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Decompress a payload into the chunk's bincode.
+    fn decompress(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let (&tag, body) = payload.split_first().context("empty chunk payload")?;
+        let raw = match tag {
+            RAW => body.to_vec(),
+            ZSTD => zstd::stream::decode_all(body)?,
+            ZSTD_DICTIONARY => {
+                // frames record the id of their dictionary, so decoding with any other fails
+                if self.dictionary.is_none() && self.fallback.is_none() {
+                    bail!("chunk was compressed with a dictionary, but the world has none");
+                }
+                let mut result = Err(anyhow::anyhow!("no dictionary decodes the chunk"));
+                for dictionary in self.dictionary.iter().chain(&self.fallback) {
+                    let mut raw = Vec::new();
+                    result = zstd::stream::Decoder::with_dictionary(body, dictionary)
+                        .and_then(|mut decoder| decoder.read_to_end(&mut raw))
+                        .map(|_| raw)
+                        .map_err(anyhow::Error::from);
+                    if result.is_ok() {
+                        break;
+                    }
+                }
+                result?
+            }
+            LZ4 => lz4_flex::decompress_size_prepended(body)?,
+            _ => bail!("unknown chunk encoding {}", tag),
+        };
+        Ok(raw)
+    }
+}
+
+/// Train a compression dictionary of at most the given size from a sample of chunks.
+pub fn train_dictionary(chunks: &[Chunk], max_size: usize) -> anyhow::Result<Vec<u8>> {
+    let samples = chunks
+        .iter()
+        .map(bincode::serialize)
+        .collect::<Result<Vec<_>, _>>()?;
+    zstd::dict::from_samples(&samples, max_size).context("failed to train dictionary")
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+use super::ChunkStore;
+use crate::chunk::{BlockPos, BlockType, ChunkPos};
+
+/// How a world is checked as it is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegrityCheck {
+    /// One in however many saved chunks is decoded and inspected. 1 checks every chunk.
+    pub sample: usize,
+    /// Whether problems found are fixed, by forgetting corrupt chunks and stripping orphaned block
+    /// data.
+    pub repair: bool,
+}
+
+impl Default for IntegrityCheck {
+    fn default() -> Self {
+        Self {
+            sample: 16,
+            repair: false,
+        }
+    }
+}
+
+/// The problems found by an integrity check.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// The number of saved chunks.
+    pub chunks: usize,
+    /// The number of chunks decoded and inspected.
+    pub sampled: usize,
+    /// Problems with the store's bookkeeping, such as region file headers.
+    pub layout: Vec<String>,
+    /// Chunks that failed to decode, or that decoded to a chunk at a different position.
+    pub corrupt: Vec<ChunkPos>,
+    /// Block data attached to empty blocks, left behind by blocks that were removed.
+    pub orphans: Vec<(ChunkPos, BlockPos)>,
+    /// Whether the problems were repaired.
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    /// Check if no problems were found.
+    pub fn is_clean(&self) -> bool {
+        self.layout.is_empty() && self.corrupt.is_empty() && self.orphans.is_empty()
+    }
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "checked {} of {} chunks: {} layout problems, {} corrupt chunks, {} orphaned block data",
+            self.sampled,
+            self.chunks,
+            self.layout.len(),
+            self.corrupt.len(),
+            self.orphans.len()
+        )?;
+        if self.repaired && !self.is_clean() {
+            write!(f, " (repaired)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check a store for damage: its bookkeeping, whether a sample of its chunks decode to the
+/// positions they are saved at, and whether those chunks have block data on empty blocks.
+pub fn check(store: &dyn ChunkStore, options: IntegrityCheck) -> anyhow::Result<IntegrityReport> {
+    let mut report = IntegrityReport {
+        layout: store.check_layout(options.repair)?,
+        repaired: options.repair,
+        ..Default::default()
+    };
+    let positions = store.positions()?;
+    report.chunks = positions.len();
+    for pos in positions.into_iter().step_by(options.sample.max(1)) {
+        report.sampled += 1;
+        let mut chunk = match store.read_chunk(pos) {
+            Ok(Some(chunk)) if chunk.position == pos => chunk,
+            Ok(None) => continue,
+            Ok(Some(_)) | Err(_) => {
+                report.corrupt.push(pos);
+                if options.repair {
+                    store.remove_chunk(pos)?;
+                }
+                continue;
+            }
+        };
+
+        let orphans = chunk
+            .block_data()
+            .map(|(&block, _)| block)
+            .filter(|&block| *chunk.block_at(block) == BlockType::EMPTY)
+            .collect::<Vec<_>>();
+        if orphans.is_empty() {
+            continue;
+        }
+        report
+            .orphans
+            .extend(orphans.iter().map(|&block| (pos, block)));
+        if options.repair {
+            for block in orphans {
+                chunk.remove_block_data(block);
+            }
+            store.write_chunk(&chunk)?;
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,36 @@
+use std::sync::Mutex;
+
+use super::ChunkStore;
+use crate::chunk::{key::ChunkMap, Chunk, ChunkPos};
+
+/// A chunk store that keeps saved chunks in memory, for tests and throwaway worlds.
+#[derive(Default)]
+pub struct MemoryStore {
+    chunks: Mutex<ChunkMap<Chunk>>,
+}
+
+impl ChunkStore for MemoryStore {
+    fn read_chunk(&self, pos: ChunkPos) -> anyhow::Result<Option<Chunk>> {
+        Ok(self.chunks.lock().unwrap().get(&pos.key()).cloned())
+    }
+
+    fn write_chunk(&self, chunk: &Chunk) -> anyhow::Result<()> {
+        let mut chunk = chunk.clone();
+        chunk.mark_clean();
+        self.chunks
+            .lock()
+            .unwrap()
+            .insert(chunk.position.key(), chunk);
+        Ok(())
+    }
+
+    fn remove_chunk(&self, pos: ChunkPos) -> anyhow::Result<()> {
+        self.chunks.lock().unwrap().remove(&pos.key());
+        Ok(())
+    }
+
+    fn positions(&self) -> anyhow::Result<Vec<ChunkPos>> {
+        let chunks = self.chunks.lock().unwrap();
+        Ok(chunks.keys().map(|&key| key.into()).collect())
+    }
+}
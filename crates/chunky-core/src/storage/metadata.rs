@@ -0,0 +1,152 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context};
+use bincode::Options;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::chunk::{generation::preset::WorldPreset, mesh::MeshingMode};
+
+/// The name of the metadata file in the world directory.
+const METADATA_FILE: &str = "world.meta";
+
+/// The name of the file in the world directory holding the dictionary the world's chunks are being
+/// recompressed with, until every chunk has been and it replaces the dictionary in the metadata.
+const PENDING_DICTIONARY_FILE: &str = "dictionary.pending";
+
+/// The bytes every versioned metadata file starts with. Metadata written before worlds were
+/// versioned has no header.
+const MAGIC: &[u8; 4] = b"CHKW";
+
+/// Information about a saved world as a whole, rather than any one chunk.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct WorldMetadata {
+    /// The format version of the world's saved chunks, or zero if the world predates versioning.
+    pub version: u32,
+    /// The zstd dictionary chunks are compressed with, if one has been trained.
+    pub dictionary: Option<Vec<u8>>,
+    /// The number of fixed ticks the world has run for.
+    pub tick: u64,
+    /// How the world's chunks are meshed.
+    pub meshing: MeshingMode,
+    /// The preset the world was created with, or none if it was generated however the environment
+    /// asked at the time.
+    pub preset: Option<WorldPreset>,
+}
+
+impl WorldMetadata {
+    /// Read the metadata of the world in the given directory, or the defaults for a world that has
+    /// none.
+    pub fn load(world_path: &Path) -> anyhow::Result<Self> {
+        let path = world_path.join(METADATA_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let bytes =
+            fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        match bytes.strip_prefix(MAGIC) {
+            Some(body) => {
+                Self::from_versioned(body).with_context(|| format!("{} is corrupt", path.display()))
+            }
+            None => Self::from_unversioned(&bytes)
+                .with_context(|| format!("{} is corrupt", path.display())),
+        }
+    }
+
+    /// Parse versioned metadata, falling back to the layout written before worlds recorded their
+    /// preset.
+    fn from_versioned(body: &[u8]) -> anyhow::Result<Self> {
+        if let Some(metadata) = deserialize_exact(body) {
+            return Ok(metadata);
+        }
+        if let Some((version, dictionary, tick, meshing)) = deserialize_exact(body) {
+            return Ok(Self {
+                version,
+                dictionary,
+                tick,
+                meshing,
+                ..Default::default()
+            });
+        }
+        bail!("metadata does not match any versioned layout")
+    }
+
+    /// Parse metadata written before worlds were versioned, trying each layout used until then
+    /// from newest to oldest.
+    fn from_unversioned(bytes: &[u8]) -> anyhow::Result<Self> {
+        if let Some((dictionary, tick, meshing)) = deserialize_exact(bytes) {
+            return Ok(Self {
+                dictionary,
+                tick,
+                meshing,
+                ..Default::default()
+            });
+        }
+        if let Some((dictionary, tick)) = deserialize_exact(bytes) {
+            return Ok(Self {
+                dictionary,
+                tick,
+                ..Default::default()
+            });
+        }
+        if let Some(dictionary) = deserialize_exact(bytes) {
+            return Ok(Self {
+                dictionary,
+                ..Default::default()
+            });
+        }
+        bail!("metadata does not match any unversioned layout")
+    }
+
+    /// Write the metadata of the world in the given directory.
+    pub fn save(&self, world_path: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(world_path)?;
+        let path = world_path.join(METADATA_FILE);
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(bincode::serialize(self)?);
+        fs::write(&path, bytes).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Read the dictionary the chunks of the world in the given directory are partway through being
+/// recompressed with, or `None` if no recompression is unfinished.
+pub fn load_pending_dictionary(world_path: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+    let path = world_path.join(PENDING_DICTIONARY_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read(&path)
+        .map(Some)
+        .with_context(|| format!("failed to read {}", path.display()))
+}
+
+/// Record the dictionary the chunks of the world in the given directory are about to be
+/// recompressed with, so chunks already recompressed can be read if the recompression is
+/// interrupted.
+pub fn save_pending_dictionary(world_path: &Path, dictionary: &[u8]) -> anyhow::Result<()> {
+    fs::create_dir_all(world_path)?;
+    let path = world_path.join(PENDING_DICTIONARY_FILE);
+    fs::write(&path, dictionary).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Make the pending dictionary of the world in the given directory its dictionary, once every chunk
+/// has been recompressed with it.
+pub fn commit_pending_dictionary(world_path: &Path) -> anyhow::Result<()> {
+    let Some(dictionary) = load_pending_dictionary(world_path)? else {
+        return Ok(());
+    };
+    let mut metadata = WorldMetadata::load(world_path)?;
+    metadata.dictionary = Some(dictionary);
+    metadata.save(world_path)?;
+    let path = world_path.join(PENDING_DICTIONARY_FILE);
+    fs::remove_file(&path).with_context(|| format!("failed to remove {}", path.display()))
+}
+
+/// Deserialize a value that must span the whole of the given bytes, so that layouts which are
+/// prefixes of one another can be told apart.
+fn deserialize_exact<T: DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(bytes)
+        .ok()
+}
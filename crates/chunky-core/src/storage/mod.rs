@@ -0,0 +1,283 @@
+pub mod codec;
+pub mod integrity;
+pub mod memory;
+pub mod metadata;
+pub mod region;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use std::{path::Path, str::FromStr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use codec::{ChunkCodec, Compression};
+use metadata::WorldMetadata;
+
+use crate::chunk::{migration::FORMAT_VERSION, Chunk, ChunkPos};
+
+/// A place chunks can be saved to and loaded from.
+pub trait ChunkStore: Send + Sync {
+    /// Read the chunk at the given position, or `None` if it has not been saved.
+    fn read_chunk(&self, pos: ChunkPos) -> anyhow::Result<Option<Chunk>>;
+
+    /// Write a chunk, replacing any previously saved copy.
+    fn write_chunk(&self, chunk: &Chunk) -> anyhow::Result<()>;
+
+    /// Return the positions of every saved chunk.
+    fn positions(&self) -> anyhow::Result<Vec<ChunkPos>>;
+
+    /// Forget the saved chunk at the given position, so it is generated afresh.
+    fn remove_chunk(&self, pos: ChunkPos) -> anyhow::Result<()>;
+
+    /// Check the store's own bookkeeping, such as the headers of region files, returning a
+    /// description of each problem found. Problems are fixed if `repair` is set, at the cost of
+    /// the chunks involved.
+    fn check_layout(&self, _repair: bool) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+}
+
+/// The kinds of chunk store a world can be saved in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Region files in the world directory.
+    #[default]
+    Region,
+    /// A SQLite database in the world directory, for transactional writes. Requires the `sqlite`
+    /// feature.
+    Sqlite,
+    /// Memory only, so nothing outlives the process.
+    Memory,
+}
+
+impl FromStr for StorageBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "region" => Ok(Self::Region),
+            "sqlite" => Ok(Self::Sqlite),
+            "memory" => Ok(Self::Memory),
+            _ => anyhow::bail!("unknown storage backend {}", s),
+        }
+    }
+}
+
+impl StorageBackend {
+    /// Open a store of this kind for the world in the given directory, compressing chunks with the
+    /// given compression and the world's dictionary if it has one. Chunks compressed with the
+    /// dictionary of an unfinished recompression can be read too.
+    pub fn open(
+        self,
+        world_path: &Path,
+        compression: Compression,
+    ) -> anyhow::Result<Arc<dyn ChunkStore>> {
+        let mut metadata = WorldMetadata::load(world_path)?;
+        let codec = ChunkCodec::new(compression, metadata.dictionary.clone())
+            .with_fallback_dictionary(metadata::load_pending_dictionary(world_path)?);
+        if self != Self::Memory && metadata.version != FORMAT_VERSION {
+            self.migrate(world_path, codec.clone().reading_version(metadata.version))?;
+            metadata.version = FORMAT_VERSION;
+            metadata.save(world_path)?;
+        }
+        self.open_with(world_path, codec)
+    }
+
+    /// Rewrite every chunk of the world in the given directory in the current format version,
+    /// reading them with a codec for the version they were saved in.
+    fn migrate(self, world_path: &Path, codec: ChunkCodec) -> anyhow::Result<()> {
+        let old = self.open_with(world_path, codec.clone())?;
+        let positions = old.positions()?;
+        if !positions.is_empty() {
+            info!(
+                "Upgrading {} chunks to format version {}",
+                positions.len(),
+                FORMAT_VERSION
+            );
+        }
+        let store = self.open_with(world_path, codec.reading_version(FORMAT_VERSION))?;
+        for pos in positions {
+            // chunks are upgraded as they are read, and written back in the current version
+            if let Some(chunk) = old.read_chunk(pos)? {
+                store.write_chunk(&chunk)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a store of this kind for the world in the given directory, encoding chunks with the
+    /// given codec.
+    pub fn open_with(
+        self,
+        world_path: &Path,
+        codec: ChunkCodec,
+    ) -> anyhow::Result<Arc<dyn ChunkStore>> {
+        Ok(match self {
+            Self::Region => Arc::new(region::RegionStore::open(world_path.join("region"), codec)?),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => Arc::new(sqlite::SqliteStore::open(
+                world_path.join("world.sqlite"),
+                codec,
+            )?),
+            #[cfg(not(feature = "sqlite"))]
+            Self::Sqlite => anyhow::bail!("this build does not support sqlite storage"),
+            Self::Memory => Arc::new(memory::MemoryStore::default()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs};
+
+    use super::*;
+    use crate::chunk::{
+        density::DensityVolume, scheduled::ScheduledChange, BlockData, BlockPos, BlockType,
+        CHUNK_SIZE,
+    };
+
+    /// A chunk in the layout of format version 1: its position, its blocks run-length encoded in
+    /// [`BlockPos::all`] order, its block data, its scheduled changes, and a density per block.
+    type ChunkV1 = (
+        ChunkPos,
+        Vec<(BlockType, u16)>,
+        Vec<(BlockPos, BlockData)>,
+        Vec<ScheduledChange>,
+        Option<Vec<i8>>,
+    );
+
+    #[test]
+    fn upgrade_v1_world() {
+        let root = std::env::temp_dir().join(format!("chunky-upgrade-v1-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        // a wall of stone, the first blocks in order, with a sign by it and a block due to change
+        let pos = ChunkPos::new(2, -1, 3);
+        let wall = (CHUNK_SIZE as u16).pow(2);
+        let volume = (CHUNK_SIZE as u16).pow(3) - wall;
+        let sign = BlockPos::new(4, 1, 5);
+        let data = BlockData {
+            state: BTreeMap::from([("text".to_owned(), "hello".to_owned())]),
+            ..Default::default()
+        };
+        let change = ScheduledChange {
+            tick: 40,
+            pos: BlockPos::new(1, 1, 1),
+            block: BlockType::SAND,
+        };
+        let mut density = DensityVolume::filled(-1.0);
+        for pos in BlockPos::all().filter(|pos| pos.x == 0) {
+            density.set(pos, 1.0);
+        }
+        let chunk: ChunkV1 = (
+            pos,
+            vec![(BlockType::STONE, wall), (BlockType::EMPTY, volume)],
+            vec![(sign, data.clone())],
+            vec![change],
+            Some(density.quantized().to_vec()),
+        );
+
+        // save the chunk as a version 1 world would have, uncompressed
+        WorldMetadata {
+            version: 1,
+            ..Default::default()
+        }
+        .save(&root)
+        .unwrap();
+        let mut payload = vec![codec::RAW];
+        payload.extend(bincode::serialize(&chunk).unwrap());
+        region::RegionStore::open(root.join("region"), ChunkCodec::default())
+            .unwrap()
+            .write_payload(pos, &payload)
+            .unwrap();
+
+        let store = StorageBackend::Region
+            .open(&root, Compression::None)
+            .unwrap();
+        assert_eq!(WorldMetadata::load(&root).unwrap().version, FORMAT_VERSION);
+        let chunk = store.read_chunk(pos).unwrap().unwrap();
+        assert_eq!(chunk.position, pos);
+        for pos in BlockPos::all() {
+            let expected = match pos.x {
+                0 => BlockType::STONE,
+                _ => BlockType::EMPTY,
+            };
+            assert_eq!(*chunk.block_at(pos), expected);
+        }
+        assert_eq!(chunk.block_data_at(sign), Some(&data));
+        assert_eq!(chunk.scheduled(), [change]);
+        let density = chunk.density().unwrap();
+        assert!(density.get(BlockPos::new(0, 0, 0)) > 0.0);
+        assert!(density.get(BlockPos::new(1, 0, 0)) < 0.0);
+        // layouts added since version 1 start out empty
+        assert!(chunk.entities().is_empty());
+        assert_eq!(chunk.states().count(), 0);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn read_interrupted_recompression() {
+        let root = std::env::temp_dir().join(format!("chunky-recompress-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        // chunks with a few layers of different blocks, so there is something to train on
+        let chunks = (0..64)
+            .map(|i| {
+                let mut chunk = Chunk::empty(ChunkPos::new(i, 0, 0));
+                chunk.set_blocks(
+                    BlockPos::all()
+                        .filter(|pos| u32::from(pos.y) < i as u32 % 8 + 4)
+                        .map(|pos| match (pos.y + pos.x) % 3 {
+                            0 => (pos, BlockType::STONE),
+                            1 => (pos, BlockType::DIRT),
+                            _ => (pos, BlockType::SAND),
+                        }),
+                );
+                chunk
+            })
+            .collect::<Vec<_>>();
+        let old = codec::train_dictionary(&chunks[..32], 4096).unwrap();
+        let new = codec::train_dictionary(&chunks[32..], 4096).unwrap();
+
+        // the first half is still compressed with the old dictionary, the rest with the new one
+        WorldMetadata {
+            version: FORMAT_VERSION,
+            dictionary: Some(old.clone()),
+            ..Default::default()
+        }
+        .save(&root)
+        .unwrap();
+        metadata::save_pending_dictionary(&root, &new).unwrap();
+        for (chunk, dictionary) in chunks
+            .iter()
+            .zip(std::iter::repeat_n(&old, 32).chain(std::iter::repeat(&new)))
+        {
+            StorageBackend::Region
+                .open_with(
+                    &root,
+                    ChunkCodec::new(Compression::Zstd, Some(dictionary.clone())),
+                )
+                .unwrap()
+                .write_chunk(chunk)
+                .unwrap();
+        }
+
+        let store = StorageBackend::Region
+            .open(&root, Compression::Zstd)
+            .unwrap();
+        for chunk in &chunks {
+            let read = store.read_chunk(chunk.position).unwrap().unwrap();
+            assert!(BlockPos::all().all(|pos| read.block_at(pos) == chunk.block_at(pos)));
+        }
+
+        // once committed, the new dictionary is the world's, and the old one is forgotten
+        metadata::commit_pending_dictionary(&root).unwrap();
+        assert_eq!(WorldMetadata::load(&root).unwrap().dictionary, Some(new));
+        assert_eq!(metadata::load_pending_dictionary(&root).unwrap(), None);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,470 @@
+use std::{
+    collections::{hash_map, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context};
+
+use super::{codec::ChunkCodec, ChunkStore};
+use crate::chunk::{Chunk, ChunkPos};
+
+/// The size of a region along one axis, measured in chunks.
+pub const REGION_SIZE: i64 = 32;
+
+/// The number of chunks in a region.
+const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+
+/// The size of an offset table entry, in bytes.
+const ENTRY_SIZE: u64 = 8;
+
+/// The size of the offset table at the start of every region file, in bytes.
+const HEADER_SIZE: u64 = REGION_CHUNKS as u64 * ENTRY_SIZE;
+
+/// The unit chunk payloads are allocated in, in bytes.
+const SECTOR_SIZE: u64 = 4096;
+
+/// The number of sectors taken up by the offset table, before the first payload.
+const HEADER_SECTORS: u64 = HEADER_SIZE / SECTOR_SIZE;
+
+/// The position of a region in region coordinates.
+type RegionCoords = (i64, i64, i64);
+
+/// The location of a chunk's payload within a region file.
+#[derive(Debug, Default, Clone, Copy)]
+struct Entry {
+    /// The first sector of the payload, or zero if the chunk has not been saved.
+    sector: u32,
+    /// The length of the payload, in bytes.
+    length: u32,
+}
+
+impl Entry {
+    /// Return the number of sectors the payload occupies.
+    fn sectors(&self) -> u64 {
+        (self.length as u64).div_ceil(SECTOR_SIZE)
+    }
+}
+
+/// A chunk store that groups chunks into region files of 32x32x32 chunks. Each file starts with an
+/// offset table locating every chunk's payload, so a world of millions of chunks needs only
+/// thousands of files.
+pub struct RegionStore {
+    /// The directory containing the region files.
+    root: PathBuf,
+    /// Region files that have already been opened.
+    files: Mutex<HashMap<RegionCoords, File>>,
+    /// The encoding of chunk payloads.
+    codec: ChunkCodec,
+}
+
+impl RegionStore {
+    /// Open a region store in the given directory, creating it if it does not exist.
+    pub fn open(root: impl AsRef<Path>, codec: ChunkCodec) -> anyhow::Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)
+            .with_context(|| format!("failed to create {}", root.display()))?;
+        Ok(Self {
+            root,
+            files: Mutex::new(HashMap::new()),
+            codec,
+        })
+    }
+
+    /// Parse the region coordinates from the name of a region file.
+    fn parse_file_name(name: &str) -> Option<RegionCoords> {
+        let mut parts = name.strip_prefix("r.")?.strip_suffix(".bin")?.split('.');
+        let mut next = || parts.next()?.parse().ok();
+        Some((next()?, next()?, next()?))
+    }
+
+    /// Return the region containing the given chunk.
+    fn region_of(pos: ChunkPos) -> RegionCoords {
+        (
+            pos.x.div_euclid(REGION_SIZE),
+            pos.y.div_euclid(REGION_SIZE),
+            pos.z.div_euclid(REGION_SIZE),
+        )
+    }
+
+    /// Return the offset of the given chunk's entry in the offset table.
+    fn entry_offset(pos: ChunkPos) -> u64 {
+        let (x, y, z) = (
+            pos.x.rem_euclid(REGION_SIZE),
+            pos.y.rem_euclid(REGION_SIZE),
+            pos.z.rem_euclid(REGION_SIZE),
+        );
+        ((z * REGION_SIZE + x) * REGION_SIZE + y) as u64 * ENTRY_SIZE
+    }
+
+    /// Get the file of the given region, opening it if needed. Missing files are created with an
+    /// empty offset table if `create` is set, and otherwise `None` is returned.
+    fn file<'a>(
+        &self,
+        files: &'a mut HashMap<RegionCoords, File>,
+        region: RegionCoords,
+        create: bool,
+    ) -> anyhow::Result<Option<&'a mut File>> {
+        let entry = match files.entry(region) {
+            hash_map::Entry::Occupied(entry) => return Ok(Some(entry.into_mut())),
+            hash_map::Entry::Vacant(entry) => entry,
+        };
+        let (x, y, z) = region;
+        let path = self.root.join(format!("r.{}.{}.{}.bin", x, y, z));
+        if !path.exists() && !create {
+            return Ok(None);
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        if file.metadata()?.len() < HEADER_SIZE {
+            file.set_len(HEADER_SIZE)?;
+        }
+        Ok(Some(entry.insert(file)))
+    }
+
+    /// Read the offset table entry of the given chunk.
+    fn read_entry(file: &mut File, pos: ChunkPos) -> anyhow::Result<Entry> {
+        let mut bytes = [0; ENTRY_SIZE as usize];
+        file.seek(SeekFrom::Start(Self::entry_offset(pos)))?;
+        file.read_exact(&mut bytes)?;
+        let [a, b, c, d, e, f, g, h] = bytes;
+        Ok(Entry {
+            sector: u32::from_le_bytes([a, b, c, d]),
+            length: u32::from_le_bytes([e, f, g, h]),
+        })
+    }
+
+    /// Return the coordinates of every region file in the store.
+    fn regions(&self) -> anyhow::Result<Vec<RegionCoords>> {
+        let mut regions = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let name = entry?.file_name();
+            regions.extend(Self::parse_file_name(&name.to_string_lossy()));
+        }
+        Ok(regions)
+    }
+
+    /// Return the position of every chunk in the given region, in the order of the offset table.
+    fn chunks_of(region: RegionCoords) -> impl Iterator<Item = ChunkPos> {
+        let origin = ChunkPos::new(
+            region.0 * REGION_SIZE,
+            region.1 * REGION_SIZE,
+            region.2 * REGION_SIZE,
+        );
+        itertools::iproduct!(0..REGION_SIZE, 0..REGION_SIZE, 0..REGION_SIZE)
+            .map(move |offset| origin + offset.into())
+    }
+
+    /// Write an encoded payload as the chunk at the given position, replacing any previous one.
+    pub(super) fn write_payload(&self, pos: ChunkPos, payload: &[u8]) -> anyhow::Result<()> {
+        let length = u32::try_from(payload.len()).context("chunk too large")?;
+
+        let mut files = self.files.lock().unwrap();
+        let file = self
+            .file(&mut files, Self::region_of(pos), true)?
+            .expect("region file is created on write");
+        let mut entry = Self::read_entry(file, pos)?;
+
+        // reuse the existing sectors if the payload still fits, otherwise append
+        let sectors = (length as u64).div_ceil(SECTOR_SIZE);
+        if entry.sector == 0 || sectors > entry.sectors() {
+            let end = file.metadata()?.len().div_ceil(SECTOR_SIZE);
+            entry.sector = u32::try_from(end).context("region file too large")?;
+        }
+        entry.length = length;
+
+        file.seek(SeekFrom::Start(entry.sector as u64 * SECTOR_SIZE))?;
+        file.write_all(payload)?;
+        Self::write_entry(file, pos, entry)?;
+        Ok(())
+    }
+
+    /// Write the offset table entry of the given chunk.
+    fn write_entry(file: &mut File, pos: ChunkPos, entry: Entry) -> anyhow::Result<()> {
+        file.seek(SeekFrom::Start(Self::entry_offset(pos)))?;
+        file.write_all(&entry.sector.to_le_bytes())?;
+        file.write_all(&entry.length.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+impl ChunkStore for RegionStore {
+    fn read_chunk(&self, pos: ChunkPos) -> anyhow::Result<Option<Chunk>> {
+        let mut files = self.files.lock().unwrap();
+        let Some(file) = self.file(&mut files, Self::region_of(pos), false)? else {
+            return Ok(None);
+        };
+        let entry = Self::read_entry(file, pos)?;
+        if entry.sector == 0 {
+            return Ok(None);
+        }
+        // a corrupt entry must not size the allocation below
+        let start = entry.sector as u64;
+        if start < HEADER_SECTORS {
+            bail!("corrupt chunk at {:?}: overlaps the offset table", pos);
+        }
+        if start * SECTOR_SIZE + entry.length as u64 > file.metadata()?.len() {
+            bail!(
+                "corrupt chunk at {:?}: runs past the end of its region file",
+                pos
+            );
+        }
+
+        let mut payload = vec![0; entry.length as usize];
+        file.seek(SeekFrom::Start(entry.sector as u64 * SECTOR_SIZE))?;
+        file.read_exact(&mut payload)?;
+        let chunk = self
+            .codec
+            .decode(&payload)
+            .with_context(|| format!("corrupt chunk at {:?}", pos))?;
+        Ok(Some(chunk))
+    }
+
+    fn write_chunk(&self, chunk: &Chunk) -> anyhow::Result<()> {
+        self.write_payload(chunk.position, &self.codec.encode(chunk)?)
+    }
+
+    fn positions(&self) -> anyhow::Result<Vec<ChunkPos>> {
+        let mut positions = Vec::new();
+        for region in self.regions()? {
+            let mut files = self.files.lock().unwrap();
+            let Some(file) = self.file(&mut files, region, false)? else {
+                continue;
+            };
+
+            let mut header = vec![0; HEADER_SIZE as usize];
+            file.seek(SeekFrom::Start(0))?;
+            file.read_exact(&mut header)?;
+            positions.extend(Self::chunks_of(region).filter(|&pos| {
+                let offset = Self::entry_offset(pos) as usize;
+                header[offset..offset + 4] != [0; 4]
+            }));
+        }
+        Ok(positions)
+    }
+
+    fn remove_chunk(&self, pos: ChunkPos) -> anyhow::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        if let Some(file) = self.file(&mut files, Self::region_of(pos), false)? {
+            Self::write_entry(file, pos, Entry::default())?;
+        }
+        Ok(())
+    }
+
+    /// Check that every chunk's payload lies past the offset table, within the file, and clear of
+    /// every other payload. Repairing clears the offending entries, so those chunks are generated
+    /// afresh; of two overlapping payloads, the one starting later is cleared.
+    fn check_layout(&self, repair: bool) -> anyhow::Result<Vec<String>> {
+        let mut problems = Vec::new();
+        for region in self.regions()? {
+            let mut files = self.files.lock().unwrap();
+            let Some(file) = self.file(&mut files, region, false)? else {
+                continue;
+            };
+            let length = file.metadata()?.len();
+
+            let mut entries = Vec::new();
+            for pos in Self::chunks_of(region) {
+                let entry = Self::read_entry(file, pos)?;
+                if entry.sector != 0 {
+                    entries.push((pos, entry));
+                }
+            }
+            entries.sort_by_key(|(_, entry)| entry.sector);
+
+            let mut bad = Vec::new();
+            let mut end = HEADER_SECTORS;
+            for (pos, entry) in entries {
+                let start = entry.sector as u64;
+                let problem = if start < HEADER_SECTORS {
+                    Some("overlaps the offset table")
+                } else if entry.length == 0 {
+                    Some("has an empty payload")
+                } else if start * SECTOR_SIZE + entry.length as u64 > length {
+                    Some("runs past the end of its region file")
+                } else if start < end {
+                    Some("overlaps another chunk")
+                } else {
+                    None
+                };
+                match problem {
+                    Some(problem) => {
+                        problems.push(format!("chunk {:?} {}", pos, problem));
+                        bad.push(pos);
+                    }
+                    None => end = start + entry.sectors(),
+                }
+            }
+            if repair {
+                for pos in bad {
+                    Self::write_entry(file, pos, Entry::default())?;
+                }
+            }
+        }
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{
+        chunk::{BlockPos, BlockType},
+        storage::codec::Compression,
+    };
+
+    /// A store in a fresh directory under the temporary directory, removed when dropped.
+    struct TestStore {
+        root: PathBuf,
+        store: RegionStore,
+    }
+
+    impl TestStore {
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("chunky-region-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            // uncompressed, so payload sizes follow the number of blocks
+            let codec = ChunkCodec::new(Compression::None, None);
+            let store = RegionStore::open(&root, codec).unwrap();
+            Self { root, store }
+        }
+    }
+
+    impl Drop for TestStore {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    /// Return the blocks of a chunk, for comparing chunks.
+    fn blocks_of(chunk: &Chunk) -> Vec<(BlockPos, BlockType)> {
+        chunk.blocks().collect_vec()
+    }
+
+    #[test]
+    fn round_trip() {
+        let test = TestStore::new("round-trip");
+        // negative coordinates land in a different region, at the far end of its offset table
+        let positions = [ChunkPos::new(0, 0, 0), ChunkPos::new(-1, 3, -40)];
+        let chunks = positions.map(|pos| {
+            let mut chunk = Chunk::empty(pos);
+            chunk.set_block(BlockPos::new(1, 2, 3), BlockType::STONE);
+            chunk.set_block(BlockPos::new(4, 5, 6), BlockType::GLASS);
+            chunk
+        });
+        for chunk in &chunks {
+            test.store.write_chunk(chunk).unwrap();
+        }
+
+        for chunk in &chunks {
+            let read = test.store.read_chunk(chunk.position).unwrap().unwrap();
+            assert_eq!(read.position, chunk.position);
+            assert_eq!(blocks_of(&read), blocks_of(chunk));
+        }
+        let positions = test.store.positions().unwrap();
+        assert_eq!(positions.len(), 2);
+        assert!(chunks
+            .iter()
+            .all(|chunk| positions.contains(&chunk.position)));
+        assert!(test.store.check_layout(false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn overwrite_with_larger_payload() {
+        let test = TestStore::new("overwrite");
+        let (first, second) = (ChunkPos::new(0, 0, 0), ChunkPos::new(1, 0, 0));
+        let mut small = Chunk::empty(first);
+        small.set_block(BlockPos::new(0, 0, 0), BlockType::DIRT);
+        let mut neighbour = Chunk::empty(second);
+        neighbour.set_block(BlockPos::new(7, 7, 7), BlockType::SAND);
+        test.store.write_chunk(&small).unwrap();
+        test.store.write_chunk(&neighbour).unwrap();
+
+        // a checkerboard spans many sectors, so it no longer fits where the small chunk was
+        let mut large = Chunk::empty(first);
+        large.set_blocks(BlockPos::all().filter_map(|pos| {
+            ((pos.x + pos.y + pos.z) % 2 == 0).then_some((pos, BlockType::STONE))
+        }));
+        let codec = ChunkCodec::new(Compression::None, None);
+        assert!(codec.encode(&large).unwrap().len() as u64 > SECTOR_SIZE);
+        test.store.write_chunk(&large).unwrap();
+
+        let read = test.store.read_chunk(first).unwrap().unwrap();
+        assert_eq!(blocks_of(&read), blocks_of(&large));
+        let read = test.store.read_chunk(second).unwrap().unwrap();
+        assert_eq!(blocks_of(&read), blocks_of(&neighbour));
+        assert!(test.store.check_layout(false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_absent_chunk() {
+        let test = TestStore::new("absent");
+        // neither the region file nor the chunk exists
+        assert!(test
+            .store
+            .read_chunk(ChunkPos::new(0, 0, 0))
+            .unwrap()
+            .is_none());
+
+        // the region file exists, but the chunk's entry is empty
+        test.store
+            .write_chunk(&Chunk::empty(ChunkPos::new(1, 1, 1)))
+            .unwrap();
+        assert!(test
+            .store
+            .read_chunk(ChunkPos::new(0, 0, 0))
+            .unwrap()
+            .is_none());
+
+        // removing a chunk clears its entry
+        test.store.remove_chunk(ChunkPos::new(1, 1, 1)).unwrap();
+        assert!(test
+            .store
+            .read_chunk(ChunkPos::new(1, 1, 1))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn read_corrupt_entry() {
+        let test = TestStore::new("corrupt");
+        let pos = ChunkPos::new(0, 0, 0);
+        test.store.write_chunk(&Chunk::empty(pos)).unwrap();
+        let overwrite = |entry: fn(Entry) -> Entry| {
+            let mut files = test.store.files.lock().unwrap();
+            let file = test
+                .store
+                .file(&mut files, RegionStore::region_of(pos), false)
+                .unwrap()
+                .unwrap();
+            let old = RegionStore::read_entry(file, pos).unwrap();
+            RegionStore::write_entry(file, pos, entry(old)).unwrap();
+        };
+
+        // an entry pointing into the offset table
+        overwrite(|entry| Entry { sector: 1, ..entry });
+        assert!(test.store.read_chunk(pos).is_err());
+        overwrite(|entry| Entry {
+            // the first sector past the offset table, which is where the chunk was written
+            sector: HEADER_SECTORS as u32,
+            ..entry
+        });
+
+        // an entry claiming far more bytes than the file holds, read without allocating them
+        overwrite(|entry| Entry {
+            length: u32::MAX,
+            ..entry
+        });
+        assert!(test.store.read_chunk(pos).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use std::{path::Path, sync::Mutex};
+
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{codec::ChunkCodec, ChunkStore};
+use crate::chunk::{Chunk, ChunkPos};
+
+/// A chunk store backed by a SQLite database, with one row per chunk.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+    codec: ChunkCodec,
+}
+
+impl SqliteStore {
+    /// Open the database at the given path, creating it if it does not exist.
+    pub fn open(path: impl AsRef<Path>, codec: ChunkCodec) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection =
+            Connection::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        connection.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            CREATE TABLE IF NOT EXISTS chunks (
+                x INTEGER NOT NULL,
+                y INTEGER NOT NULL,
+                z INTEGER NOT NULL,
+                data BLOB NOT NULL,
+                PRIMARY KEY (x, y, z)
+            );",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            codec,
+        })
+    }
+}
+
+impl ChunkStore for SqliteStore {
+    fn read_chunk(&self, pos: ChunkPos) -> anyhow::Result<Option<Chunk>> {
+        let connection = self.connection.lock().unwrap();
+        let data: Option<Vec<u8>> = connection
+            .query_row(
+                "SELECT data FROM chunks WHERE x = ?1 AND y = ?2 AND z = ?3",
+                params![pos.x, pos.y, pos.z],
+                |row| row.get(0),
+            )
+            .optional()?;
+        data.map(|data| {
+            self.codec
+                .decode(&data)
+                .with_context(|| format!("corrupt chunk at {:?}", pos))
+        })
+        .transpose()
+    }
+
+    fn write_chunk(&self, chunk: &Chunk) -> anyhow::Result<()> {
+        let data = self.codec.encode(chunk)?;
+        let pos = chunk.position;
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO chunks (x, y, z, data) VALUES (?1, ?2, ?3, ?4)",
+            params![pos.x, pos.y, pos.z, data],
+        )?;
+        Ok(())
+    }
+
+    fn remove_chunk(&self, pos: ChunkPos) -> anyhow::Result<()> {
+        self.connection.lock().unwrap().execute(
+            "DELETE FROM chunks WHERE x = ?1 AND y = ?2 AND z = ?3",
+            params![pos.x, pos.y, pos.z],
+        )?;
+        Ok(())
+    }
+
+    fn positions(&self) -> anyhow::Result<Vec<ChunkPos>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT x, y, z FROM chunks")?;
+        let positions = statement
+            .query_map([], |row| {
+                Ok(ChunkPos::new(row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(positions)
+    }
+}
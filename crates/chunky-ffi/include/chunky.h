@@ -0,0 +1,148 @@
+#ifndef CHUNKY_H
+#define CHUNKY_H
+
+/* Generated by cbindgen from crates/chunky-ffi. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * How a chunk is turned into a mesh.
+ */
+typedef enum ChunkyMeshingMode {
+  /**
+   * A cube for every block, culling hidden faces.
+   */
+  CHUNKY_MESHING_MODE_BLOCKY,
+  /**
+   * A smooth surface through the chunk's density field.
+   */
+  CHUNKY_MESHING_MODE_SMOOTH,
+} ChunkyMeshingMode;
+
+/**
+ * A world of generated chunks, owned by the caller.
+ */
+typedef struct ChunkyWorld ChunkyWorld;
+
+/**
+ * The buffers of a chunk's mesh, as an indexed triangle list in the chunk's local coordinates.
+ * Positions and normals hold three floats per vertex, and colours four. The buffers are owned by
+ * the library and must be released with [`chunky_mesh_free`].
+ */
+typedef struct ChunkyMesh {
+  float *positions;
+  float *normals;
+  float *colors;
+  uintptr_t vertex_count;
+  uint32_t *indices;
+  uintptr_t index_count;
+} ChunkyMesh;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a world that generates terrain from the given seed.
+ */
+struct ChunkyWorld *chunky_world_new(uint32_t seed);
+
+/**
+ * Destroy a world and every chunk in it.
+ *
+ * # Safety
+ *
+ * The world must have come from [`chunky_world_new`] and not have been freed already. Null is
+ * ignored.
+ */
+void chunky_world_free(struct ChunkyWorld *world);
+
+/**
+ * Generate the chunk at the given chunk coordinates, replacing it if it was already generated.
+ * Features reaching into neighbouring chunks are placed in them once they are generated too.
+ * Returns false, and generates nothing, if the coordinates are beyond the range of a chunk key.
+ *
+ * # Safety
+ *
+ * The world must be a live handle from [`chunky_world_new`].
+ */
+bool chunky_world_generate_chunk(struct ChunkyWorld *world, int64_t x, int64_t y, int64_t z);
+
+/**
+ * Check if the chunk at the given chunk coordinates has been generated. Coordinates beyond the
+ * range of a chunk key never have been.
+ *
+ * # Safety
+ *
+ * The world must be a live handle from [`chunky_world_new`].
+ */
+bool chunky_world_has_chunk(const struct ChunkyWorld *world, int64_t x, int64_t y, int64_t z);
+
+/**
+ * Get the block at the given world block coordinates, or -1 if its chunk has not been generated.
+ *
+ * # Safety
+ *
+ * The world must be a live handle from [`chunky_world_new`].
+ */
+int32_t chunky_world_get_block(const struct ChunkyWorld *world, int32_t x, int32_t y, int32_t z);
+
+/**
+ * Set the block at the given world block coordinates. Returns false if the block is unknown or
+ * its chunk has not been generated.
+ *
+ * # Safety
+ *
+ * The world must be a live handle from [`chunky_world_new`].
+ */
+bool chunky_world_set_block(struct ChunkyWorld *world,
+                            int32_t x,
+                            int32_t y,
+                            int32_t z,
+                            uint32_t block);
+
+/**
+ * Build the mesh of the chunk at the given chunk coordinates into `out`. Neighbours that have not
+ * been generated are treated as solid, so the faces against them are culled. Returns false, and
+ * leaves `out` empty, if the chunk has not been generated.
+ *
+ * # Safety
+ *
+ * The world must be a live handle from [`chunky_world_new`], and `out` must point to writable
+ * memory for a [`ChunkyMesh`]. Any buffers already in `out` are not freed.
+ */
+bool chunky_world_mesh_chunk(const struct ChunkyWorld *world,
+                             int64_t x,
+                             int64_t y,
+                             int64_t z,
+                             enum ChunkyMeshingMode mode,
+                             struct ChunkyMesh *out);
+
+/**
+ * Release the buffers of a mesh built by [`chunky_world_mesh_chunk`], leaving it empty.
+ *
+ * # Safety
+ *
+ * The mesh must have been filled in by [`chunky_world_mesh_chunk`], and its buffers and counts
+ * left unchanged. Null is ignored.
+ */
+void chunky_mesh_free(struct ChunkyMesh *mesh);
+
+/**
+ * Return the number of block types. Blocks are numbered from zero up to this.
+ */
+uint32_t chunky_block_count(void);
+
+/**
+ * Check if the given block hides the blocks behind it, or false if the block is unknown.
+ */
+bool chunky_block_is_opaque(uint32_t block);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHUNKY_H */
@@ -0,0 +1,318 @@
+//! A C ABI over the core voxel engine, so tools and engines written in other languages can
+//! generate, edit, and mesh chunks. The header in `include/chunky.h` is generated from this crate
+//! with cbindgen.
+//!
+//! Worlds are opaque handles created with [`chunky_world_new`] and destroyed with
+//! [`chunky_world_free`]. Blocks are identified by their number in the block registry, and
+//! block coordinates are world block coordinates.
+
+use std::ptr;
+
+use chunky_core::chunk::{
+    generation::{
+        pipeline::GenerationPipeline,
+        structure::{self, PendingBlocks},
+    },
+    key::ChunkMap,
+    mesh::{self, LightingMode, MeshData, MeshingMode},
+    registry::{registry, Transparency},
+    BlockPos, BlockType, Chunk, ChunkPos,
+};
+use glam::IVec3;
+use noise::OpenSimplex;
+
+/// A world of generated chunks, owned by the caller.
+pub struct ChunkyWorld {
+    /// The noise terrain is generated from.
+    noise: OpenSimplex,
+    /// The stages chunks are generated through, kept so their caches last between chunks.
+    pipeline: GenerationPipeline,
+    /// The chunks that have been generated.
+    chunks: ChunkMap<Chunk>,
+    /// Blocks of features that reach into chunks which have not been generated yet.
+    pending: PendingBlocks,
+}
+
+/// How a chunk is turned into a mesh.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkyMeshingMode {
+    /// A cube for every block, culling hidden faces.
+    Blocky,
+    /// A smooth surface through the chunk's density field.
+    Smooth,
+}
+
+impl From<ChunkyMeshingMode> for MeshingMode {
+    fn from(mode: ChunkyMeshingMode) -> Self {
+        match mode {
+            ChunkyMeshingMode::Blocky => MeshingMode::Blocky,
+            ChunkyMeshingMode::Smooth => MeshingMode::Smooth,
+        }
+    }
+}
+
+/// The buffers of a chunk's mesh, as an indexed triangle list in the chunk's local coordinates.
+/// Positions and normals hold three floats per vertex, and colours four. The buffers are owned by
+/// the library and must be released with [`chunky_mesh_free`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct ChunkyMesh {
+    pub positions: *mut f32,
+    pub normals: *mut f32,
+    pub colors: *mut f32,
+    pub vertex_count: usize,
+    pub indices: *mut u32,
+    pub index_count: usize,
+}
+
+impl ChunkyMesh {
+    /// A mesh with no buffers.
+    const EMPTY: Self = Self {
+        positions: ptr::null_mut(),
+        normals: ptr::null_mut(),
+        colors: ptr::null_mut(),
+        vertex_count: 0,
+        indices: ptr::null_mut(),
+        index_count: 0,
+    };
+}
+
+impl From<MeshData> for ChunkyMesh {
+    fn from(data: MeshData) -> Self {
+        Self {
+            vertex_count: data.positions.len(),
+            positions: into_raw(data.positions.into_iter().flatten().collect()),
+            normals: into_raw(data.normals.into_iter().flatten().collect()),
+            colors: into_raw(data.colors.into_iter().flatten().collect()),
+            index_count: data.indices.len(),
+            indices: into_raw(data.indices),
+        }
+    }
+}
+
+/// Hand a buffer over to the caller. Its length is recorded alongside it in [`ChunkyMesh`].
+fn into_raw<T>(values: Vec<T>) -> *mut T {
+    Box::into_raw(values.into_boxed_slice()) as *mut T
+}
+
+/// Take back a buffer handed over by [`into_raw`].
+///
+/// # Safety
+///
+/// The pointer must have come from [`into_raw`] with a buffer of the given length, and not have
+/// been freed already.
+unsafe fn free_raw<T>(values: *mut T, len: usize) {
+    if !values.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(values, len)));
+    }
+}
+
+/// Create a world that generates terrain from the given seed.
+#[no_mangle]
+pub extern "C" fn chunky_world_new(seed: u32) -> *mut ChunkyWorld {
+    Box::into_raw(Box::new(ChunkyWorld {
+        noise: OpenSimplex::new(seed),
+        pipeline: GenerationPipeline::default(),
+        chunks: ChunkMap::default(),
+        pending: PendingBlocks::default(),
+    }))
+}
+
+/// Destroy a world and every chunk in it.
+///
+/// # Safety
+///
+/// The world must have come from [`chunky_world_new`] and not have been freed already. Null is
+/// ignored.
+#[no_mangle]
+pub unsafe extern "C" fn chunky_world_free(world: *mut ChunkyWorld) {
+    if !world.is_null() {
+        drop(Box::from_raw(world));
+    }
+}
+
+/// Generate the chunk at the given chunk coordinates, replacing it if it was already generated.
+/// Features reaching into neighbouring chunks are placed in them once they are generated too.
+/// Returns false, and generates nothing, if the coordinates are beyond the range of a chunk key.
+///
+/// # Safety
+///
+/// The world must be a live handle from [`chunky_world_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chunky_world_generate_chunk(
+    world: *mut ChunkyWorld,
+    x: i64,
+    y: i64,
+    z: i64,
+) -> bool {
+    let pos = ChunkPos::new(x, y, z);
+    let (Some(world), Some(key)) = (world.as_mut(), pos.checked_key()) else {
+        return false;
+    };
+    let mut chunk = Chunk::empty(pos);
+    let spilled = world.pipeline.generate(&mut chunk, &world.noise);
+    structure::place(&mut chunk, world.pending.take(pos));
+    world.chunks.insert(key, chunk);
+    for (pos, block_pos, block) in spilled {
+        // features at the edge of the range reach into chunks that cannot be stored
+        let Some(key) = pos.checked_key() else {
+            continue;
+        };
+        match world.chunks.get_mut(&key) {
+            Some(chunk) => structure::place(chunk, [(block_pos, block)]),
+            None => world.pending.defer((pos, block_pos, block)),
+        }
+    }
+    true
+}
+
+/// Check if the chunk at the given chunk coordinates has been generated. Coordinates beyond the
+/// range of a chunk key never have been.
+///
+/// # Safety
+///
+/// The world must be a live handle from [`chunky_world_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chunky_world_has_chunk(
+    world: *const ChunkyWorld,
+    x: i64,
+    y: i64,
+    z: i64,
+) -> bool {
+    let key = ChunkPos::new(x, y, z).checked_key();
+    world
+        .as_ref()
+        .zip(key)
+        .is_some_and(|(world, key)| world.chunks.contains_key(&key))
+}
+
+/// Get the block at the given world block coordinates, or -1 if its chunk has not been generated.
+///
+/// # Safety
+///
+/// The world must be a live handle from [`chunky_world_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chunky_world_get_block(
+    world: *const ChunkyWorld,
+    x: i32,
+    y: i32,
+    z: i32,
+) -> i32 {
+    let pos = IVec3::new(x, y, z);
+    world
+        .as_ref()
+        .and_then(|world| world.chunks.get(&ChunkPos::from_world_block(pos).key()))
+        .map_or(-1, |chunk| {
+            let block = *chunk.block_at(BlockPos::from_world_block(pos));
+            block.id() as i32
+        })
+}
+
+/// Set the block at the given world block coordinates. Returns false if the block is unknown or
+/// its chunk has not been generated.
+///
+/// # Safety
+///
+/// The world must be a live handle from [`chunky_world_new`].
+#[no_mangle]
+pub unsafe extern "C" fn chunky_world_set_block(
+    world: *mut ChunkyWorld,
+    x: i32,
+    y: i32,
+    z: i32,
+    block: u32,
+) -> bool {
+    let pos = IVec3::new(x, y, z);
+    let Some(block) = u16::try_from(block)
+        .ok()
+        .map(BlockType::from_id)
+        .filter(|&block| registry().get(block).is_some())
+    else {
+        return false;
+    };
+    let Some(chunk) = world
+        .as_mut()
+        .and_then(|world| world.chunks.get_mut(&ChunkPos::from_world_block(pos).key()))
+    else {
+        return false;
+    };
+    chunk.set_block(BlockPos::from_world_block(pos), block);
+    true
+}
+
+/// Build the mesh of the chunk at the given chunk coordinates into `out`. Neighbours that have not
+/// been generated are treated as solid, so the faces against them are culled. Returns false, and
+/// leaves `out` empty, if the chunk has not been generated.
+///
+/// # Safety
+///
+/// The world must be a live handle from [`chunky_world_new`], and `out` must point to writable
+/// memory for a [`ChunkyMesh`]. Any buffers already in `out` are not freed.
+#[no_mangle]
+pub unsafe extern "C" fn chunky_world_mesh_chunk(
+    world: *const ChunkyWorld,
+    x: i64,
+    y: i64,
+    z: i64,
+    mode: ChunkyMeshingMode,
+    out: *mut ChunkyMesh,
+) -> bool {
+    let Some(out) = out.as_mut() else {
+        return false;
+    };
+    *out = ChunkyMesh::EMPTY;
+    let pos = ChunkPos::new(x, y, z);
+    let Some((world, chunk)) = world
+        .as_ref()
+        .and_then(|world| Some((world, world.chunks.get(&pos.checked_key()?)?)))
+    else {
+        return false;
+    };
+    let neighbours = ChunkPos::FACE_NEIGHBOURS.map(|dir| {
+        let neighbour = (pos + dir)
+            .checked_key()
+            .and_then(|key| world.chunks.get(&key));
+        match neighbour {
+            Some(neighbour) => neighbour.clone(),
+            None => Chunk::empty(pos + dir).filled(BlockType::STONE),
+        }
+    });
+    *out =
+        mesh::build_with_neighbours(chunk, &neighbours, mode.into(), LightingMode::Vertex).into();
+    true
+}
+
+/// Release the buffers of a mesh built by [`chunky_world_mesh_chunk`], leaving it empty.
+///
+/// # Safety
+///
+/// The mesh must have been filled in by [`chunky_world_mesh_chunk`], and its buffers and counts
+/// left unchanged. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn chunky_mesh_free(mesh: *mut ChunkyMesh) {
+    let Some(mesh) = mesh.as_mut() else {
+        return;
+    };
+    let vertices = mesh.vertex_count;
+    free_raw(mesh.positions, vertices * 3);
+    free_raw(mesh.normals, vertices * 3);
+    free_raw(mesh.colors, vertices * 4);
+    free_raw(mesh.indices, mesh.index_count);
+    *mesh = ChunkyMesh::EMPTY;
+}
+
+/// Return the number of block types. Blocks are numbered from zero up to this.
+#[no_mangle]
+pub extern "C" fn chunky_block_count() -> u32 {
+    registry().iter().count() as u32
+}
+
+/// Check if the given block hides the blocks behind it, or false if the block is unknown.
+#[no_mangle]
+pub extern "C" fn chunky_block_is_opaque(block: u32) -> bool {
+    u16::try_from(block)
+        .ok()
+        .and_then(|block| registry().get(BlockType::from_id(block)))
+        .is_some_and(|definition| definition.transparency == Transparency::Opaque)
+}
@@ -0,0 +1,147 @@
+use bevy::{asset::io::file::FileAssetReader, audio::Volume, prelude::*, utils::HashMap};
+
+use crate::chunk::{blocks::BlockRegistry, BlockBroken, BlockPlaced, Chunks};
+
+/// The directory sounds are read from, relative to the base asset path.
+const ASSETS_PATH: &str = "assets";
+
+/// The distance walked between footsteps, in blocks.
+const STEP_LENGTH: f32 = 1.6;
+
+/// The loudness of a footstep, next to that of breaking and placing blocks.
+const STEP_VOLUME: f32 = 0.4;
+
+/// How far below an entity's feet the block it stands on is looked for, in blocks.
+const GROUND_DEPTH: f32 = 0.05;
+
+/// A plugin playing the sounds blocks make when they are broken or placed, and when entities with
+/// [`Footsteps`] walk on them, positioned where they happen.
+pub struct BlockAudioPlugin;
+
+impl Plugin for BlockAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockSoundHandles>()
+            .add_systems(Startup, load_block_sounds)
+            .add_systems(
+                Update,
+                (
+                    play_block_sounds
+                        .run_if(on_event::<BlockBroken>().or_else(on_event::<BlockPlaced>())),
+                    play_footsteps,
+                ),
+            );
+    }
+}
+
+/// A component for entities whose footsteps are heard as they walk over blocks.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Footsteps {
+    /// The distance walked since the last footstep, in blocks.
+    walked: f32,
+    /// Where the entity was last frame, if it has been seen before.
+    last: Option<Vec3>,
+}
+
+/// The loaded sounds of every registered block, by path, kept so they stay loaded.
+#[derive(Default, Resource)]
+struct BlockSoundHandles(HashMap<String, Handle<AudioSource>>);
+
+impl BlockSoundHandles {
+    /// Return the sound at the given path, if it has finished loading.
+    fn get(
+        &self,
+        path: Option<&str>,
+        sources: &Assets<AudioSource>,
+    ) -> Option<Handle<AudioSource>> {
+        let handle = self.0.get(path?)?;
+        sources.contains(handle).then(|| handle.clone())
+    }
+}
+
+/// Load the sounds of every registered block. Sounds whose files are missing are skipped, so
+/// blocks can name sounds that have not been recorded yet.
+fn load_block_sounds(
+    asset_server: Res<AssetServer>,
+    blocks: Res<BlockRegistry>,
+    mut handles: ResMut<BlockSoundHandles>,
+) {
+    let root = FileAssetReader::get_base_path().join(ASSETS_PATH);
+    for (_, definition) in blocks.iter() {
+        for path in definition.sounds.iter() {
+            if handles.0.contains_key(path) || !root.join(path).is_file() {
+                continue;
+            }
+            handles
+                .0
+                .insert(path.to_owned(), asset_server.load(path.to_owned()));
+        }
+    }
+}
+
+/// Play a sound at the centre of the given block, fading with distance from the listener.
+fn play_at(commands: &mut Commands, sound: Handle<AudioSource>, pos: IVec3, volume: f32) {
+    commands
+        .spawn(AudioSourceBundle {
+            source: sound,
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new(volume))
+                .with_spatial(true),
+        })
+        .insert(TransformBundle::from_transform(
+            Transform::from_translation(pos.as_vec3() + Vec3::splat(0.5)),
+        ));
+}
+
+/// Play the sounds of blocks that have been broken or placed.
+fn play_block_sounds(
+    mut commands: Commands,
+    handles: Res<BlockSoundHandles>,
+    sources: Res<Assets<AudioSource>>,
+    mut broken: EventReader<BlockBroken>,
+    mut placed: EventReader<BlockPlaced>,
+) {
+    let broken = broken.read().map(|event| {
+        let sounds = &event.block.definition().sounds;
+        (event.pos, sounds.break_sound.as_deref())
+    });
+    let placed = placed.read().map(|event| {
+        let sounds = &event.block.definition().sounds;
+        (event.pos, sounds.place_sound.as_deref())
+    });
+    for (pos, path) in broken.chain(placed) {
+        if let Some(sound) = handles.get(path, &sources) {
+            play_at(&mut commands, sound, pos, 1.0);
+        }
+    }
+}
+
+/// Play a footstep each time an entity with [`Footsteps`] walks [`STEP_LENGTH`] across the ground,
+/// in the sound of the block it is standing on. Distance covered in the air does not count.
+fn play_footsteps(
+    mut commands: Commands,
+    chunks: Res<Chunks>,
+    handles: Res<BlockSoundHandles>,
+    sources: Res<Assets<AudioSource>>,
+    mut walkers: Query<(&mut Footsteps, &GlobalTransform)>,
+) {
+    for (mut footsteps, transform) in walkers.iter_mut() {
+        let feet = transform.translation();
+        let last = footsteps.last.replace(feet).unwrap_or(feet);
+        let ground = (feet - Vec3::Y * GROUND_DEPTH).floor().as_ivec3();
+        let Some(block) = chunks
+            .block_at_world_block(ground)
+            .filter(|block| block.definition().solid)
+        else {
+            continue;
+        };
+        footsteps.walked += (feet - last).xz().length();
+        if footsteps.walked < STEP_LENGTH {
+            continue;
+        }
+        footsteps.walked = 0.0;
+        let path = block.definition().sounds.step_sound.as_deref();
+        if let Some(sound) = handles.get(path, &sources) {
+            play_at(&mut commands, sound, ground, STEP_VOLUME);
+        }
+    }
+}
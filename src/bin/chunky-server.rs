@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use bevy::{
+    app::ScheduleRunnerPlugin, diagnostic::LogDiagnosticsPlugin, log::LogPlugin, prelude::*,
+};
+use chunky::{
+    chunk::{
+        generation::{
+            preset_from_env, seed::TerrainNoise, worker::GenerationBackend, world_preset,
+            GenerationStages,
+        },
+        metrics::{CACHE_HIT_RATE, CHUNKS_EVICTED, LOAD_LATENCY_P50, LOAD_LATENCY_P99},
+        ChunkPlugin, Chunks,
+    },
+    crash::{self, CrashReportPlugin},
+    net::{
+        metrics,
+        server::{ServerConfig, ServerPlugin, TICK_RATE},
+    },
+    storage::{metrics as storage_metrics, AutosaveSettings, IntegritySettings, WorldStorage},
+};
+
+fn main() -> anyhow::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "server.toml".into());
+    let config = ServerConfig::load(path)?;
+    let storage = WorldStorage::open(config.storage, &config.world_path, config.compression)?;
+    let autosave = AutosaveSettings {
+        interval: Duration::from_secs(config.autosave_interval),
+        snapshot_interval: Duration::from_secs(config.snapshot_interval),
+        chunks_per_frame: config.autosave_chunks_per_tick,
+        ..default()
+    };
+    let requested = match config.preset {
+        Some(preset) => Some(preset),
+        None => preset_from_env()?,
+    };
+    let preset = world_preset(&config.world_path, requested)?;
+    let noise = match config.seed {
+        Some(seed) => TerrainNoise::new(seed),
+        None => TerrainNoise::from_env()?,
+    };
+    let mut chunks = Chunks::with_memory_budget(
+        config
+            .chunk_memory_budget
+            .map(|megabytes| megabytes * 1024 * 1024),
+    );
+    chunks.set_max_age(
+        config
+            .chunk_max_age
+            .map(|seconds| (seconds as f64 * TICK_RATE) as u64),
+    );
+    chunks.set_height_bounds(config.min_chunk_y, config.max_chunk_y);
+
+    App::new()
+        .add_plugins((
+            MinimalPlugins
+                .set(config.tasks.plugin())
+                .set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                    1.0 / TICK_RATE,
+                ))),
+            LogPlugin {
+                custom_layer: crash::log_layer,
+                ..default()
+            },
+            CrashReportPlugin,
+            ChunkPlugin { headless: true },
+            ServerPlugin,
+            LogDiagnosticsPlugin {
+                wait_duration: Duration::from_secs(10),
+                filter: Some(vec![
+                    metrics::BYTES_IN,
+                    metrics::BYTES_OUT,
+                    metrics::CHUNKS_SENT,
+                    metrics::DELTAS_SENT,
+                    metrics::ROUND_TRIP_TIME,
+                    CACHE_HIT_RATE,
+                    CHUNKS_EVICTED,
+                    LOAD_LATENCY_P50,
+                    LOAD_LATENCY_P99,
+                    storage_metrics::CHUNKS_PENDING,
+                    storage_metrics::SAVE_LAG,
+                    storage_metrics::WRITE_TIME,
+                ]),
+                ..default()
+            },
+        ))
+        .insert_resource(config)
+        .insert_resource(chunks)
+        .insert_resource(storage)
+        .insert_resource(autosave)
+        .insert_resource(IntegritySettings::from_env()?)
+        .insert_resource(GenerationBackend::from_env(preset))
+        .insert_resource(GenerationStages::for_preset(preset)?)
+        .insert_resource(noise)
+        .run();
+
+    Ok(())
+}
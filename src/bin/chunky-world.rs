@@ -0,0 +1,201 @@
+use std::{path::PathBuf, time::Instant};
+
+use anyhow::{bail, Context};
+use chunky::{
+    chunk::{
+        generation::{
+            pipeline_from_env, preset_from_env,
+            seed::TerrainNoise,
+            settings::read_terrain_settings,
+            structure::{self, PendingBlocks},
+            worker::generate_local,
+            world_preset,
+        },
+        key::ChunkSet,
+        mesh::MeshingMode,
+        ChunkPos,
+    },
+    storage::{
+        codec::{train_dictionary, ChunkCodec, Compression},
+        metadata::{self, WorldMetadata},
+        StorageBackend,
+    },
+};
+use itertools::{iproduct, Itertools};
+
+/// The number of chunks sampled when training a dictionary.
+const DEFAULT_SAMPLES: usize = 1000;
+
+/// The maximum size of a trained dictionary, in bytes.
+const DICTIONARY_SIZE: usize = 64 * 1024;
+
+const USAGE: &str = "usage:
+    chunky-world train-dictionary <world> [region|sqlite] [samples]
+    chunky-world set-meshing <world> <blocky|smooth>
+    chunky-world pregen <world> radius=<chunks> [region|sqlite]";
+
+/// Maintenance commands for saved worlds.
+fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["train-dictionary", world, rest @ ..] => {
+            let backend = match rest.first() {
+                Some(backend) => backend.parse()?,
+                None => StorageBackend::Region,
+            };
+            let samples = match rest.get(1) {
+                Some(samples) => samples.parse().context("invalid sample count")?,
+                None => DEFAULT_SAMPLES,
+            };
+            train(PathBuf::from(world), backend, samples)
+        }
+        ["set-meshing", world, mode] => {
+            let world_path = PathBuf::from(world);
+            let mut metadata = WorldMetadata::load(&world_path)?;
+            metadata.meshing = mode.parse::<MeshingMode>()?;
+            metadata.save(&world_path)
+        }
+        ["pregen", world, radius, rest @ ..] => {
+            let radius = radius
+                .strip_prefix("radius=")
+                .context("expected radius=<chunks>")?
+                .parse()
+                .context("invalid radius")?;
+            let backend = match rest.first() {
+                Some(backend) => backend.parse()?,
+                None => StorageBackend::Region,
+            };
+            pregen(PathBuf::from(world), backend, radius)
+        }
+        _ => bail!(USAGE),
+    }
+}
+
+/// Train a compression dictionary from a sample of the world's chunks, then recompress every chunk
+/// with it. Dictionaries only apply to zstd, so every chunk is rewritten with zstd. The new
+/// dictionary is kept beside the world's metadata until every chunk has been recompressed, so both
+/// it and the old one can be read if recompression is interrupted, and running this again finishes
+/// the recompression rather than training another.
+fn train(world_path: PathBuf, backend: StorageBackend, samples: usize) -> anyhow::Result<()> {
+    let store = backend.open(&world_path, Compression::Zstd)?;
+    let positions = store.positions()?;
+    if positions.is_empty() {
+        bail!("{} has no saved chunks", world_path.display());
+    }
+
+    let dictionary = match metadata::load_pending_dictionary(&world_path)? {
+        Some(dictionary) => {
+            println!("Resuming an interrupted recompression");
+            dictionary
+        }
+        None => {
+            let step = (positions.len() / samples).max(1);
+            let sample = positions
+                .iter()
+                .step_by(step)
+                .filter_map(|&pos| store.read_chunk(pos).transpose())
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            println!("Training dictionary on {} chunks", sample.len());
+            let dictionary = train_dictionary(&sample, DICTIONARY_SIZE)?;
+            metadata::save_pending_dictionary(&world_path, &dictionary)?;
+            dictionary
+        }
+    };
+
+    // chunks are read with either dictionary, and written with the new one
+    let old = WorldMetadata::load(&world_path)?.dictionary;
+    let store = backend.open_with(
+        &world_path,
+        ChunkCodec::new(Compression::Zstd, Some(dictionary)).with_fallback_dictionary(old),
+    )?;
+    println!("Recompressing {} chunks", positions.len());
+    for pos in positions {
+        if let Some(chunk) = store.read_chunk(pos)? {
+            store.write_chunk(&chunk)?;
+        }
+    }
+    metadata::commit_pending_dictionary(&world_path)?;
+    println!("Done");
+    Ok(())
+}
+
+/// Generate and save every chunk within the given radius of the origin that has not been saved
+/// already, from the seed in the environment, the world's preset, and the game's terrain settings.
+/// Features reaching past the radius are cut off at its edge.
+fn pregen(world_path: PathBuf, backend: StorageBackend, radius: i64) -> anyhow::Result<()> {
+    let store = backend.open(&world_path, Compression::default())?;
+    let metadata = WorldMetadata::load(&world_path)?;
+    let noise = TerrainNoise::from_env()?;
+    let stages = match world_preset(&world_path, preset_from_env()?)? {
+        Some(preset) => preset.pipeline(),
+        None => pipeline_from_env()?,
+    };
+    stages.set_terrain(read_terrain_settings()?);
+
+    let saved = store
+        .positions()?
+        .into_iter()
+        .map(ChunkPos::key)
+        .collect::<ChunkSet>();
+    let wanted = iproduct!(-radius..=radius, -radius..=radius, -radius..=radius)
+        .map(|(x, y, z)| ChunkPos::new(x, y, z))
+        .filter(|pos| !saved.contains(&pos.key()))
+        .collect::<Vec<_>>();
+    let targets = wanted.iter().map(|pos| pos.key()).collect::<ChunkSet>();
+    println!(
+        "Generating {} chunks with seed {}",
+        wanted.len(),
+        noise.seed()
+    );
+
+    let start = Instant::now();
+    let mut pending = PendingBlocks::default();
+    let mut written = ChunkSet::default();
+    let mut reported = 0;
+    for (i, &pos) in wanted.iter().enumerate() {
+        let (mut chunk, spilled) = generate_local(pos, &noise, &stages);
+        // only smooth worlds keep a density field, since blocky meshes follow the blocks alone
+        if metadata.meshing != MeshingMode::Smooth {
+            chunk.clear_density();
+        }
+        structure::place(&mut chunk, pending.take(pos));
+        store.write_chunk(&chunk)?;
+        written.insert(pos.key());
+
+        // finish features in chunks already written, and hold the rest for chunks still to come
+        for (target, blocks) in spilled.into_iter().into_group_map_by(|&(pos, _, _)| pos) {
+            if written.contains(&target.key()) {
+                let Some(mut chunk) = store.read_chunk(target)? else {
+                    continue;
+                };
+                structure::place(
+                    &mut chunk,
+                    blocks.into_iter().map(|(_, pos, block)| (pos, block)),
+                );
+                store.write_chunk(&chunk)?;
+            } else if targets.contains(&target.key()) {
+                blocks.into_iter().for_each(|block| pending.defer(block));
+            }
+        }
+
+        let percent = (i + 1) * 100 / wanted.len();
+        if percent >= reported + 5 {
+            reported = percent;
+            println!("{}% ({} of {} chunks)", percent, i + 1, wanted.len());
+        }
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "Generated {} chunks in {:.1}s ({:.1} chunks/s)",
+        wanted.len(),
+        elapsed,
+        wanted.len() as f64 / elapsed.max(f64::EPSILON)
+    );
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::{
+    io::{self, BufReader, BufWriter},
+    sync::Arc,
+};
+
+use chunky::{
+    chunk::{
+        generation::{
+            erosion::ErosionSettings, pipeline_from_env, seed::TerrainNoise,
+            template::StructureTemplate, terrain::TerrainSettings, worker::generate_local,
+        },
+        ChunkPos,
+    },
+    net::protocol::{read_message, write_message},
+};
+
+/// A generation worker, answering each chunk position read from standard input, along with the
+/// generation stages to skip, the shape of the terrain, how to erode it, and the structure
+/// templates to place, with the generated chunk, and the blocks of its features that fall in other
+/// chunks, on standard output. Terrain is generated from the seed in the environment, or flat if
+/// the environment asks for a flat world. Exits when standard input is closed.
+fn main() -> anyhow::Result<()> {
+    let noise = TerrainNoise::from_env()?;
+    let mut input = BufReader::new(io::stdin().lock());
+    let mut output = BufWriter::new(io::stdout().lock());
+    // one pipeline serves every request, so the region tiles it caches outlive each chunk
+    let stages = pipeline_from_env()?;
+    let names = stages
+        .timings()
+        .into_iter()
+        .map(|timing| timing.name)
+        .collect::<Vec<_>>();
+    while let Ok((pos, disabled, terrain, erosion, templates)) = read_message::<
+        _,
+        (
+            ChunkPos,
+            Vec<String>,
+            TerrainSettings,
+            ErosionSettings,
+            Vec<StructureTemplate>,
+        ),
+    >(&mut input)
+    {
+        stages.set_terrain(terrain);
+        stages.set_erosion(erosion);
+        stages.set_templates(templates.into_iter().map(Arc::new).collect());
+        for &name in &names {
+            stages.set_enabled(name, !disabled.iter().any(|disabled| disabled == name));
+        }
+        write_message(&mut output, &generate_local(pos, &noise, &stages))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use bevy::{prelude::*, window::CursorGrabMode};
+
+use crate::chunk::{BlockPos, BlockType, ChunkCommand, ChunkPos, Chunks};
+
+/// The farthest away a block can be broken or placed from, in blocks.
+pub const REACH: f32 = 6.0;
+
+/// The number of stages the crack overlay darkens through as a block breaks.
+const CRACK_STAGES: u32 = 10;
+
+/// The opacity of the crack overlay at its last stage, just before the block breaks.
+const MAX_CRACK_OPACITY: f32 = 0.6;
+
+/// How much larger the crack overlay is than the block it covers, so it does not flicker against
+/// the block's faces.
+const CRACK_OVERLAY_SIZE: f32 = 1.01;
+
+/// A plugin for breaking blocks by holding the left mouse button on them, taking as long as the
+/// block's hardness, with an overlay darkening over the block as it cracks.
+pub struct BlockBreakingPlugin;
+
+impl Plugin for BlockBreakingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockBreaking>()
+            .add_systems(Startup, spawn_crack_overlay)
+            .add_systems(Update, (break_targeted_block, show_crack_overlay).chain());
+    }
+}
+
+/// The block being broken, and how far breaking it has got.
+#[derive(Debug, Default, Resource)]
+pub struct BlockBreaking {
+    target: Option<IVec3>,
+    progress: f32,
+}
+
+impl BlockBreaking {
+    /// Return the world block coordinates of the block being broken, if any.
+    pub fn target(&self) -> Option<IVec3> {
+        self.target
+    }
+
+    /// Return how far breaking the target has got, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    /// Return the crack stage the target has reached, from 0 to [`CRACK_STAGES`].
+    pub fn stage(&self) -> u32 {
+        (self.progress * CRACK_STAGES as f32) as u32
+    }
+
+    /// Start breaking the given block, or stop breaking anything, losing any progress.
+    fn retarget(&mut self, target: Option<IVec3>) {
+        if self.target != target {
+            self.target = target;
+            self.progress = 0.0;
+        }
+    }
+}
+
+/// A component for the overlay drawn over the block being broken, holding the material of each
+/// crack stage, darkest last.
+#[derive(Component)]
+struct CrackOverlay(Vec<Handle<StandardMaterial>>);
+
+/// Spawn the crack overlay, hidden until a block is being broken.
+fn spawn_crack_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let stages = (0..=CRACK_STAGES)
+        .map(|stage| {
+            let opacity = stage as f32 / CRACK_STAGES as f32 * MAX_CRACK_OPACITY;
+            materials.add(StandardMaterial {
+                base_color: Color::BLACK.with_alpha(opacity),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })
+        })
+        .collect::<Vec<_>>();
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::from_length(CRACK_OVERLAY_SIZE)),
+            material: stages[0].clone(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CrackOverlay(stages),
+    ));
+}
+
+/// Break the block the camera is looking at while the left mouse button is held on it, taking its
+/// hardness in seconds. Letting go, or looking at another block, starts over. Clicks that lock the
+/// cursor do not break anything.
+fn break_targeted_block(
+    mouse: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+    windows: Query<&Window>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    chunks: Res<Chunks>,
+    mut breaking: ResMut<BlockBreaking>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    let locked = windows
+        .get_single()
+        .is_ok_and(|window| window.cursor.grab_mode == CursorGrabMode::Locked);
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+    if !locked || !mouse.pressed(MouseButton::Left) {
+        breaking.retarget(None);
+        return;
+    }
+    let hit = chunks.raycast(camera.translation(), camera.forward(), REACH);
+    breaking.retarget(hit.map(|hit| hit.world_block()));
+    let Some(target) = breaking.target else {
+        return;
+    };
+    let hardness = chunks
+        .block_at_world_block(target)
+        .map_or(f32::INFINITY, |block| block.hardness());
+    breaking.progress += time.delta_seconds() / hardness.max(f32::EPSILON);
+    if breaking.progress < 1.0 {
+        return;
+    }
+    chunk_commands.send(ChunkCommand::ModifyBlock(
+        ChunkPos::from_world_block(target),
+        BlockPos::from_world_block(target),
+        BlockType::EMPTY,
+    ));
+    breaking.retarget(None);
+}
+
+/// Move the crack overlay onto the block being broken, darkening it with each stage of cracking,
+/// and hide it when nothing is being broken.
+fn show_crack_overlay(
+    breaking: Res<BlockBreaking>,
+    mut overlays: Query<(
+        &CrackOverlay,
+        &mut Transform,
+        &mut Visibility,
+        &mut Handle<StandardMaterial>,
+    )>,
+) {
+    let Ok((overlay, mut transform, mut visibility, mut material)) = overlays.get_single_mut()
+    else {
+        return;
+    };
+    let Some(target) = breaking.target().filter(|_| breaking.stage() > 0) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Visible;
+    transform.translation = target.as_vec3() + Vec3::splat(0.5);
+    let stage = &overlay.0[breaking.stage().min(CRACK_STAGES) as usize];
+    if *material != *stage {
+        *material = stage.clone();
+    }
+}
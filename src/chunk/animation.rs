@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::ChunkMesh;
+
+/// How newly loaded chunks rise into place, softening the pop-in of terrain at the edge of the
+/// loaded world. Remeshed chunks are swapped in place.
+#[derive(Debug, Clone, Resource)]
+pub struct ChunkAnimation {
+    /// Whether new chunks rise in, or appear in place at once.
+    pub enabled: bool,
+    /// How long new chunks take to rise into place.
+    pub duration: Duration,
+    /// How far below their place new chunks start, in blocks.
+    pub depth: f32,
+}
+
+impl Default for ChunkAnimation {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duration: Duration::from_millis(300),
+            depth: 8.0,
+        }
+    }
+}
+
+impl ChunkAnimation {
+    /// Return where a new chunk mesh starts, and the component rising it into place, or `None` if
+    /// chunks appear in place.
+    pub(super) fn start(&self, target: Vec3) -> (Vec3, Option<RisingIn>) {
+        if !self.enabled || self.duration.is_zero() {
+            return (target, None);
+        }
+        let rising = RisingIn {
+            timer: Timer::new(self.duration, TimerMode::Once),
+            depth: self.depth,
+        };
+        (target - Vec3::Y * self.depth, Some(rising))
+    }
+}
+
+/// A chunk mesh rising into place.
+#[derive(Component)]
+pub(super) struct RisingIn {
+    timer: Timer,
+    /// How far below its place the mesh started, in blocks.
+    depth: f32,
+}
+
+/// Move rising chunk meshes towards their place, easing out as they arrive.
+pub(super) fn rise_chunk_meshes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut rising: Query<(Entity, &ChunkMesh, &mut RisingIn, &mut Transform)>,
+) {
+    for (entity, chunk_mesh, mut rise, mut transform) in &mut rising {
+        rise.timer.tick(time.delta());
+        let remaining = (1.0 - rise.timer.fraction()).powi(3);
+        transform.translation = chunk_mesh.position.to_world() - Vec3::Y * rise.depth * remaining;
+        if rise.timer.finished() {
+            commands.entity(entity).remove::<RisingIn>();
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use super::{key::ChunkSet, BlockType, ChunkCommand, ChunkPos, Chunks};
+
+/// The most chunks queued for a remesh each frame after block appearances change, so a change to a
+/// common block does not stall a frame remeshing every loaded chunk at once.
+const REMESHES_PER_FRAME: usize = 16;
+
+/// An event sent when the way blocks are drawn changes at runtime, such as when a content pack is
+/// loaded or hot reloaded, so chunks built from the old appearance are remeshed.
+#[derive(Event, Debug, Clone)]
+pub struct BlockAppearanceChanged {
+    /// The blocks whose appearance changed.
+    pub blocks: Vec<BlockType>,
+}
+
+/// A counter of block appearance changes, bumped whenever [`BlockAppearanceChanged`] is sent, along
+/// with the chunks still waiting to be remeshed because of them.
+#[derive(Debug, Default, Resource)]
+pub struct AppearanceGeneration {
+    /// The number of appearance changes so far.
+    generation: u64,
+    /// Loaded chunks containing changed blocks that have not been queued for a remesh yet.
+    stale: VecDeque<ChunkPos>,
+    /// The chunks in `stale`, so a chunk is not queued twice.
+    queued: ChunkSet,
+}
+
+impl AppearanceGeneration {
+    /// Return the number of appearance changes so far, so caches built from block appearances can
+    /// tell when they are out of date.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Return the number of chunks still waiting to be remeshed.
+    pub fn stale(&self) -> usize {
+        self.stale.len()
+    }
+}
+
+/// Bump the appearance generation, and queue every loaded chunk containing a changed block to be
+/// remeshed.
+pub(super) fn invalidate_appearances(
+    mut events: EventReader<BlockAppearanceChanged>,
+    mut appearance: ResMut<AppearanceGeneration>,
+    chunks: Res<Chunks>,
+) {
+    let changed = events
+        .read()
+        .flat_map(|event| event.blocks.iter().copied())
+        .collect::<Vec<_>>();
+    appearance.generation += 1;
+    let appearance = &mut *appearance;
+    let stale = chunks
+        .iter()
+        .filter(|chunk| chunk.palette().iter().any(|block| changed.contains(block)))
+        .map(|chunk| chunk.position)
+        .filter(|pos| appearance.queued.insert(pos.key()));
+    appearance.stale.extend(stale);
+}
+
+/// Check if any chunks are waiting to be remeshed for an appearance change.
+pub(super) fn appearances_stale(appearance: Res<AppearanceGeneration>) -> bool {
+    !appearance.stale.is_empty()
+}
+
+/// Remesh a few of the chunks left stale by appearance changes, skipping any since unloaded.
+pub(super) fn remesh_stale_chunks(
+    mut appearance: ResMut<AppearanceGeneration>,
+    chunks: Res<Chunks>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    let appearance = &mut *appearance;
+    let count = appearance.stale.len().min(REMESHES_PER_FRAME);
+    let queued = &mut appearance.queued;
+    chunk_commands.send_batch(
+        appearance
+            .stale
+            .drain(..count)
+            .inspect(|pos| {
+                queued.remove(&pos.key());
+            })
+            .filter(|&pos| chunks.is_loaded(pos))
+            .map(ChunkCommand::Remesh),
+    );
+}
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+
+use super::{
+    random_tick::{RandomTick, RandomTickBehaviours},
+    registry::{self, BlockDefinition},
+    BlockType,
+};
+
+/// The blocks of the world. Plugins register their blocks while the app is built, with
+/// [`BlockAppExtension::register_block`], and the registry is installed for chunks and meshers to
+/// look blocks up in once every plugin is built.
+#[derive(Debug, Default, Resource, Deref)]
+pub struct BlockRegistry(registry::BlockRegistry);
+
+pub trait BlockAppExtension {
+    /// Register a block, numbered after every block registered before it. Blocks must be registered
+    /// in the same order whenever a world is loaded.
+    fn register_block(&mut self, definition: BlockDefinition) -> &mut Self;
+
+    /// Set what a block does when a random tick picks it, such as grass spreading.
+    fn on_random_tick(&mut self, block: BlockType, behaviour: RandomTick) -> &mut Self;
+}
+
+impl BlockAppExtension for App {
+    fn register_block(&mut self, definition: BlockDefinition) -> &mut Self {
+        let name = definition.name.clone();
+        let mut blocks = self
+            .world_mut()
+            .get_resource_or_insert_with(BlockRegistry::default);
+        if let Err(err) = blocks.0.register(definition) {
+            panic!("failed to register block {:?}: {:?}", name, err);
+        }
+        self
+    }
+
+    fn on_random_tick(&mut self, block: BlockType, behaviour: RandomTick) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(RandomTickBehaviours::default)
+            .insert(block, behaviour);
+        self
+    }
+}
+
+/// Install the registered blocks, so they can be looked up by number.
+pub(super) fn install_blocks(app: &mut App) {
+    let blocks = app
+        .world_mut()
+        .get_resource_or_insert_with(BlockRegistry::default)
+        .0
+        .clone();
+    if let Err(err) = blocks.install() {
+        error!("Failed to install the block registry: {:?}", err);
+    }
+}
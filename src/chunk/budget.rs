@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use super::{ChunkCommand, Chunks};
+
+/// Unload the least recently accessed clean chunks while the loaded chunks use more memory than
+/// the budget allows. Modified chunks are kept until they have been saved, and chunks within the
+/// area loaded around a player are kept, as they would only be loaded again straight away.
+pub(super) fn enforce_memory_budget(
+    mut chunks: ResMut<Chunks>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    chunks.frame += 1;
+    let Some(budget) = chunks.memory_budget else {
+        return;
+    };
+    let mut usage = chunks
+        .iter()
+        .map(|chunk| chunk.memory_usage())
+        .sum::<usize>();
+    if usage <= budget {
+        return;
+    }
+
+    let candidates = chunks
+        .iter()
+        .filter(|chunk| !chunk.is_dirty() && !chunks.is_busy(chunk.position))
+        .filter(|chunk| !chunks.in_load_area(chunk.position))
+        .sorted_by_key(|chunk| chunk.last_access());
+    let mut evicted = 0;
+    for chunk in candidates {
+        if usage <= budget {
+            break;
+        }
+        usage -= chunk.memory_usage();
+        chunk_commands.send(ChunkCommand::Unload(chunk.position));
+        evicted += 1;
+    }
+    if evicted == 0 {
+        return;
+    }
+    chunks.counters.record_evictions(evicted);
+    debug!(
+        "Evicting {} chunks to stay within the memory budget",
+        evicted
+    );
+}
+
+/// Unload chunks that have not been accessed for longer than the maximum age, so chunks touched
+/// once during a long session do not stay in memory. Modified chunks are saved as they unload.
+pub(super) fn unload_stale_chunks(
+    chunks: Res<Chunks>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    let Some(max_age) = chunks.max_age else {
+        return;
+    };
+    let stale = chunks
+        .iter()
+        .filter(|chunk| chunks.frame.saturating_sub(chunk.last_access()) > max_age)
+        .filter(|chunk| !chunks.is_busy(chunk.position))
+        .map(|chunk| ChunkCommand::Unload(chunk.position))
+        .collect::<Vec<_>>();
+    if stale.is_empty() {
+        return;
+    }
+    debug!(
+        "Unloading {} chunks unaccessed for {} frames",
+        stale.len(),
+        max_age
+    );
+    chunks.counters.record_evictions(stale.len());
+    chunk_commands.send_batch(stale);
+}
@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use anyhow::bail;
+use bevy::{ecs::event::ManualEventReader, prelude::*, utils::HashMap};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{entity::EntityRecord, ChunkCommand, ChunkLoaded, ChunkPos, Chunks};
+
+/// How often the entities standing in loaded chunks are recorded in them, so autosaves catch them.
+/// Entities are also recorded as their chunk unloads, and when the app exits.
+pub(super) const RECORD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A kind of entity kept in the chunk it stands in, such as a dropped item: it is saved with the
+/// chunk, despawned when the chunk unloads, and spawned again with a [`SpatialBundle`] at its saved
+/// position when the chunk loads. Games add anything else it needs, such as a mesh, when the
+/// component is added.
+///
+/// Entities are saved with bincode by default. Kinds saved some other way, or whose layout has
+/// changed, override [`ChunkEntity::save`] and [`ChunkEntity::load`].
+pub trait ChunkEntity: Component + Serialize + DeserializeOwned {
+    /// The name the kind is saved under, which must not change once worlds have been saved.
+    const KIND: &'static str;
+
+    /// The version of the kind's data layout. Bump it, and read the older layout in
+    /// [`ChunkEntity::load`], when the layout changes.
+    const VERSION: u32 = 1;
+
+    /// Serialize the entity's data.
+    fn save(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Deserialize the entity's data, saved in the given version of the layout.
+    fn load(version: u32, data: &[u8]) -> anyhow::Result<Self> {
+        if version != Self::VERSION {
+            bail!(
+                "{} entities of version {} cannot be read, expected version {}",
+                Self::KIND,
+                version,
+                Self::VERSION
+            );
+        }
+        Ok(bincode::deserialize(data)?)
+    }
+}
+
+/// The kinds of entity kept in chunks, by name.
+#[derive(Default, Resource)]
+pub struct ChunkEntityKinds(HashMap<&'static str, EntityKind>);
+
+/// Every entity of a kind, along with its record or the error saving it.
+type SavedEntities = Vec<(Entity, anyhow::Result<EntityRecord>)>;
+
+/// The type-erased way to save and spawn a kind of entity.
+#[derive(Clone, Copy)]
+struct EntityKind {
+    /// Save every entity of the kind.
+    save: fn(&mut World) -> SavedEntities,
+    /// Spawn an entity of the kind from its record.
+    spawn: fn(&mut World, &EntityRecord) -> anyhow::Result<()>,
+}
+
+pub trait ChunkEntityAppExtension {
+    /// Keep entities with the given component in the chunks they stand in.
+    fn register_chunk_entity<T: ChunkEntity>(&mut self) -> &mut Self;
+}
+
+impl ChunkEntityAppExtension for App {
+    fn register_chunk_entity<T: ChunkEntity>(&mut self) -> &mut Self {
+        let kind = EntityKind {
+            save: save_kind::<T>,
+            spawn: spawn_kind::<T>,
+        };
+        let previous = self
+            .world_mut()
+            .get_resource_or_insert_with(ChunkEntityKinds::default)
+            .0
+            .insert(T::KIND, kind);
+        assert!(
+            previous.is_none(),
+            "chunk entity kind {} is already registered",
+            T::KIND
+        );
+        self
+    }
+}
+
+fn save_kind<T: ChunkEntity>(world: &mut World) -> Vec<(Entity, anyhow::Result<EntityRecord>)> {
+    world
+        .query::<(Entity, &T, &Transform)>()
+        .iter(world)
+        .map(|(entity, component, transform)| {
+            let record = component.save().map(|data| EntityRecord {
+                kind: T::KIND.into(),
+                version: T::VERSION,
+                position: transform.translation.into(),
+                data,
+            });
+            (entity, record)
+        })
+        .collect()
+}
+
+fn spawn_kind<T: ChunkEntity>(world: &mut World, record: &EntityRecord) -> anyhow::Result<()> {
+    let component = T::load(record.version, &record.data)?;
+    let position = Vec3::from(record.position);
+    world.spawn((
+        component,
+        SpatialBundle::from_transform(Transform::from_translation(position)),
+    ));
+    Ok(())
+}
+
+/// Save every entity of a registered kind, grouped by the chunk it stands in.
+fn save_entities(world: &mut World) -> HashMap<ChunkPos, Vec<(Entity, EntityRecord)>> {
+    let kinds = world
+        .get_resource::<ChunkEntityKinds>()
+        .map(|kinds| kinds.0.values().copied().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut saved = HashMap::<_, Vec<_>>::new();
+    for kind in kinds {
+        for (entity, record) in (kind.save)(world) {
+            match record {
+                Ok(record) => saved
+                    .entry(ChunkPos::from_world(Vec3::from(record.position)))
+                    .or_default()
+                    .push((entity, record)),
+                Err(err) => error!("Failed to save entity {:?}: {:?}", entity, err),
+            }
+        }
+    }
+    saved
+}
+
+/// Replace the records of the given chunk with the given entities. Records of kinds that are not
+/// registered, perhaps by a plugin that is not loaded, are kept as they are.
+fn record_chunk(
+    world: &mut World,
+    pos: ChunkPos,
+    entities: impl IntoIterator<Item = EntityRecord>,
+) {
+    let registered = |record: &EntityRecord| {
+        world
+            .get_resource::<ChunkEntityKinds>()
+            .is_some_and(|kinds| kinds.0.contains_key(record.kind.as_str()))
+    };
+    let Some(chunk) = world.resource::<Chunks>().get(pos) else {
+        return;
+    };
+    let records = chunk
+        .entities()
+        .iter()
+        .filter(|record| !registered(record))
+        .cloned()
+        .chain(entities)
+        .collect();
+    if let Some(chunk) = world.resource_mut::<Chunks>().get_mut(pos) {
+        chunk.set_entities(records);
+    }
+}
+
+/// Record the entities standing in every loaded chunk, so they are saved along with it. Entities
+/// outside the loaded chunks are not saved.
+pub(super) fn record_chunk_entities(world: &mut World) {
+    let mut saved = save_entities(world);
+    let loaded = world
+        .resource::<Chunks>()
+        .iter()
+        .map(|chunk| chunk.position)
+        .collect::<Vec<_>>();
+    for pos in loaded {
+        let entities = saved.remove(&pos).unwrap_or_default();
+        record_chunk(world, pos, entities.into_iter().map(|(_, record)| record));
+    }
+}
+
+/// Record and despawn the entities standing in chunks about to unload, so they are saved with the
+/// chunk and spawned again when it is next loaded.
+pub(super) fn stash_unloading_entities(
+    world: &mut World,
+    mut unloads: Local<ManualEventReader<ChunkCommand>>,
+) {
+    let events = world.resource::<Events<ChunkCommand>>();
+    let unloading = unloads
+        .read(events)
+        .filter_map(|command| match command {
+            ChunkCommand::Unload(pos) => Some(*pos),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if unloading.is_empty() {
+        return;
+    }
+    let mut saved = save_entities(world);
+    for pos in unloading {
+        // entities in chunks that are still loading stay where they are
+        if world.resource::<Chunks>().get(pos).is_none() {
+            continue;
+        }
+        let entities = saved.remove(&pos).unwrap_or_default();
+        for (entity, _) in &entities {
+            world.entity_mut(*entity).despawn_recursive();
+        }
+        record_chunk(world, pos, entities.into_iter().map(|(_, record)| record));
+    }
+}
+
+/// Spawn the entities recorded in chunks that have just loaded.
+pub(super) fn respawn_chunk_entities(
+    world: &mut World,
+    mut loads: Local<ManualEventReader<ChunkLoaded>>,
+) {
+    let events = world.resource::<Events<ChunkLoaded>>();
+    let loaded = loads
+        .read(events)
+        .map(|ChunkLoaded(pos)| *pos)
+        .collect::<Vec<_>>();
+    for pos in loaded {
+        let Some(chunk) = world.resource::<Chunks>().get(pos) else {
+            continue;
+        };
+        for record in chunk.entities().to_vec() {
+            let kind = world
+                .get_resource::<ChunkEntityKinds>()
+                .and_then(|kinds| kinds.0.get(record.kind.as_str()).copied());
+            let Some(kind) = kind else {
+                continue;
+            };
+            if let Err(err) = (kind.spawn)(world, &record) {
+                error!(
+                    "Failed to spawn {} entity in {:?}: {:?}",
+                    record.kind, pos, err
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use super::{state::BlockState, updates::BlockUpdate, BlockType, Chunks};
+use crate::environment::Environment;
+
+/// The speed falling blocks stop speeding up at, in blocks per second. Kept below a block per
+/// fixed tick, so a falling block cannot pass through a block without landing on it.
+const TERMINAL_VELOCITY: f32 = 40.0;
+
+/// A component for blocks that have lost their support and are falling, until they land and are
+/// placed back into the world.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct FallingBlock {
+    /// The block that is falling.
+    pub block: BlockType,
+    /// The state the block had, and is placed back with.
+    pub state: BlockState,
+    /// The speed the block is falling at, in blocks per second.
+    pub velocity: f32,
+}
+
+/// Check if the block at the given world block coordinates falls, and has nothing solid beneath it.
+/// Blocks over chunks that are not loaded stay where they are.
+fn is_unsupported(chunks: &Chunks, pos: IVec3) -> bool {
+    let falls = chunks
+        .block_at_world_block(pos)
+        .is_some_and(|block| block.definition().falls);
+    let open_below = chunks
+        .block_at_world_block(pos - IVec3::Y)
+        .is_some_and(|block| !block.definition().solid);
+    falls && open_below
+}
+
+/// Turn falling blocks sent block updates into falling entities, if they have lost their support.
+/// Taking a block out updates the block above it, so a column of sand falls away one block per tick.
+pub(super) fn drop_unsupported_blocks(
+    mut commands: Commands,
+    mut updates: EventReader<BlockUpdate>,
+    mut chunks: ResMut<Chunks>,
+) {
+    for &BlockUpdate(pos) in updates.read() {
+        if !is_unsupported(&chunks, pos) {
+            continue;
+        }
+        let Some(state) = chunks.state_at_world_block(pos) else {
+            continue;
+        };
+        let Some(block) = chunks.set_block_at_world_block(pos, BlockType::EMPTY) else {
+            continue;
+        };
+        commands.spawn((
+            SpatialBundle::from_transform(Transform::from_translation(
+                pos.as_vec3() + Vec3::splat(0.5),
+            )),
+            FallingBlock {
+                block,
+                state,
+                velocity: 0.0,
+            },
+        ));
+    }
+}
+
+/// Move falling blocks down, placing them back into the world on top of the first solid block they
+/// reach. Blocks land on top of chunks that are not loaded, rather than fall into them.
+pub(super) fn move_falling_blocks(
+    mut commands: Commands,
+    time: Res<Time>,
+    environment: Res<Environment>,
+    mut chunks: ResMut<Chunks>,
+    mut falling: Query<(Entity, &mut FallingBlock, &mut Transform)>,
+) {
+    for (entity, mut falling, mut transform) in falling.iter_mut() {
+        falling.velocity =
+            (falling.velocity + environment.gravity * time.delta_seconds()).min(TERMINAL_VELOCITY);
+        transform.translation.y -= falling.velocity * time.delta_seconds();
+
+        let bottom = (transform.translation - Vec3::Y * 0.5).floor().as_ivec3();
+        let landed = chunks
+            .block_at_world_block(bottom)
+            .is_none_or(|block| block.definition().solid);
+        if !landed {
+            continue;
+        }
+        let pos = bottom + IVec3::Y;
+        chunks.set_block_at_world_block(pos, falling.block);
+        chunks.set_block_state_at_world_block(pos, falling.state);
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Give falling blocks a cube in the colour of their block.
+pub(super) fn show_falling_blocks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    falling: Query<(Entity, &FallingBlock), Added<FallingBlock>>,
+) {
+    for (entity, falling) in falling.iter() {
+        let [r, g, b, a] = falling.block.definition().color;
+        commands.entity(entity).insert((
+            meshes.add(Cuboid::from_length(1.0)),
+            materials.add(StandardMaterial::from_color(Color::linear_rgba(r, g, b, a))),
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+
+pub use chunky_core::chunk::fluid::{flow, level, state, surface_height, MAX_LEVEL};
+
+use super::{
+    scheduled::{BlockTicks, WorldClock},
+    updates::BlockUpdate,
+    BlockType, Chunks,
+};
+
+/// The number of fixed ticks water waits before flowing on from a block it has just reached.
+pub const FLOW_DELAY: u64 = 5;
+
+/// Wake the water sent block updates, and let the water that has come due flow on. The blocks water
+/// flows into are set like any other edit, so they and their neighbours are updated in turn, and
+/// the affected chunks are remeshed as it spreads.
+pub(super) fn flow_liquids(
+    clock: Res<WorldClock>,
+    mut updates: EventReader<BlockUpdate>,
+    mut ticks: ResMut<BlockTicks>,
+    mut chunks: ResMut<Chunks>,
+) {
+    let next = clock.tick() + FLOW_DELAY;
+    for BlockUpdate(pos) in updates.read() {
+        if chunks.block_at_world_block(*pos) == Some(BlockType::WATER) {
+            ticks.schedule(*pos, next);
+        }
+    }
+
+    for pos in ticks.take_due(clock.tick()) {
+        if chunks.block_at_world_block(pos) != Some(BlockType::WATER) {
+            continue;
+        }
+        let Some(current) = chunks.state_at_world_block(pos) else {
+            continue;
+        };
+        let targets = flow(pos, level(current), |target| {
+            Some((
+                chunks.block_at_world_block(target)?,
+                chunks.state_at_world_block(target)?,
+            ))
+        });
+        for (target, level) in targets {
+            chunks.set_block_at_world_block(target, BlockType::WATER);
+            chunks.set_block_state_at_world_block(target, state(level));
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// The carve stage on the GPU: pushes the density of every sample near the surface in or out by 3D
+// OpenSimplex noise, matching the noise crate's implementation so both stages carve alike.
+
+struct Params {
+    origin: vec3<i32>,
+    height: u32,
+    scale: f32,
+    strength: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> perm: array<u32, 256>;
+@group(0) @binding(2) var<storage, read_write> density: array<f32>;
+
+const CHUNK_SIZE: u32 = 32u;
+const STRETCH: f32 = -1.0 / 6.0;
+const SQUISH: f32 = 1.0 / 3.0;
+const NORM: f32 = 1.0 / 14.0;
+const DIAG: f32 = 0.70710678;
+const DIAG2: f32 = 0.57735027;
+
+fn hash(vertex: vec3<i32>) -> u32 {
+    let h = perm[u32(vertex.x & 255)];
+    return perm[perm[h ^ u32(vertex.y & 255)] ^ u32(vertex.z & 255)];
+}
+
+fn gradient(index: u32) -> vec3<f32> {
+    // the 12 edges twice, then the 8 corners
+    var gradients = array<vec3<f32>, 32>(
+        vec3(DIAG, DIAG, 0.0), vec3(-DIAG, DIAG, 0.0), vec3(DIAG, -DIAG, 0.0), vec3(-DIAG, -DIAG, 0.0),
+        vec3(DIAG, 0.0, DIAG), vec3(-DIAG, 0.0, DIAG), vec3(DIAG, 0.0, -DIAG), vec3(-DIAG, 0.0, -DIAG),
+        vec3(0.0, DIAG, DIAG), vec3(0.0, -DIAG, DIAG), vec3(0.0, DIAG, -DIAG), vec3(0.0, -DIAG, -DIAG),
+        vec3(DIAG, DIAG, 0.0), vec3(-DIAG, DIAG, 0.0), vec3(DIAG, -DIAG, 0.0), vec3(-DIAG, -DIAG, 0.0),
+        vec3(DIAG, 0.0, DIAG), vec3(-DIAG, 0.0, DIAG), vec3(DIAG, 0.0, -DIAG), vec3(-DIAG, 0.0, -DIAG),
+        vec3(0.0, DIAG, DIAG), vec3(0.0, -DIAG, DIAG), vec3(0.0, DIAG, -DIAG), vec3(0.0, -DIAG, -DIAG),
+        vec3(DIAG2, DIAG2, DIAG2), vec3(-DIAG2, DIAG2, DIAG2), vec3(DIAG2, -DIAG2, DIAG2), vec3(-DIAG2, -DIAG2, DIAG2),
+        vec3(DIAG2, DIAG2, -DIAG2), vec3(-DIAG2, DIAG2, -DIAG2), vec3(DIAG2, -DIAG2, -DIAG2), vec3(-DIAG2, -DIAG2, -DIAG2),
+    );
+    return gradients[index % 32u];
+}
+
+// The contribution of the given corner of the cell to the noise.
+fn surflet(offset: vec3<f32>, cell: vec3<f32>, position: vec3<f32>) -> f32 {
+    let delta = position - SQUISH * (offset.x + offset.y + offset.z) - offset;
+    let t = 2.0 - dot(delta, delta);
+    if t <= 0.0 {
+        return 0.0;
+    }
+    return t * t * t * t * dot(delta, gradient(hash(vec3<i32>(cell + offset))));
+}
+
+fn open_simplex_3d(point: vec3<f32>) -> f32 {
+    let stretched = point + (point.x + point.y + point.z) * STRETCH;
+    let cell = floor(stretched);
+    let origin = cell + (cell.x + cell.y + cell.z) * SQUISH;
+    let relative = stretched - cell;
+    let region = relative.x + relative.y + relative.z;
+    let position = point - origin;
+
+    var value = 0.0;
+    if region <= 1.0 {
+        value += surflet(vec3(0.0, 0.0, 0.0), cell, position);
+        value += surflet(vec3(1.0, 0.0, 0.0), cell, position);
+        value += surflet(vec3(0.0, 1.0, 0.0), cell, position);
+        value += surflet(vec3(0.0, 0.0, 1.0), cell, position);
+    } else if region >= 2.0 {
+        value += surflet(vec3(1.0, 1.0, 0.0), cell, position);
+        value += surflet(vec3(1.0, 0.0, 1.0), cell, position);
+        value += surflet(vec3(0.0, 1.0, 1.0), cell, position);
+        value += surflet(vec3(1.0, 1.0, 1.0), cell, position);
+    } else {
+        value += surflet(vec3(1.0, 0.0, 0.0), cell, position);
+        value += surflet(vec3(0.0, 1.0, 0.0), cell, position);
+        value += surflet(vec3(0.0, 0.0, 1.0), cell, position);
+        value += surflet(vec3(1.0, 1.0, 0.0), cell, position);
+        value += surflet(vec3(1.0, 0.0, 1.0), cell, position);
+        value += surflet(vec3(0.0, 1.0, 1.0), cell, position);
+    }
+    return value * NORM;
+}
+
+// Samples are laid out by x, then y, then z, as the generation context keeps them.
+@compute @workgroup_size(64)
+fn carve(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= arrayLength(&density) {
+        return;
+    }
+    let value = density[index];
+    if abs(value) > params.strength {
+        return;
+    }
+    let z = index % CHUNK_SIZE;
+    let y = index / CHUNK_SIZE % params.height;
+    let x = index / (CHUNK_SIZE * params.height);
+    let world = vec3<f32>(params.origin + vec3<i32>(vec3(x, y, z)));
+    density[index] = value + params.strength * open_simplex_3d(world / params.scale);
+}
@@ -0,0 +1,237 @@
+use std::{
+    borrow::Cow,
+    sync::{mpsc, Arc, Mutex, OnceLock},
+};
+
+use anyhow::Context;
+use bevy::{prelude::*, tasks::block_on};
+use itertools::iproduct;
+use noise::{
+    permutationtable::{NoiseHasher, PermutationTable},
+    Seedable,
+};
+use wgpu::util::DeviceExt;
+
+use super::{
+    pipeline::{GenerationContext, GenerationPipeline, GenerationStage},
+    terrain::Carve,
+};
+use crate::chunk::{BlockPos, BlockType, CHUNK_SIZE};
+
+/// The number of samples each GPU invocation group carves.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Swap the stages of the given pipeline that can run on the GPU for ones that do.
+pub fn on_gpu(pipeline: GenerationPipeline) -> GenerationPipeline {
+    pipeline.replacing(Carve.name(), GpuCarve::default())
+}
+
+/// The carve stage evaluated in a compute shader: the 3D noise carving overhangs is sampled for
+/// every block of the chunk at once, which dominates generation on the CPU. The GPU is set up
+/// when the first chunk is carved, and chunks are carved on the CPU instead if there is none or it
+/// fails.
+///
+/// The shader samples the same noise as [`Carve`] in single precision, so the odd block right at
+/// the surface can differ from a world generated on the CPU.
+#[derive(Default)]
+pub struct GpuCarve {
+    /// The GPU carving is done on, or none if it could not be set up.
+    gpu: OnceLock<Option<CarveShader>>,
+}
+
+/// The compiled carve shader, and the GPU it runs on.
+struct CarveShader {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    /// The permutation table of the noise last carved with, along with its seed.
+    permutations: Mutex<Option<(u32, Arc<wgpu::Buffer>)>>,
+}
+
+impl CarveShader {
+    /// Compile the shader for the first GPU found.
+    fn new() -> anyhow::Result<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..default()
+        }))
+        .context("no GPU found")?;
+        let (device, queue) = block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("terrain generation"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_defaults(),
+            },
+            None,
+        ))?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("carve"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("carve.wgsl"))),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("carve"),
+            layout: None,
+            module: &module,
+            entry_point: "carve",
+            compilation_options: default(),
+        });
+        info!("Carving terrain on {}", adapter.get_info().name);
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            permutations: Mutex::new(None),
+        })
+    }
+
+    /// Return the permutation table of the noise with the given seed, uploading it if the seed has
+    /// changed.
+    fn permutations(&self, seed: u32) -> Arc<wgpu::Buffer> {
+        let mut permutations = self.permutations.lock().unwrap();
+        if let Some((_, buffer)) = permutations.as_ref().filter(|(cached, _)| *cached == seed) {
+            return buffer.clone();
+        }
+        let table = PermutationTable::new(seed);
+        let values = (0..256)
+            .flat_map(|i| (table.hash(&[i]) as u32).to_le_bytes())
+            .collect::<Vec<_>>();
+        let buffer = Arc::new(
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("permutations"),
+                    contents: &values,
+                    usage: wgpu::BufferUsages::STORAGE,
+                }),
+        );
+        *permutations = Some((seed, buffer.clone()));
+        buffer
+    }
+
+    /// Carve the given density samples, laid out by x, then y, then z, and read them back.
+    fn carve(&self, context: &GenerationContext, samples: &[f32]) -> anyhow::Result<Vec<f32>> {
+        let (ox, oy, oz) = context.origin();
+        let mut params = Vec::with_capacity(32);
+        for value in [ox, oy, oz] {
+            params.extend((value as i32).to_le_bytes());
+        }
+        params.extend((GenerationContext::HEIGHT as u32).to_le_bytes());
+        params.extend((context.terrain.overhang_scale as f32).to_le_bytes());
+        params.extend(context.terrain.overhang_strength.to_le_bytes());
+        params.resize(32, 0);
+
+        let size = std::mem::size_of_val(samples) as u64;
+        let uniforms = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("carve params"),
+                contents: &params,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let density = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("density"),
+                contents: &samples
+                    .iter()
+                    .flat_map(|sample| sample.to_le_bytes())
+                    .collect::<Vec<_>>(),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("density readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let permutations = self.permutations(context.noise.seed());
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("carve"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: permutations.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: density.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&default());
+        {
+            let mut pass = encoder.begin_compute_pass(&default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups((samples.len() as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&density, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+        let carved = slice
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        Ok(carved)
+    }
+}
+
+impl GenerationStage for GpuCarve {
+    fn name(&self) -> &'static str {
+        Carve.name()
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let shader = self.gpu.get_or_init(|| {
+            CarveShader::new()
+                .map_err(|err| error!("Carving on the CPU, the GPU is unavailable: {:?}", err))
+                .ok()
+        });
+        let Some(shader) = shader else {
+            return Carve.run(context);
+        };
+        let samples = iproduct!(0..CHUNK_SIZE, 0..GenerationContext::HEIGHT, 0..CHUNK_SIZE)
+            .map(|(x, y, z)| context.density(x, y, z))
+            .collect::<Vec<_>>();
+        let carved = match shader.carve(context, &samples) {
+            Ok(carved) => carved,
+            Err(err) => {
+                error!("Carving on the GPU failed: {:?}", err);
+                return Carve.run(context);
+            }
+        };
+
+        let mut blocks = Vec::new();
+        let positions = iproduct!(0..CHUNK_SIZE, 0..GenerationContext::HEIGHT, 0..CHUNK_SIZE);
+        for ((x, y, z), (&density, &carved)) in positions.zip(samples.iter().zip(&carved)) {
+            if carved == density {
+                continue;
+            }
+            context.set_density(x, y, z, carved);
+            let solid = carved > 0.0;
+            if solid != (density > 0.0) && y < CHUNK_SIZE as i64 {
+                let block = if solid {
+                    BlockType::STONE
+                } else {
+                    BlockType::EMPTY
+                };
+                blocks.push((BlockPos::new(x, y as u8, z), block));
+            }
+        }
+        context.chunk.set_blocks(blocks);
+    }
+}
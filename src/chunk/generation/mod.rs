@@ -0,0 +1,110 @@
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod seed;
+pub mod settings;
+pub mod templates;
+pub mod worker;
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+pub use chunky_core::chunk::generation::{
+    biome, erosion, flat, hydrology, pipeline, preset, preview, structure, template, terrain,
+    Heightmap,
+};
+use flat::FlatGenerator;
+use pipeline::GenerationPipeline;
+use preset::WorldPreset;
+
+use crate::storage::metadata::WorldMetadata;
+
+/// The environment variable giving the layers of a flat world from the bottom up, such as
+/// `stone*3,dirt*2,grass`. Worlds are generated from noise if it is not set.
+pub const FLAT_ENV: &str = "CHUNKY_FLAT_WORLD";
+
+/// The environment variable naming the [`WorldPreset`] new worlds are created with, such as `void`.
+/// Takes precedence over [`FLAT_ENV`].
+pub const PRESET_ENV: &str = "CHUNKY_WORLD_PRESET";
+
+/// The environment variable asking for the stages that can run on the GPU to do so, when built with
+/// the `gpu` feature.
+pub const GPU_ENV: &str = "CHUNKY_GPU_GENERATION";
+
+/// The stages chunks are generated in. The pipeline is shared by every generation task, so its
+/// stages can be toggled and timed while the world runs.
+#[derive(Clone, Default, Resource, Deref)]
+pub struct GenerationStages(Arc<GenerationPipeline>);
+
+impl GenerationStages {
+    /// Build the stages chosen in the environment, with [`pipeline_from_env`].
+    pub fn from_env() -> anyhow::Result<Self> {
+        pipeline_from_env().map(|pipeline| Self(Arc::new(pipeline)))
+    }
+
+    /// Build the stages of the given preset, or those chosen in the environment if there is none.
+    pub fn for_preset(preset: Option<WorldPreset>) -> anyhow::Result<Self> {
+        match preset {
+            Some(preset) => Ok(Self(Arc::new(accelerate(preset.pipeline())))),
+            None => Self::from_env(),
+        }
+    }
+}
+
+/// Return the preset named in [`PRESET_ENV`], if any.
+pub fn preset_from_env() -> anyhow::Result<Option<WorldPreset>> {
+    match std::env::var(PRESET_ENV) {
+        Ok(preset) => preset
+            .parse()
+            .map(Some)
+            .with_context(|| format!("invalid {}", PRESET_ENV)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Settle the preset of the world in the given directory: the one it was created with, or else the
+/// requested one, which is recorded so the world keeps it. The requested preset is ignored for a
+/// world that already has one, and a world generated before presets were recorded has none unless
+/// one is requested.
+pub fn world_preset(
+    world_path: &Path,
+    requested: Option<WorldPreset>,
+) -> anyhow::Result<Option<WorldPreset>> {
+    let mut metadata = WorldMetadata::load(world_path)?;
+    match (metadata.preset, requested) {
+        (Some(preset), _) => Ok(Some(preset)),
+        (None, Some(requested)) => {
+            metadata.preset = Some(requested);
+            metadata.save(world_path)?;
+            Ok(Some(requested))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// Build the pipeline of the preset named in [`PRESET_ENV`] if it is set, a flat world if
+/// [`FLAT_ENV`] is set, and the full pipeline otherwise, on the GPU if [`GPU_ENV`] asks for it.
+pub fn pipeline_from_env() -> anyhow::Result<GenerationPipeline> {
+    if let Some(preset) = preset_from_env()? {
+        return Ok(accelerate(preset.pipeline()));
+    }
+    match std::env::var(FLAT_ENV) {
+        Ok(layers) => {
+            let flat = layers
+                .parse::<FlatGenerator>()
+                .with_context(|| format!("invalid {}", FLAT_ENV))?;
+            Ok(GenerationPipeline::empty().with_stage(flat))
+        }
+        Err(_) => Ok(accelerate(GenerationPipeline::default())),
+    }
+}
+
+/// Move the stages of the given pipeline that can run on the GPU there if [`GPU_ENV`] asks for it.
+fn accelerate(pipeline: GenerationPipeline) -> GenerationPipeline {
+    #[cfg(feature = "gpu")]
+    if std::env::var_os(GPU_ENV).is_some() {
+        return gpu::on_gpu(pipeline);
+    }
+    pipeline
+}
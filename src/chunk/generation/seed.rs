@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+/// The environment variable setting the seed terrain is generated from.
+pub const SEED_ENV: &str = "CHUNKY_SEED";
+
+/// The noise terrain is generated from. The noise is built once from the world's seed, and shared
+/// by every generation task.
+#[derive(Clone, Resource)]
+pub struct TerrainNoise {
+    /// The seed the noise was built from.
+    seed: u32,
+    /// The noise itself.
+    noise: Arc<noise::OpenSimplex>,
+}
+
+impl Default for TerrainNoise {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl TerrainNoise {
+    /// Build the noise for the given seed.
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            noise: Arc::new(noise::OpenSimplex::new(seed)),
+        }
+    }
+
+    /// Build the noise for the seed in [`SEED_ENV`], or the default seed if it is not set.
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var(SEED_ENV) {
+            Ok(seed) => parse_seed(&seed).map(Self::new),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Return the seed the noise was built from.
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Return the noise.
+    pub fn noise(&self) -> &noise::OpenSimplex {
+        &self.noise
+    }
+}
+
+/// Parse a seed given on the command line or in the environment.
+pub fn parse_seed(seed: &str) -> anyhow::Result<u32> {
+    seed.trim()
+        .parse()
+        .with_context(|| format!("invalid seed {:?}", seed))
+}
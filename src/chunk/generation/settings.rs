@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use anyhow::Context;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+
+use super::{terrain::TerrainSettings, GenerationStages};
+use crate::chunk::{ChunkCommand, Chunks};
+
+/// The path of the terrain settings file, relative to the assets directory.
+pub const TERRAIN_SETTINGS_PATH: &str = "terrain.toml";
+
+/// The directory the game reads its assets from.
+const ASSETS_PATH: &str = "assets";
+
+/// Read the terrain settings file directly, for tools that run without an asset server. Falls back
+/// to the default settings if there is no file.
+pub fn read_terrain_settings() -> anyhow::Result<TerrainSettings> {
+    let path = Path::new(ASSETS_PATH).join(TERRAIN_SETTINGS_PATH);
+    if !path.exists() {
+        return Ok(TerrainSettings::default());
+    }
+    let source = std::fs::read_to_string(&path)?;
+    toml::from_str(&source)
+        .with_context(|| format!("invalid terrain settings in {}", path.display()))
+}
+
+/// Terrain settings loaded from a TOML file.
+#[derive(Debug, Clone, Asset, TypePath)]
+pub struct TerrainSettingsAsset(pub TerrainSettings);
+
+/// Loads [`TerrainSettingsAsset`]s from TOML files.
+#[derive(Default)]
+pub struct TerrainSettingsLoader;
+
+impl AssetLoader for TerrainSettingsLoader {
+    type Asset = TerrainSettingsAsset;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> anyhow::Result<TerrainSettingsAsset> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source).await?;
+        Ok(TerrainSettingsAsset(toml::from_str(&source)?))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["toml"]
+    }
+}
+
+/// A plugin shaping terrain by the settings in [`TERRAIN_SETTINGS_PATH`], and regenerating the
+/// loaded chunks whenever the file changes. Edits to regenerated chunks are lost.
+pub struct TerrainSettingsPlugin;
+
+impl Plugin for TerrainSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TerrainSettingsAsset>()
+            .init_asset_loader::<TerrainSettingsLoader>()
+            .add_systems(Startup, load_terrain_settings)
+            .add_systems(
+                Update,
+                apply_terrain_settings.run_if(on_event::<AssetEvent<TerrainSettingsAsset>>()),
+            );
+    }
+}
+
+/// The handle keeping the terrain settings loaded.
+#[derive(Resource)]
+struct TerrainSettingsHandle(Handle<TerrainSettingsAsset>);
+
+/// Start loading the terrain settings.
+fn load_terrain_settings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load(TERRAIN_SETTINGS_PATH);
+    commands.insert_resource(TerrainSettingsHandle(handle));
+}
+
+/// Hand loaded or changed terrain settings to the generation stages, regenerating every loaded
+/// chunk if the shape of the terrain changed.
+fn apply_terrain_settings(
+    mut events: EventReader<AssetEvent<TerrainSettingsAsset>>,
+    handle: Res<TerrainSettingsHandle>,
+    assets: Res<Assets<TerrainSettingsAsset>>,
+    stages: Res<GenerationStages>,
+    chunks: Res<Chunks>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    let changed = events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+            *id == handle.0.id()
+        }
+        _ => false,
+    });
+    let Some(TerrainSettingsAsset(terrain)) = assets.get(&handle.0).filter(|_| changed) else {
+        return;
+    };
+    if *terrain == stages.terrain() {
+        return;
+    }
+    info!("Terrain settings changed, regenerating loaded chunks");
+    stages.set_terrain(*terrain);
+    // regenerated chunks are meshed alone, so remesh them once they are back
+    chunk_commands.send_batch(chunks.iter().flat_map(|chunk| {
+        [
+            ChunkCommand::Regenerate(chunk.position),
+            ChunkCommand::Remesh(chunk.position),
+        ]
+    }));
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    prelude::*,
+};
+use itertools::Itertools;
+
+use super::{template::StructureTemplate, GenerationStages};
+use crate::chunk::{ChunkCommand, Chunks};
+
+/// The folder structure templates are loaded from, relative to the assets directory.
+pub const STRUCTURES_PATH: &str = "structures";
+
+/// How far away a structure can be pasted, in blocks.
+const PASTE_DISTANCE: f32 = 64.0;
+
+/// A structure template loaded from a TOML file with the `.structure` extension.
+#[derive(Debug, Clone, Asset, TypePath)]
+pub struct StructureTemplateAsset(pub Arc<StructureTemplate>);
+
+/// Loads [`StructureTemplateAsset`]s from TOML files.
+#[derive(Default)]
+pub struct StructureTemplateLoader;
+
+impl AssetLoader for StructureTemplateLoader {
+    type Asset = StructureTemplateAsset;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> anyhow::Result<StructureTemplateAsset> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source).await?;
+        Ok(StructureTemplateAsset(Arc::new(
+            StructureTemplate::from_toml(&source)?,
+        )))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["structure"]
+    }
+}
+
+/// The structure templates that have been loaded, by name, in the order the generation stages
+/// index them.
+#[derive(Debug, Default, Resource)]
+pub struct StructureTemplates {
+    templates: Vec<(String, Arc<StructureTemplate>)>,
+    /// The index of the template pasted with P.
+    selected: usize,
+}
+
+impl StructureTemplates {
+    /// Return the template pasted with P, along with its name.
+    pub fn selected(&self) -> Option<(&str, &Arc<StructureTemplate>)> {
+        self.templates
+            .get(self.selected)
+            .map(|(name, template)| (name.as_str(), template))
+    }
+}
+
+/// A plugin loading structure templates from [`STRUCTURES_PATH`] for the generation stages to
+/// scatter, reloading them whenever they change. O cycles through the templates, and P pastes one
+/// onto the block the camera is looking at.
+pub struct StructureTemplatePlugin;
+
+impl Plugin for StructureTemplatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<StructureTemplateAsset>()
+            .init_asset_loader::<StructureTemplateLoader>()
+            .init_resource::<StructureTemplates>()
+            .add_systems(Startup, load_structure_templates)
+            .add_systems(
+                Update,
+                (
+                    apply_structure_templates.run_if(
+                        on_event::<AssetEvent<StructureTemplateAsset>>()
+                            .or_else(on_event::<AssetEvent<LoadedFolder>>()),
+                    ),
+                    select_structure_template,
+                    paste_structure_template,
+                ),
+            );
+    }
+}
+
+/// The handle keeping the structure templates loaded.
+#[derive(Resource)]
+struct StructureFolder(Handle<LoadedFolder>);
+
+/// Start loading the structure templates.
+fn load_structure_templates(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load_folder(STRUCTURES_PATH);
+    commands.insert_resource(StructureFolder(handle));
+}
+
+/// Hand the loaded templates to the generation stages, ordered by path so each keeps its index.
+/// Only chunks generated from then on are affected.
+fn apply_structure_templates(
+    folder: Res<StructureFolder>,
+    folders: Res<Assets<LoadedFolder>>,
+    assets: Res<Assets<StructureTemplateAsset>>,
+    stages: Res<GenerationStages>,
+    mut templates: ResMut<StructureTemplates>,
+) {
+    let Some(folder) = folders.get(&folder.0) else {
+        return;
+    };
+    let loaded = folder
+        .handles
+        .iter()
+        .filter_map(|handle| {
+            let handle = handle.clone().try_typed::<StructureTemplateAsset>().ok()?;
+            let name = handle.path()?.path().file_stem()?.to_string_lossy();
+            let StructureTemplateAsset(template) = assets.get(&handle)?;
+            Some((name.into_owned(), template.clone()))
+        })
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .collect_vec();
+    if loaded == templates.templates {
+        return;
+    }
+    info!("Loaded {} structure templates", loaded.len());
+    stages.set_templates(
+        loaded
+            .iter()
+            .map(|(_, template)| template.clone())
+            .collect(),
+    );
+    templates.templates = loaded;
+    templates.selected = 0;
+}
+
+/// Select the next structure template when O is pressed.
+fn select_structure_template(
+    input: Res<ButtonInput<KeyCode>>,
+    mut templates: ResMut<StructureTemplates>,
+) {
+    if !input.just_pressed(KeyCode::KeyO) || templates.templates.is_empty() {
+        return;
+    }
+    templates.selected = (templates.selected + 1) % templates.templates.len();
+    if let Some((name, _)) = templates.selected() {
+        info!("Selected structure template {}", name);
+    }
+}
+
+/// Paste the selected structure template with its anchor on the block the camera is looking at
+/// when P is pressed.
+fn paste_structure_template(
+    input: Res<ButtonInput<KeyCode>>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    chunks: Res<Chunks>,
+    templates: Res<StructureTemplates>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    if !input.just_pressed(KeyCode::KeyP) {
+        return;
+    }
+    let (Some((_, template)), Ok(camera)) = (templates.selected(), cameras.get_single()) else {
+        return;
+    };
+    let hit = chunks.raycast(camera.translation(), camera.forward(), PASTE_DISTANCE);
+    if let Some(hit) = hit {
+        chunk_commands.send(ChunkCommand::PasteStructure(
+            hit.adjacent_block(),
+            template.clone(),
+        ));
+    }
+}
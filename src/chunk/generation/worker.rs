@@ -0,0 +1,282 @@
+use std::{
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context};
+use bevy::prelude::*;
+
+use super::{
+    erosion::ErosionSettings,
+    pipeline::GenerationPipeline,
+    preset::WorldPreset,
+    seed::{TerrainNoise, SEED_ENV},
+    structure::SpilledBlock,
+    template::StructureTemplate,
+    terrain::TerrainSettings,
+    PRESET_ENV,
+};
+use crate::{
+    chunk::{Chunk, ChunkPos},
+    net::protocol::{read_message, write_message},
+};
+
+/// The environment variable naming a worker executable to generate chunks in.
+pub const WORKER_ENV: &str = "CHUNKY_WORLDGEN_WORKER";
+
+/// The longest a worker process may take to generate a chunk before it is considered stuck, killed,
+/// and restarted. Generous, since the first chunk of a region erodes the whole region.
+const WORKER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where chunks are generated.
+#[derive(Default, Clone, Resource)]
+pub enum GenerationBackend {
+    /// Generate chunks on the async compute pool of this process.
+    #[default]
+    Local,
+    /// Generate chunks in a separate worker process, so a crashing generator cannot take the app
+    /// down with it.
+    Worker(Arc<GenerationWorker>),
+}
+
+impl GenerationBackend {
+    /// Use a worker process generating worlds of the given preset if [`WORKER_ENV`] names one, and
+    /// otherwise generate locally.
+    pub fn from_env(preset: Option<WorldPreset>) -> Self {
+        match std::env::var_os(WORKER_ENV) {
+            Some(path) => {
+                // leave half the cores to the app, which meshes and lights the chunks
+                let processes = thread::available_parallelism().map_or(1, |n| n.get() / 2);
+                Self::Worker(Arc::new(GenerationWorker::new(
+                    path.into(),
+                    preset,
+                    processes,
+                )))
+            }
+            None => Self::Local,
+        }
+    }
+
+    /// Generate the chunk at the given position from the given noise through the given stages,
+    /// along with the blocks of its features that fall in other chunks. Waiting for a worker does
+    /// not hold up the task pool. If the worker fails or gets stuck, it is restarted and the chunk
+    /// is generated locally instead.
+    pub async fn generate(
+        &self,
+        pos: ChunkPos,
+        noise: &TerrainNoise,
+        stages: &GenerationPipeline,
+    ) -> (Chunk, Vec<SpilledBlock>) {
+        match self {
+            Self::Local => generate_local(pos, noise, stages),
+            Self::Worker(worker) => match worker.generate(pos, noise.seed(), stages).await {
+                Ok(generated) => generated,
+                Err(err) => {
+                    error!("Generation worker failed on {:?}: {:?}", pos, err);
+                    generate_local(pos, noise, stages)
+                }
+            },
+        }
+    }
+}
+
+/// Generate the chunk at the given position from the given noise through the given stages in this
+/// process, along with the blocks of its features that fall in other chunks.
+pub fn generate_local(
+    pos: ChunkPos,
+    noise: &TerrainNoise,
+    stages: &GenerationPipeline,
+) -> (Chunk, Vec<SpilledBlock>) {
+    let mut chunk = Chunk::empty(pos);
+    let spilled = stages.generate(&mut chunk, noise.noise());
+    (chunk, spilled)
+}
+
+/// A chunk to generate, along with the generation stages to skip, the shape of the terrain, how to
+/// erode it, and the structure templates to place, as sent to a worker process.
+type WorkerRequest = (
+    ChunkPos,
+    Vec<&'static str>,
+    TerrainSettings,
+    ErosionSettings,
+    Vec<StructureTemplate>,
+);
+
+/// A generated chunk, and the blocks of its features that fall in other chunks, as sent back by a
+/// worker process.
+type WorkerResponse = (Chunk, Vec<SpilledBlock>);
+
+/// A chunk waiting for a worker process to generate it.
+struct WorkerJob {
+    /// The seed to generate terrain from.
+    seed: u32,
+    request: WorkerRequest,
+    /// Where to send the generated chunk.
+    reply: async_channel::Sender<anyhow::Result<WorkerResponse>>,
+}
+
+/// A running worker process and its pipes. Responses are read on a thread of their own, so waiting
+/// for one can time out.
+struct WorkerProcess {
+    /// The seed the process generates terrain from.
+    seed: u32,
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    responses: mpsc::Receiver<anyhow::Result<WorkerResponse>>,
+}
+
+impl WorkerProcess {
+    /// Start the worker executable at the given path, generating terrain from the given seed and
+    /// worlds of the given preset.
+    fn spawn(path: &PathBuf, preset: Option<WorldPreset>, seed: u32) -> anyhow::Result<Self> {
+        let mut command = Command::new(path);
+        command.env(SEED_ENV, seed.to_string());
+        if let Some(preset) = preset {
+            command.env(PRESET_ENV, preset.name());
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start {}", path.display()))?;
+        let stdin = BufWriter::new(child.stdin.take().context("worker has no stdin")?);
+        let mut stdout = BufReader::new(child.stdout.take().context("worker has no stdout")?);
+        // the reader stops once the process exits and its output closes
+        let (sender, responses) = mpsc::channel();
+        thread::spawn(move || loop {
+            let response = read_message(&mut stdout);
+            let failed = response.is_err();
+            if sender.send(response).is_err() || failed {
+                break;
+            }
+        });
+        info!("Started generation worker {}", path.display());
+        Ok(Self {
+            seed,
+            child,
+            stdin,
+            responses,
+        })
+    }
+
+    /// Send a request to the process and wait for its response, up to [`WORKER_TIMEOUT`].
+    fn request(&mut self, request: &WorkerRequest) -> anyhow::Result<WorkerResponse> {
+        write_message(&mut self.stdin, request)?;
+        match self.responses.recv_timeout(WORKER_TIMEOUT) {
+            Ok(response) => response,
+            Err(RecvTimeoutError::Timeout) => Err(anyhow!(
+                "worker did not respond within {:?}",
+                WORKER_TIMEOUT
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(anyhow!("worker exited")),
+        }
+    }
+
+    /// Stop the process.
+    fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A pool of worker processes that generate chunks, speaking length-prefixed bincode over their
+/// standard input and output. Each process is driven by a thread of its own, which takes chunks
+/// from a shared queue, so chunks are generated in parallel and waiting on a process never blocks
+/// the task pools. Processes are started on first use, and restarted after they fail or get stuck.
+pub struct GenerationWorker {
+    /// The queue of chunks waiting to be generated. Closed when the worker is dropped, which stops
+    /// the threads and their processes.
+    jobs: mpsc::Sender<WorkerJob>,
+}
+
+impl GenerationWorker {
+    /// Create a pool of the given number of processes running the given executable, generating
+    /// worlds of the given preset.
+    pub fn new(path: PathBuf, preset: Option<WorldPreset>, processes: usize) -> Self {
+        let (jobs, queue) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        for _ in 0..processes.max(1) {
+            let (path, queue) = (path.clone(), queue.clone());
+            thread::spawn(move || run_worker(&path, preset, &queue));
+        }
+        Self { jobs }
+    }
+
+    /// Generate the chunk at the given position from the given seed in a worker process, skipping
+    /// the stages turned off in the given pipeline, shaping and eroding terrain by its settings, and
+    /// placing its structure templates, along with the blocks of its features that fall in other
+    /// chunks. A process is restarted if it was started with a different seed.
+    pub async fn generate(
+        &self,
+        pos: ChunkPos,
+        seed: u32,
+        stages: &GenerationPipeline,
+    ) -> anyhow::Result<(Chunk, Vec<SpilledBlock>)> {
+        let templates = stages
+            .templates()
+            .iter()
+            .map(|template| StructureTemplate::clone(template))
+            .collect::<Vec<_>>();
+        let request = (
+            pos,
+            stages.disabled(),
+            stages.terrain(),
+            stages.erosion(),
+            templates,
+        );
+        let (reply, response) = async_channel::bounded(1);
+        self.jobs
+            .send(WorkerJob {
+                seed,
+                request,
+                reply,
+            })
+            .map_err(|_| anyhow!("generation workers have stopped"))?;
+        response
+            .recv()
+            .await
+            .map_err(|_| anyhow!("generation worker dropped the chunk"))?
+    }
+}
+
+/// Generate the chunks taken from the queue in a worker process, until the queue closes.
+fn run_worker(
+    path: &PathBuf,
+    preset: Option<WorldPreset>,
+    queue: &Mutex<mpsc::Receiver<WorkerJob>>,
+) {
+    let mut process: Option<WorkerProcess> = None;
+    loop {
+        // hold the lock only while waiting, so the other threads can take jobs while this works
+        let Ok(job) = queue.lock().unwrap().recv() else {
+            break;
+        };
+        if let Some(worker) = process.take_if(|worker| worker.seed != job.seed) {
+            worker.kill();
+        }
+        let result = match &mut process {
+            Some(worker) => Ok(worker),
+            None => {
+                WorkerProcess::spawn(path, preset, job.seed).map(|worker| process.insert(worker))
+            }
+        }
+        .and_then(|worker| worker.request(&job.request));
+        if result.is_err() {
+            // the process is in an unknown state, or stuck, so start a fresh one next time
+            if let Some(worker) = process.take() {
+                worker.kill();
+            }
+        }
+        let _ = job.reply.send_blocking(result);
+    }
+    if let Some(worker) = process {
+        worker.kill();
+    }
+}
@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+
+pub use chunky_core::chunk::light::{
+    light_chunk, relight_block, relight_blocks, remove_light, spread_light, LightChannel,
+    LightWorld,
+};
+
+use super::{
+    light::LightLevel, section::Section, BlockPos, BlockType, ChunkLoaded, ChunkPos, Chunks,
+};
+
+impl LightWorld for Chunks {
+    fn block_at(&self, pos: IVec3) -> Option<BlockType> {
+        self.block_at_world_block(pos)
+    }
+
+    fn light_at(&self, pos: IVec3) -> Option<LightLevel> {
+        self.light_at_world_block(pos)
+    }
+
+    fn set_light(&mut self, pos: IVec3, level: LightLevel) {
+        self.set_light_at_world_block(pos, level);
+    }
+
+    fn is_empty_section(&self, pos: IVec3) -> Option<bool> {
+        self.get(ChunkPos::from_world_block(pos)).map(|chunk| {
+            chunk.sections()[Section::index_of(BlockPos::from_world_block(pos))].is_empty()
+        })
+    }
+}
+
+/// Relight the blocks set since the last fixed tick, so light spreads from placed emitters and
+/// into dug-out spaces, and is taken away by removing emitters or walling light out.
+pub(super) fn relight_changed_blocks(mut chunks: ResMut<Chunks>) {
+    let changed = chunks.changed().to_vec();
+    relight_blocks(&mut *chunks, changed);
+}
+
+/// Light chunks as they are loaded, letting sunlight down into them, spreading light from the
+/// blocks in them that give it off, and spreading light in from the chunks around them.
+pub(super) fn light_loaded_chunks(
+    mut loaded: EventReader<ChunkLoaded>,
+    mut chunks: ResMut<Chunks>,
+) {
+    for ChunkLoaded(pos) in loaded.read() {
+        light_chunk(&mut *chunks, pos.to_world().as_ivec3());
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::{
+    asset::load_internal_asset,
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+
+/// The shader discarding chunk geometry above the clip plane.
+const CHUNK_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x0063_6875_6e6b_795f_636c_6970);
+
+/// How brightly chunk lightmaps light the faces they cover, matching the ambient light at midday.
+const LIGHTMAP_EXPOSURE: f32 = 500.0;
+
+/// The material chunk meshes are drawn with.
+pub type ChunkMaterial = ExtendedMaterial<StandardMaterial, ClipPlane>;
+
+/// A horizontal plane above which chunk geometry is not drawn, exposing the inside of the terrain.
+#[derive(Debug, Clone, Asset, AsBindGroup, Reflect)]
+pub struct ClipPlane {
+    /// The world height of the plane, in blocks.
+    #[uniform(100)]
+    pub height: f32,
+}
+
+impl Default for ClipPlane {
+    /// A plane too high to clip anything.
+    fn default() -> Self {
+        Self { height: f32::MAX }
+    }
+}
+
+impl MaterialExtension for ClipPlane {
+    fn fragment_shader() -> ShaderRef {
+        CHUNK_SHADER_HANDLE.into()
+    }
+}
+
+/// The material shared by every chunk mesh, so the clip plane moves for all of them at once.
+#[derive(Resource, Deref)]
+pub struct ChunkMaterialHandle(Handle<ChunkMaterial>);
+
+impl FromWorld for ChunkMaterialHandle {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<ChunkMaterial>>();
+        Self(materials.add(ChunkMaterial {
+            base: StandardMaterial {
+                // water is drawn partly transparent, dithered so chunks need not be sorted
+                alpha_mode: AlphaMode::AlphaToCoverage,
+                lightmap_exposure: LIGHTMAP_EXPOSURE,
+                ..StandardMaterial::from_color(Color::BLACK)
+            },
+            extension: ClipPlane::default(),
+        }))
+    }
+}
+
+/// A plugin for drawing chunk meshes with [`ChunkMaterial`].
+pub struct ChunkMaterialPlugin;
+
+impl Plugin for ChunkMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, CHUNK_SHADER_HANDLE, "material.wgsl", Shader::from_wgsl);
+        app.add_plugins(MaterialPlugin::<ChunkMaterial>::default())
+            .init_resource::<ChunkMaterialHandle>();
+    }
+}
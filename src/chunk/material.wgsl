@@ -0,0 +1,27 @@
+// The chunk material: the standard PBR material, with every fragment above the clip height
+// discarded so the inside of the terrain can be seen.
+
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+
+struct ClipPlane {
+    height: f32,
+}
+
+@group(2) @binding(100) var<uniform> clip_plane: ClipPlane;
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    if in.world_position.y > clip_plane.height {
+        discard;
+    }
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
@@ -0,0 +1,50 @@
+use bevy::{
+    prelude::{Image, Mesh},
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+
+pub use chunky_core::chunk::mesh::*;
+
+/// A chunk's mesh converted for Bevy, along with the lightmap its faces are lit by, if it has one.
+pub type ChunkMeshAssets = (Mesh, Option<Image>);
+
+/// Convert the geometry of a chunk's mesh into a Bevy mesh, and its lightmap into an image.
+pub fn to_bevy_mesh(data: MeshData) -> ChunkMeshAssets {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::all())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, data.positions)
+        .with_inserted_indices(Indices::U32(data.indices))
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, data.normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, data.colors);
+    let Some(lightmap) = data.lightmap.filter(|lightmap| lightmap.width > 0) else {
+        return (mesh, None);
+    };
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, data.lightmap_uvs);
+    (mesh, Some(to_bevy_image(&lightmap)))
+}
+
+/// Convert a chunk's lightmap into a grey image, filtered linearly so light blends across faces.
+fn to_bevy_image(lightmap: &lightmap::Lightmap) -> Image {
+    let texels = lightmap
+        .texels
+        .iter()
+        .flat_map(|&brightness| [brightness, brightness, brightness, u8::MAX])
+        .collect();
+    let mut image = Image::new(
+        Extent3d {
+            width: lightmap.width,
+            height: lightmap.height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        texels,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::linear();
+    image
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    utils::{HashMap, Instant},
+};
+
+use super::{ChunkLoaded, ChunkPos, Chunks};
+
+/// The share of chunk lookups that found their chunk loaded, as a percentage.
+pub const CACHE_HIT_RATE: DiagnosticPath = DiagnosticPath::const_new("chunks/hit_rate");
+
+/// Chunks unloaded for being over the memory budget or too long unaccessed, per second.
+pub const CHUNKS_EVICTED: DiagnosticPath = DiagnosticPath::const_new("chunks/evicted");
+
+/// The median time from a chunk being asked for to its mesh being shown, in milliseconds.
+pub const LOAD_LATENCY_P50: DiagnosticPath = DiagnosticPath::const_new("chunks/latency_p50");
+
+/// The 95th percentile time from a chunk being asked for to its mesh being shown, in milliseconds.
+pub const LOAD_LATENCY_P95: DiagnosticPath = DiagnosticPath::const_new("chunks/latency_p95");
+
+/// The 99th percentile time from a chunk being asked for to its mesh being shown, in milliseconds.
+pub const LOAD_LATENCY_P99: DiagnosticPath = DiagnosticPath::const_new("chunks/latency_p99");
+
+/// The number of recent loads the latency percentiles are taken over.
+const LATENCY_WINDOW: usize = 256;
+
+/// Running totals of chunk lookups and evictions.
+#[derive(Debug, Default)]
+pub(super) struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evicted: AtomicU64,
+}
+
+impl CacheCounters {
+    /// Record a lookup, which hit if it found its chunk loaded.
+    pub(super) fn record_lookup(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record chunks being evicted.
+    pub(super) fn record_evictions(&self, count: usize) {
+        self.evicted.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Read the totals.
+    pub(super) fn snapshot(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Totals of chunk lookups and evictions since the chunks were created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of lookups that found their chunk loaded.
+    pub hits: u64,
+    /// The number of lookups of chunks that were not loaded.
+    pub misses: u64,
+    /// The number of chunks unloaded to bound memory.
+    pub evicted: u64,
+}
+
+impl CacheStats {
+    /// Return the share of lookups that found their chunk loaded, or `None` if there were none.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+/// The times chunks took to load, from the [`super::ChunkCommand::Load`] asking for them to the
+/// frame they were inserted into the world, which is the frame their mesh is first shown. Only the
+/// most recent loads are kept, so the percentiles follow changes to the pipeline as they happen.
+#[derive(Debug, Default, Resource)]
+pub struct ChunkLatency {
+    /// When each chunk still loading was asked for.
+    started: HashMap<ChunkPos, Instant>,
+    /// The latencies of the most recent loads, in milliseconds, oldest first.
+    samples: VecDeque<f64>,
+}
+
+impl ChunkLatency {
+    /// Record a chunk being asked for.
+    pub(super) fn start(&mut self, pos: ChunkPos) {
+        self.started.entry(pos).or_insert_with(Instant::now);
+    }
+
+    /// Record a chunk being loaded, if it was asked for.
+    fn finish(&mut self, pos: ChunkPos) {
+        let Some(started) = self.started.remove(&pos) else {
+            return;
+        };
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples
+            .push_back(started.elapsed().as_secs_f64() * 1000.0);
+    }
+
+    /// Return the latency the given share of recent loads finished within, in milliseconds, or
+    /// `None` if no chunks have loaded yet.
+    pub fn percentile(&self, share: f64) -> Option<f64> {
+        let mut sorted = self.samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort_by(f64::total_cmp);
+        let last = sorted.len().checked_sub(1)?;
+        Some(sorted[(last as f64 * share.clamp(0.0, 1.0)).round() as usize])
+    }
+}
+
+/// A plugin for measuring how well the loaded chunks serve lookups, and how long chunks take to
+/// appear, through Bevy diagnostics.
+pub struct ChunkMetricsPlugin;
+
+impl Plugin for ChunkMetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkLatency>()
+            .register_diagnostic(Diagnostic::new(CACHE_HIT_RATE).with_suffix("%"))
+            .register_diagnostic(Diagnostic::new(CHUNKS_EVICTED).with_suffix("/s"))
+            .register_diagnostic(Diagnostic::new(LOAD_LATENCY_P50).with_suffix(" ms"))
+            .register_diagnostic(Diagnostic::new(LOAD_LATENCY_P95).with_suffix(" ms"))
+            .register_diagnostic(Diagnostic::new(LOAD_LATENCY_P99).with_suffix(" ms"))
+            .add_systems(Last, (measure_chunks, measure_latency));
+    }
+}
+
+/// Convert the totals since the last frame into a hit rate and eviction rate.
+fn measure_chunks(
+    mut diagnostics: Diagnostics,
+    time: Res<Time>,
+    chunks: Res<Chunks>,
+    mut last: Local<CacheStats>,
+) {
+    let delta = time.delta_seconds_f64();
+    if delta <= 0.0 {
+        return;
+    }
+    let stats = chunks.cache_stats();
+    let interval = CacheStats {
+        hits: stats.hits - last.hits,
+        misses: stats.misses - last.misses,
+        evicted: stats.evicted - last.evicted,
+    };
+    *last = stats;
+
+    if let Some(rate) = interval.hit_rate() {
+        diagnostics.add_measurement(&CACHE_HIT_RATE, || rate * 100.0);
+    }
+    diagnostics.add_measurement(&CHUNKS_EVICTED, || interval.evicted as f64 / delta);
+}
+
+/// Finish timing the chunks loaded this frame, and report the latency percentiles of recent loads.
+fn measure_latency(
+    mut diagnostics: Diagnostics,
+    mut latency: ResMut<ChunkLatency>,
+    mut loaded: EventReader<ChunkLoaded>,
+) {
+    for ChunkLoaded(pos) in loaded.read() {
+        latency.finish(*pos);
+    }
+    for (path, share) in [
+        (&LOAD_LATENCY_P50, 0.5),
+        (&LOAD_LATENCY_P95, 0.95),
+        (&LOAD_LATENCY_P99, 0.99),
+    ] {
+        if let Some(value) = latency.percentile(share) {
+            diagnostics.add_measurement(path, || value);
+        }
+    }
+}
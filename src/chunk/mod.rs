@@ -1,295 +1,601 @@
-mod mesh;
+pub mod animation;
+pub mod appearance;
+pub mod blocks;
+mod budget;
+pub mod entities;
+pub mod falling;
+pub mod fluid;
+pub mod generation;
+pub mod lighting;
+pub mod material;
+pub mod mesh;
+pub mod metrics;
+pub mod progress;
+pub mod queue;
+pub mod random_tick;
+pub mod raycast;
+pub mod scheduled;
+pub mod undo;
+pub mod updates;
 
-use std::{
-    cmp::Ordering,
-    collections::BTreeMap,
-    fmt::Debug,
-    ops::{Add, Sub},
-};
+use std::sync::Arc;
 
+use animation::{rise_chunk_meshes, ChunkAnimation, RisingIn};
+use appearance::{
+    appearances_stale, invalidate_appearances, remesh_stale_chunks, AppearanceGeneration,
+    BlockAppearanceChanged,
+};
 use bevy::{
-    pbr::wireframe::Wireframe,
+    pbr::Lightmap,
     prelude::*,
     tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
-    utils::{HashMap, HashSet},
+    time::common_conditions::on_timer,
+    utils::HashMap,
+};
+use blocks::{install_blocks, BlockAppExtension, BlockRegistry};
+use budget::{enforce_memory_budget, unload_stale_chunks};
+use delta::ChunkDelta;
+use density::SculptBrush;
+use entities::{
+    record_chunk_entities, respawn_chunk_entities, stash_unloading_entities, ChunkEntityKinds,
+    RECORD_INTERVAL,
+};
+use falling::{drop_unsupported_blocks, move_falling_blocks, show_falling_blocks, FallingBlock};
+use fluid::flow_liquids;
+use generation::{
+    pipeline::GenerationPipeline,
+    seed::TerrainNoise,
+    structure::{self, PendingBlocks, SpilledBlock},
+    template::StructureTemplate,
+    worker::GenerationBackend,
+    GenerationStages,
+};
+use itertools::{iproduct, Itertools};
+use key::{ChunkMap, ChunkSet};
+use light::LightLevel;
+use lighting::{light_loaded_chunks, relight_changed_blocks};
+use material::{ChunkMaterialHandle, ChunkMaterialPlugin};
+use mesh::{ChunkMeshAssets, LightingMode, MeshData, MeshingMode};
+use metrics::{CacheCounters, CacheStats, ChunkLatency, ChunkMetricsPlugin};
+use micro::MicroResolution;
+use progress::{
+    track_load_progress, GenerationProgress, LoadProgress, LoadStage, ProgressReporter,
+};
+use queue::{dispatch_loads, loads_pending, LoadQueue};
+use random_tick::{decay_leaves, run_random_ticks, spread_grass, RandomTickSettings};
+use scheduled::{run_scheduled_changes, BlockTicks, ScheduledChange, WorldClock};
+use section::Section;
+use state::BlockState;
+use undo::{UndoEntry, UndoHistory};
+use updates::{send_block_updates, BlockUpdate, BlockUpdateQueue, BlockUpdateSettings};
+
+pub use chunky_core::chunk::{
+    delta, density, entity, key, light, micro, migration, occupancy, registry, section, state,
+    surface, BlockData, BlockPos, BlockType, Chunk, ChunkPos, ItemStack, CHUNK_SIZE,
 };
-use itertools::iproduct;
-use mesh::ChunkNeighbours;
-use noise::NoiseFn;
-
-/// The size of a chunk along one axis, measured in blocks.
-pub const CHUNK_SIZE: u8 = 32;
-
-/// A position of a chunk in the world in chunk coordinates.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct ChunkPos {
-    pub x: i64,
-    pub y: i64,
-    pub z: i64,
+
+use crate::{
+    channel::ChannelAppExtension,
+    environment::Environment,
+    player::RenderDistance,
+    storage::{
+        autosave, check_integrity, has_store, metadata::WorldMetadata,
+        metrics::StorageMetricsPlugin, save_on_exit, AutosaveSettings, ChunkStore,
+        IntegritySettings, WorldStorage,
+    },
+};
+
+/// The environment variable choosing how chunk meshes are lit, `vertex` or `lightmap`.
+pub const LIGHTING_ENV: &str = "CHUNKY_LIGHTING";
+
+/// A collection of chunks.
+#[derive(Default, Resource)]
+pub struct Chunks {
+    /// A set of chunks that are busy, i.e. undergoing loading, unloading, or mesh building.
+    busy: ChunkSet,
+    /// A map of chunk positions to chunks.
+    chunks: ChunkMap<Chunk>,
+    /// A set of chunks that have been modified and need their meshes rebuilt.
+    remesh: ChunkSet,
+    /// Blocks of features that reach into chunks which have not been loaded yet.
+    pending: PendingBlocks,
+    /// The world block coordinates of blocks changed since the last fixed tick, however they were
+    /// changed, for relighting them and sending the blocks around them [`BlockUpdate`]s.
+    changed: Vec<IVec3>,
+    /// The blocks changed in each watched chunk since they were last taken, however they were
+    /// changed, for passing on to whatever watches the chunk, such as the clients subscribed to it.
+    watched: ChunkMap<Vec<BlockPos>>,
+    /// The number of frames that have passed, used to stamp chunk accesses.
+    frame: u64,
+    /// The estimated memory the loaded chunks may use before the least recently accessed are
+    /// unloaded, in bytes. Unlimited if `None`.
+    memory_budget: Option<usize>,
+    /// The number of frames a chunk may go unaccessed before it is unloaded, and saved if it has
+    /// been modified. Unlimited if `None`.
+    max_age: Option<u64>,
+    /// The lowest and highest chunk Y coordinates that are generated and loaded. Unbounded in
+    /// either direction if `None`.
+    height_bounds: (Option<i64>, Option<i64>),
+    /// The chunks the players are in, and how far around each the chunks they need are loaded. The
+    /// memory budget never evicts chunks within these areas, which would only be loaded again.
+    load_areas: Vec<(ChunkPos, RenderDistance)>,
+    /// Counts of lookups and evictions, for measuring how well the loaded chunks serve lookups.
+    counters: CacheCounters,
 }
 
-impl From<(i64, i64, i64)> for ChunkPos {
-    fn from((x, y, z): (i64, i64, i64)) -> Self {
-        Self::new(x, y, z)
+impl Chunks {
+    /// Create an empty collection of chunks with the given memory budget, in bytes.
+    pub fn with_memory_budget(memory_budget: Option<usize>) -> Self {
+        Self {
+            memory_budget,
+            ..Default::default()
+        }
     }
-}
 
-impl Add for ChunkPos {
-    type Output = Self;
+    /// Set the estimated memory the loaded chunks may use before the least recently accessed are
+    /// unloaded, in bytes. Unlimited if `None`.
+    pub fn set_memory_budget(&mut self, memory_budget: Option<usize>) {
+        self.memory_budget = memory_budget;
+    }
 
-    fn add(self, other: Self) -> Self {
-        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    /// Set the number of frames a chunk may go unaccessed before it is unloaded, and saved if it
+    /// has been modified. Unlimited if `None`.
+    pub fn set_max_age(&mut self, max_age: Option<u64>) {
+        self.max_age = max_age;
     }
-}
 
-impl Sub for ChunkPos {
-    type Output = Self;
+    /// Set the lowest and highest chunk Y coordinates that are generated and loaded. Chunks
+    /// outside them are never loaded, and features reaching into them are cut off. Unbounded in
+    /// either direction if `None`.
+    pub fn set_height_bounds(&mut self, min: Option<i64>, max: Option<i64>) {
+        self.height_bounds = (min, max);
+    }
 
-    fn sub(self, other: Self) -> Self {
-        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    /// Set the chunks the players are in, and how far around each the chunks they need are loaded.
+    pub fn set_load_areas(&mut self, areas: impl IntoIterator<Item = (ChunkPos, RenderDistance)>) {
+        self.load_areas.clear();
+        self.load_areas.extend(areas);
     }
-}
 
-impl ChunkPos {
-    const NORTH: Self = Self { x: 0, y: 0, z: -1 };
-    const EAST: Self = Self { x: 1, y: 0, z: 0 };
-    const SOUTH: Self = Self { x: 0, y: 0, z: 1 };
-    const WEST: Self = Self { x: -1, y: 0, z: 0 };
-    const UP: Self = Self { x: 0, y: 1, z: 0 };
-    const DOWN: Self = Self { x: 0, y: -1, z: 0 };
+    /// Check if the chunk at the given position is within the area loaded around any player.
+    pub fn in_load_area(&self, pos: ChunkPos) -> bool {
+        self.load_areas
+            .iter()
+            .any(|&(center, distance)| distance.contains(center, pos, 0))
+    }
 
-    /// Create a new chunk position.
-    pub fn new(x: i64, y: i64, z: i64) -> Self {
-        Self { x, y, z }
+    /// Check if the chunk at the given position lies within the world's height bounds.
+    pub fn in_bounds(&self, pos: ChunkPos) -> bool {
+        let (min, max) = self.height_bounds;
+        min.is_none_or(|min| pos.y >= min) && max.is_none_or(|max| pos.y <= max)
     }
 
-    /// Create a new chunk position from a world position.
-    pub fn from_world(pos: Vec3) -> Self {
-        Self {
-            x: pos.x as i64 / CHUNK_SIZE as i64,
-            y: pos.y as i64 / CHUNK_SIZE as i64,
-            z: pos.z as i64 / CHUNK_SIZE as i64,
+    /// Return how many lookups have found their chunk loaded, and how many chunks have been
+    /// evicted.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.counters.snapshot()
+    }
+
+    /// Check if the chunk at the given position is loaded. Counts as an access, but not as a
+    /// lookup in the cache statistics.
+    pub fn is_loaded(&self, pos: ChunkPos) -> bool {
+        let chunk = self.chunks.get(&pos.key());
+        if let Some(chunk) = chunk {
+            chunk.record_access(self.frame);
         }
+        chunk.is_some()
     }
 
-    pub fn to_world(&self) -> Vec3 {
-        Vec3::new(
-            self.x as f32 * CHUNK_SIZE as f32,
-            self.y as f32 * CHUNK_SIZE as f32,
-            self.z as f32 * CHUNK_SIZE as f32,
-        )
+    /// Check if the chunk at the given position is busy.
+    pub fn is_busy(&self, pos: ChunkPos) -> bool {
+        self.busy.contains(&pos.key())
     }
 
-    /// Return an iterator over the neighboring chunk positions.
-    pub fn neighbors(&self, radius: i64) -> impl Iterator<Item = ChunkPos> + '_ {
-        iproduct!(-radius..=radius, -radius..=radius, -radius..=radius)
-            .filter(|&(dx, dy, dz)| dx != 0 || dy != 0 || dz != 0)
-            .map(move |(dx, dy, dz)| ChunkPos::new(self.x + dx, self.y + dy, self.z + dz))
+    /// Mark the chunk at the given position as busy, such as while it is being written to the
+    /// world's store, returning `false` if it already was.
+    pub fn mark_busy(&mut self, pos: ChunkPos) -> bool {
+        self.busy.insert(pos.key())
     }
 
-    /// Return the largest component
-    pub fn max(&self) -> i64 {
-        self.x.max(self.y.max(self.z))
+    /// Mark the chunk at the given position as no longer busy.
+    pub fn mark_idle(&mut self, pos: ChunkPos) {
+        self.busy.remove(&pos.key());
     }
-}
 
-/// A position of a block within a chunk in block coordinates.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct BlockPos {
-    pub x: u8,
-    pub y: u8,
-    pub z: u8,
-}
+    /// Check if no chunks are loading, unloading, or waiting for their meshes to be rebuilt.
+    pub fn is_settled(&self) -> bool {
+        self.busy.is_empty() && self.remesh.is_empty()
+    }
 
-impl PartialOrd for BlockPos {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    /// Check if the chunk at the given position is unloaded.
+    pub fn is_unloaded(&self, pos: ChunkPos) -> bool {
+        !self.is_loaded(pos) && !self.is_busy(pos)
     }
-}
 
-impl Ord for BlockPos {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // enforce zxy order
-        self.z
-            .cmp(&other.z)
-            .then_with(|| self.x.cmp(&other.x))
-            .then_with(|| self.y.cmp(&other.y))
+    /// Get the chunk at the given position.
+    pub fn get(&self, pos: ChunkPos) -> Option<&Chunk> {
+        let chunk = self.chunks.get(&pos.key());
+        self.counters.record_lookup(chunk.is_some());
+        let chunk = chunk?;
+        chunk.record_access(self.frame);
+        Some(chunk)
     }
-}
 
-impl Add for BlockPos {
-    type Output = Self;
+    /// Get a mutable reference to the chunk at the given position.
+    pub fn get_mut(&mut self, pos: ChunkPos) -> Option<&mut Chunk> {
+        let chunk = self.chunks.get_mut(&pos.key());
+        self.counters.record_lookup(chunk.is_some());
+        let chunk = chunk?;
+        chunk.record_access(self.frame);
+        Some(chunk)
+    }
 
-    fn add(self, other: Self) -> Self {
-        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    /// Get the chunk at the given position without counting it as accessed or looked up, for work
+    /// such as saving that should not keep the chunk loaded.
+    pub fn peek(&self, pos: ChunkPos) -> Option<&Chunk> {
+        self.chunks.get(&pos.key())
     }
-}
 
-impl Sub for BlockPos {
-    type Output = Self;
+    /// Get a mutable reference to the chunk at the given position without counting it as accessed
+    /// or looked up.
+    pub fn peek_mut(&mut self, pos: ChunkPos) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&pos.key())
+    }
 
-    fn sub(self, other: Self) -> Self {
-        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    /// Return an iterator over loaded chunks.
+    pub fn iter(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
     }
-}
 
-impl BlockPos {
-    /// Create a new block position.
-    pub fn new(x: u8, y: u8, z: u8) -> Self {
-        Self { x, y, z }
+    /// Return a mutable iterator over loaded chunks.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Chunk> {
+        self.chunks.values_mut()
     }
 
-    /// Return an iterator over all block positions in a chunk.
-    pub fn all() -> impl Iterator<Item = BlockPos> {
-        iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE, 0..CHUNK_SIZE).map(|pos| pos.into())
+    /// Return an iterator over loaded chunks within the given Chebyshev distance of a position.
+    pub fn iter_in_radius(&self, center: ChunkPos, radius: i64) -> impl Iterator<Item = &Chunk> {
+        iproduct!(-radius..=radius, -radius..=radius, -radius..=radius)
+            .filter_map(move |offset| self.get(center + offset.into()))
     }
 
-    pub fn world_pos(&self, chunk_pos: ChunkPos) -> Vec3 {
-        Vec3::new(
-            (chunk_pos.x * CHUNK_SIZE as i64 + self.x as i64) as f32,
-            (chunk_pos.y * CHUNK_SIZE as i64 + self.y as i64) as f32,
-            (chunk_pos.z * CHUNK_SIZE as i64 + self.z as i64) as f32,
-        )
+    /// Get the block at the given world position, or `None` if its chunk is not loaded.
+    pub fn block_at_world(&self, pos: Vec3) -> Option<BlockType> {
+        self.block_at_world_block(pos.floor().as_ivec3())
     }
-}
 
-impl From<(u8, u8, u8)> for BlockPos {
-    fn from((x, y, z): (u8, u8, u8)) -> Self {
-        Self::new(x, y, z)
+    /// Get the block at the given world block coordinates, or `None` if its chunk is not loaded.
+    pub fn block_at_world_block(&self, pos: IVec3) -> Option<BlockType> {
+        self.get(ChunkPos::from_world_block(pos))
+            .map(|chunk| *chunk.block_at(BlockPos::from_world_block(pos)))
     }
-}
 
-impl Into<(u8, u8, u8)> for BlockPos {
-    fn into(self) -> (u8, u8, u8) {
-        (self.x, self.y, self.z)
+    /// Set the block at the given world position, returning the block it replaced, or `None` if
+    /// its chunk is not loaded.
+    pub fn set_block_at_world(&mut self, pos: Vec3, block: BlockType) -> Option<BlockType> {
+        self.set_block_at_world_block(pos.floor().as_ivec3(), block)
     }
-}
 
-impl From<BlockPos> for IVec3 {
-    fn from(pos: BlockPos) -> IVec3 {
-        IVec3::new(pos.x as i32, pos.y as i32, pos.z as i32)
+    /// Set the block at the given world block coordinates, returning the block it replaced, or
+    /// `None` if its chunk is not loaded.
+    pub fn set_block_at_world_block(&mut self, pos: IVec3, block: BlockType) -> Option<BlockType> {
+        let chunk = self.get_mut(ChunkPos::from_world_block(pos))?;
+        let block_pos = BlockPos::from_world_block(pos);
+        let previous = *chunk.block_at(block_pos);
+        chunk.set_block(block_pos, block);
+        self.queue_remesh(pos, pos);
+        self.record_change(pos);
+        Some(previous)
     }
-}
 
-/// The data of a chunk.
-pub struct Chunk {
-    /// The position of the chunk in the world.
-    pub position: ChunkPos,
-    /// The block data of the chunk.
-    data: BTreeMap<BlockPos, BlockType>,
-}
+    /// Get the state of the block at the given world block coordinates, or `None` if its chunk is
+    /// not loaded.
+    pub fn state_at_world_block(&self, pos: IVec3) -> Option<BlockState> {
+        self.get(ChunkPos::from_world_block(pos))
+            .map(|chunk| chunk.state_at(BlockPos::from_world_block(pos)))
+    }
 
-impl Debug for Chunk {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Chunk")
-            .field("position", &self.position)
-            .field("blocks", &self.data.len())
-            .finish()
+    /// Set the state of the block at the given world block coordinates, such as the direction it
+    /// faces, returning the state it replaced, or `None` if its chunk is not loaded.
+    pub fn set_block_state_at_world_block(
+        &mut self,
+        pos: IVec3,
+        state: BlockState,
+    ) -> Option<BlockState> {
+        let chunk = self.get_mut(ChunkPos::from_world_block(pos))?;
+        let block_pos = BlockPos::from_world_block(pos);
+        let previous = chunk.state_at(block_pos);
+        chunk.set_block_state(block_pos, state);
+        self.queue_remesh(pos, pos);
+        self.record_change(pos);
+        Some(previous)
     }
-}
 
-impl Chunk {
-    /// Create an empty chunk.
-    pub fn empty(position: ChunkPos) -> Self {
-        Self {
-            position,
-            data: BTreeMap::new(),
-        }
+    /// Return the world block coordinates of the blocks set since the last fixed tick.
+    pub fn changed(&self) -> &[IVec3] {
+        &self.changed
     }
 
-    /// Create a chunk filled with a block.
-    pub fn filled(mut self, block: BlockType) -> Self {
-        self.fill(block);
-        self
+    /// Take the world block coordinates of the blocks set since this was last called.
+    pub fn take_changed(&mut self) -> Vec<IVec3> {
+        std::mem::take(&mut self.changed)
     }
 
-    /// Get the block at the given position.
-    pub fn block_at<I: Into<BlockPos>>(&self, pos: I) -> &BlockType {
-        &self.data.get(&pos.into()).unwrap_or(&BlockType::Empty)
+    /// Start recording the blocks changed in the given chunk, to be taken with
+    /// [`Chunks::take_edits`]. Does nothing if the chunk is already watched.
+    pub fn watch(&mut self, pos: ChunkPos) {
+        self.watched.entry(pos.key()).or_default();
     }
 
-    /// Return an iterator over all blocks in the chunk, ordered by their position.
-    pub fn blocks(&self) -> impl Iterator<Item = (BlockPos, BlockType)> + '_ {
-        BlockPos::all().filter_map(move |pos| self.data.get(&pos).map(|&block| (pos, block)))
+    /// Stop recording the blocks changed in the given chunk, dropping any not yet taken.
+    pub fn unwatch(&mut self, pos: ChunkPos) {
+        self.watched.remove(&pos.key());
     }
 
-    /// Generate the chunk.
-    fn generate_mut(&mut self, noise: &noise::OpenSimplex) {
-        for (x, y, z) in iproduct!(0..CHUNK_SIZE, 0..CHUNK_SIZE, 0..CHUNK_SIZE) {
-            let nx = (self.position.x * CHUNK_SIZE as i64 + x as i64) as f64;
-            let ny = (self.position.y * CHUNK_SIZE as i64 + y as i64) as f64;
-            let nz = (self.position.z * CHUNK_SIZE as i64 + z as i64) as f64;
-            let value = noise.get([nx / 10.0, ny / 10.0, nz / 10.0]);
-            if value > 0.0 {
-                self.set_block((x, y, z), BlockType::Stone);
-            }
+    /// Return the positions of the watched chunks.
+    pub fn watched(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.watched.keys().map(|&key| ChunkPos::from(key))
+    }
+
+    /// Take the positions of the blocks changed in a watched chunk since this was last called for
+    /// it, without repeats. Empty if the chunk is not watched.
+    pub fn take_edits(&mut self, pos: ChunkPos) -> Vec<BlockPos> {
+        self.watched
+            .get_mut(&pos.key())
+            .map(|edits| std::mem::take(edits).into_iter().unique().collect())
+            .unwrap_or_default()
+    }
+
+    /// Record a change to the block at the given world block coordinates, for relighting it,
+    /// sending the blocks around it [`BlockUpdate`]s, and passing it on if its chunk is watched.
+    fn record_change(&mut self, pos: IVec3) {
+        self.changed.push(pos);
+        self.record_edit(pos);
+    }
+
+    /// Record a change to the block at the given world block coordinates for passing on, if its
+    /// chunk is watched.
+    fn record_edit(&mut self, pos: IVec3) {
+        if let Some(edits) = self.watched.get_mut(&ChunkPos::from_world_block(pos).key()) {
+            edits.push(BlockPos::from_world_block(pos));
         }
     }
 
-    /// Set the block at the given position.
-    fn set_block<Pos: Into<BlockPos>>(&mut self, pos: Pos, block: BlockType) {
-        self.data.insert(pos.into(), block);
+    /// Get the light level at the given world block coordinates, or `None` if its chunk is not
+    /// loaded.
+    pub fn light_at_world_block(&self, pos: IVec3) -> Option<LightLevel> {
+        self.get(ChunkPos::from_world_block(pos))
+            .map(|chunk| chunk.light_at(BlockPos::from_world_block(pos)))
     }
 
-    /// Fill the chunk with a block.
-    fn fill(&mut self, block: BlockType) {
-        for pos in BlockPos::all() {
-            self.set_block(pos, block);
+    /// Set the light level at the given world block coordinates, and rebuild the meshes it lights
+    /// if it changed. Does nothing if its chunk is not loaded.
+    pub fn set_light_at_world_block(&mut self, pos: IVec3, level: LightLevel) {
+        let Some(chunk) = self.get_mut(ChunkPos::from_world_block(pos)) else {
+            return;
+        };
+        let block_pos = BlockPos::from_world_block(pos);
+        if chunk.light_at(block_pos) == level {
+            return;
         }
+        chunk.set_light(block_pos, level);
+        self.queue_remesh(pos, pos);
     }
-}
 
-/// The type of a block in the world.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub enum BlockType {
-    #[default]
-    Empty,
-    Stone,
-}
+    /// Carve the block at the given world block coordinates, removing the sub-voxel nearest to the
+    /// given world position. Returns whether anything was removed, or `None` if the block's chunk is
+    /// not loaded.
+    pub fn carve_at_world_block(
+        &mut self,
+        pos: IVec3,
+        point: Vec3,
+        resolution: MicroResolution,
+    ) -> Option<bool> {
+        let chunk = self.get_mut(ChunkPos::from_world_block(pos))?;
+        let carved = chunk.carve(
+            BlockPos::from_world_block(pos),
+            point - pos.as_vec3(),
+            resolution,
+        );
+        if carved {
+            self.queue_remesh(pos, pos);
+        }
+        Some(carved)
+    }
 
-impl BlockType {
-    /// Check if this block is opaque.
-    pub fn is_opaque(&self) -> bool {
-        match self {
-            Self::Stone => true,
-            _ => false,
+    /// Sculpt the terrain around the given world position with a brush. Blocks in chunks that are
+    /// not loaded are left untouched.
+    pub fn sculpt(&mut self, center: Vec3, brush: SculptBrush) {
+        let min = (center - brush.radius).floor().as_ivec3();
+        let max = (center + brush.radius).floor().as_ivec3();
+        let (min_chunk, max_chunk) = (
+            ChunkPos::from_world_block(min),
+            ChunkPos::from_world_block(max),
+        );
+        for (x, y, z) in iproduct!(
+            min_chunk.x..=max_chunk.x,
+            min_chunk.y..=max_chunk.y,
+            min_chunk.z..=max_chunk.z
+        ) {
+            let Some(chunk) = self.get_mut(ChunkPos::new(x, y, z)) else {
+                continue;
+            };
+            let origin = chunk.position.to_world().as_ivec3();
+            let lo = min.max(origin);
+            let hi = max.min(origin + IVec3::splat(CHUNK_SIZE as i32 - 1));
+            chunk.sculpt(iproduct!(lo.x..=hi.x, lo.y..=hi.y, lo.z..=hi.z).filter_map(
+                |(x, y, z)| {
+                    let pos = IVec3::new(x, y, z);
+                    // measure from the centre of the block
+                    let distance = center.distance(pos.as_vec3() + 0.5);
+                    (distance < brush.radius)
+                        .then(|| (BlockPos::from_world_block(pos), brush.falloff(distance)))
+                },
+            ));
         }
+        self.queue_remesh(min, max);
     }
-}
 
-/// A collection of chunks.
-#[derive(Default, Resource)]
-pub struct Chunks {
-    /// A set of chunks that are busy, i.e. undergoing loading, unloading, or mesh building.
-    busy: HashSet<ChunkPos>,
-    /// A map of chunk positions to chunks.
-    chunks: HashMap<ChunkPos, Chunk>,
-}
+    /// Queue a change to the block at the given world block coordinates, to happen at the given
+    /// tick of the [`WorldClock`]. Returns `false` if the block's chunk is not loaded.
+    pub fn schedule_block_change(&mut self, pos: IVec3, block: BlockType, tick: u64) -> bool {
+        let Some(chunk) = self.get_mut(ChunkPos::from_world_block(pos)) else {
+            return false;
+        };
+        chunk.schedule(ScheduledChange {
+            tick,
+            pos: BlockPos::from_world_block(pos),
+            block,
+        });
+        true
+    }
 
-impl Chunks {
-    /// Get the chunk at the given position.
-    pub fn is_loaded(&self, pos: ChunkPos) -> bool {
-        self.get(pos).is_some()
+    /// Fill the box between the two world block coordinates (inclusive) with a block. Blocks in
+    /// chunks that are not loaded are left untouched, and blocks that already hold it are not
+    /// counted as changed.
+    pub fn fill_region(&mut self, min: IVec3, max: IVec3, block: BlockType) {
+        let (min, max) = (min.min(max), min.max(max));
+        let (min_chunk, max_chunk) = (
+            ChunkPos::from_world_block(min),
+            ChunkPos::from_world_block(max),
+        );
+        for (x, y, z) in iproduct!(
+            min_chunk.x..=max_chunk.x,
+            min_chunk.y..=max_chunk.y,
+            min_chunk.z..=max_chunk.z
+        ) {
+            let Some(chunk) = self.get_mut(ChunkPos::new(x, y, z)) else {
+                continue;
+            };
+            // clamp the region to this chunk, in world block coordinates
+            let origin = chunk.position.to_world().as_ivec3();
+            let lo = min.max(origin);
+            let hi = max.min(origin + IVec3::splat(CHUNK_SIZE as i32 - 1));
+            let changed = iproduct!(lo.x..=hi.x, lo.y..=hi.y, lo.z..=hi.z)
+                .map(|(x, y, z)| IVec3::new(x, y, z))
+                .filter(|&pos| *chunk.block_at(BlockPos::from_world_block(pos)) != block)
+                .collect_vec();
+            chunk.set_blocks(
+                changed
+                    .iter()
+                    .map(|&pos| (BlockPos::from_world_block(pos), block)),
+            );
+            changed.into_iter().for_each(|pos| self.record_change(pos));
+        }
+        self.queue_remesh(min, max);
     }
 
-    /// Check if the chunk at the given position is busy.
-    pub fn is_busy(&self, pos: ChunkPos) -> bool {
-        self.busy.contains(&pos)
+    /// Place a structure with its anchor at the given world block coordinates, replacing the
+    /// blocks it covers. Blocks falling in chunks that are not loaded are left out.
+    pub fn paste_structure(&mut self, anchor: IVec3, template: &StructureTemplate) {
+        for (pos, block) in template.blocks_at(anchor) {
+            self.set_block_at_world_block(pos, block);
+        }
     }
 
-    /// Check if the chunk at the given position is unloaded.
-    pub fn is_unloaded(&self, pos: ChunkPos) -> bool {
-        !self.is_loaded(pos) && !self.is_busy(pos)
+    /// Make an edit confined to the box between the two world block coordinates (inclusive),
+    /// returning the changes that undo it in the loaded chunks the box covers. The entry is empty
+    /// if the edit changed nothing, such as when none of those chunks are loaded.
+    pub fn edit_undoable(
+        &mut self,
+        min: IVec3,
+        max: IVec3,
+        edit: impl FnOnce(&mut Self),
+    ) -> UndoEntry {
+        let (min_chunk, max_chunk) = (
+            ChunkPos::from_world_block(min.min(max)),
+            ChunkPos::from_world_block(min.max(max)),
+        );
+        let before = iproduct!(
+            min_chunk.x..=max_chunk.x,
+            min_chunk.y..=max_chunk.y,
+            min_chunk.z..=max_chunk.z
+        )
+        .filter_map(|(x, y, z)| self.get(ChunkPos::new(x, y, z)).cloned())
+        .collect_vec();
+        edit(self);
+        before
+            .iter()
+            .filter_map(|base| {
+                let chunk = self.get(base.position)?;
+                let delta = ChunkDelta::between(base, chunk);
+                (!delta.is_empty()).then(|| delta.reverted(base))
+            })
+            .collect()
     }
 
-    /// Get the chunk at the given position.
-    pub fn get(&self, pos: ChunkPos) -> Option<&Chunk> {
-        self.chunks.get(&pos)
+    /// Insert a chunk received from elsewhere, such as from a server, replacing any loaded copy,
+    /// and queue it and its neighbours for remeshing.
+    pub fn insert(&mut self, chunk: Chunk) {
+        chunk.record_access(self.frame);
+        let origin = chunk.position.to_world().as_ivec3();
+        self.busy.remove(&chunk.position.key());
+        self.chunks.insert(chunk.position.key(), chunk);
+        self.queue_remesh(origin, origin + IVec3::splat(CHUNK_SIZE as i32 - 1));
     }
 
-    /// Return an iterator over loaded chunks.
-    pub fn iter(&self) -> impl Iterator<Item = &Chunk> {
-        self.chunks.values()
+    /// Apply a delta to its chunk, returning `false` if the chunk is not loaded.
+    pub fn apply_delta(&mut self, delta: &ChunkDelta) -> bool {
+        let Some(chunk) = self.get_mut(delta.position) else {
+            return false;
+        };
+        delta.apply(chunk);
+        let origin = delta.position.to_world().as_ivec3();
+        self.queue_remesh(origin, origin + IVec3::splat(CHUNK_SIZE as i32 - 1));
+        for pos in delta.positions() {
+            self.record_change(origin + IVec3::from(pos));
+        }
+        true
+    }
+
+    /// Queue a remesh of every loaded chunk whose mesh may be affected by changes to the box
+    /// between the two world block coordinates (inclusive), including neighbours that share a face
+    /// with the box.
+    fn queue_remesh(&mut self, min: IVec3, max: IVec3) {
+        let min_chunk = ChunkPos::from_world_block(min - IVec3::ONE);
+        let max_chunk = ChunkPos::from_world_block(max + IVec3::ONE);
+        for (x, y, z) in iproduct!(
+            min_chunk.x..=max_chunk.x,
+            min_chunk.y..=max_chunk.y,
+            min_chunk.z..=max_chunk.z
+        ) {
+            let pos = ChunkPos::new(x, y, z);
+            if self.is_loaded(pos) {
+                self.remesh.insert(pos.key());
+            }
+        }
+    }
+
+    /// Place blocks of features that reach out of a generated chunk into the chunks they fall in,
+    /// holding those for chunks that are not loaded until they are.
+    fn place_spilled<I: IntoIterator<Item = SpilledBlock>>(&mut self, spilled: I) {
+        for (pos, blocks) in spilled.into_iter().into_group_map_by(|&(pos, _, _)| pos) {
+            if !self.in_bounds(pos) {
+                continue;
+            }
+            let Some(chunk) = self.chunks.get_mut(&pos.key()) else {
+                blocks
+                    .into_iter()
+                    .for_each(|block| self.pending.defer(block));
+                continue;
+            };
+            let origin = pos.to_world().as_ivec3();
+            structure::place(chunk, blocks.iter().map(|&(_, pos, block)| (pos, block)));
+            for (_, pos, _) in blocks {
+                let world = origin + IVec3::from(pos);
+                self.queue_remesh(world, world);
+                self.record_edit(world);
+            }
+        }
+    }
+
+    /// Return copies of the six neighbours of the given chunk, in the order expected by
+    /// [`mesh::build_with_neighbours`]. Neighbours that are not loaded are treated as solid stone,
+    /// so the faces they share with the chunk are culled.
+    fn neighbours_of(&self, pos: ChunkPos) -> [Chunk; 6] {
+        ChunkPos::FACE_NEIGHBOURS.map(|dir| match self.get(pos + dir) {
+            Some(chunk) => chunk.clone(),
+            None => Chunk::empty(pos + dir).filled(BlockType::STONE),
+        })
     }
 }
 
@@ -302,66 +608,533 @@ pub enum ChunkCommand {
     Unload(ChunkPos),
     /// Modify a block at the given position.
     ModifyBlock(ChunkPos, BlockPos, BlockType),
+    /// Fill the box between two world block coordinates (inclusive) with a block.
+    FillRegion(IVec3, IVec3, BlockType),
+    /// Rebuild the mesh of a chunk at the given position.
+    Remesh(ChunkPos),
+    /// Generate a loaded chunk afresh, discarding its edits and any saved copy.
+    Regenerate(ChunkPos),
+    /// Place a structure with its anchor at the given world block coordinates.
+    PasteStructure(IVec3, Arc<StructureTemplate>),
+    /// Undo the newest block edit, fill, or structure placement still in the [`UndoHistory`].
+    /// Changes to chunks that have since unloaded are lost.
+    Undo,
 }
 
 #[derive(Event)]
 pub enum ChunkEvent {
-    /// The chunk was successfully loaded.
-    LoadComplete(Chunk, Mesh),
+    /// The chunk was successfully loaded, along with the blocks of its features that fall in other
+    /// chunks if it was generated, and its mesh if meshing is enabled.
+    LoadComplete(Box<Chunk>, Vec<SpilledBlock>, Option<ChunkMeshAssets>),
     /// The chunk was successfully unloaded.
     UnloadComplete(ChunkPos),
+    /// The chunk's mesh was rebuilt from the given revision of its data.
+    RemeshComplete(ChunkPos, u64, ChunkMeshAssets),
 }
 
+/// An event sent when a chunk's mesh has been built or rebuilt.
+#[derive(Event)]
+pub struct ChunkMeshed {
+    /// The position of the chunk.
+    pub position: ChunkPos,
+    /// The revision of the chunk data the mesh was built from.
+    pub revision: u64,
+    /// The mesh of the chunk.
+    pub mesh: Mesh,
+    /// The lightmap the mesh's faces are lit by, if chunks are lit with lightmaps.
+    pub lightmap: Option<Image>,
+}
+
+/// An event sent when a chunk has been loaded, or generated afresh.
+#[derive(Event)]
+pub struct ChunkLoaded(pub ChunkPos);
+
+/// An event sent when a chunk has been unloaded.
+#[derive(Event)]
+pub struct ChunkUnloaded(pub ChunkPos);
+
+/// An event sent when a block is broken by a player, such as with [`ChunkCommand::ModifyBlock`]
+/// or a thrown projectile, after it has been removed.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BlockBroken {
+    /// The world block coordinates of the block.
+    pub pos: IVec3,
+    /// The block that was broken.
+    pub block: BlockType,
+}
+
+/// An event sent when a block is placed by a player, such as with [`ChunkCommand::ModifyBlock`],
+/// after it has been set.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BlockPlaced {
+    /// The world block coordinates of the block.
+    pub pos: IVec3,
+    /// The block that was placed.
+    pub block: BlockType,
+}
+
+/// An event sent when a block has been set with [`ChunkCommand::ModifyBlock`], along with the
+/// changes that undo it, so the edit can be rolled back later, such as when a server rejects it.
+#[derive(Event, Debug, Clone)]
+pub struct BlockEdited {
+    /// The world block coordinates of the block.
+    pub pos: IVec3,
+    /// The block that was set.
+    pub block: BlockType,
+    /// The changes that undo the edit.
+    pub undo: UndoEntry,
+}
+
+/// A component for entities displaying a chunk's mesh.
+#[derive(Component)]
+pub struct ChunkMesh {
+    /// The position of the chunk.
+    pub position: ChunkPos,
+    /// The revision of the chunk data the current mesh was built from.
+    pub revision: u64,
+}
+
+/// A map of chunk positions to the entities displaying their meshes.
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct ChunkMeshEntities(HashMap<ChunkPos, Entity>);
+
 /// A component for storing a running chunk task.
 #[derive(Component)]
 struct ChunkTask(Task<anyhow::Result<ChunkEvent>>);
 
+/// Settings for the chunk pipeline.
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct ChunkSettings {
+    /// Whether chunk meshes are built and displayed.
+    pub meshing: bool,
+    /// How chunk meshes are built, which is chosen per world.
+    pub mesh_mode: MeshingMode,
+    /// How chunk meshes are lit.
+    pub lighting: LightingMode,
+    /// Whether edits other than setting single blocks are made: fills, structure pastes, undos and
+    /// regeneration. These are refused while joined to a server, which only hears of single block
+    /// edits.
+    pub bulk_edits: bool,
+}
+
+/// Return the lighting mode chosen in [`LIGHTING_ENV`], or vertex light if it is not set.
+fn lighting_from_env() -> LightingMode {
+    let Ok(lighting) = std::env::var(LIGHTING_ENV) else {
+        return LightingMode::default();
+    };
+    lighting.parse().unwrap_or_else(|err| {
+        warn!("Ignoring {}: {:?}", LIGHTING_ENV, err);
+        LightingMode::default()
+    })
+}
+
+/// The stages of the chunk pipeline, so other systems can be ordered around the engine's. Tasks are
+/// polled and their results applied in [`PreUpdate`], commands are taken in and modified chunks
+/// remeshed in [`PostUpdate`], and chunks are saved in [`Last`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+pub enum ChunkSystems {
+    /// Reads [`ChunkCommand`]s and starts the tasks they call for.
+    CommandIntake,
+    /// Polls running chunk tasks, inserting loaded chunks into [`Chunks`] and sending
+    /// [`ChunkMeshed`] and [`ChunkUnloaded`] events.
+    TaskPolling,
+    /// Applies the results of finished tasks to the world, such as spawning chunk meshes.
+    ApplyResults,
+    /// Starts rebuilding the meshes of modified chunks.
+    Remesh,
+    /// Writes modified chunks to the world's store.
+    Persistence,
+}
+
 /// Plugin for handling chunk events.
-pub struct ChunkPlugin;
+#[derive(Default)]
+pub struct ChunkPlugin {
+    /// Run without building or displaying chunk meshes, e.g. on a dedicated server.
+    pub headless: bool,
+}
 
 impl Plugin for ChunkPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ChunkCommand>()
+        app.add_plugins((ChunkMetricsPlugin, StorageMetricsPlugin))
+            .add_event::<ChunkCommand>()
+            .add_event::<ChunkMeshed>()
+            .add_event::<ChunkLoaded>()
+            .add_event::<ChunkUnloaded>()
+            .add_event::<BlockUpdate>()
+            .add_event::<BlockBroken>()
+            .add_event::<BlockPlaced>()
+            .add_event::<BlockEdited>()
+            .add_event::<BlockAppearanceChanged>()
+            .insert_resource(ChunkSettings {
+                meshing: !self.headless,
+                mesh_mode: MeshingMode::default(),
+                lighting: lighting_from_env(),
+                bulk_edits: true,
+            })
             .init_resource::<Chunks>()
-            .add_systems(PreUpdate, poll_chunk_events)
-            .add_systems(PostUpdate, process_chunk_commands);
+            .init_resource::<BlockRegistry>()
+            .add_channel::<GenerationProgress>()
+            .init_resource::<LoadQueue>()
+            .init_resource::<LoadProgress>()
+            .init_resource::<UndoHistory>()
+            .init_resource::<AppearanceGeneration>()
+            .init_resource::<WorldStorage>()
+            .init_resource::<AutosaveSettings>()
+            .init_resource::<IntegritySettings>()
+            .init_resource::<GenerationBackend>()
+            .init_resource::<GenerationStages>()
+            .init_resource::<TerrainNoise>()
+            .init_resource::<WorldClock>()
+            .init_resource::<BlockTicks>()
+            .init_resource::<BlockUpdateQueue>()
+            .init_resource::<BlockUpdateSettings>()
+            .init_resource::<RandomTickSettings>()
+            .on_random_tick(BlockType::GRASS, spread_grass)
+            .on_random_tick(BlockType::LEAVES, decay_leaves)
+            .init_resource::<Environment>()
+            .configure_sets(
+                PreUpdate,
+                (ChunkSystems::TaskPolling, ChunkSystems::ApplyResults).chain(),
+            )
+            .configure_sets(
+                PostUpdate,
+                (ChunkSystems::CommandIntake, ChunkSystems::Remesh).chain(),
+            )
+            .configure_sets(Last, ChunkSystems::Persistence)
+            .add_systems(PreStartup, check_integrity.run_if(has_store))
+            .add_systems(Startup, load_world_metadata)
+            .add_systems(
+                FixedUpdate,
+                (
+                    run_scheduled_changes,
+                    run_random_ticks,
+                    relight_changed_blocks,
+                    send_block_updates,
+                    flow_liquids,
+                    drop_unsupported_blocks,
+                    move_falling_blocks.run_if(any_with_component::<FallingBlock>),
+                )
+                    .chain(),
+            )
+            // skip the pipeline's systems on frames with nothing for them to do
+            .add_systems(
+                PreUpdate,
+                track_load_progress.run_if(on_event::<GenerationProgress>()),
+            )
+            .add_systems(
+                PreUpdate,
+                poll_chunk_events
+                    .run_if(any_with_component::<ChunkTask>)
+                    .in_set(ChunkSystems::TaskPolling),
+            )
+            .add_systems(
+                PreUpdate,
+                light_loaded_chunks
+                    .run_if(on_event::<ChunkLoaded>())
+                    .in_set(ChunkSystems::ApplyResults),
+            )
+            .add_systems(
+                PreUpdate,
+                respawn_chunk_entities
+                    .run_if(resource_exists::<ChunkEntityKinds>.and_then(on_event::<ChunkLoaded>()))
+                    .in_set(ChunkSystems::ApplyResults),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    (
+                        enforce_memory_budget,
+                        unload_stale_chunks,
+                        stash_unloading_entities.run_if(resource_exists::<ChunkEntityKinds>),
+                        process_chunk_commands.run_if(on_event::<ChunkCommand>()),
+                        dispatch_loads.run_if(loads_pending),
+                    )
+                        .chain()
+                        .in_set(ChunkSystems::CommandIntake),
+                    remesh_modified_chunks
+                        .run_if(remesh_pending)
+                        .in_set(ChunkSystems::Remesh),
+                ),
+            )
+            .add_systems(
+                Last,
+                record_chunk_entities
+                    .run_if(
+                        resource_exists::<ChunkEntityKinds>
+                            .and_then(on_timer(RECORD_INTERVAL).or_else(on_event::<AppExit>())),
+                    )
+                    .before(ChunkSystems::Persistence),
+            )
+            .add_systems(
+                Last,
+                (
+                    autosave.run_if(has_store),
+                    save_on_exit.run_if(on_event::<AppExit>()),
+                )
+                    .chain()
+                    .in_set(ChunkSystems::Persistence),
+            );
+        if !self.headless {
+            app.add_plugins(ChunkMaterialPlugin)
+                .init_resource::<ChunkMeshEntities>()
+                .init_resource::<ChunkAnimation>()
+                .add_systems(
+                    Update,
+                    (
+                        rise_chunk_meshes.run_if(any_with_component::<RisingIn>),
+                        show_falling_blocks.run_if(any_with_component::<FallingBlock>),
+                    ),
+                )
+                .add_systems(
+                    PostUpdate,
+                    (
+                        invalidate_appearances.run_if(on_event::<BlockAppearanceChanged>()),
+                        remesh_stale_chunks.run_if(appearances_stale),
+                    )
+                        .chain()
+                        .before(ChunkSystems::CommandIntake),
+                )
+                .add_systems(
+                    PreUpdate,
+                    update_chunk_meshes
+                        .run_if(on_event::<ChunkMeshed>().or_else(on_event::<ChunkUnloaded>()))
+                        .in_set(ChunkSystems::ApplyResults),
+                );
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        install_blocks(app);
+    }
+}
+
+/// Restore the world's clock and meshing mode from its metadata.
+fn load_world_metadata(
+    storage: Res<WorldStorage>,
+    mut clock: ResMut<WorldClock>,
+    mut settings: ResMut<ChunkSettings>,
+) {
+    let Some(path) = &storage.path else {
+        return;
+    };
+    match WorldMetadata::load(path) {
+        Ok(metadata) => {
+            *clock = WorldClock::new(metadata.tick);
+            settings.mesh_mode = metadata.meshing;
+        }
+        Err(err) => error!("Failed to load world metadata: {:?}", err),
     }
 }
 
-/// System that processes
+/// System that processes chunk commands.
 fn process_chunk_commands(
     mut commands: Commands,
-    mut chunk_commands: EventReader<ChunkCommand>,
+    (mut chunk_commands, mut broken, mut placed, mut edited): (
+        EventReader<ChunkCommand>,
+        EventWriter<BlockBroken>,
+        EventWriter<BlockPlaced>,
+        EventWriter<BlockEdited>,
+    ),
     mut chunks: ResMut<Chunks>,
+    (mut queue, mut progress, mut history, mut latency): (
+        ResMut<LoadQueue>,
+        ResMut<LoadProgress>,
+        ResMut<UndoHistory>,
+        ResMut<ChunkLatency>,
+    ),
+    settings: Res<ChunkSettings>,
+    storage: Res<WorldStorage>,
+    (generator, noise, stages): (
+        Res<GenerationBackend>,
+        Res<TerrainNoise>,
+        Res<GenerationStages>,
+    ),
 ) {
     let pool = AsyncComputeTaskPool::get();
-    if chunk_commands.len() != 0 {
+    if !chunk_commands.is_empty() {
         info!("Processing {} chunk commands", chunk_commands.len());
     }
     for chunk_command in chunk_commands.read() {
         let task = match chunk_command {
             ChunkCommand::Load(pos) => {
-                chunks.busy.insert(*pos);
-                pool.spawn(load_chunk(*pos))
+                if !chunks.in_bounds(*pos) {
+                    continue;
+                }
+                // queued chunks count as busy, so they are not asked for again while they wait
+                if chunks.busy.insert(pos.key()) {
+                    queue.push(*pos);
+                    progress.total += 1;
+                    latency.start(*pos);
+                }
+                continue;
             }
             ChunkCommand::Unload(pos) => {
-                chunks.busy.insert(*pos);
-                pool.spawn(unload_chunk(*pos))
+                // a chunk still being saved or regenerated is left for whoever asked to ask again
+                if chunks.is_busy(*pos) {
+                    continue;
+                }
+                // take the chunk out now, so it is not unloaded twice or edited while saving
+                let Some(chunk) = chunks.chunks.remove(&pos.key()) else {
+                    continue;
+                };
+                chunks.busy.insert(pos.key());
+                let dirty = chunk.is_dirty().then_some(chunk);
+                pool.spawn(unload_chunk(*pos, dirty, storage.store.clone()))
             }
             ChunkCommand::ModifyBlock(pos, block_pos, block) => {
-                pool.spawn(modify_block(*pos, *block_pos, *block))
+                let world = pos.to_world().as_ivec3() + IVec3::from(*block_pos);
+                let mut previous = None;
+                let undo = chunks.edit_undoable(world, world, |chunks| {
+                    previous = chunks.set_block_at_world_block(world, *block);
+                });
+                if !undo.is_empty() {
+                    edited.send(BlockEdited {
+                        pos: world,
+                        block: *block,
+                        undo: undo.clone(),
+                    });
+                    history.push(undo);
+                }
+                // setting a block to itself changes nothing, and replacing one block with
+                // another breaks the old block as well as placing the new one
+                let Some(previous) = previous.filter(|previous| previous != block) else {
+                    continue;
+                };
+                if previous != BlockType::EMPTY {
+                    broken.send(BlockBroken {
+                        pos: world,
+                        block: previous,
+                    });
+                }
+                if *block != BlockType::EMPTY {
+                    placed.send(BlockPlaced {
+                        pos: world,
+                        block: *block,
+                    });
+                }
+                continue;
+            }
+            ChunkCommand::FillRegion(..)
+            | ChunkCommand::PasteStructure(..)
+            | ChunkCommand::Undo
+            | ChunkCommand::Regenerate(_)
+                if !settings.bulk_edits =>
+            {
+                warn!("Only single blocks can be edited while joined to a server");
+                continue;
+            }
+            ChunkCommand::FillRegion(min, max, block) => {
+                let undo = chunks
+                    .edit_undoable(*min, *max, |chunks| chunks.fill_region(*min, *max, *block));
+                if !undo.is_empty() {
+                    history.push(undo);
+                }
+                continue;
+            }
+            ChunkCommand::Remesh(pos) => {
+                chunks.remesh.insert(pos.key());
+                continue;
+            }
+            ChunkCommand::PasteStructure(anchor, template) => {
+                let min = *anchor - IVec3::from(template.anchor);
+                let max = min + UVec3::from(template.size).as_ivec3() - IVec3::ONE;
+                let undo = chunks
+                    .edit_undoable(min, max, |chunks| chunks.paste_structure(*anchor, template));
+                if !undo.is_empty() {
+                    history.push(undo);
+                }
+                continue;
+            }
+            ChunkCommand::Undo => {
+                let Some(entry) = history.pop() else {
+                    continue;
+                };
+                for delta in &entry {
+                    if !chunks.apply_delta(delta) {
+                        warn!("Cannot undo changes to unloaded chunk {:?}", delta.position);
+                    }
+                }
+                continue;
+            }
+            ChunkCommand::Regenerate(pos) => {
+                let Some(revision) = chunks.get(*pos).map(Chunk::revision) else {
+                    continue;
+                };
+                if chunks.is_busy(*pos) {
+                    warn!("Cannot regenerate busy chunk {:?}", pos);
+                    continue;
+                }
+                chunks.busy.insert(pos.key());
+                pool.spawn(regenerate_chunk(
+                    *pos,
+                    revision,
+                    *settings,
+                    generator.clone(),
+                    noise.clone(),
+                    stages.clone(),
+                ))
             }
         };
         commands.spawn(ChunkTask(task));
     }
 }
 
+/// Check if any modified chunks are waiting for their meshes to be rebuilt.
+fn remesh_pending(chunks: Res<Chunks>) -> bool {
+    !chunks.remesh.is_empty()
+}
+
+/// System that rebuilds the meshes of modified chunks, leaving those still loading for later.
+fn remesh_modified_chunks(
+    mut commands: Commands,
+    mut chunks: ResMut<Chunks>,
+    mut meshed: EventWriter<ChunkMeshed>,
+    settings: Res<ChunkSettings>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    let pending = chunks.remesh.drain().map(ChunkPos::from).collect_vec();
+    if !settings.meshing {
+        return;
+    }
+    for pos in pending {
+        if chunks.is_busy(pos) {
+            chunks.remesh.insert(pos.key());
+            continue;
+        }
+        let Some(chunk) = chunks.get(pos) else {
+            continue;
+        };
+        // blocky meshes only have faces for blocks in the chunk, so a chunk whose sections are all
+        // empty needs neither a task nor copies of its neighbours
+        if settings.mesh_mode == MeshingMode::Blocky
+            && chunk.sections().iter().all(Section::is_empty)
+        {
+            let (mesh, lightmap) = mesh::to_bevy_mesh(MeshData::default());
+            meshed.send(ChunkMeshed {
+                position: pos,
+                revision: chunk.revision(),
+                mesh,
+                lightmap,
+            });
+            continue;
+        }
+        let task = pool.spawn(remesh_chunk(
+            chunk.clone(),
+            chunks.neighbours_of(pos),
+            settings.mesh_mode,
+            settings.lighting,
+        ));
+        commands.spawn(ChunkTask(task));
+    }
+}
+
+/// System that polls running chunk tasks and applies their results.
 fn poll_chunk_events(
     mut commands: Commands,
     mut tasks: Query<(Entity, &mut ChunkTask)>,
     mut chunks: ResMut<Chunks>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshed: EventWriter<ChunkMeshed>,
+    (mut loaded, mut unloaded): (EventWriter<ChunkLoaded>, EventWriter<ChunkUnloaded>),
 ) {
     tasks
         .iter_mut()
@@ -376,65 +1149,219 @@ fn poll_chunk_events(
         })
         .for_each(|(entity, event)| {
             match event {
-                ChunkEvent::LoadComplete(chunk, mesh) => {
-                    // spawn shit mesh
-                    commands.spawn((PbrBundle {
-                        transform: Transform::from_translation(chunk.position.to_world()),
-                        mesh: meshes.add(mesh),
-                        material: materials.add(StandardMaterial::from_color(Color::BLACK)),
-                        ..default()
-                    },));
-                    chunks.chunks.insert(chunk.position, chunk);
+                ChunkEvent::LoadComplete(chunk, spilled, mesh) => {
+                    if let Some((mesh, lightmap)) = mesh {
+                        meshed.send(ChunkMeshed {
+                            position: chunk.position,
+                            revision: chunk.revision(),
+                            mesh,
+                            lightmap,
+                        });
+                    }
+                    chunks.busy.remove(&chunk.position.key());
+                    chunk.record_access(chunks.frame);
+                    let pos = chunk.position;
+                    chunks.chunks.insert(pos.key(), *chunk);
+                    loaded.send(ChunkLoaded(pos));
+                    // finish features generated into the chunk while it was not loaded
+                    let pending = chunks.pending.take(pos);
+                    let pending = pending
+                        .into_iter()
+                        .map(|(block_pos, block)| (pos, block_pos, block));
+                    chunks.place_spilled(pending.chain(spilled));
                 }
                 ChunkEvent::UnloadComplete(pos) => {
-                    chunks.chunks.remove(&pos);
-                    chunks.busy.remove(&pos);
+                    chunks.busy.remove(&pos.key());
+                    unloaded.send(ChunkUnloaded(pos));
+                }
+                ChunkEvent::RemeshComplete(position, revision, (mesh, lightmap)) => {
+                    meshed.send(ChunkMeshed {
+                        position,
+                        revision,
+                        mesh,
+                        lightmap,
+                    });
                 }
             }
             commands.entity(entity).despawn();
         });
 }
 
-pub async fn load_chunk(pos: ChunkPos) -> anyhow::Result<ChunkEvent> {
-    let noise = noise::OpenSimplex::new(0);
-
-    // load all neighbouring chunks
-    let mut chunk = Chunk::empty(pos);
-    let north = Chunk::empty(pos + ChunkPos::NORTH).filled(BlockType::Stone);
-    let east = Chunk::empty(pos + ChunkPos::EAST).filled(BlockType::Stone);
-    let south = Chunk::empty(pos + ChunkPos::SOUTH).filled(BlockType::Stone);
-    let west = Chunk::empty(pos + ChunkPos::WEST).filled(BlockType::Stone);
-    let up = Chunk::empty(pos + ChunkPos::UP).filled(BlockType::Stone);
-    let down = Chunk::empty(pos + ChunkPos::DOWN).filled(BlockType::Stone);
-
-    // generate
-    chunk.generate_mut(&noise);
-
-    // construct neighbours
-    let data = ChunkNeighbours {
-        chunk: &chunk,
-        north: &north,
-        east: &east,
-        south: &south,
-        west: &west,
-        up: &up,
-        down: &down,
+/// System that spawns, updates, and despawns the entities displaying chunk meshes.
+fn update_chunk_meshes(
+    mut commands: Commands,
+    mut meshed: EventReader<ChunkMeshed>,
+    mut unloaded: EventReader<ChunkUnloaded>,
+    mut entities: ResMut<ChunkMeshEntities>,
+    mut mesh_entities: Query<(Entity, &mut ChunkMesh, &mut Handle<Mesh>)>,
+    (mut meshes, mut images): (ResMut<Assets<Mesh>>, ResMut<Assets<Image>>),
+    (material, animation): (Res<ChunkMaterialHandle>, Res<ChunkAnimation>),
+) {
+    for ChunkMeshed {
+        position,
+        revision,
+        mesh,
+        lightmap,
+    } in meshed.read()
+    {
+        let lightmap = lightmap.clone().map(|image| Lightmap {
+            image: images.add(image),
+            uv_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+        });
+        let existing = entities.get(position).copied();
+        if let Some((entity, mut chunk_mesh, mut handle)) =
+            existing.and_then(|entity| mesh_entities.get_mut(entity).ok())
+        {
+            // remeshes can finish out of order, so never replace a newer mesh
+            if chunk_mesh.revision <= *revision {
+                chunk_mesh.revision = *revision;
+                *handle = meshes.add(mesh.clone());
+                match lightmap {
+                    Some(lightmap) => commands.entity(entity).insert(lightmap),
+                    None => commands.entity(entity).remove::<Lightmap>(),
+                };
+            }
+            continue;
+        }
+
+        // spawn shit mesh
+        let (translation, rising) = animation.start(position.to_world());
+        let mut mesh_entity = commands.spawn((
+            MaterialMeshBundle {
+                transform: Transform::from_translation(translation),
+                mesh: meshes.add(mesh.clone()),
+                material: material.clone(),
+                ..default()
+            },
+            ChunkMesh {
+                position: *position,
+                revision: *revision,
+            },
+        ));
+        if let Some(rising) = rising {
+            mesh_entity.insert(rising);
+        }
+        if let Some(lightmap) = lightmap {
+            mesh_entity.insert(lightmap);
+        }
+        entities.insert(*position, mesh_entity.id());
+    }
+
+    for ChunkUnloaded(pos) in unloaded.read() {
+        if let Some(mesh_entity) = entities.remove(pos) {
+            commands.entity(mesh_entity).despawn();
+        }
+    }
+}
+
+async fn load_chunk(
+    pos: ChunkPos,
+    settings: ChunkSettings,
+    storage: Option<Arc<dyn ChunkStore>>,
+    generator: GenerationBackend,
+    noise: TerrainNoise,
+    stages: GenerationStages,
+    progress: ProgressReporter,
+) -> anyhow::Result<ChunkEvent> {
+    // load the saved chunk if there is one, otherwise generate it
+    let saved = match storage {
+        Some(storage) => {
+            progress.report(LoadStage::Reading);
+            storage
+                .read_chunk(pos)
+                .inspect_err(|_| progress.report(LoadStage::Failed))?
+        }
+        None => None,
+    };
+    let (mut chunk, spilled) = match saved {
+        Some(chunk) => (chunk, Vec::new()),
+        None => {
+            progress.report(LoadStage::Generating);
+            generate_chunk(pos, settings, &generator, &noise, &stages).await
+        }
     };
+    let mesh = settings.meshing.then(|| {
+        progress.report(LoadStage::Meshing);
+        mesh::to_bevy_mesh(mesh::build_isolated(
+            &chunk,
+            settings.mesh_mode,
+            settings.lighting,
+        ))
+    });
 
-    // mesh
-    let mesh = mesh::build(data);
+    // a freshly loaded chunk matches both its mesh and what is saved or would be generated
+    chunk.mark_clean();
 
-    Ok(ChunkEvent::LoadComplete(chunk, mesh))
+    progress.report(LoadStage::Done);
+    Ok(ChunkEvent::LoadComplete(Box::new(chunk), spilled, mesh))
 }
 
-pub async fn unload_chunk(pos: ChunkPos) -> anyhow::Result<ChunkEvent> {
-    Ok(ChunkEvent::UnloadComplete(pos))
+pub async fn regenerate_chunk(
+    pos: ChunkPos,
+    revision: u64,
+    settings: ChunkSettings,
+    generator: GenerationBackend,
+    noise: TerrainNoise,
+    stages: GenerationStages,
+) -> anyhow::Result<ChunkEvent> {
+    let (mut chunk, spilled) = generate_chunk(pos, settings, &generator, &noise, &stages).await;
+    chunk.supersede(revision);
+    let mesh = settings.meshing.then(|| {
+        mesh::to_bevy_mesh(mesh::build_isolated(
+            &chunk,
+            settings.mesh_mode,
+            settings.lighting,
+        ))
+    });
+    Ok(ChunkEvent::LoadComplete(Box::new(chunk), spilled, mesh))
+}
+
+/// Generate the chunk at the given position for a world with the given settings through the given
+/// stages, along with the blocks of its features that fall in other chunks.
+async fn generate_chunk(
+    pos: ChunkPos,
+    settings: ChunkSettings,
+    generator: &GenerationBackend,
+    noise: &TerrainNoise,
+    stages: &GenerationPipeline,
+) -> (Chunk, Vec<SpilledBlock>) {
+    let (mut chunk, spilled) = generator.generate(pos, noise, stages).await;
+    // only smooth worlds keep a density field, since blocky meshes follow the blocks alone
+    if settings.mesh_mode != MeshingMode::Smooth {
+        chunk.clear_density();
+    }
+    (chunk, spilled)
 }
 
-pub async fn modify_block(
+pub async fn unload_chunk(
     pos: ChunkPos,
-    block_pos: BlockPos,
-    block: BlockType,
+    dirty: Option<Chunk>,
+    storage: Option<Arc<dyn ChunkStore>>,
+) -> anyhow::Result<ChunkEvent> {
+    // write back modified chunks, so edits survive the chunk unloading
+    if let (Some(chunk), Some(storage)) = (dirty, storage) {
+        if let Err(err) = storage.write_chunk(&chunk) {
+            error!("Failed to save chunk {:?}: {:?}", pos, err);
+        }
+    }
+    Ok(ChunkEvent::UnloadComplete(pos))
+}
+
+pub async fn remesh_chunk(
+    chunk: Chunk,
+    neighbours: [Chunk; 6],
+    mode: MeshingMode,
+    lighting: LightingMode,
 ) -> anyhow::Result<ChunkEvent> {
-    todo!()
+    let mesh = mesh::to_bevy_mesh(mesh::build_with_neighbours(
+        &chunk,
+        &neighbours,
+        mode,
+        lighting,
+    ));
+    Ok(ChunkEvent::RemeshComplete(
+        chunk.position,
+        chunk.revision(),
+        mesh,
+    ))
 }
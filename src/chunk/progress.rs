@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use super::ChunkPos;
+use crate::channel::ChannelSender;
+
+/// A step of loading a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadStage {
+    /// Reading the chunk from the world's store.
+    Reading,
+    /// Generating the chunk, as it has not been saved.
+    Generating,
+    /// Building the chunk's mesh.
+    Meshing,
+    /// The chunk has loaded.
+    Done,
+    /// The chunk failed to load.
+    Failed,
+}
+
+impl LoadStage {
+    /// Check if loading has finished, whether or not it succeeded.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Done | Self::Failed)
+    }
+}
+
+/// An event sent from chunk loading tasks as they reach each step, so the world can be seen
+/// filling in rather than silently popping in.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GenerationProgress {
+    /// The position of the chunk.
+    pub pos: ChunkPos,
+    /// The step the chunk has reached.
+    pub stage: LoadStage,
+}
+
+/// Reports the progress of a single chunk load from its task.
+pub(super) struct ProgressReporter {
+    pos: ChunkPos,
+    sender: ChannelSender<GenerationProgress>,
+}
+
+impl ProgressReporter {
+    /// Create a reporter for the chunk at the given position.
+    pub(super) fn new(pos: ChunkPos, sender: ChannelSender<GenerationProgress>) -> Self {
+        Self { pos, sender }
+    }
+
+    /// Report that the chunk has reached the given step. Reports sent after the app has closed
+    /// are dropped.
+    pub(super) fn report(&self, stage: LoadStage) {
+        let _ = self.sender.send(GenerationProgress {
+            pos: self.pos,
+            stage,
+        });
+    }
+}
+
+/// The number of chunks asked to load since the loader was last idle, and how many of them have
+/// finished, e.g. for showing "loading 14/27 chunks".
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct LoadProgress {
+    /// The number of chunks asked to load.
+    pub total: usize,
+    /// The number of those chunks that have finished loading.
+    pub finished: usize,
+}
+
+impl LoadProgress {
+    /// Check if any chunks are still loading.
+    pub fn is_loading(&self) -> bool {
+        self.finished < self.total
+    }
+}
+
+/// Count the chunks that have finished loading, starting afresh once all of them have.
+pub(super) fn track_load_progress(
+    mut events: EventReader<GenerationProgress>,
+    mut progress: ResMut<LoadProgress>,
+) {
+    progress.finished += events
+        .read()
+        .filter(|event| event.stage.is_finished())
+        .count();
+    if !progress.is_loading() {
+        *progress = LoadProgress::default();
+    }
+}
@@ -0,0 +1,159 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{
+    math::Affine3A,
+    prelude::*,
+    render::primitives::{Aabb, Frustum},
+    tasks::AsyncComputeTaskPool,
+};
+
+use super::{
+    generation::{seed::TerrainNoise, worker::GenerationBackend, GenerationStages},
+    key::ChunkKey,
+    load_chunk,
+    progress::{GenerationProgress, ProgressReporter},
+    ChunkPos, ChunkSettings, ChunkTask, CHUNK_SIZE,
+};
+use crate::{channel::ChannelSender, storage::WorldStorage};
+
+/// How many times further away a chunk outside the camera's view is treated as being when ordering
+/// loads, squared to match the queue's squared distances.
+const OUT_OF_VIEW_PENALTY: i64 = 4;
+
+/// Chunks waiting to be loaded, handed to the task pool nearest the players first rather than in
+/// the order they were asked for, and those the camera can see before those behind it.
+#[derive(Debug, Default, Resource)]
+pub struct LoadQueue {
+    /// The queued chunks, keyed on their squared distance to the nearest focus.
+    heap: BinaryHeap<Reverse<(i64, ChunkKey)>>,
+    /// The chunks the players are in.
+    focus: Vec<ChunkPos>,
+    /// The view of the local camera, if there is one.
+    view: Option<Frustum>,
+}
+
+impl LoadQueue {
+    /// Set the chunks the players are in, re-sorting the queue if they have moved.
+    pub fn set_focus(&mut self, focus: impl IntoIterator<Item = ChunkPos>) {
+        let focus = focus.into_iter().collect::<Vec<_>>();
+        if focus == self.focus {
+            return;
+        }
+        self.focus = focus;
+        self.resort();
+    }
+
+    /// Set the view of the local camera, loading the chunks it can see first, re-sorting the queue
+    /// if the view has changed. Without a view, as on a dedicated server, chunks are loaded by
+    /// distance alone.
+    pub fn set_view(&mut self, view: Option<Frustum>) {
+        // frustums cannot be compared directly, but the planes bounding them can
+        let planes = |view: &Option<Frustum>| {
+            view.as_ref()
+                .map(|view| view.half_spaces.map(|half_space| half_space.normal_d()))
+        };
+        if planes(&view) == planes(&self.view) {
+            return;
+        }
+        self.view = view;
+        self.resort();
+    }
+
+    /// Return the number of chunks waiting to be loaded.
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Check if no chunks are waiting to be loaded.
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Queue a chunk to be loaded.
+    pub(super) fn push(&mut self, pos: ChunkPos) {
+        self.heap.push(Reverse((self.priority(pos), pos.key())));
+    }
+
+    /// Re-sort the queue after the focus or view have changed.
+    fn resort(&mut self) {
+        let keys = std::mem::take(&mut self.heap).into_iter();
+        self.heap = keys
+            .map(|Reverse((_, key))| Reverse((self.priority(key.into()), key)))
+            .collect();
+    }
+
+    /// Take the queued chunk nearest a focus.
+    fn pop(&mut self) -> Option<ChunkPos> {
+        self.heap.pop().map(|Reverse((_, key))| key.into())
+    }
+
+    /// Return the squared distance from the given chunk to the nearest focus, or zero if there are
+    /// none. Euclidean rather than Chebyshev distance, so the chunks straight out from a focus load
+    /// before those in the corners of its view. Chunks outside the camera's view are treated as
+    /// further away, so turning around does not reveal missing terrain.
+    fn priority(&self, pos: ChunkPos) -> i64 {
+        let distance = self
+            .focus
+            .iter()
+            .map(|&center| pos.distance_squared(center))
+            .min()
+            .unwrap_or(0);
+        match self.view.is_some_and(|view| !in_view(&view, pos)) {
+            true => distance * OUT_OF_VIEW_PENALTY,
+            false => distance,
+        }
+    }
+}
+
+/// Check if any of the given chunk is within the view, ignoring the near and far planes.
+fn in_view(view: &Frustum, pos: ChunkPos) -> bool {
+    let min = pos.to_world();
+    let aabb = Aabb::from_min_max(min, min + Vec3::splat(CHUNK_SIZE as f32));
+    view.intersects_obb(&aabb, &Affine3A::IDENTITY, false, false)
+}
+
+/// A component marking chunk tasks that are loading a chunk.
+#[derive(Component)]
+pub(super) struct LoadTask;
+
+/// Check if any chunks are waiting to be loaded.
+pub(super) fn loads_pending(queue: Res<LoadQueue>) -> bool {
+    !queue.is_empty()
+}
+
+/// Start loading the nearest queued chunks, keeping no more loads running than the task pool has
+/// threads, so chunks queued later but nearer are not stuck behind ones already handed out.
+pub(super) fn dispatch_loads(
+    mut commands: Commands,
+    mut queue: ResMut<LoadQueue>,
+    running: Query<(), With<LoadTask>>,
+    settings: Res<ChunkSettings>,
+    storage: Res<WorldStorage>,
+    (generator, noise, stages): (
+        Res<GenerationBackend>,
+        Res<TerrainNoise>,
+        Res<GenerationStages>,
+    ),
+    progress: Res<ChannelSender<GenerationProgress>>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    let free = pool
+        .thread_num()
+        .max(1)
+        .saturating_sub(running.iter().len());
+    for _ in 0..free {
+        let Some(pos) = queue.pop() else {
+            break;
+        };
+        let task = pool.spawn(load_chunk(
+            pos,
+            *settings,
+            storage.store.clone(),
+            generator.clone(),
+            noise.clone(),
+            stages.clone(),
+            ProgressReporter::new(pos, progress.clone()),
+        ));
+        commands.spawn((ChunkTask(task), LoadTask));
+    }
+}
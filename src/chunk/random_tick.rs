@@ -0,0 +1,124 @@
+use bevy::{prelude::*, utils::HashMap};
+use chunky_core::chunk::generation::mix;
+use itertools::iproduct;
+
+use super::{scheduled::WorldClock, BlockPos, BlockType, ChunkPos, Chunks, CHUNK_SIZE};
+
+/// How far leaves may be from the nearest wood before they decay, in blocks along each axis.
+const LEAF_REACH: i32 = 5;
+
+/// What a block does when a random tick picks it, given the world, the block's world block
+/// coordinates, and a random roll to make any choices with.
+pub type RandomTick = fn(&mut Chunks, IVec3, u64);
+
+/// The behaviours blocks run when a random tick picks them, such as grass spreading.
+#[derive(Default, Resource)]
+pub struct RandomTickBehaviours(HashMap<BlockType, RandomTick>);
+
+impl RandomTickBehaviours {
+    /// Set what the given block does when a random tick picks it, replacing what it did before.
+    pub fn insert(&mut self, block: BlockType, behaviour: RandomTick) {
+        self.0.insert(block, behaviour);
+    }
+
+    /// Return what the given block does when a random tick picks it, if anything.
+    pub fn get(&self, block: BlockType) -> Option<RandomTick> {
+        self.0.get(&block).copied()
+    }
+}
+
+/// How often blocks are picked for random ticks.
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct RandomTickSettings {
+    /// The number of blocks picked in each loaded chunk every fixed tick. Zero turns random ticks
+    /// off.
+    pub blocks_per_chunk: usize,
+}
+
+impl Default for RandomTickSettings {
+    fn default() -> Self {
+        Self {
+            blocks_per_chunk: 3,
+        }
+    }
+}
+
+/// Return a well-distributed roll for the given tick, chunk, and draw, so random ticks play out the
+/// same way whenever a world is replayed from the same tick.
+fn roll(tick: u64, chunk: ChunkPos, draw: u64) -> u64 {
+    mix(
+        tick,
+        &[chunk.x as u64, chunk.y as u64, chunk.z as u64, draw],
+    )
+}
+
+/// Pick blocks at random in every loaded chunk, and run the behaviours of those that have one.
+pub(super) fn run_random_ticks(
+    clock: Res<WorldClock>,
+    settings: Res<RandomTickSettings>,
+    behaviours: Res<RandomTickBehaviours>,
+    mut chunks: ResMut<Chunks>,
+) {
+    let size = CHUNK_SIZE as u64;
+    let mut picked = Vec::new();
+    for chunk in chunks.iter() {
+        if chunk.occupancy().is_empty() {
+            continue;
+        }
+        let origin = chunk.position.to_world().as_ivec3();
+        for draw in 0..settings.blocks_per_chunk as u64 {
+            let roll = roll(clock.tick(), chunk.position, draw);
+            let pos = BlockPos::new(
+                (roll % size) as u8,
+                (roll / size % size) as u8,
+                (roll / size / size % size) as u8,
+            );
+            if let Some(behaviour) = behaviours.get(*chunk.block_at(pos)) {
+                // the low bits went on the position, so pass the rest on
+                picked.push((behaviour, origin + IVec3::from(pos), roll >> 15));
+            }
+        }
+    }
+    for (behaviour, pos, roll) in picked {
+        behaviour(&mut chunks, pos, roll);
+    }
+}
+
+/// Spread grass onto a nearby dirt block open to the air, or turn the grass back into dirt if it
+/// has been covered.
+pub fn spread_grass(chunks: &mut Chunks, pos: IVec3, roll: u64) {
+    let covered = |chunks: &Chunks, pos: IVec3| {
+        chunks
+            .block_at_world_block(pos + IVec3::Y)
+            .is_none_or(|block| block.definition().solid)
+    };
+    if covered(chunks, pos) {
+        chunks.set_block_at_world_block(pos, BlockType::DIRT);
+        return;
+    }
+    // pick a neighbour from the 3x3x3 box around the grass
+    let offset = IVec3::new(
+        (roll % 3) as i32 - 1,
+        (roll / 3 % 3) as i32 - 1,
+        (roll / 9 % 3) as i32 - 1,
+    );
+    let target = pos + offset;
+    if chunks.block_at_world_block(target) == Some(BlockType::DIRT) && !covered(chunks, target) {
+        chunks.set_block_at_world_block(target, BlockType::GRASS);
+    }
+}
+
+/// Remove leaves with no wood near enough to hold them up, such as those left when a tree is cut
+/// down.
+pub fn decay_leaves(chunks: &mut Chunks, pos: IVec3, _roll: u64) {
+    let reach = -LEAF_REACH..=LEAF_REACH;
+    let held = iproduct!(reach.clone(), reach.clone(), reach).any(|(x, y, z)| {
+        // leaves next to unloaded chunks are left alone, since their wood may be there
+        chunks
+            .block_at_world_block(pos + IVec3::new(x, y, z))
+            .is_none_or(|block| block.has_tag("#log"))
+    });
+    if !held {
+        chunks.set_block_at_world_block(pos, BlockType::EMPTY);
+    }
+}
@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use super::{mesh::Face, BlockPos, ChunkPos, Chunks};
+
+/// A solid block hit by a ray cast through the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// The position of the block within its chunk.
+    pub block_pos: BlockPos,
+    /// The position of the chunk the block is in.
+    pub chunk_pos: ChunkPos,
+    /// The face of the block the ray entered through. Rays starting inside a block hit the face
+    /// turned back along them.
+    pub face: Face,
+    /// The distance along the ray to where it entered the block, in blocks.
+    pub distance: f32,
+}
+
+impl RaycastHit {
+    /// Return the world block coordinates of the block that was hit.
+    pub fn world_block(&self) -> IVec3 {
+        self.chunk_pos.to_world().as_ivec3() + IVec3::from(self.block_pos)
+    }
+
+    /// Return the world block coordinates of the block in front of the face that was hit, where a
+    /// block placed against it would go.
+    pub fn adjacent_block(&self) -> IVec3 {
+        self.world_block() + Vec3::from(self.face).as_ivec3()
+    }
+}
+
+/// Return the face whose normal points along the given axis, in the given direction.
+fn face_along(axis: usize, positive: bool) -> Face {
+    match (axis, positive) {
+        (0, true) => Face::East,
+        (0, false) => Face::West,
+        (1, true) => Face::Up,
+        (1, false) => Face::Down,
+        (_, true) => Face::North,
+        (_, false) => Face::South,
+    }
+}
+
+impl Chunks {
+    /// Cast a ray through the world, stepping from block to block along it, and return the first
+    /// solid block it enters within the given distance. Blocks in chunks that are not loaded are
+    /// passed through.
+    pub fn raycast(&self, origin: Vec3, direction: Dir3, max_distance: f32) -> Option<RaycastHit> {
+        let direction = *direction;
+        let step = direction.signum();
+        let mut block = origin.floor().as_ivec3();
+        // the distance along the ray to cross a whole block, and to the next block boundary, per axis
+        let delta = direction.recip().abs();
+        let mut next = (block.as_vec3() + step.max(Vec3::ZERO) - origin) / direction;
+        // a ray starting inside a block hits the face turned back along its longest axis
+        let abs = direction.abs();
+        let longest = match (abs.x >= abs.y, abs.x >= abs.z, abs.y >= abs.z) {
+            (true, true, _) => 0,
+            (false, _, true) => 1,
+            _ => 2,
+        };
+        let mut face = face_along(longest, step[longest] < 0.0);
+        let mut distance = 0.0;
+        // the chunk the ray is passing through, looked up once per chunk rather than per block
+        let mut current = None;
+        while distance <= max_distance {
+            let chunk_pos = ChunkPos::from_world_block(block);
+            if current.is_none_or(|(pos, _)| pos != chunk_pos) {
+                current = Some((chunk_pos, self.get(chunk_pos)));
+            }
+            let block_pos = BlockPos::from_world_block(block);
+            let solid = current.and_then(|(_, chunk)| chunk).is_some_and(|chunk| {
+                chunk.occupancy().may_contain(block_pos) && chunk.block_at(block_pos).is_solid()
+            });
+            if solid {
+                return Some(RaycastHit {
+                    block_pos,
+                    chunk_pos,
+                    face,
+                    distance,
+                });
+            }
+            let axis = match (next.x < next.y, next.x < next.z, next.y < next.z) {
+                (true, true, _) => 0,
+                (false, _, true) => 1,
+                _ => 2,
+            };
+            distance = next[axis];
+            next[axis] += delta[axis];
+            block[axis] += step[axis] as i32;
+            face = face_along(axis, step[axis] < 0.0);
+        }
+        None
+    }
+}
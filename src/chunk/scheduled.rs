@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::HashSet};
+
+pub use chunky_core::chunk::scheduled::ScheduledChange;
+
+use super::{ChunkPos, Chunks};
+
+/// The number of fixed ticks the world has run for. Scheduled block changes are timed against this
+/// clock, which is saved with the world.
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct WorldClock {
+    tick: u64,
+}
+
+impl WorldClock {
+    /// Create a clock starting at the given tick.
+    pub fn new(tick: u64) -> Self {
+        Self { tick }
+    }
+
+    /// Return the current tick.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+}
+
+/// Blocks waiting to be ticked at a later tick of the [`WorldClock`], such as water waiting to flow.
+/// Unlike scheduled changes these are not saved, so blocks settle where they are when the world is
+/// closed.
+#[derive(Debug, Default, Resource)]
+pub struct BlockTicks {
+    due: BTreeMap<u64, HashSet<IVec3>>,
+}
+
+impl BlockTicks {
+    /// Tick the block at the given world block coordinates at the given tick. A block is only
+    /// ticked once per tick, however many times it is scheduled.
+    pub fn schedule(&mut self, pos: IVec3, tick: u64) {
+        self.due.entry(tick).or_default().insert(pos);
+    }
+
+    /// Take the blocks due to be ticked at or before the given tick.
+    pub fn take_due(&mut self, tick: u64) -> Vec<IVec3> {
+        let later = self.due.split_off(&(tick + 1));
+        std::mem::replace(&mut self.due, later)
+            .into_values()
+            .flatten()
+            .collect()
+    }
+
+    /// Return the number of block ticks waiting.
+    pub fn len(&self) -> usize {
+        self.due.values().map(HashSet::len).sum()
+    }
+
+    /// Check if no block ticks are waiting.
+    pub fn is_empty(&self) -> bool {
+        self.due.is_empty()
+    }
+}
+
+/// Advance the world's clock, and make the block changes that have come due. Changes in chunks that
+/// were unloaded when they came due happen as soon as the chunk is loaded again.
+pub(super) fn run_scheduled_changes(mut clock: ResMut<WorldClock>, mut chunks: ResMut<Chunks>) {
+    clock.tick += 1;
+    let due = chunks
+        .iter()
+        .filter(|chunk| {
+            chunk
+                .next_scheduled_tick()
+                .is_some_and(|tick| tick <= clock.tick)
+        })
+        .map(|chunk| chunk.position)
+        .collect::<Vec<ChunkPos>>();
+    for pos in due {
+        let Some(chunk) = chunks.get_mut(pos) else {
+            continue;
+        };
+        let changes = chunk.take_scheduled(clock.tick);
+        chunk.set_blocks(changes.iter().map(|change| (change.pos, change.block)));
+        let origin = pos.to_world().as_ivec3();
+        for change in changes {
+            let world = origin + IVec3::from(change.pos);
+            chunks.queue_remesh(world, world);
+            chunks.record_change(world);
+        }
+    }
+}
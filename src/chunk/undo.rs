@@ -0,0 +1,324 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+use super::delta::ChunkDelta;
+
+/// The memory undo entries may use before the oldest are spilled to disk, in bytes.
+pub const DEFAULT_MEMORY_CAP: usize = 64 * 1024 * 1024;
+
+/// The number of spill files created so far, so each history gets its own.
+static SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// The changes that undo one edit, with a delta for each chunk it touched.
+pub type UndoEntry = Vec<ChunkDelta>;
+
+/// The edits made to the world, newest last, as the changes that undo them. Entries are kept in
+/// memory up to a cap, past which the oldest are compressed and spilled to a temporary file, and
+/// read back as the history is undone down to them.
+#[derive(Resource)]
+pub struct UndoHistory {
+    /// The memory entries may use before the oldest are spilled, in bytes.
+    memory_cap: usize,
+    /// The entries kept in memory, oldest first, along with their estimated size.
+    entries: VecDeque<(UndoEntry, usize)>,
+    /// The estimated memory used by the entries kept in memory, in bytes.
+    memory: usize,
+    /// The file entries older than those in memory are spilled to, once any have been.
+    spill: Option<SpillFile>,
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMORY_CAP)
+    }
+}
+
+impl UndoHistory {
+    /// Create an empty history that keeps up to the given memory of entries in memory, in bytes.
+    pub fn new(memory_cap: usize) -> Self {
+        Self {
+            memory_cap,
+            entries: VecDeque::new(),
+            memory: 0,
+            spill: None,
+        }
+    }
+
+    /// Set the memory entries may use before the oldest are spilled to disk, in bytes.
+    pub fn set_memory_cap(&mut self, memory_cap: usize) {
+        self.memory_cap = memory_cap;
+        self.enforce_cap();
+    }
+
+    /// Return the number of edits that can be undone, including those spilled to disk.
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.spilled()
+    }
+
+    /// Check if there are no edits to undo.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the number of entries spilled to disk.
+    pub fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.offsets.len())
+    }
+
+    /// Return the estimated memory used by the entries kept in memory, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.memory
+    }
+
+    /// Record the changes that undo an edit. Edits that changed nothing are not recorded.
+    pub fn push(&mut self, entry: UndoEntry) {
+        let entry = entry
+            .into_iter()
+            .filter(|delta| !delta.is_empty())
+            .collect::<Vec<_>>();
+        if entry.is_empty() {
+            return;
+        }
+        let size = entry.iter().map(ChunkDelta::memory_usage).sum();
+        self.memory += size;
+        self.entries.push_back((entry, size));
+        self.enforce_cap();
+    }
+
+    /// Take the changes that undo the newest edit, reading them back from disk if they were
+    /// spilled.
+    pub fn pop(&mut self) -> Option<UndoEntry> {
+        if let Some((entry, size)) = self.entries.pop_back() {
+            self.memory -= size;
+            return Some(entry);
+        }
+        let spill = self.spill.as_mut()?;
+        match spill.pop() {
+            Ok(entry) => entry,
+            Err(err) => {
+                // the spilled entries cannot be trusted past a failed read
+                error!("Failed to read back spilled undo history: {:?}", err);
+                self.spill = None;
+                None
+            }
+        }
+    }
+
+    /// Forget every edit.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.memory = 0;
+        self.spill = None;
+    }
+
+    /// Spill the oldest entries in memory until they fit under the cap.
+    fn enforce_cap(&mut self) {
+        while self.memory > self.memory_cap {
+            let Some((entry, size)) = self.entries.pop_front() else {
+                break;
+            };
+            self.memory -= size;
+            if let Err(err) = self.spill(&entry) {
+                // older entries could no longer be reached in order, so they are dropped too
+                error!(
+                    "Failed to spill undo history, forgetting older edits: {:?}",
+                    err
+                );
+                self.spill = None;
+            }
+        }
+    }
+
+    /// Append an entry to the spill file, creating it if nothing has been spilled yet.
+    fn spill(&mut self, entry: &UndoEntry) -> anyhow::Result<()> {
+        if self.spill.is_none() {
+            self.spill = Some(SpillFile::create()?);
+        }
+        self.spill.as_mut().unwrap().push(entry)
+    }
+}
+
+/// A temporary file of spilled undo entries, each compressed with lz4, oldest first. The file is
+/// deleted when it is dropped.
+struct SpillFile {
+    path: PathBuf,
+    file: File,
+    /// The offset each entry starts at, oldest first. Each runs to the next, or the end of the file.
+    offsets: Vec<u64>,
+}
+
+impl SpillFile {
+    /// Create an empty spill file in the temporary directory.
+    fn create() -> anyhow::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "chunky-undo-{}-{}.bin",
+            std::process::id(),
+            SPILL_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+        Ok(Self {
+            path,
+            file,
+            offsets: Vec::new(),
+        })
+    }
+
+    /// Append an entry to the end of the file.
+    fn push(&mut self, entry: &UndoEntry) -> anyhow::Result<()> {
+        let payload = lz4_flex::compress_prepend_size(&bincode::serialize(entry)?);
+        let offset = self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&payload)?;
+        self.offsets.push(offset);
+        Ok(())
+    }
+
+    /// Read back the newest entry and cut it from the end of the file.
+    fn pop(&mut self) -> anyhow::Result<Option<UndoEntry>> {
+        let Some(offset) = self.offsets.pop() else {
+            return Ok(None);
+        };
+        self.file.seek(SeekFrom::Start(offset))?;
+        let mut payload = Vec::new();
+        self.file.read_to_end(&mut payload)?;
+        self.file.set_len(offset)?;
+        let entry = bincode::deserialize(&lz4_flex::decompress_size_prepended(&payload)?)?;
+        Ok(Some(entry))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::{BlockPos, BlockType, ChunkPos};
+
+    /// An entry telling itself apart from others by its chunk position and revision.
+    fn entry(n: i64) -> UndoEntry {
+        let mut delta = ChunkDelta::new(ChunkPos::new(n, 0, 0), n as u64);
+        delta.set_block(BlockPos::new(1, 2, 3), BlockType::STONE);
+        vec![delta]
+    }
+
+    /// The estimated size of each entry from [`entry`], which all change a single block.
+    fn entry_size() -> usize {
+        entry(0).iter().map(ChunkDelta::memory_usage).sum()
+    }
+
+    /// Pop every entry from a history, newest first.
+    fn drain(history: &mut UndoHistory) -> Vec<UndoEntry> {
+        std::iter::from_fn(|| history.pop()).collect()
+    }
+
+    #[test]
+    fn spilled_entries_pop_in_reverse_order() {
+        let mut history = UndoHistory::new(1);
+        for n in 0..5 {
+            history.push(entry(n));
+        }
+        assert!(history.spilled() > 0);
+        assert_eq!(history.len(), 5);
+
+        let popped = drain(&mut history);
+        assert_eq!(popped, (0..5).rev().map(entry).collect::<Vec<_>>());
+        assert!(history.is_empty());
+        assert_eq!(history.memory_usage(), 0);
+    }
+
+    #[test]
+    fn enforce_cap_spills_oldest_first() {
+        let mut history = UndoHistory::new(usize::MAX);
+        for n in 0..4 {
+            history.push(entry(n));
+        }
+        assert_eq!(history.spilled(), 0);
+
+        // room for one entry, so the three oldest go to disk and the newest stays
+        history.set_memory_cap(entry_size());
+        assert_eq!(history.spilled(), 3);
+        assert_eq!(history.memory_usage(), entry_size());
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.entries[0].0, entry(3));
+
+        assert_eq!(
+            drain(&mut history),
+            (0..4).rev().map(entry).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn memory_and_spilled_entries_interleave() {
+        let mut history = UndoHistory::new(2 * entry_size());
+        for n in 0..5 {
+            history.push(entry(n));
+        }
+        assert_eq!(history.spilled(), 3);
+
+        // two from memory, then one read back from disk
+        assert_eq!(history.pop(), Some(entry(4)));
+        assert_eq!(history.pop(), Some(entry(3)));
+        assert_eq!(history.pop(), Some(entry(2)));
+        assert_eq!(history.spilled(), 2);
+
+        // a new edit goes on top of what is left on disk
+        history.push(entry(5));
+        assert_eq!(history.len(), 3);
+        assert_eq!(drain(&mut history), [entry(5), entry(1), entry(0)]);
+    }
+
+    #[test]
+    fn spill_file_pop_truncates() {
+        let mut spill = SpillFile::create().unwrap();
+        spill.push(&entry(0)).unwrap();
+        let end = spill.file.metadata().unwrap().len();
+        spill.push(&entry(1)).unwrap();
+        assert!(spill.file.metadata().unwrap().len() > end);
+
+        assert_eq!(spill.pop().unwrap(), Some(entry(1)));
+        assert_eq!(spill.file.metadata().unwrap().len(), end);
+        assert_eq!(spill.pop().unwrap(), Some(entry(0)));
+        assert_eq!(spill.file.metadata().unwrap().len(), 0);
+        assert_eq!(spill.pop().unwrap(), None);
+
+        let path = spill.path.clone();
+        assert!(path.exists());
+        drop(spill);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn failed_read_back_drops_spill_file() {
+        let mut history = UndoHistory::new(1);
+        for n in 0..3 {
+            history.push(entry(n));
+        }
+        let spill = history.spill.as_mut().unwrap();
+        let path = spill.path.clone();
+        // cut the newest entry short, so it no longer decompresses
+        let offset = *spill.offsets.last().unwrap();
+        spill.file.set_len(offset + 2).unwrap();
+
+        assert_eq!(history.pop(), None);
+        assert_eq!(history.spilled(), 0);
+        assert!(history.is_empty());
+        assert!(!path.exists());
+    }
+}
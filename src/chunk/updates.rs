@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{mesh::Face, Chunks};
+
+/// An event telling the block at the given world block coordinates that it, or one of the six
+/// blocks sharing a face with it, has changed, so it can react, such as sand falling into a hole
+/// dug beneath it. Sent during [`FixedUpdate`], so readers should run there too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct BlockUpdate(pub IVec3);
+
+/// How many block updates are sent each fixed tick.
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct BlockUpdateSettings {
+    /// The most updates sent in a fixed tick. The rest wait for the next, so a large edit is
+    /// spread over several ticks rather than stalling one.
+    pub per_tick: usize,
+}
+
+impl Default for BlockUpdateSettings {
+    fn default() -> Self {
+        Self { per_tick: 4096 }
+    }
+}
+
+/// Blocks waiting to be sent a [`BlockUpdate`], in the order they were disturbed. A block is only
+/// queued once however many of its neighbours change before it is updated.
+#[derive(Debug, Default, Resource)]
+pub struct BlockUpdateQueue {
+    queue: VecDeque<IVec3>,
+    queued: HashSet<IVec3>,
+}
+
+impl BlockUpdateQueue {
+    /// Queue an update for the block at the given world block coordinates, unless it is queued.
+    pub fn push(&mut self, pos: IVec3) {
+        if self.queued.insert(pos) {
+            self.queue.push_back(pos);
+        }
+    }
+
+    /// Take the longest-waiting update.
+    fn pop(&mut self) -> Option<IVec3> {
+        let pos = self.queue.pop_front()?;
+        self.queued.remove(&pos);
+        Some(pos)
+    }
+
+    /// Return the number of updates waiting.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Check if no updates are waiting.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// Queue updates for the blocks set since the last fixed tick and their neighbours, and send as
+/// many as the budget allows. Blocks set while reacting to updates are queued on the next tick.
+pub(super) fn send_block_updates(
+    mut chunks: ResMut<Chunks>,
+    mut queue: ResMut<BlockUpdateQueue>,
+    settings: Res<BlockUpdateSettings>,
+    mut updates: EventWriter<BlockUpdate>,
+) {
+    for pos in chunks.take_changed() {
+        queue.push(pos);
+        for face in Face::ALL {
+            queue.push(pos + Vec3::from(face).as_ivec3());
+        }
+    }
+    let budget = settings.per_tick.min(queue.len());
+    updates.send_batch((0..budget).filter_map(|_| queue.pop()).map(BlockUpdate));
+}
@@ -0,0 +1,241 @@
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fmt::{Debug, Write},
+    fs,
+    panic::PanicHookInfo,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, Once, OnceLock, PoisonError, TryLockError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    log::{
+        tracing_subscriber::{layer::Context, Layer},
+        BoxedLayer,
+    },
+    prelude::*,
+    time::common_conditions::on_timer,
+    utils::tracing::{
+        field::{Field, Visit},
+        Event, Subscriber,
+    },
+};
+
+use crate::{
+    chunk::{generation::seed::TerrainNoise, ChunkPos, ChunkSettings, Chunks},
+    net::server::{Clients, ServerConfig},
+};
+
+/// The number of recent log entries kept for crash reports.
+const LOG_CAPACITY: usize = 256;
+
+/// The directory crash reports are written to.
+const CRASH_DIR: &str = "crash-reports";
+
+/// How often the state kept for crash reports is refreshed.
+const CONTEXT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The state of the app kept for crash reports. The world cannot be reached from a panic hook, so
+/// this is refreshed periodically and shared with the hook instead.
+#[derive(Default)]
+struct CrashContext {
+    /// The most recent log entries, oldest first.
+    log: VecDeque<String>,
+    /// The configuration the app is running with.
+    config: String,
+    /// The seed terrain is generated from.
+    seed: Option<u32>,
+    /// The positions of the players.
+    players: Vec<Vec3>,
+    /// A summary of the loaded chunks.
+    chunks: String,
+}
+
+/// A handle to the state written into crash reports, shared by the log layer, the panic hook, and
+/// the systems keeping it up to date.
+#[derive(Clone, Default, Resource)]
+pub struct CrashReporter(Arc<Mutex<CrashContext>>);
+
+impl CrashReporter {
+    /// Return the reporter shared by the whole process.
+    pub fn global() -> &'static Self {
+        static REPORTER: OnceLock<CrashReporter> = OnceLock::new();
+        REPORTER.get_or_init(Self::default)
+    }
+
+    /// Lock the context, ignoring panics in other threads that held it.
+    fn lock(&self) -> MutexGuard<'_, CrashContext> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record a log entry, dropping the oldest once [`LOG_CAPACITY`] are kept.
+    fn record_log(&self, entry: String) {
+        let mut context = self.lock();
+        if context.log.len() == LOG_CAPACITY {
+            context.log.pop_front();
+        }
+        context.log.push_back(entry);
+    }
+
+    /// Write a crash report for the given panic into a new timestamped directory in
+    /// [`CRASH_DIR`], returning the directory.
+    fn write_report(&self, info: &PanicHookInfo) -> anyhow::Result<PathBuf> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let dir = PathBuf::from(CRASH_DIR).join(format!("crash-{}", timestamp));
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join("panic.txt"),
+            format!("{}\n\n{}", info, Backtrace::force_capture()),
+        )?;
+
+        // the panic may have happened while the context was locked on this thread
+        let context = match self.0.try_lock() {
+            Ok(context) => context,
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(TryLockError::WouldBlock) => anyhow::bail!("crash context is locked"),
+        };
+        let log = context.log.iter().fold(String::new(), |mut log, entry| {
+            let _ = writeln!(log, "{}", entry);
+            log
+        });
+        fs::write(dir.join("log.txt"), log)?;
+
+        let mut summary = String::new();
+        match context.seed {
+            Some(seed) => writeln!(summary, "seed: {}", seed)?,
+            None => writeln!(summary, "seed: unknown")?,
+        }
+        for position in &context.players {
+            writeln!(
+                summary,
+                "player: {:.2} {:.2} {:.2}",
+                position.x, position.y, position.z
+            )?;
+        }
+        writeln!(summary, "\n{}", context.chunks)?;
+        writeln!(summary, "{}", context.config)?;
+        fs::write(dir.join("context.txt"), summary)?;
+        Ok(dir)
+    }
+}
+
+/// A log layer keeping the most recent log entries for crash reports. Set this as the
+/// `custom_layer` of the [`LogPlugin`](bevy::log::LogPlugin).
+pub fn log_layer(_app: &mut App) -> Option<BoxedLayer> {
+    Some(Box::new(CrashLogLayer))
+}
+
+/// The layer behind [`log_layer`].
+struct CrashLogLayer;
+
+impl<S: Subscriber> Layer<S> for CrashLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut entry = format!("{} {}:", metadata.level(), metadata.target());
+        event.record(&mut EntryVisitor(&mut entry));
+        CrashReporter::global().record_log(entry);
+    }
+}
+
+/// Formats the fields of a log event into a log entry.
+struct EntryVisitor<'a>(&'a mut String);
+
+impl Visit for EntryVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let _ = match field.name() {
+            "message" => write!(self.0, " {:?}", value),
+            name => write!(self.0, " {}={:?}", name, value),
+        };
+    }
+}
+
+/// A plugin that writes a crash report when the app panics, with the panic, the most recent log
+/// entries, and a summary of the app's state, so reported crashes can be reproduced. Log entries
+/// are only kept if [`log_layer`] is installed.
+pub struct CrashReportPlugin;
+
+impl Plugin for CrashReportPlugin {
+    fn build(&self, app: &mut App) {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                previous(info);
+                match CrashReporter::global().write_report(info) {
+                    Ok(dir) => eprintln!("Crash report written to {}", dir.display()),
+                    Err(err) => eprintln!("Failed to write crash report: {:?}", err),
+                }
+            }));
+        });
+        app.insert_resource(CrashReporter::global().clone())
+            .add_systems(
+                Last,
+                (record_world_context, record_player_context).run_if(on_timer(CONTEXT_INTERVAL)),
+            );
+    }
+}
+
+/// Record the configuration, seed, and loaded chunks for crash reports.
+fn record_world_context(
+    reporter: Res<CrashReporter>,
+    chunks: Res<Chunks>,
+    noise: Option<Res<TerrainNoise>>,
+    settings: Option<Res<ChunkSettings>>,
+    config: Option<Res<ServerConfig>>,
+) {
+    let (mut loaded, mut dirty, mut memory) = (0, 0, 0);
+    let mut bounds: Option<(ChunkPos, ChunkPos)> = None;
+    for chunk in chunks.iter() {
+        loaded += 1;
+        dirty += chunk.is_dirty() as usize;
+        memory += chunk.memory_usage();
+        let pos = chunk.position;
+        bounds = Some(match bounds {
+            Some((min, max)) => (
+                ChunkPos::new(min.x.min(pos.x), min.y.min(pos.y), min.z.min(pos.z)),
+                ChunkPos::new(max.x.max(pos.x), max.y.max(pos.y), max.z.max(pos.z)),
+            ),
+            None => (pos, pos),
+        });
+    }
+    let mut summary = format!(
+        "loaded chunks: {} ({} modified, ~{} KiB)\n",
+        loaded,
+        dirty,
+        memory / 1024
+    );
+    if let Some((min, max)) = bounds {
+        let _ = writeln!(summary, "loaded from {:?} to {:?}", min, max);
+    }
+
+    let mut config_text = String::new();
+    if let Some(settings) = settings {
+        let _ = writeln!(config_text, "{:#?}", *settings);
+    }
+    if let Some(config) = config {
+        let _ = writeln!(config_text, "{:#?}", *config);
+    }
+
+    let mut context = reporter.lock();
+    context.chunks = summary;
+    context.config = config_text;
+    context.seed = noise.map(|noise| noise.seed());
+}
+
+/// Record the positions of the players for crash reports. These are the cameras on a client, and
+/// the connected clients on a server.
+fn record_player_context(
+    reporter: Res<CrashReporter>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    clients: Option<Res<Clients>>,
+) {
+    let mut players = cameras
+        .iter()
+        .map(GlobalTransform::translation)
+        .collect::<Vec<_>>();
+    if let Some(clients) = clients {
+        players.extend(clients.values().map(|client| client.position));
+    }
+    reporter.lock().players = players;
+}
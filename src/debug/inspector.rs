@@ -0,0 +1,330 @@
+use std::{fmt::Write, fs, path::PathBuf};
+
+use anyhow::Context;
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use super::pointer_ray;
+use crate::chunk::{BlockPos, BlockType, Chunk, ChunkCommand, ChunkPos, Chunks, CHUNK_SIZE};
+
+/// How far away a chunk can be selected from, in blocks.
+const SELECT_DISTANCE: f32 = 128.0;
+
+/// The directory chunk dumps are written to.
+const DUMP_DIR: &str = "dumps";
+
+/// The colour of an inspector button at rest.
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+
+/// The colour of an inspector button under the cursor.
+const BUTTON_HOVERED_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+
+/// The colour of an inspector button being pressed.
+const BUTTON_PRESSED_COLOR: Color = Color::srgb(0.35, 0.35, 0.35);
+
+/// A plugin for selecting a chunk by clicking it while holding Alt, and rebuilding, dumping, or
+/// inspecting it from a panel of buttons.
+pub struct ChunkInspectorPlugin;
+
+impl Plugin for ChunkInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedChunk>()
+            .add_systems(Startup, spawn_inspector_panel)
+            .add_systems(
+                Update,
+                (
+                    select_chunk,
+                    press_inspector_buttons,
+                    update_inspector_panel,
+                    draw_selected_chunk,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The chunk selected in the inspector.
+#[derive(Debug, Default, Resource)]
+struct SelectedChunk {
+    /// The position of the selected chunk, if one is selected.
+    position: Option<ChunkPos>,
+    /// Whether the light field of the selected chunk is drawn.
+    show_light: bool,
+}
+
+/// An action the inspector can take on the selected chunk, attached to the button taking it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+enum InspectorAction {
+    /// Generate the chunk afresh, discarding its edits.
+    Regenerate,
+    /// Rebuild the chunk's mesh.
+    Remesh,
+    /// Write the chunk's data to a text file.
+    Dump,
+    /// Show or hide the chunk's light field.
+    ToggleLight,
+}
+
+impl InspectorAction {
+    /// Every action, in the order their buttons are shown.
+    const ALL: [Self; 4] = [
+        Self::Regenerate,
+        Self::Remesh,
+        Self::Dump,
+        Self::ToggleLight,
+    ];
+
+    /// Return the label of the action's button.
+    fn label(self) -> &'static str {
+        match self {
+            Self::Regenerate => "Regenerate",
+            Self::Remesh => "Remesh",
+            Self::Dump => "Dump to file",
+            Self::ToggleLight => "Toggle light field",
+        }
+    }
+}
+
+/// A marker component for the inspector panel.
+#[derive(Component)]
+struct InspectorPanel;
+
+/// A marker component for the text describing the selected chunk.
+#[derive(Component)]
+struct InspectorTitle;
+
+fn spawn_inspector_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(8.0),
+                    left: Val::Px(8.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            InspectorPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                ),
+                InspectorTitle,
+            ));
+            for action in InspectorAction::ALL {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..default()
+                        },
+                        action,
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            action.label(),
+                            TextStyle {
+                                font_size: 14.0,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+/// Select the chunk under the cursor when the left mouse button is clicked while holding Alt, or
+/// the chunk in the centre of the view if the cursor is locked. Clicking nothing clears the
+/// selection.
+fn select_chunk(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    chunks: Res<Chunks>,
+    mut selected: ResMut<SelectedChunk>,
+) {
+    if !keys.pressed(KeyCode::AltLeft) || !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (camera, transform) = cameras.single();
+    let Some((origin, direction)) = pointer_ray(windows.single(), camera, transform) else {
+        return;
+    };
+    let hit = chunks.raycast(origin, direction, SELECT_DISTANCE);
+    selected.position = hit.map(|hit| hit.chunk_pos);
+}
+
+/// Take the action of any inspector button that was pressed, and shade the buttons as they are
+/// hovered and pressed.
+fn press_inspector_buttons(
+    mut buttons: Query<
+        (&Interaction, &InspectorAction, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    chunks: Res<Chunks>,
+    mut selected: ResMut<SelectedChunk>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    for (interaction, action, mut color) in buttons.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => BUTTON_PRESSED_COLOR,
+            Interaction::Hovered => BUTTON_HOVERED_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        }
+        .into();
+        let (Interaction::Pressed, Some(pos)) = (interaction, selected.position) else {
+            continue;
+        };
+        match action {
+            InspectorAction::Regenerate => {
+                // the regenerated chunk is meshed alone, so remesh it once it is back
+                chunk_commands
+                    .send_batch([ChunkCommand::Regenerate(pos), ChunkCommand::Remesh(pos)]);
+            }
+            InspectorAction::Remesh => {
+                chunk_commands.send(ChunkCommand::Remesh(pos));
+            }
+            InspectorAction::Dump => match chunks.get(pos).map(dump_chunk) {
+                Some(Ok(path)) => info!("Dumped chunk {:?} to {}", pos, path.display()),
+                Some(Err(err)) => error!("Failed to dump chunk {:?}: {:?}", pos, err),
+                None => {}
+            },
+            InspectorAction::ToggleLight => selected.show_light = !selected.show_light,
+        }
+    }
+}
+
+/// Show the inspector panel while a loaded chunk is selected, describing the chunk in its title.
+/// The selection is cleared if the chunk is unloaded.
+fn update_inspector_panel(
+    chunks: Res<Chunks>,
+    mut selected: ResMut<SelectedChunk>,
+    mut panels: Query<&mut Visibility, With<InspectorPanel>>,
+    mut titles: Query<&mut Text, With<InspectorTitle>>,
+) {
+    let chunk = selected.position.and_then(|pos| chunks.get(pos));
+    let mut visibility = panels.single_mut();
+    let Some(chunk) = chunk else {
+        selected.position = None;
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Visible;
+
+    let mut text = titles.single_mut();
+    let section = &mut text.sections[0].value;
+    section.clear();
+    let pos = chunk.position;
+    let _ = writeln!(section, "chunk {} {} {}", pos.x, pos.y, pos.z);
+    let _ = write!(
+        section,
+        "revision {}, {} block types",
+        chunk.revision(),
+        chunk.palette().len()
+    );
+}
+
+/// Outline the selected chunk, and draw its light field if enabled. Light is only drawn in the
+/// open blocks next to the terrain, where it shows on the chunk's faces.
+fn draw_selected_chunk(mut gizmos: Gizmos, chunks: Res<Chunks>, selected: Res<SelectedChunk>) {
+    let Some(chunk) = selected.position.and_then(|pos| chunks.get(pos)) else {
+        return;
+    };
+    let size = CHUNK_SIZE as f32;
+    let origin = chunk.position.to_world();
+    gizmos.cuboid(
+        Transform::from_translation(origin + size / 2.0).with_scale(Vec3::splat(size)),
+        Color::srgb(1.0, 1.0, 0.0),
+    );
+    if !selected.show_light {
+        return;
+    }
+    for pos in BlockPos::all() {
+        if chunk.block_at(pos).is_opaque() || !borders_opaque(chunk, pos) {
+            continue;
+        }
+        // from blue in the dark to red in full light
+        let brightness = chunk.light_at(pos).brightness();
+        gizmos.cuboid(
+            Transform::from_translation(origin + IVec3::from(pos).as_vec3() + 0.5)
+                .with_scale(Vec3::splat(0.2)),
+            Color::hsl(240.0 * (1.0 - brightness), 1.0, 0.5),
+        );
+    }
+}
+
+/// Check if any block sharing a face with the given block, within the same chunk, is opaque.
+fn borders_opaque(chunk: &Chunk, pos: BlockPos) -> bool {
+    let block = IVec3::from(pos);
+    let size = IVec3::splat(CHUNK_SIZE as i32);
+    IVec3::AXES
+        .iter()
+        .flat_map(|&axis| [block + axis, block - axis])
+        .filter(|neighbour| neighbour.cmpge(IVec3::ZERO).all() && neighbour.cmplt(size).all())
+        .any(|neighbour| {
+            chunk
+                .block_at(BlockPos::from_world_block(neighbour))
+                .is_opaque()
+        })
+}
+
+/// Write a readable dump of a chunk to [`DUMP_DIR`], returning the path it was written to. Blocks
+/// are drawn one horizontal layer at a time from the bottom of the chunk up, with rows running
+/// along x.
+fn dump_chunk(chunk: &Chunk) -> anyhow::Result<PathBuf> {
+    let pos = chunk.position;
+    let mut dump = String::new();
+    writeln!(dump, "position: {} {} {}", pos.x, pos.y, pos.z)?;
+    writeln!(dump, "revision: {}", chunk.revision())?;
+    writeln!(dump, "palette: {:?}", chunk.palette())?;
+    writeln!(dump, "density field: {}", chunk.density().is_some())?;
+    for (pos, data) in chunk.block_data() {
+        writeln!(dump, "block data at {:?}: {:?}", pos, data)?;
+    }
+    for change in chunk.scheduled() {
+        writeln!(dump, "scheduled: {:?}", change)?;
+    }
+    for y in 0..CHUNK_SIZE {
+        writeln!(dump, "\nlayer {}", y)?;
+        for z in 0..CHUNK_SIZE {
+            let row = (0..CHUNK_SIZE)
+                .map(|x| match *chunk.block_at((x, y, z)) {
+                    BlockType::EMPTY => '.',
+                    BlockType::STONE => '#',
+                    BlockType::WATER => '~',
+                    BlockType::GRASS => '"',
+                    BlockType::DIRT => '%',
+                    BlockType::SAND => ':',
+                    BlockType::SNOW => '*',
+                    BlockType::WOOD => '|',
+                    BlockType::LEAVES => '&',
+                    BlockType::CACTUS => '!',
+                    BlockType::GRAVEL => ',',
+                    BlockType::GLASS => 'o',
+                    BlockType::LAMP => '*',
+                    _ => '?',
+                })
+                .collect::<String>();
+            writeln!(dump, "{}", row)?;
+        }
+    }
+
+    fs::create_dir_all(DUMP_DIR)?;
+    let path = PathBuf::from(DUMP_DIR).join(format!("chunk.{}.{}.{}.txt", pos.x, pos.y, pos.z));
+    fs::write(&path, dump).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
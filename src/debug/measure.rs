@@ -0,0 +1,341 @@
+use std::{
+    fmt::Write as _,
+    io::Write as _,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context};
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use super::pointer_ray;
+use crate::chunk::{BlockPos, ChunkPos, Chunks};
+
+/// How far away a block can be marked from, in blocks.
+const MARK_DISTANCE: f32 = 256.0;
+
+/// The colour markers and measurements are drawn in.
+const MARKER_COLOR: Color = Color::srgb(1.0, 0.8, 0.1);
+
+/// The colour of a measurement panel button at rest.
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+
+/// The colour of a measurement panel button under the cursor.
+const BUTTON_HOVERED_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+
+/// The clipboard commands tried in turn to copy text, since each platform has its own.
+const CLIPBOARD_COMMANDS: [&[&str]; 5] = [
+    &["pbcopy"],
+    &["clip"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// A plugin for marking blocks and measuring between them, for filing precise reports of
+/// generation and meshing artifacts. M marks the block under the cursor, or in the centre of the
+/// view if the cursor is locked, and Shift+M clears the markers. The markers are listed in a panel,
+/// with the distance and volume between the last two, and buttons to copy their coordinates.
+pub struct MeasurePlugin;
+
+impl Plugin for MeasurePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Markers>()
+            .add_systems(Startup, spawn_measure_panel)
+            .add_systems(
+                Update,
+                (
+                    place_markers,
+                    press_measure_buttons,
+                    update_measure_panel.run_if(resource_changed::<Markers>),
+                    draw_markers,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The blocks marked for measuring, in the order they were marked.
+#[derive(Debug, Default, Resource)]
+struct Markers(Vec<IVec3>);
+
+impl Markers {
+    /// Describe the marker at the given index, with its label, block, and chunk.
+    fn describe(&self, index: usize) -> String {
+        let block = self.0[index];
+        let chunk = ChunkPos::from_world_block(block);
+        let local = BlockPos::from_world_block(block);
+        format!(
+            "{}: block ({}, {}, {}) in chunk ({}, {}, {}) at ({}, {}, {})",
+            label(index),
+            block.x,
+            block.y,
+            block.z,
+            chunk.x,
+            chunk.y,
+            chunk.z,
+            local.x,
+            local.y,
+            local.z,
+        )
+    }
+
+    /// Describe the distance and the volume of the box between the last two markers, if there are
+    /// two.
+    fn measurement(&self) -> Option<String> {
+        let [from, to] = self.0.last_chunk::<2>()?;
+        let offset = *to - *from;
+        let size = offset.abs() + IVec3::ONE;
+        Some(format!(
+            "{} to {}: {:.2} blocks apart, offset ({}, {}, {}), box {}x{}x{} of {} blocks",
+            label(self.0.len() - 2),
+            label(self.0.len() - 1),
+            offset.as_vec3().length(),
+            offset.x,
+            offset.y,
+            offset.z,
+            size.x,
+            size.y,
+            size.z,
+            size.x as i64 * size.y as i64 * size.z as i64,
+        ))
+    }
+
+    /// Describe every marker, and the measurement between the last two.
+    fn report(&self) -> String {
+        let mut report = String::new();
+        for index in 0..self.0.len() {
+            let _ = writeln!(report, "{}", self.describe(index));
+        }
+        if let Some(measurement) = self.measurement() {
+            let _ = writeln!(report, "{}", measurement);
+        }
+        report
+    }
+}
+
+/// Return the label of the marker at the given index: A to Z, then A2 to Z2, and so on.
+fn label(index: usize) -> String {
+    let letter = char::from(b'A' + (index % 26) as u8);
+    match index / 26 {
+        0 => letter.to_string(),
+        round => format!("{}{}", letter, round + 1),
+    }
+}
+
+/// What a measurement panel button copies or does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+enum MeasureAction {
+    /// Copy the marker at the given index.
+    Copy(usize),
+    /// Copy every marker and the measurement.
+    CopyAll,
+    /// Remove every marker.
+    Clear,
+}
+
+/// A marker component for the measurement panel.
+#[derive(Component)]
+struct MeasurePanel;
+
+fn spawn_measure_panel(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.0),
+                right: Val::Px(8.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        MeasurePanel,
+    ));
+}
+
+/// Mark the block under the cursor when M is pressed, or clear the markers with Shift+M.
+fn place_markers(
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    chunks: Res<Chunks>,
+    mut markers: ResMut<Markers>,
+) {
+    if !keys.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+    if keys.pressed(KeyCode::ShiftLeft) {
+        markers.0.clear();
+        return;
+    }
+    let (camera, transform) = cameras.single();
+    let Some((origin, direction)) = pointer_ray(windows.single(), camera, transform) else {
+        return;
+    };
+    if let Some(hit) = chunks.raycast(origin, direction, MARK_DISTANCE) {
+        markers.0.push(hit.world_block());
+        info!("Marked {}", markers.describe(markers.0.len() - 1));
+    }
+}
+
+/// Copy markers or clear them as the panel's buttons are pressed, shading the buttons as they are
+/// hovered.
+fn press_measure_buttons(
+    mut buttons: Query<(&Interaction, &MeasureAction, &mut BackgroundColor), Changed<Interaction>>,
+    mut markers: ResMut<Markers>,
+) {
+    for (interaction, action, mut color) in buttons.iter_mut() {
+        *color = match interaction {
+            Interaction::None => BUTTON_COLOR,
+            _ => BUTTON_HOVERED_COLOR,
+        }
+        .into();
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let text = match action {
+            MeasureAction::Copy(index) if *index < markers.0.len() => markers.describe(*index),
+            MeasureAction::Copy(_) => continue,
+            MeasureAction::CopyAll => markers.report(),
+            MeasureAction::Clear => {
+                markers.0.clear();
+                continue;
+            }
+        };
+        match copy_to_clipboard(&text) {
+            Ok(()) => info!("Copied to the clipboard:\n{}", text.trim_end()),
+            // the log still has the text, for copying by hand
+            Err(err) => warn!("Failed to copy to the clipboard: {:?}\n{}", err, text),
+        }
+    }
+}
+
+/// Rebuild the panel listing the markers, hiding it while there are none.
+fn update_measure_panel(
+    mut commands: Commands,
+    markers: Res<Markers>,
+    mut panels: Query<(Entity, &mut Visibility), With<MeasurePanel>>,
+) {
+    let (panel, mut visibility) = panels.single_mut();
+    commands.entity(panel).despawn_descendants();
+    if markers.0.is_empty() {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+
+    commands.entity(panel).with_children(|parent| {
+        for index in 0..markers.0.len() {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    spawn_label(row, markers.describe(index));
+                    spawn_button(row, "Copy", MeasureAction::Copy(index));
+                });
+        }
+        if let Some(measurement) = markers.measurement() {
+            spawn_label(parent, measurement);
+        }
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    column_gap: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|row| {
+                spawn_button(row, "Copy all", MeasureAction::CopyAll);
+                spawn_button(row, "Clear", MeasureAction::Clear);
+            });
+    });
+}
+
+/// Spawn a line of text in the measurement panel.
+fn spawn_label(parent: &mut ChildBuilder, text: String) {
+    parent.spawn(TextBundle::from_section(
+        text,
+        TextStyle {
+            font_size: 14.0,
+            ..default()
+        },
+    ));
+}
+
+/// Spawn a button taking the given action in the measurement panel.
+fn spawn_button(parent: &mut ChildBuilder, text: &str, action: MeasureAction) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            action,
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: 14.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Outline the marked blocks, and draw the line and box between the last two.
+fn draw_markers(mut gizmos: Gizmos, markers: Res<Markers>) {
+    for block in &markers.0 {
+        let centre = block.as_vec3() + Vec3::splat(0.5);
+        gizmos.cuboid(
+            Transform::from_translation(centre).with_scale(Vec3::splat(1.02)),
+            MARKER_COLOR,
+        );
+    }
+    let Some([from, to]) = markers.0.last_chunk::<2>() else {
+        return;
+    };
+    let (from, to) = (from.as_vec3(), to.as_vec3());
+    gizmos.line(from + Vec3::splat(0.5), to + Vec3::splat(0.5), MARKER_COLOR);
+    let (min, max) = (from.min(to), from.max(to) + Vec3::ONE);
+    gizmos.cuboid(
+        Transform::from_translation((min + max) / 2.0).with_scale(max - min),
+        MARKER_COLOR.with_alpha(0.5),
+    );
+}
+
+/// Copy text to the system clipboard, through the first of the platform's clipboard commands that
+/// is installed.
+fn copy_to_clipboard(text: &str) -> anyhow::Result<()> {
+    for command in CLIPBOARD_COMMANDS {
+        let Ok(mut child) = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .spawn()
+        else {
+            continue;
+        };
+        child
+            .stdin
+            .take()
+            .context("clipboard command has no input")?
+            .write_all(text.as_bytes())?;
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    bail!("no clipboard command is available")
+}
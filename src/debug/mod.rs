@@ -0,0 +1,312 @@
+mod inspector;
+mod measure;
+mod ping;
+mod xray;
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use bevy::{
+    diagnostic::DiagnosticsStore, pbr::wireframe::Wireframe, prelude::*, window::CursorGrabMode,
+};
+
+use crate::{
+    chunk::{generation::GenerationStages, key::ChunkMap, progress::LoadProgress, Chunk, Chunks},
+    storage::codec::ChunkCodec,
+};
+
+/// How often the chunk statistics are recomputed, in seconds.
+const CHUNK_STATS_INTERVAL: f32 = 1.0;
+
+/// The width of the longest bar in the chunk statistics histograms, in characters.
+const HISTOGRAM_WIDTH: usize = 24;
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            inspector::ChunkInspectorPlugin,
+            measure::MeasurePlugin,
+            ping::LoadPingPlugin,
+            xray::XRayPlugin,
+        ))
+        .init_resource::<ChunkStats>()
+        .add_systems(
+            Startup,
+            (
+                spawn_debug_cube,
+                spawn_diagnostics_overlay,
+                spawn_chunk_stats_panel,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                update_diagnostics_overlay,
+                toggle_chunk_stats_panel,
+                update_chunk_stats_panel,
+            ),
+        );
+        // .add_systems(Update, draw_debug_gizmos);
+    }
+}
+
+/// Return the ray through the cursor, or through the centre of the view if the cursor is locked.
+fn pointer_ray(
+    window: &Window,
+    camera: &Camera,
+    transform: &GlobalTransform,
+) -> Option<(Vec3, Dir3)> {
+    match window.cursor.grab_mode {
+        CursorGrabMode::None => window
+            .cursor_position()
+            .and_then(|cursor| camera.viewport_to_world(transform, cursor))
+            .map(|ray| (ray.origin, ray.direction)),
+        _ => Some((transform.translation(), transform.forward())),
+    }
+}
+
+/// A marker component for the text listing the current diagnostics.
+#[derive(Component)]
+struct DiagnosticsOverlay;
+
+/// A marker component for the text plotting the chunk statistics.
+#[derive(Component)]
+struct ChunkStatsPanel;
+
+/// The statistics of a single chunk, cached until the chunk is next modified.
+#[derive(Debug, Clone, Copy)]
+struct ChunkSummary {
+    /// The revision of the chunk the summary was taken at.
+    revision: u64,
+    /// The size of the chunk once serialized and compressed, in bytes.
+    size: usize,
+    /// The number of distinct block types in the chunk.
+    palette: usize,
+}
+
+/// Statistics about the loaded chunks, for tuning storage and generation settings.
+#[derive(Resource)]
+struct ChunkStats {
+    /// The summary of every loaded chunk.
+    summaries: ChunkMap<ChunkSummary>,
+    /// The timer until the statistics are next recomputed.
+    timer: Timer,
+}
+
+impl Default for ChunkStats {
+    fn default() -> Self {
+        Self {
+            summaries: ChunkMap::default(),
+            timer: Timer::from_seconds(CHUNK_STATS_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+impl ChunkSummary {
+    /// Summarize a chunk.
+    fn new(codec: &ChunkCodec, chunk: &Chunk) -> Self {
+        Self {
+            revision: chunk.revision(),
+            size: codec.encode(chunk).map_or(0, |payload| payload.len()),
+            palette: chunk.palette().len(),
+        }
+    }
+}
+
+pub fn spawn_debug_cube(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
+            transform: Transform::from_translation(Vec3::new(0.0, 0.5, 0.0)),
+            ..Default::default()
+        },
+        Wireframe,
+    ));
+}
+
+pub fn draw_debug_gizmos(mut gizmos: Gizmos, query: Query<&Transform, With<Camera>>) {
+    let transform = query.single();
+    let origin = transform.forward() * 10.0 + transform.translation;
+    gizmos.arrow(origin, origin + Vec3::X, Color::srgb(1.0, 0.0, 0.0));
+    gizmos.arrow(origin, origin + Vec3::Y, Color::srgb(0.0, 1.0, 0.0));
+    gizmos.arrow(origin, origin + Vec3::Z, Color::srgb(0.0, 0.0, 1.0));
+}
+
+pub fn spawn_diagnostics_overlay(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        DiagnosticsOverlay,
+    ));
+}
+
+/// List every registered diagnostic in the overlay, such as frame times and network traffic, under
+/// the progress of any chunks loading.
+fn update_diagnostics_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    progress: Res<LoadProgress>,
+    mut query: Query<&mut Text, With<DiagnosticsOverlay>>,
+) {
+    let mut text = query.single_mut();
+    let section = &mut text.sections[0].value;
+    section.clear();
+    if progress.is_loading() {
+        let _ = writeln!(
+            section,
+            "loading {}/{} chunks",
+            progress.finished, progress.total
+        );
+    }
+    for diagnostic in diagnostics.iter() {
+        if let Some(value) = diagnostic.smoothed() {
+            let _ = writeln!(
+                section,
+                "{}: {:.1}{}",
+                diagnostic.path(),
+                value,
+                diagnostic.suffix
+            );
+        }
+    }
+}
+
+pub fn spawn_chunk_stats_panel(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 14.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        Visibility::Hidden,
+        ChunkStatsPanel,
+    ));
+}
+
+/// Show or hide the chunk statistics panel when F3 is pressed.
+fn toggle_chunk_stats_panel(
+    input: Res<ButtonInput<KeyCode>>,
+    mut query: Query<&mut Visibility, With<ChunkStatsPanel>>,
+) {
+    if !input.just_pressed(KeyCode::F3) {
+        return;
+    }
+    let mut visibility = query.single_mut();
+    *visibility = match *visibility {
+        Visibility::Hidden => Visibility::Visible,
+        _ => Visibility::Hidden,
+    };
+}
+
+/// Plot the distribution of serialized chunk sizes and palette sizes across the loaded chunks, and
+/// list the average time each generation stage takes. Chunks are only summarized again once they
+/// have been modified.
+fn update_chunk_stats_panel(
+    time: Res<Time>,
+    chunks: Res<Chunks>,
+    stages: Res<GenerationStages>,
+    mut stats: ResMut<ChunkStats>,
+    mut query: Query<(&mut Text, &Visibility), With<ChunkStatsPanel>>,
+) {
+    let (mut text, visibility) = query.single_mut();
+    if !stats.timer.tick(time.delta()).just_finished() || *visibility == Visibility::Hidden {
+        return;
+    }
+
+    let codec = ChunkCodec::default();
+    let mut summaries = ChunkMap::default();
+    for chunk in chunks.iter() {
+        let key = chunk.position.key();
+        let summary = match stats.summaries.get(&key) {
+            Some(summary) if summary.revision == chunk.revision() => *summary,
+            _ => ChunkSummary::new(&codec, chunk),
+        };
+        summaries.insert(key, summary);
+    }
+    stats.summaries = summaries;
+
+    let section = &mut text.sections[0].value;
+    section.clear();
+    let total = stats.summaries.len();
+    let _ = writeln!(section, "loaded chunks: {}", total);
+    if total == 0 {
+        return;
+    }
+
+    // sizes are bucketed by the next power of two
+    let mut sizes = BTreeMap::new();
+    let mut palettes = BTreeMap::new();
+    for summary in stats.summaries.values() {
+        *sizes.entry(summary.size.next_power_of_two()).or_insert(0) += 1;
+        *palettes.entry(summary.palette).or_insert(0) += 1;
+    }
+    let uniform = palettes.get(&1).copied().unwrap_or(0);
+    let _ = writeln!(
+        section,
+        "uniform chunks: {:.1}%",
+        uniform as f64 / total as f64 * 100.0
+    );
+
+    let _ = writeln!(section, "\nserialized size");
+    write_histogram(
+        section,
+        sizes
+            .iter()
+            .map(|(size, count)| (format!("<={} B", size), *count)),
+    );
+    let _ = writeln!(section, "\npalette entries");
+    write_histogram(
+        section,
+        palettes
+            .iter()
+            .map(|(palette, count)| (palette.to_string(), *count)),
+    );
+
+    let _ = writeln!(section, "\ngeneration stages");
+    for timing in stages.timings() {
+        match timing.enabled {
+            true => {
+                let average = timing.average().as_secs_f64() * 1000.0;
+                let _ = writeln!(section, "{}: {:.2} ms", timing.name, average);
+            }
+            false => {
+                let _ = writeln!(section, "{}: off", timing.name);
+            }
+        }
+    }
+}
+
+/// Write a histogram as one labelled bar per bucket, scaled so the largest bucket fills
+/// [`HISTOGRAM_WIDTH`].
+fn write_histogram(output: &mut String, buckets: impl Iterator<Item = (String, usize)>) {
+    let buckets = buckets.collect::<Vec<_>>();
+    let max = buckets
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for (label, count) in buckets {
+        let width = (count * HISTOGRAM_WIDTH).div_ceil(max);
+        let _ = writeln!(output, "{:>10} {} {}", label, "#".repeat(width), count);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::{
+    audio::{AddAudioSource, Decodable, Source, SpatialScale, Volume},
+    prelude::*,
+};
+
+use crate::chunk::{ChunkMeshed, CHUNK_SIZE};
+
+/// The distance from the camera within which meshed chunks ping, in chunks.
+const PING_RADIUS: f32 = 3.0;
+
+/// The sample rate pings are synthesized at, in hertz.
+const SAMPLE_RATE: u32 = 44_100;
+
+/// The pitch of a ping, in hertz.
+const PING_FREQUENCY: f32 = 880.0;
+
+/// The length of a ping.
+const PING_DURATION: Duration = Duration::from_millis(80);
+
+/// The loudness of a ping, before it fades with distance.
+const PING_VOLUME: f32 = 0.3;
+
+/// A plugin playing a short ping at the centre of each chunk meshed near the camera, toggled with
+/// F4, so the order and latency of chunk loading can be heard while moving around.
+pub struct LoadPingPlugin;
+
+impl Plugin for LoadPingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<Ping>()
+            .init_resource::<LoadPings>()
+            .add_systems(Startup, create_ping)
+            .add_systems(Update, (toggle_load_pings, ping_meshed_chunks).chain());
+    }
+}
+
+/// A synthesized ping: a sine wave fading out.
+#[derive(Debug, Clone, Copy, Asset, TypePath)]
+struct Ping;
+
+impl Decodable for Ping {
+    type DecoderItem = f32;
+    type Decoder = PingDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        PingDecoder { sample: 0 }
+    }
+}
+
+/// Yields the samples of a [`Ping`].
+struct PingDecoder {
+    /// The index of the next sample.
+    sample: u32,
+}
+
+impl PingDecoder {
+    /// The number of samples in a ping.
+    const LENGTH: u32 = (SAMPLE_RATE as u64 * PING_DURATION.as_millis() as u64 / 1000) as u32;
+}
+
+impl Iterator for PingDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.sample >= Self::LENGTH {
+            return None;
+        }
+        let t = self.sample as f32 / SAMPLE_RATE as f32;
+        let fade = 1.0 - self.sample as f32 / Self::LENGTH as f32;
+        self.sample += 1;
+        Some((TAU * PING_FREQUENCY * t).sin() * fade * fade)
+    }
+}
+
+impl Source for PingDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some((Self::LENGTH - self.sample) as usize)
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(PING_DURATION)
+    }
+}
+
+/// Whether meshed chunks ping, and the ping they play.
+#[derive(Default, Resource)]
+struct LoadPings {
+    enabled: bool,
+    ping: Handle<Ping>,
+}
+
+/// Add the ping to the assets.
+fn create_ping(mut pings: ResMut<LoadPings>, mut assets: ResMut<Assets<Ping>>) {
+    pings.ping = assets.add(Ping);
+}
+
+/// Turn pings on or off when F4 is pressed.
+fn toggle_load_pings(input: Res<ButtonInput<KeyCode>>, mut pings: ResMut<LoadPings>) {
+    if input.just_pressed(KeyCode::F4) {
+        pings.enabled = !pings.enabled;
+        info!(
+            "Chunk load pings {}",
+            if pings.enabled { "on" } else { "off" }
+        );
+    }
+}
+
+/// Play a ping at the centre of each chunk meshed within [`PING_RADIUS`] of the camera.
+fn ping_meshed_chunks(
+    mut commands: Commands,
+    pings: Res<LoadPings>,
+    mut meshed: EventReader<ChunkMeshed>,
+    cameras: Query<&GlobalTransform, With<SpatialListener>>,
+) {
+    if !pings.enabled {
+        meshed.clear();
+        return;
+    }
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+    let size = CHUNK_SIZE as f32;
+    for event in meshed.read() {
+        let center = event.position.to_world() + Vec3::splat(size / 2.0);
+        if center.distance(camera.translation()) > PING_RADIUS * size {
+            continue;
+        }
+        commands
+            .spawn(AudioSourceBundle {
+                source: pings.ping.clone(),
+                settings: PlaybackSettings::DESPAWN
+                    .with_volume(Volume::new(PING_VOLUME))
+                    .with_spatial(true)
+                    // measure distances in chunks, so nearby pings stay audible
+                    .with_spatial_scale(SpatialScale::new(1.0 / size)),
+            })
+            .insert(TransformBundle::from_transform(
+                Transform::from_translation(center),
+            ));
+    }
+}
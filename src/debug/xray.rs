@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+
+use crate::chunk::material::{ChunkMaterial, ChunkMaterialHandle};
+
+/// How fast the clip plane moves while Page Up or Page Down is held, in blocks per second.
+const PLANE_SPEED: f32 = 16.0;
+
+/// A plugin for slicing the world open: F5 clips chunk geometry above a horizontal plane, starting
+/// at the camera's height, which Page Up and Page Down move, exposing caves and ores.
+pub struct XRayPlugin;
+
+impl Plugin for XRayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XRay>()
+            .add_systems(Update, (control_xray, update_clip_plane).chain());
+    }
+}
+
+/// The height of the clip plane, if x-ray mode is on.
+#[derive(Debug, Default, Resource)]
+struct XRay {
+    height: Option<f32>,
+}
+
+/// Toggle x-ray mode with F5, and move the plane with Page Up and Page Down.
+fn control_xray(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    mut xray: ResMut<XRay>,
+) {
+    if input.just_pressed(KeyCode::F5) {
+        xray.height = match xray.height {
+            Some(_) => None,
+            None => cameras
+                .get_single()
+                .map_or(Some(0.0), |camera| Some(camera.translation().y.floor())),
+        };
+    }
+    let Some(height) = &mut xray.height else {
+        return;
+    };
+    let step = PLANE_SPEED * time.delta_seconds();
+    if input.pressed(KeyCode::PageUp) {
+        *height += step;
+    }
+    if input.pressed(KeyCode::PageDown) {
+        *height -= step;
+    }
+}
+
+/// Move the clip plane of the chunk material to follow x-ray mode.
+fn update_clip_plane(
+    xray: Res<XRay>,
+    material: Res<ChunkMaterialHandle>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    if !xray.is_changed() {
+        return;
+    }
+    if let Some(material) = materials.get_mut(&**material) {
+        material.extension.height = xray.height.unwrap_or(f32::MAX);
+    }
+}
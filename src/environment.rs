@@ -0,0 +1,188 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    pbr::{FogFalloff, FogSettings},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::scheduled::WorldClock;
+
+/// The environment variable naming the dimension the game is played in.
+pub const DIMENSION_ENV: &str = "CHUNKY_DIMENSION";
+
+/// The dimension played in if none is named.
+pub const DEFAULT_DIMENSION: &str = "overworld";
+
+/// The folder dimension files are read from, relative to the assets directory.
+const DIMENSIONS_PATH: &str = "dimensions";
+
+/// The brightness of the ambient light at midday.
+const AMBIENT_BRIGHTNESS: f32 = 500.0;
+
+/// The share of daylight left at midnight.
+const NIGHT_LIGHT: f32 = 0.15;
+
+/// The physical and visual character of a dimension.
+#[derive(Debug, Clone, Copy, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Environment {
+    /// The downward acceleration of falling things, in blocks per second squared.
+    pub gravity: f32,
+    /// The colour of the sky at midday, as linear RGB.
+    pub sky_color: [f32; 3],
+    /// The colour of the fog at midday, as linear RGB.
+    pub fog_color: [f32; 3],
+    /// The distance fog starts at, in blocks.
+    pub fog_start: f32,
+    /// The distance beyond which everything is hidden by fog, in blocks.
+    pub fog_end: f32,
+    /// The length of a day, in seconds. Always midday if zero.
+    pub day_length: f32,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            gravity: 9.81,
+            sky_color: [0.45, 0.65, 0.9],
+            fog_color: [0.6, 0.7, 0.85],
+            fog_start: 128.0,
+            fog_end: 320.0,
+            day_length: 1200.0,
+        }
+    }
+}
+
+impl Environment {
+    /// Return how much of the midday light there is after the given number of seconds, from
+    /// [`NIGHT_LIGHT`] at midnight to 1 at midday. Days start at midday.
+    pub fn daylight(&self, seconds: f32) -> f32 {
+        if self.day_length <= 0.0 {
+            return 1.0;
+        }
+        let sun = (TAU * seconds / self.day_length).cos() * 0.5 + 0.5;
+        NIGHT_LIGHT + (1.0 - NIGHT_LIGHT) * sun
+    }
+}
+
+/// A dimension's environment loaded from a TOML file with the `.dimension` extension.
+#[derive(Debug, Clone, Asset, TypePath)]
+pub struct EnvironmentAsset(pub Environment);
+
+/// Loads [`EnvironmentAsset`]s from TOML files.
+#[derive(Default)]
+pub struct EnvironmentLoader;
+
+impl AssetLoader for EnvironmentLoader {
+    type Asset = EnvironmentAsset;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> anyhow::Result<EnvironmentAsset> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source).await?;
+        Ok(EnvironmentAsset(toml::from_str(&source)?))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dimension"]
+    }
+}
+
+/// A plugin giving the world the gravity, sky, fog, and day length of a dimension, read from
+/// `dimensions/<name>.dimension` and picked up again whenever the file changes.
+pub struct EnvironmentPlugin {
+    /// The name of the dimension.
+    pub dimension: String,
+}
+
+impl EnvironmentPlugin {
+    /// Play in the dimension named in [`DIMENSION_ENV`], or [`DEFAULT_DIMENSION`] if it is not set.
+    pub fn from_env() -> Self {
+        Self {
+            dimension: std::env::var(DIMENSION_ENV).unwrap_or_else(|_| DEFAULT_DIMENSION.into()),
+        }
+    }
+}
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        let path = format!("{}/{}.dimension", DIMENSIONS_PATH, self.dimension);
+        app.init_asset::<EnvironmentAsset>()
+            .init_asset_loader::<EnvironmentLoader>()
+            .init_resource::<Environment>()
+            .init_resource::<WorldClock>()
+            .add_systems(
+                Startup,
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    commands.insert_resource(EnvironmentHandle(asset_server.load(path.clone())));
+                },
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_environment.run_if(on_event::<AssetEvent<EnvironmentAsset>>()),
+                    update_sky,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The handle keeping the dimension's environment loaded.
+#[derive(Resource)]
+struct EnvironmentHandle(Handle<EnvironmentAsset>);
+
+/// Replace the environment when the dimension file is loaded or changed.
+fn apply_environment(
+    mut events: EventReader<AssetEvent<EnvironmentAsset>>,
+    handle: Res<EnvironmentHandle>,
+    assets: Res<Assets<EnvironmentAsset>>,
+    mut environment: ResMut<Environment>,
+) {
+    let changed = events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+            *id == handle.0.id()
+        }
+        _ => false,
+    });
+    if let Some(EnvironmentAsset(loaded)) = assets.get(&handle.0).filter(|_| changed) {
+        info!("Environment changed");
+        *environment = *loaded;
+    }
+}
+
+/// Colour the sky and fog, and dim the ambient light, by the time of day on the world's clock.
+fn update_sky(
+    mut commands: Commands,
+    environment: Res<Environment>,
+    clock: Res<WorldClock>,
+    fixed: Res<Time<Fixed>>,
+    cameras: Query<Entity, With<Camera3d>>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ambient: ResMut<AmbientLight>,
+) {
+    let seconds = clock.tick() as f32 * fixed.timestep().as_secs_f32();
+    let daylight = environment.daylight(seconds);
+    let shade = |[r, g, b]: [f32; 3]| Color::linear_rgb(r * daylight, g * daylight, b * daylight);
+
+    clear_color.0 = shade(environment.sky_color);
+    ambient.brightness = AMBIENT_BRIGHTNESS * daylight;
+    for camera in &cameras {
+        commands.entity(camera).insert(FogSettings {
+            color: shade(environment.fog_color),
+            falloff: FogFalloff::Linear {
+                start: environment.fog_start,
+                end: environment.fog_end,
+            },
+            ..default()
+        });
+    }
+}
@@ -0,0 +1,177 @@
+use std::hash::{BuildHasher, RandomState};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+};
+
+use crate::chunk::generation::{
+    preview::Preview, seed::TerrainNoise, settings::read_terrain_settings, terrain::TerrainSettings,
+};
+
+/// The number of previews along each side of the grid.
+const GRID_SIZE: usize = 3;
+
+/// The width and depth of each preview, in pixels.
+const PREVIEW_SIZE: usize = 128;
+
+/// The number of blocks each preview pixel covers along each axis.
+const BLOCKS_PER_PIXEL: i64 = 16;
+
+/// The size each preview is drawn at, in logical pixels.
+const TILE_SIZE: f32 = 256.0;
+
+/// A plugin for choosing a seed by eye before creating a world: previews of random seeds are shown
+/// in a grid, R shows a fresh set, and clicking a preview prints its seed and exits.
+pub struct SeedExplorerPlugin;
+
+impl Plugin for SeedExplorerPlugin {
+    fn build(&self, app: &mut App) {
+        let terrain = read_terrain_settings()
+            .map_err(|err| error!("Previewing the default terrain: {:?}", err))
+            .unwrap_or_default();
+        app.insert_resource(PreviewTerrain(terrain))
+            .add_systems(Startup, (spawn_camera, spawn_grid))
+            .add_systems(Update, (reroll_seeds, bake_previews, pick_seed));
+    }
+}
+
+/// The terrain settings previews are baked with.
+#[derive(Resource)]
+struct PreviewTerrain(TerrainSettings);
+
+/// A marker component for the node holding the preview grid.
+#[derive(Component)]
+struct PreviewGrid;
+
+/// A component for the preview of a seed.
+#[derive(Component)]
+struct SeedTile(u32);
+
+/// A component for the task baking a seed's preview.
+#[derive(Component)]
+struct PreviewTask(Task<Preview>);
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+}
+
+/// Spawn a grid of previews of random seeds, baking each on the compute task pool.
+fn spawn_grid(mut commands: Commands, terrain: Res<PreviewTerrain>) {
+    let pool = AsyncComputeTaskPool::get();
+    let random = RandomState::new();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Grid,
+                    grid_template_columns: RepeatedGridTrack::px(GRID_SIZE as u16, TILE_SIZE),
+                    justify_content: JustifyContent::Center,
+                    align_content: AlignContent::Center,
+                    column_gap: Val::Px(8.0),
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            },
+            PreviewGrid,
+        ))
+        .with_children(|grid| {
+            for i in 0..GRID_SIZE * GRID_SIZE {
+                let seed = random.hash_one(i) as u32;
+                let terrain = terrain.0;
+                let task = pool.spawn(async move {
+                    let noise = TerrainNoise::new(seed);
+                    Preview::bake(noise.noise(), &terrain, PREVIEW_SIZE, BLOCKS_PER_PIXEL)
+                });
+                grid.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(TILE_SIZE),
+                            height: Val::Px(TILE_SIZE),
+                            align_items: AlignItems::End,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    SeedTile(seed),
+                    PreviewTask(task),
+                ))
+                .with_children(|tile| {
+                    tile.spawn(TextBundle::from_section(
+                        seed.to_string(),
+                        TextStyle {
+                            font_size: 16.0,
+                            ..default()
+                        },
+                    ));
+                });
+            }
+        });
+}
+
+/// Replace the previews with a fresh set of seeds when R is pressed.
+fn reroll_seeds(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    grids: Query<Entity, With<PreviewGrid>>,
+    terrain: Res<PreviewTerrain>,
+) {
+    if !input.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+    for grid in &grids {
+        commands.entity(grid).despawn_recursive();
+    }
+    spawn_grid(commands, terrain);
+}
+
+/// Show each preview once it has been baked.
+fn bake_previews(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut PreviewTask)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (entity, mut task) in &mut tasks {
+        let Some(preview) = block_on(poll_once(&mut task.0)) else {
+            continue;
+        };
+        let image = Image::new(
+            Extent3d {
+                width: preview.size as u32,
+                height: preview.size as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            preview.pixels,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        commands
+            .entity(entity)
+            .remove::<PreviewTask>()
+            .insert(UiImage::new(images.add(image)));
+    }
+}
+
+/// Print the seed of a clicked preview and exit, so the world can be created with it.
+fn pick_seed(
+    tiles: Query<(&Interaction, &SeedTile), Changed<Interaction>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, &SeedTile(seed)) in &tiles {
+        if *interaction == Interaction::Pressed {
+            println!(
+                "Picked seed {}, start the world with: chunky {}",
+                seed, seed
+            );
+            exit.send(AppExit::Success);
+        }
+    }
+}
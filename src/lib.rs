@@ -0,0 +1,17 @@
+pub mod audio;
+pub mod breaking;
+pub mod channel;
+pub mod chunk;
+pub mod crash;
+pub mod debug;
+pub mod environment;
+pub mod explorer;
+pub mod net;
+pub mod outline;
+pub mod particles;
+pub mod physics;
+pub mod player;
+pub mod projectile;
+pub mod shadow;
+pub mod storage;
+pub mod tasks;
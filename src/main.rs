@@ -1,4 +1,8 @@
+use std::path::Path;
+
 use bevy::{
+    diagnostic::FrameTimeDiagnosticsPlugin,
+    log::LogPlugin,
     pbr::wireframe::WireframePlugin,
     prelude::*,
     render::{
@@ -7,29 +11,123 @@ use bevy::{
     },
 };
 
-mod channel;
-mod chunk;
-mod debug;
-mod player;
+use chunky::{
+    audio::BlockAudioPlugin,
+    breaking::BlockBreakingPlugin,
+    chunk::{
+        generation::{
+            preset_from_env,
+            seed::{parse_seed, TerrainNoise},
+            settings::TerrainSettingsPlugin,
+            templates::StructureTemplatePlugin,
+            worker::GenerationBackend,
+            world_preset, GenerationStages,
+        },
+        ChunkPlugin,
+    },
+    crash::{self, CrashReportPlugin},
+    debug::DebugPlugin,
+    environment::EnvironmentPlugin,
+    explorer::SeedExplorerPlugin,
+    net::client::{ClientConfig, ClientPlugin},
+    outline::BlockOutlinePlugin,
+    particles::BreakParticlesPlugin,
+    player::PlayerPlugin,
+    projectile::ProjectilePlugin,
+    shadow::BlobShadowPlugin,
+    storage::{codec::Compression, IntegritySettings, StorageBackend, WorldStorage},
+    tasks::TaskPoolConfig,
+};
+
+/// The argument opening the seed explorer instead of the world.
+const EXPLORE_SEEDS_ARG: &str = "--explore-seeds";
 
-use chunk::ChunkPlugin;
-use debug::DebugPlugin;
-use player::PlayerPlugin;
+/// The prefix of the argument choosing the preset of a new world, such as `--preset=void`.
+const PRESET_ARG: &str = "--preset=";
+
+/// The directory the world is saved in.
+const WORLD_PATH: &str = "world";
 
 fn main() {
-    App::default()
-        .add_plugins((
-            DefaultPlugins.set(RenderPlugin {
+    if std::env::args().nth(1).as_deref() == Some(EXPLORE_SEEDS_ARG) {
+        App::new()
+            .add_plugins((DefaultPlugins, SeedExplorerPlugin))
+            .run();
+        return;
+    }
+    // a server keeps the world it streams, so nothing is saved locally while joined to one
+    let client = ClientConfig::from_env();
+    let storage = match client {
+        Some(_) => WorldStorage::default(),
+        None => WorldStorage::open(StorageBackend::Region, WORLD_PATH, Compression::default())
+            .map_err(|err| eprintln!("World will not be saved: {:?}", err))
+            .unwrap_or_default(),
+    };
+    // the seed can be given as an argument, overriding the environment
+    let noise = match std::env::args().skip(1).find(|arg| !arg.starts_with("--")) {
+        Some(seed) => parse_seed(&seed).map(TerrainNoise::new),
+        None => TerrainNoise::from_env(),
+    }
+    .map_err(|err| eprintln!("Using the default seed: {:?}", err))
+    .unwrap_or_default();
+    let integrity = IntegritySettings::from_env()
+        .map_err(|err| eprintln!("World will not be checked: {:?}", err))
+        .unwrap_or_default();
+    // so can the preset of a new world, while an existing world keeps the preset it was created with
+    let requested =
+        match std::env::args().find_map(|arg| arg.strip_prefix(PRESET_ARG).map(str::to_owned)) {
+            Some(preset) => preset.parse().map(Some),
+            None => preset_from_env(),
+        }
+        .map_err(|err| eprintln!("Ignoring the requested preset: {:?}", err))
+        .unwrap_or_default();
+    let preset = world_preset(Path::new(WORLD_PATH), requested)
+        .map_err(|err| eprintln!("World preset will not be saved: {:?}", err))
+        .unwrap_or(requested);
+    let stages = GenerationStages::for_preset(preset)
+        .map_err(|err| eprintln!("Generating terrain from noise: {:?}", err))
+        .unwrap_or_default();
+
+    let mut app = App::default();
+    app.add_plugins((
+        DefaultPlugins
+            .set(RenderPlugin {
                 render_creation: RenderCreation::Automatic(WgpuSettings {
                     features: WgpuFeatures::POLYGON_MODE_LINE,
                     ..default()
                 }),
                 ..default()
-            }),
-            WireframePlugin,
-            DebugPlugin,
-            ChunkPlugin,
-            PlayerPlugin,
-        ))
-        .run();
+            })
+            .set(LogPlugin {
+                custom_layer: crash::log_layer,
+                ..default()
+            })
+            .set(TaskPoolConfig::default().plugin()),
+        CrashReportPlugin,
+        WireframePlugin,
+        FrameTimeDiagnosticsPlugin,
+        DebugPlugin,
+        ChunkPlugin::default(),
+        TerrainSettingsPlugin,
+        StructureTemplatePlugin,
+        PlayerPlugin::default(),
+        ProjectilePlugin,
+        BlobShadowPlugin,
+        EnvironmentPlugin::from_env(),
+    ))
+    .add_plugins((
+        BlockAudioPlugin,
+        BreakParticlesPlugin,
+        BlockBreakingPlugin,
+        BlockOutlinePlugin,
+    ))
+    .insert_resource(GenerationBackend::from_env(preset))
+    .insert_resource(noise)
+    .insert_resource(stages)
+    .insert_resource(storage)
+    .insert_resource(integrity);
+    if let Some(client) = client {
+        app.add_plugins(ClientPlugin).insert_resource(client);
+    }
+    app.run();
 }
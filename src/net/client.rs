@@ -0,0 +1,233 @@
+use std::{
+    io::BufReader,
+    net::{Shutdown, TcpStream},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+
+use super::{
+    prediction::PredictedEdits,
+    protocol::{handshake, read_message, write_message, ClientMessage, ServerMessage},
+};
+use crate::{
+    channel::{ChannelAppExtension, ChannelSender},
+    chunk::{BlockEdited, ChunkCommand, ChunkLoaded, ChunkSettings, ChunkSystems, Chunks},
+    player::Player,
+    storage::codec::ChunkCodec,
+};
+
+/// The environment variable giving the address of a server to join, such as `localhost:7777`. The
+/// world is generated and saved locally if it is not set.
+pub const SERVER_ENV: &str = "CHUNKY_SERVER";
+
+/// The environment variable giving the name the player joins a server with.
+pub const NAME_ENV: &str = "CHUNKY_NAME";
+
+/// The time between reports of the player's position to the server.
+const POSITION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The server a client joins, and the name it joins with.
+#[derive(Debug, Clone, Resource)]
+pub struct ClientConfig {
+    /// The address of the server.
+    pub address: String,
+    /// The name the player joins with.
+    pub name: String,
+}
+
+impl ClientConfig {
+    /// Read the server to join from [`SERVER_ENV`] and [`NAME_ENV`], or `None` if no server is set.
+    pub fn from_env() -> Option<Self> {
+        let address = std::env::var(SERVER_ENV).ok()?;
+        let name = std::env::var(NAME_ENV).unwrap_or_else(|_| "player".into());
+        Some(Self { address, name })
+    }
+}
+
+/// An event forwarded from the connection threads to the client.
+#[derive(Event)]
+pub enum ClientEvent {
+    /// The client connected and completed the handshake, and can send messages to the server
+    /// through the given sender.
+    Connected(mpsc::Sender<ClientMessage>),
+    /// The server sent a message.
+    Message(ServerMessage),
+    /// The connection to the server closed, or could not be made.
+    Disconnected,
+}
+
+/// The connection to the server.
+#[derive(Resource)]
+pub struct ServerConnection {
+    /// A sender for messages to the server.
+    sender: mpsc::Sender<ClientMessage>,
+}
+
+impl ServerConnection {
+    /// Send a message to the server. Messages sent after the connection closes are dropped.
+    pub fn send(&self, message: ClientMessage) {
+        let _ = self.sender.send(message);
+    }
+}
+
+/// A plugin for running the client side of the multiplayer protocol, joining the server given by
+/// the [`ClientConfig`] resource. Chunks are streamed from the server rather than generated, and
+/// block edits are applied straight away, then rolled back if the server rejects them. Edits other
+/// than setting single blocks are refused while connected.
+pub struct ClientPlugin;
+
+impl Plugin for ClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_channel::<ClientEvent>()
+            .init_resource::<PredictedEdits>()
+            .add_systems(Startup, connect)
+            .add_systems(
+                Update,
+                (
+                    handle_client_events,
+                    report_position.run_if(
+                        resource_exists::<ServerConnection>.and_then(on_timer(POSITION_INTERVAL)),
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                send_predicted_edits
+                    .run_if(resource_exists::<ServerConnection>.and_then(on_event::<BlockEdited>()))
+                    .after(ChunkSystems::CommandIntake),
+            );
+    }
+}
+
+/// Connect to the server on a background thread.
+fn connect(config: Res<ClientConfig>, events: Res<ChannelSender<ClientEvent>>) {
+    let address = config.address.clone();
+    let events = events.clone();
+    thread::spawn(move || {
+        if let Err(err) = run_connection(&address, &events) {
+            error!("Lost the connection to {}: {:?}", address, err);
+        }
+        let _ = events.send(ClientEvent::Disconnected);
+    });
+}
+
+/// Forward messages between the client and the server, until the connection closes.
+fn run_connection(address: &str, events: &mpsc::Sender<ClientEvent>) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(address)?;
+    let writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    handshake(&mut reader, &mut &writer)?;
+    info!("Connected to {}", address);
+
+    // write outbound messages until the client drops the connection
+    let (tx, rx) = mpsc::channel::<ClientMessage>();
+    thread::spawn(move || {
+        for message in rx {
+            if write_message(&mut &writer, &message).is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown(Shutdown::Both);
+    });
+
+    let _ = events.send(ClientEvent::Connected(tx));
+    loop {
+        let message = read_message(&mut reader)?;
+        let _ = events.send(ClientEvent::Message(message));
+    }
+}
+
+/// Handle the connection opening and closing, and messages from the server.
+fn handle_client_events(
+    mut commands: Commands,
+    mut events: EventReader<ClientEvent>,
+    connection: Option<Res<ServerConnection>>,
+    (config, mut settings): (Res<ClientConfig>, ResMut<ChunkSettings>),
+    mut chunks: ResMut<Chunks>,
+    mut predictions: ResMut<PredictedEdits>,
+    (mut loaded, mut chunk_commands): (EventWriter<ChunkLoaded>, EventWriter<ChunkCommand>),
+) {
+    for event in events.read() {
+        let message = match event {
+            ClientEvent::Connected(sender) => {
+                let _ = sender.send(ClientMessage::Join {
+                    name: config.name.clone(),
+                });
+                commands.insert_resource(ServerConnection {
+                    sender: sender.clone(),
+                });
+                // the server only hears of single block edits, so others would go unseen
+                settings.bulk_edits = false;
+                continue;
+            }
+            ClientEvent::Disconnected => {
+                warn!("Disconnected from {}", config.address);
+                commands.remove_resource::<ServerConnection>();
+                settings.bulk_edits = true;
+                continue;
+            }
+            ClientEvent::Message(message) => message,
+        };
+        match message {
+            ServerMessage::Welcome { client_id, .. } => {
+                info!("Joined {} as client {}", config.address, client_id);
+            }
+            ServerMessage::Disconnect { reason } => {
+                warn!("Disconnected from {}: {}", config.address, reason);
+            }
+            ServerMessage::ChunkData(chunk) => {
+                let pos = chunk.position;
+                chunks.insert(chunk.as_ref().clone());
+                loaded.send(ChunkLoaded(pos));
+            }
+            // the payload is tagged with its compression, so any codec can decode it
+            ServerMessage::CompressedChunk(payload) => {
+                match ChunkCodec::default().decode(payload) {
+                    Ok(chunk) => {
+                        let pos = chunk.position;
+                        chunks.insert(chunk);
+                        loaded.send(ChunkLoaded(pos));
+                    }
+                    Err(err) => error!("Failed to decode a chunk from the server: {:?}", err),
+                }
+            }
+            ServerMessage::UnloadChunk(pos) => {
+                chunk_commands.send(ChunkCommand::Unload(*pos));
+            }
+            ServerMessage::BlockChanged(pos, block) => {
+                predictions.apply_authoritative(&mut chunks, *pos, *block);
+            }
+            ServerMessage::EditResult { sequence, accepted } => {
+                predictions.reconcile(&mut chunks, *sequence, *accepted);
+            }
+            ServerMessage::Ping { sent_at } => {
+                if let Some(connection) = &connection {
+                    connection.send(ClientMessage::Pong { sent_at: *sent_at });
+                }
+            }
+        }
+    }
+}
+
+/// Send the blocks the player has edited to the server, recording each edit as a prediction to
+/// roll back if the server rejects it.
+fn send_predicted_edits(
+    mut edited: EventReader<BlockEdited>,
+    mut predictions: ResMut<PredictedEdits>,
+    connection: Res<ServerConnection>,
+) {
+    for BlockEdited { pos, block, undo } in edited.read() {
+        connection.send(predictions.predict(*pos, *block, undo.clone()));
+    }
+}
+
+/// Report the player's position to the server, which streams the chunks around it.
+fn report_position(players: Query<&Transform, With<Player>>, connection: Res<ServerConnection>) {
+    if let Ok(transform) = players.get_single() {
+        connection.send(ClientMessage::Position(transform.translation));
+    }
+}
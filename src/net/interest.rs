@@ -0,0 +1,80 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::chunk::{key::ChunkMap, ChunkPos};
+
+/// Which clients are subscribed to which chunks. A client is subscribed to a chunk once it has been
+/// sent the chunk, and only receives changes to the chunks it is subscribed to.
+#[derive(Debug, Default, Resource)]
+pub struct Subscriptions {
+    /// The clients subscribed to each chunk.
+    by_chunk: ChunkMap<HashSet<u64>>,
+    /// The chunks each client is subscribed to.
+    by_client: HashMap<u64, HashSet<ChunkPos>>,
+}
+
+impl Subscriptions {
+    /// Subscribe a client to a chunk, returning `false` if it was already subscribed.
+    pub fn subscribe(&mut self, client_id: u64, pos: ChunkPos) -> bool {
+        self.by_chunk
+            .entry(pos.key())
+            .or_default()
+            .insert(client_id);
+        self.by_client.entry(client_id).or_default().insert(pos)
+    }
+
+    /// Unsubscribe a client from a chunk, returning `false` if it was not subscribed.
+    pub fn unsubscribe(&mut self, client_id: u64, pos: ChunkPos) -> bool {
+        if let Some(clients) = self.by_chunk.get_mut(&pos.key()) {
+            clients.remove(&client_id);
+            if clients.is_empty() {
+                self.by_chunk.remove(&pos.key());
+            }
+        }
+        self.by_client
+            .get_mut(&client_id)
+            .is_some_and(|chunks| chunks.remove(&pos))
+    }
+
+    /// Unsubscribe a client from every chunk.
+    pub fn unsubscribe_all(&mut self, client_id: u64) {
+        for pos in self.by_client.remove(&client_id).unwrap_or_default() {
+            self.unsubscribe(client_id, pos);
+        }
+    }
+
+    /// Unsubscribe every client from a chunk, such as when it is unloaded, returning the clients
+    /// that were subscribed.
+    pub fn unsubscribe_chunk(&mut self, pos: ChunkPos) -> HashSet<u64> {
+        let clients = self.by_chunk.remove(&pos.key()).unwrap_or_default();
+        for client_id in &clients {
+            if let Some(chunks) = self.by_client.get_mut(client_id) {
+                chunks.remove(&pos);
+            }
+        }
+        clients
+    }
+
+    /// Check if a client is subscribed to a chunk.
+    pub fn is_subscribed(&self, client_id: u64, pos: ChunkPos) -> bool {
+        self.by_client
+            .get(&client_id)
+            .is_some_and(|chunks| chunks.contains(&pos))
+    }
+
+    /// Return an iterator over the clients subscribed to a chunk.
+    pub fn subscribers(&self, pos: ChunkPos) -> impl Iterator<Item = u64> + '_ {
+        self.by_chunk.get(&pos.key()).into_iter().flatten().copied()
+    }
+
+    /// Return an iterator over the chunks a client is subscribed to.
+    pub fn chunks_of(&self, client_id: u64) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.by_client
+            .get(&client_id)
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+/// Bytes received from clients per second.
+pub const BYTES_IN: DiagnosticPath = DiagnosticPath::const_new("net/bytes_in");
+
+/// Bytes sent to clients per second.
+pub const BYTES_OUT: DiagnosticPath = DiagnosticPath::const_new("net/bytes_out");
+
+/// Chunks sent to clients per second.
+pub const CHUNKS_SENT: DiagnosticPath = DiagnosticPath::const_new("net/chunks_sent");
+
+/// Block changes sent to clients per second.
+pub const DELTAS_SENT: DiagnosticPath = DiagnosticPath::const_new("net/deltas_sent");
+
+/// The mean round-trip time to connected clients, in milliseconds.
+pub const ROUND_TRIP_TIME: DiagnosticPath = DiagnosticPath::const_new("net/rtt");
+
+/// Running totals of network traffic, shared with the connection threads.
+#[derive(Debug, Default, Clone, Resource)]
+pub struct NetworkCounters {
+    /// The total number of bytes received.
+    pub bytes_in: Arc<AtomicU64>,
+    /// The total number of bytes sent.
+    pub bytes_out: Arc<AtomicU64>,
+    /// The total number of chunks sent.
+    pub chunks_sent: Arc<AtomicU64>,
+    /// The total number of block changes sent.
+    pub deltas_sent: Arc<AtomicU64>,
+}
+
+/// The most recent round-trip time to each client, in milliseconds.
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+pub struct RoundTripTimes(bevy::utils::HashMap<u64, f64>);
+
+/// The counter totals at the last measurement.
+#[derive(Default)]
+struct LastTotals {
+    bytes_in: u64,
+    bytes_out: u64,
+    chunks_sent: u64,
+    deltas_sent: u64,
+}
+
+/// A plugin for measuring network traffic through Bevy diagnostics.
+pub struct NetworkMetricsPlugin;
+
+impl Plugin for NetworkMetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkCounters>()
+            .init_resource::<RoundTripTimes>()
+            .register_diagnostic(Diagnostic::new(BYTES_IN).with_suffix(" B/s"))
+            .register_diagnostic(Diagnostic::new(BYTES_OUT).with_suffix(" B/s"))
+            .register_diagnostic(Diagnostic::new(CHUNKS_SENT).with_suffix("/s"))
+            .register_diagnostic(Diagnostic::new(DELTAS_SENT).with_suffix("/s"))
+            .register_diagnostic(Diagnostic::new(ROUND_TRIP_TIME).with_suffix(" ms"))
+            .add_systems(Last, measure_network);
+    }
+}
+
+/// Convert the counter totals into per-second rates.
+fn measure_network(
+    mut diagnostics: Diagnostics,
+    time: Res<Time>,
+    counters: Res<NetworkCounters>,
+    round_trip_times: Res<RoundTripTimes>,
+    mut last: Local<LastTotals>,
+) {
+    let delta = time.delta_seconds_f64();
+    if delta <= 0.0 {
+        return;
+    }
+
+    let mut rate = |path: &DiagnosticPath, counter: &AtomicU64, last: &mut u64| {
+        let total = counter.load(Ordering::Relaxed);
+        let change = total - *last;
+        *last = total;
+        diagnostics.add_measurement(path, || change as f64 / delta);
+    };
+    rate(&BYTES_IN, &counters.bytes_in, &mut last.bytes_in);
+    rate(&BYTES_OUT, &counters.bytes_out, &mut last.bytes_out);
+    rate(&CHUNKS_SENT, &counters.chunks_sent, &mut last.chunks_sent);
+    rate(&DELTAS_SENT, &counters.deltas_sent, &mut last.deltas_sent);
+
+    if !round_trip_times.is_empty() {
+        diagnostics.add_measurement(&ROUND_TRIP_TIME, || {
+            round_trip_times.values().sum::<f64>() / round_trip_times.len() as f64
+        });
+    }
+}
+
+/// A stream wrapper that adds the number of bytes read or written to a counter.
+pub struct Counted<S> {
+    inner: S,
+    counter: Arc<AtomicU64>,
+}
+
+impl<S> Counted<S> {
+    /// Wrap a stream, counting its traffic in the given counter.
+    pub fn new(inner: S, counter: Arc<AtomicU64>) -> Self {
+        Self { inner, counter }
+    }
+}
+
+impl<S: Read> Read for Counted<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.counter.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+impl<S: Write> Write for Counted<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.counter.fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,6 @@
+pub mod client;
+pub mod interest;
+pub mod metrics;
+pub mod prediction;
+pub mod protocol;
+pub mod server;
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use super::protocol::ClientMessage;
+use crate::chunk::{delta::ChunkDelta, undo::UndoEntry, BlockPos, BlockType, ChunkPos, Chunks};
+
+/// A block edit applied locally before the server has confirmed it.
+#[derive(Debug, Clone, PartialEq)]
+struct PredictedEdit {
+    /// The world block coordinates of the edit.
+    pos: IVec3,
+    /// The changes that roll the edit back if the server rejects it.
+    undo: UndoEntry,
+}
+
+/// Block edits that have been applied locally but not yet acknowledged by the server, so they can
+/// be rolled back if the server rejects them.
+#[derive(Debug, Default, Resource)]
+pub struct PredictedEdits {
+    /// The sequence number of the next edit.
+    next_sequence: u32,
+    /// The edits awaiting acknowledgement, keyed by their sequence number.
+    pending: BTreeMap<u32, PredictedEdit>,
+}
+
+impl PredictedEdits {
+    /// Record an edit that has been applied locally, along with the changes that undo it, returning
+    /// the message asking the server to make it.
+    pub fn predict(&mut self, pos: IVec3, block: BlockType, undo: UndoEntry) -> ClientMessage {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.pending.insert(sequence, PredictedEdit { pos, undo });
+        ClientMessage::SetBlock {
+            sequence,
+            pos,
+            block,
+        }
+    }
+
+    /// Resolve an edit once the server has accepted or rejected it. Rejected edits are rolled back,
+    /// unless a later edit to the same block is still pending, in which case that edit inherits the
+    /// changes that roll it back.
+    pub fn reconcile(&mut self, chunks: &mut Chunks, sequence: u32, accepted: bool) {
+        let Some(edit) = self.pending.remove(&sequence) else {
+            return;
+        };
+        if accepted {
+            return;
+        }
+        match self.later_edit_mut(sequence, edit.pos) {
+            Some(later) => later.undo = edit.undo,
+            None => {
+                for delta in &edit.undo {
+                    chunks.apply_delta(delta);
+                }
+            }
+        }
+    }
+
+    /// Apply a block change made by the server. Blocks with pending edits keep their predicted
+    /// value, but roll back to the server's block if those edits are rejected.
+    pub fn apply_authoritative(&mut self, chunks: &mut Chunks, pos: IVec3, block: BlockType) {
+        match self.pending.values_mut().find(|edit| edit.pos == pos) {
+            Some(edit) => {
+                let base_revision = edit.undo.first().map_or(0, |delta| delta.base_revision);
+                let mut undo = ChunkDelta::new(ChunkPos::from_world_block(pos), base_revision);
+                undo.set_block(BlockPos::from_world_block(pos), block);
+                edit.undo = vec![undo];
+            }
+            None => {
+                chunks.set_block_at_world_block(pos, block);
+            }
+        }
+    }
+
+    /// Return the number of edits awaiting acknowledgement.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Check if every edit has been acknowledged.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Return the earliest pending edit to the given block made after the given sequence number.
+    fn later_edit_mut(&mut self, sequence: u32, pos: IVec3) -> Option<&mut PredictedEdit> {
+        self.pending
+            .range_mut(sequence..)
+            .map(|(_, edit)| edit)
+            .find(|edit| edit.pos == pos)
+    }
+}
@@ -0,0 +1,178 @@
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context};
+use bevy::math::{IVec3, Vec3};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    chunk::{BlockType, Chunk, ChunkPos},
+    storage::codec::Compression,
+};
+
+/// The largest message either side will accept, in bytes.
+pub const MAX_MESSAGE_SIZE: u32 = 16 * 1024 * 1024;
+
+/// The version of the protocol this build speaks. It must be bumped whenever a message changes
+/// shape, so builds that would misread each other refuse to talk instead.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The optional features one side of a connection supports. Each side sends its own in the
+/// handshake, and only those both support are used.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// The compressions chunk data can be sent with, most preferred first.
+    pub compression: Vec<Compression>,
+    /// Whether chunk data can carry light levels, rather than the receiver relighting chunks.
+    pub lighting: bool,
+    /// Whether distant chunks can be sent at a lower level of detail.
+    pub lod: bool,
+}
+
+impl Capabilities {
+    /// Return the capabilities of this build. Chunks are sent without light or lower detail
+    /// versions yet, so only compression is offered.
+    pub fn supported() -> Self {
+        Self {
+            compression: vec![Compression::Lz4, Compression::Zstd],
+            lighting: false,
+            lod: false,
+        }
+    }
+
+    /// Return the capabilities supported by both this side and the other, in this side's order of
+    /// preference.
+    pub fn negotiate(&self, other: &Self) -> Self {
+        Self {
+            compression: self
+                .compression
+                .iter()
+                .copied()
+                .filter(|compression| other.compression.contains(compression))
+                .collect(),
+            lighting: self.lighting && other.lighting,
+            lod: self.lod && other.lod,
+        }
+    }
+
+    /// Return the compression chunk data is sent with: the most preferred, or none at all.
+    pub fn chunk_compression(&self) -> Compression {
+        self.compression
+            .first()
+            .copied()
+            .unwrap_or(Compression::None)
+    }
+}
+
+/// The first message each side of a connection sends, before any other. The version comes first,
+/// so a side can read it even from a build whose handshake is laid out differently.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handshake {
+    /// The version of the protocol the side speaks.
+    pub version: u32,
+    /// The optional features the side supports.
+    pub capabilities: Capabilities,
+}
+
+impl Default for Handshake {
+    /// The handshake of this build.
+    fn default() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            capabilities: Capabilities::supported(),
+        }
+    }
+}
+
+/// A message sent from a client to the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Introduce the client to the server. This must be the first message sent after the
+    /// handshake.
+    Join { name: String },
+    /// Report the position of the client's player.
+    Position(Vec3),
+    /// Set the block at the given world block coordinates. The server answers with an
+    /// [`ServerMessage::EditResult`] carrying the same sequence number.
+    SetBlock {
+        sequence: u32,
+        pos: IVec3,
+        block: BlockType,
+    },
+    /// Answer a [`ServerMessage::Ping`], echoing its timestamp.
+    Pong { sent_at: f64 },
+}
+
+/// A message sent from the server to a client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// Accept the client, assigning it an identifier.
+    Welcome { client_id: u64, view_distance: u32 },
+    /// Disconnect the client, with a reason to show the player.
+    Disconnect { reason: String },
+    /// A chunk the client can now see.
+    ChunkData(Box<Chunk>),
+    /// A chunk the client can now see, encoded with a [`ChunkCodec`] in the compression negotiated
+    /// in the handshake.
+    ///
+    /// [`ChunkCodec`]: crate::storage::codec::ChunkCodec
+    CompressedChunk(Vec<u8>),
+    /// A chunk the client can no longer see, and should forget.
+    UnloadChunk(ChunkPos),
+    /// A block was changed at the given world block coordinates.
+    BlockChanged(IVec3, BlockType),
+    /// Whether the client's edit with the given sequence number was applied.
+    EditResult { sequence: u32, accepted: bool },
+    /// Measure the round-trip time to the client, which answers with a [`ClientMessage::Pong`].
+    Ping { sent_at: f64 },
+}
+
+/// Write a length-prefixed message to a stream.
+pub fn write_message<W: Write, M: Serialize>(writer: &mut W, message: &M) -> anyhow::Result<()> {
+    let payload = bincode::serialize(message)?;
+    let len = u32::try_from(payload.len()).context("message too large")?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&payload)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a length-prefixed message from a stream, blocking until it arrives.
+pub fn read_message<R: Read, M: DeserializeOwned>(reader: &mut R) -> anyhow::Result<M> {
+    Ok(bincode::deserialize(&read_payload(reader)?)?)
+}
+
+/// Send this side's handshake and read the other side's, returning the capabilities both support.
+/// Fails with a clear message if the other side speaks a different version of the protocol, rather
+/// than misreading whatever it sends next.
+pub fn handshake<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+) -> anyhow::Result<Capabilities> {
+    let ours = Handshake::default();
+    write_message(writer, &ours)?;
+    let payload = read_payload(reader).context("connection closed during the handshake")?;
+    let version = bincode::deserialize::<u32>(&payload).context("malformed handshake")?;
+    if version != PROTOCOL_VERSION {
+        bail!(
+            "the other side speaks protocol version {}, but this build speaks version {}; \
+             both must run the same version of chunky",
+            version,
+            PROTOCOL_VERSION
+        );
+    }
+    let theirs = bincode::deserialize::<Handshake>(&payload).context("malformed handshake")?;
+    Ok(ours.capabilities.negotiate(&theirs.capabilities))
+}
+
+/// Read the payload of a length-prefixed message from a stream, blocking until it arrives.
+fn read_payload<R: Read>(reader: &mut R) -> anyhow::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_MESSAGE_SIZE {
+        bail!("message of {} bytes exceeds the maximum size", len);
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
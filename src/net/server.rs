@@ -0,0 +1,533 @@
+use std::{
+    io::BufReader,
+    net::{Shutdown, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use itertools::{iproduct, Itertools};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    interest::Subscriptions,
+    metrics::{Counted, NetworkCounters, NetworkMetricsPlugin, RoundTripTimes},
+    protocol::{
+        handshake, read_message, write_message, Capabilities, ClientMessage, ServerMessage,
+    },
+};
+use crate::{
+    channel::{ChannelAppExtension, ChannelSender},
+    chunk::{
+        blocks::BlockRegistry, generation::preset::WorldPreset, queue::LoadQueue, BlockPos, Chunk,
+        ChunkCommand, ChunkLoaded, ChunkPos, Chunks,
+    },
+    player::RenderDistance,
+    storage::{
+        codec::{ChunkCodec, Compression},
+        StorageBackend,
+    },
+    tasks::TaskPoolConfig,
+};
+
+/// The number of simulation ticks the server runs per second.
+pub const TICK_RATE: f64 = 20.0;
+
+/// The time between round-trip time measurements.
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The maximum number of chunks sent to a single client per tick.
+const CHUNKS_PER_TICK: usize = 16;
+
+/// Configuration for a dedicated server, read from `server.toml`.
+#[derive(Debug, Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// The TCP port to listen on.
+    pub port: u16,
+    /// The directory the world is saved in.
+    pub world_path: PathBuf,
+    /// The kind of store the world is saved in.
+    pub storage: StorageBackend,
+    /// The compression applied to saved chunks.
+    pub compression: Compression,
+    /// The radius of chunks streamed to each player, measured in chunks.
+    pub view_distance: u32,
+    /// The maximum number of players connected at once.
+    pub max_players: usize,
+    /// The time between autosaves of modified chunks, measured in seconds.
+    pub autosave_interval: u64,
+    /// The time between full snapshots of every loaded chunk, measured in seconds.
+    pub snapshot_interval: u64,
+    /// The maximum number of chunks autosaves write per tick.
+    pub autosave_chunks_per_tick: usize,
+    /// The estimated memory loaded chunks may use before the least recently accessed are unloaded,
+    /// measured in megabytes. Unlimited if unset.
+    pub chunk_memory_budget: Option<usize>,
+    /// The time a chunk may go unaccessed before it is unloaded, and saved if it has been
+    /// modified, measured in seconds. Unlimited if unset.
+    pub chunk_max_age: Option<u64>,
+    /// The seed terrain is generated from. Falls back to the seed in the environment if unset.
+    pub seed: Option<u32>,
+    /// The lowest chunk Y coordinate that is generated and loaded. Unbounded if unset.
+    pub min_chunk_y: Option<i64>,
+    /// The highest chunk Y coordinate that is generated and loaded. Unbounded if unset.
+    pub max_chunk_y: Option<i64>,
+    /// The preset a new world is created with. Falls back to the preset in the environment if
+    /// unset. An existing world keeps the preset it was created with.
+    pub preset: Option<WorldPreset>,
+    /// The threads given to chunk generation, saving, and systems, read from the `[tasks]` table.
+    pub tasks: TaskPoolConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 7777,
+            world_path: PathBuf::from("world"),
+            storage: StorageBackend::default(),
+            compression: Compression::default(),
+            view_distance: 4,
+            max_players: 16,
+            autosave_interval: 300,
+            snapshot_interval: 1800,
+            autosave_chunks_per_tick: 8,
+            chunk_memory_budget: None,
+            chunk_max_age: None,
+            seed: None,
+            min_chunk_y: None,
+            max_chunk_y: None,
+            preset: None,
+            tasks: TaskPoolConfig::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Read the configuration from a TOML file, falling back to the defaults if it does not exist.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            warn!(
+                "{} not found, using the default configuration",
+                path.display()
+            );
+            return Ok(Self::default());
+        }
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// An event forwarded from the connection threads to the server.
+#[derive(Event)]
+pub enum NetworkEvent {
+    /// A client connected and completed the handshake, supporting the given capabilities, and can
+    /// be sent messages through the given sender.
+    Connected(u64, mpsc::Sender<ServerMessage>, Capabilities),
+    /// A client sent a message.
+    Message(u64, ClientMessage),
+    /// A client disconnected.
+    Disconnected(u64),
+}
+
+/// A client connected to the server.
+pub struct Client {
+    /// The name the client joined with, if it has joined.
+    pub name: Option<String>,
+    /// The last reported position of the client's player.
+    pub position: Vec3,
+    /// The optional features both the client and the server support.
+    pub capabilities: Capabilities,
+    /// A sender for messages to the client.
+    sender: mpsc::Sender<ServerMessage>,
+}
+
+impl Client {
+    /// Send a message to the client. Messages to clients that have disconnected are dropped.
+    pub fn send(&self, message: ServerMessage) {
+        let _ = self.sender.send(message);
+    }
+
+    /// Send a chunk to the client, compressed if the client supports it.
+    pub fn send_chunk(&self, chunk: &Chunk) {
+        let compression = self.capabilities.chunk_compression();
+        if compression != Compression::None {
+            match ChunkCodec::new(compression, None).encode(chunk) {
+                Ok(payload) => return self.send(ServerMessage::CompressedChunk(payload)),
+                Err(err) => error!("Failed to compress chunk {:?}: {:?}", chunk.position, err),
+            }
+        }
+        self.send(ServerMessage::ChunkData(Box::new(chunk.clone())));
+    }
+}
+
+/// The clients connected to the server.
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct Clients(HashMap<u64, Client>);
+
+/// A plugin for running the server side of the multiplayer protocol.
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(NetworkMetricsPlugin)
+            .add_channel::<NetworkEvent>()
+            .init_resource::<ServerConfig>()
+            .init_resource::<Clients>()
+            .init_resource::<Subscriptions>()
+            .add_systems(Startup, start_listener)
+            .add_systems(
+                Update,
+                (
+                    handle_network_events,
+                    stream_chunks,
+                    broadcast_chunk_changes,
+                    unload_unwatched_chunks,
+                    ping_clients,
+                    record_round_trip_times,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Start listening for connections on a background thread.
+fn start_listener(
+    config: Res<ServerConfig>,
+    events: Res<ChannelSender<NetworkEvent>>,
+    counters: Res<NetworkCounters>,
+) {
+    let listener = match TcpListener::bind(("0.0.0.0", config.port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Failed to listen on port {}: {:?}", config.port, err);
+            return;
+        }
+    };
+    info!("Listening on port {}", config.port);
+
+    let events = events.clone();
+    let counters = counters.clone();
+    thread::spawn(move || {
+        for (client_id, stream) in (1..).zip(listener.incoming()) {
+            match stream {
+                Ok(stream) => {
+                    let events = events.clone();
+                    let counters = counters.clone();
+                    thread::spawn(move || serve_connection(client_id, stream, events, counters));
+                }
+                Err(err) => error!("Failed to accept connection: {:?}", err),
+            }
+        }
+    });
+}
+
+/// Forward messages between a client connection and the server, until the client disconnects.
+fn serve_connection(
+    client_id: u64,
+    stream: TcpStream,
+    events: mpsc::Sender<NetworkEvent>,
+    counters: NetworkCounters,
+) {
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(Counted::new(stream, counters.bytes_in));
+
+    // refuse clients built with a different protocol before reading anything else from them
+    let mut counted = Counted::new(&writer, counters.bytes_out.clone());
+    let capabilities = match handshake(&mut reader, &mut counted) {
+        Ok(capabilities) => capabilities,
+        Err(err) => {
+            warn!("Client {} failed the handshake: {:?}", client_id, err);
+            let _ = writer.shutdown(Shutdown::Both);
+            return;
+        }
+    };
+
+    // write outbound messages until the server drops the client
+    let (tx, rx) = mpsc::channel::<ServerMessage>();
+    let bytes_out = counters.bytes_out.clone();
+    thread::spawn(move || {
+        let mut counted = Counted::new(&writer, bytes_out);
+        for message in rx {
+            if write_message(&mut counted, &message).is_err() {
+                break;
+            }
+        }
+        let _ = writer.shutdown(Shutdown::Both);
+    });
+
+    let _ = events.send(NetworkEvent::Connected(client_id, tx, capabilities));
+    while let Ok(message) = read_message(&mut reader) {
+        let _ = events.send(NetworkEvent::Message(client_id, message));
+    }
+    let _ = events.send(NetworkEvent::Disconnected(client_id));
+}
+
+/// Handle connections, disconnections, and messages from clients.
+fn handle_network_events(
+    mut events: EventReader<NetworkEvent>,
+    mut clients: ResMut<Clients>,
+    mut subscriptions: ResMut<Subscriptions>,
+    (chunks, registry): (Res<Chunks>, Res<BlockRegistry>),
+    config: Res<ServerConfig>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    for event in events.read() {
+        match event {
+            NetworkEvent::Connected(client_id, sender, capabilities) => {
+                if clients.len() >= config.max_players {
+                    let _ = sender.send(ServerMessage::Disconnect {
+                        reason: "The server is full".into(),
+                    });
+                    continue;
+                }
+                let _ = sender.send(ServerMessage::Welcome {
+                    client_id: *client_id,
+                    view_distance: config.view_distance,
+                });
+                clients.insert(
+                    *client_id,
+                    Client {
+                        name: None,
+                        position: Vec3::ZERO,
+                        capabilities: capabilities.clone(),
+                        sender: sender.clone(),
+                    },
+                );
+                info!("Client {} connected", client_id);
+            }
+            NetworkEvent::Message(client_id, message) => {
+                let Some(client) = clients.get_mut(client_id) else {
+                    continue;
+                };
+                match message {
+                    ClientMessage::Join { name } => {
+                        info!("Client {} joined as {}", client_id, name);
+                        client.name = Some(name.clone());
+                    }
+                    ClientMessage::Position(position) => client.position = *position,
+                    ClientMessage::SetBlock {
+                        sequence,
+                        pos,
+                        block,
+                    } => {
+                        // clients may only edit chunks they can see, with blocks the server knows,
+                        // and the edit is made like a local one, so it can be undone and heard,
+                        // and reaches subscribers with the chunk's other changes
+                        let chunk_pos = ChunkPos::from_world_block(*pos);
+                        let accepted = subscriptions.is_subscribed(*client_id, chunk_pos)
+                            && chunks.is_loaded(chunk_pos)
+                            && registry.get(*block).is_some();
+                        if accepted {
+                            chunk_commands.send(ChunkCommand::ModifyBlock(
+                                chunk_pos,
+                                BlockPos::from_world_block(*pos),
+                                *block,
+                            ));
+                        }
+                        client.send(ServerMessage::EditResult {
+                            sequence: *sequence,
+                            accepted,
+                        });
+                    }
+                    // handled by record_round_trip_times
+                    ClientMessage::Pong { .. } => {}
+                }
+            }
+            NetworkEvent::Disconnected(client_id) => {
+                subscriptions.unsubscribe_all(*client_id);
+                if clients.remove(client_id).is_some() {
+                    info!("Client {} disconnected", client_id);
+                }
+            }
+        }
+    }
+}
+
+/// Subscribe each client to the chunks within its view distance, nearest first, sending them as
+/// they load. Chunks that leave the view are unsubscribed.
+fn stream_chunks(
+    clients: Res<Clients>,
+    mut subscriptions: ResMut<Subscriptions>,
+    mut chunks: ResMut<Chunks>,
+    config: Res<ServerConfig>,
+    counters: Res<NetworkCounters>,
+    mut queue: ResMut<LoadQueue>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    let radius = config.view_distance as i64;
+    queue.set_focus(
+        clients
+            .values()
+            .map(|client| ChunkPos::from_world(client.position)),
+    );
+    let view = RenderDistance {
+        horizontal: config.view_distance,
+        vertical: config.view_distance,
+    };
+    chunks.set_load_areas(
+        clients
+            .values()
+            .map(|client| (ChunkPos::from_world(client.position), view)),
+    );
+    let mut requested = HashSet::new();
+
+    for (&client_id, client) in clients.iter() {
+        let center = ChunkPos::from_world(client.position);
+        let missing = iproduct!(-radius..=radius, -radius..=radius, -radius..=radius)
+            .map(|offset| center + offset.into())
+            .filter(|&pos| chunks.in_bounds(pos) && !subscriptions.is_subscribed(client_id, pos))
+            .sorted_by_key(|pos| pos.distance(center))
+            .collect_vec();
+
+        let mut budget = CHUNKS_PER_TICK;
+        for pos in missing {
+            match chunks.get(pos) {
+                Some(chunk) if budget > 0 => {
+                    client.send_chunk(chunk);
+                    subscriptions.subscribe(client_id, pos);
+                    chunks.watch(pos);
+                    counters.chunks_sent.fetch_add(1, Ordering::Relaxed);
+                    budget -= 1;
+                }
+                None if chunks.is_unloaded(pos) && requested.insert(pos) => {
+                    chunk_commands.send(ChunkCommand::Load(pos));
+                }
+                _ => {}
+            }
+        }
+
+        // forget chunks that have left the view
+        let forgotten = subscriptions
+            .chunks_of(client_id)
+            .filter(|pos| pos.distance(center) > radius)
+            .collect_vec();
+        for pos in forgotten {
+            subscriptions.unsubscribe(client_id, pos);
+            client.send(ServerMessage::UnloadChunk(pos));
+        }
+    }
+}
+
+/// Send the blocks changed in each subscribed chunk since its subscribers last saw it, however they
+/// were changed, and send chunks regenerated while subscribed again whole. Subscriptions to chunks
+/// the server has unloaded are dropped, and their subscribers told to unload them too.
+fn broadcast_chunk_changes(
+    clients: Res<Clients>,
+    mut subscriptions: ResMut<Subscriptions>,
+    mut chunks: ResMut<Chunks>,
+    mut loaded: EventReader<ChunkLoaded>,
+    counters: Res<NetworkCounters>,
+) {
+    for &ChunkLoaded(pos) in loaded.read() {
+        let Some(chunk) = chunks.get(pos) else {
+            continue;
+        };
+        for client in subscriptions
+            .subscribers(pos)
+            .filter_map(|id| clients.get(&id))
+        {
+            client.send_chunk(chunk);
+            counters.chunks_sent.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    for pos in chunks.watched().collect_vec() {
+        if !chunks.is_loaded(pos) {
+            for client in subscriptions
+                .unsubscribe_chunk(pos)
+                .iter()
+                .filter_map(|id| clients.get(id))
+            {
+                client.send(ServerMessage::UnloadChunk(pos));
+            }
+            chunks.unwatch(pos);
+            continue;
+        }
+        let subscribers = subscriptions
+            .subscribers(pos)
+            .filter_map(|id| clients.get(&id))
+            .collect_vec();
+        if subscribers.is_empty() {
+            chunks.unwatch(pos);
+            continue;
+        }
+        let edits = chunks.take_edits(pos);
+        let Some(chunk) = chunks.get(pos) else {
+            continue;
+        };
+        let origin = pos.to_world().as_ivec3();
+        for block_pos in edits {
+            let world = origin + IVec3::from(block_pos);
+            let block = *chunk.block_at(block_pos);
+            for client in &subscribers {
+                client.send(ServerMessage::BlockChanged(world, block));
+            }
+            counters
+                .deltas_sent
+                .fetch_add(subscribers.len() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Unload chunks that are not within the view distance of any client.
+fn unload_unwatched_chunks(
+    clients: Res<Clients>,
+    chunks: Res<Chunks>,
+    config: Res<ServerConfig>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    let radius = config.view_distance as i64 + 1;
+    let centers = clients
+        .values()
+        .map(|client| ChunkPos::from_world(client.position))
+        .collect_vec();
+    chunk_commands.send_batch(
+        chunks
+            .iter()
+            .map(|chunk| chunk.position)
+            .filter(|&pos| !chunks.is_busy(pos))
+            .filter(|&pos| centers.iter().all(|&center| pos.distance(center) > radius))
+            .map(ChunkCommand::Unload),
+    );
+}
+
+/// Periodically ping every client to measure round-trip times.
+fn ping_clients(clients: Res<Clients>, time: Res<Time>, mut timer: Local<Option<Timer>>) {
+    let timer = timer.get_or_insert_with(|| Timer::new(PING_INTERVAL, TimerMode::Repeating));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let sent_at = time.elapsed_seconds_f64();
+    for client in clients.values() {
+        client.send(ServerMessage::Ping { sent_at });
+    }
+}
+
+/// Record the round-trip time to each client as its pings are answered.
+fn record_round_trip_times(
+    mut events: EventReader<NetworkEvent>,
+    mut round_trip_times: ResMut<RoundTripTimes>,
+    time: Res<Time>,
+) {
+    for event in events.read() {
+        match event {
+            NetworkEvent::Message(client_id, ClientMessage::Pong { sent_at }) => {
+                let rtt = (time.elapsed_seconds_f64() - sent_at) * 1000.0;
+                round_trip_times.insert(*client_id, rtt);
+            }
+            NetworkEvent::Disconnected(client_id) => {
+                round_trip_times.remove(client_id);
+            }
+            _ => {}
+        }
+    }
+}
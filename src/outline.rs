@@ -0,0 +1,52 @@
+use bevy::{color::palettes::css::WHITE, prelude::*};
+
+use crate::{breaking::REACH, chunk::Chunks};
+
+/// The colour of the outline around the targeted block.
+const OUTLINE_COLOR: Srgba = WHITE;
+
+/// How much larger the outline is than the block it surrounds, so it is not hidden by the block's
+/// faces.
+const OUTLINE_SIZE: f32 = 1.002;
+
+/// The width of the highlight drawn on the targeted face, as a fraction of the face.
+const FACE_HIGHLIGHT_SIZE: f32 = 0.8;
+
+/// The opacity of the highlight drawn on the targeted face.
+const FACE_HIGHLIGHT_OPACITY: f32 = 0.35;
+
+/// A plugin outlining the block the camera is looking at, within reach, with the face being looked
+/// at highlighted.
+pub struct BlockOutlinePlugin;
+
+impl Plugin for BlockOutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_block_outline);
+    }
+}
+
+/// Outline the block the camera is looking at, and draw a faint square on the face the ray hit.
+fn draw_block_outline(
+    mut gizmos: Gizmos,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    chunks: Res<Chunks>,
+) {
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+    let Some(hit) = chunks.raycast(camera.translation(), camera.forward(), REACH) else {
+        return;
+    };
+    let centre = hit.world_block().as_vec3() + Vec3::splat(0.5);
+    gizmos.cuboid(
+        Transform::from_translation(centre).with_scale(Vec3::splat(OUTLINE_SIZE)),
+        OUTLINE_COLOR,
+    );
+    let normal = Vec3::from(hit.face);
+    gizmos.rect(
+        centre + normal * OUTLINE_SIZE / 2.0,
+        Quat::from_rotation_arc(Vec3::Z, normal),
+        Vec2::splat(FACE_HIGHLIGHT_SIZE),
+        OUTLINE_COLOR.with_alpha(FACE_HIGHLIGHT_OPACITY),
+    );
+}
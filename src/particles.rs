@@ -0,0 +1,166 @@
+use bevy::{prelude::*, utils::HashMap};
+use chunky_core::chunk::generation::mix;
+
+use crate::{
+    chunk::{BlockBroken, BlockType, Chunks},
+    environment::Environment,
+};
+
+/// The number of particles a broken block bursts into.
+const PARTICLES_PER_BLOCK: u64 = 12;
+
+/// The width of a particle, in blocks.
+const PARTICLE_SIZE: f32 = 0.12;
+
+/// How long a particle lasts, in seconds.
+const PARTICLE_LIFETIME: f32 = 0.8;
+
+/// The fastest a particle flies out of its block, in blocks per second.
+const PARTICLE_SPEED: f32 = 3.0;
+
+/// A plugin bursting broken blocks into small particles in the block's colour, which fall and
+/// shrink away.
+pub struct BreakParticlesPlugin;
+
+impl Plugin for BreakParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParticleAssets>()
+            .init_resource::<Environment>()
+            .add_systems(
+                Update,
+                (
+                    spawn_break_particles.run_if(on_event::<BlockBroken>()),
+                    move_particles.run_if(any_with_component::<Particle>),
+                ),
+            );
+    }
+}
+
+/// A component for particles flying out of a broken block.
+#[derive(Component, Debug, Clone)]
+pub struct Particle {
+    /// The velocity of the particle, in blocks per second.
+    pub velocity: Vec3,
+    /// The time left before the particle disappears.
+    pub lifetime: Timer,
+}
+
+/// The mesh shared by every particle, and the material of each block's particles.
+#[derive(Resource)]
+struct ParticleAssets {
+    mesh: Handle<Mesh>,
+    materials: HashMap<BlockType, Handle<StandardMaterial>>,
+}
+
+impl FromWorld for ParticleAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cuboid::from_length(PARTICLE_SIZE));
+        Self {
+            mesh,
+            materials: HashMap::new(),
+        }
+    }
+}
+
+impl ParticleAssets {
+    /// Return the material of the given block's particles, creating it the first time the block
+    /// breaks. Particles of textured blocks show their texture, and others the block's colour.
+    fn material(
+        &mut self,
+        block: BlockType,
+        asset_server: &AssetServer,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Handle<StandardMaterial> {
+        self.materials
+            .entry(block)
+            .or_insert_with(|| {
+                let definition = block.definition();
+                let material = match &definition.texture {
+                    Some(texture) => StandardMaterial {
+                        base_color_texture: Some(asset_server.load(texture.clone())),
+                        ..default()
+                    },
+                    None => {
+                        let [r, g, b, _] = definition.color;
+                        StandardMaterial::from_color(Color::linear_rgb(r, g, b))
+                    }
+                };
+                materials.add(material)
+            })
+            .clone()
+    }
+}
+
+/// Return a well-distributed value for the given block and draw, so each particle of a burst flies
+/// its own way without a random number generator.
+fn scatter(pos: IVec3, draw: u64) -> u64 {
+    mix(draw, &[pos.x as u64, pos.y as u64, pos.z as u64])
+}
+
+/// Return the given bits of a value as a fraction in `[-1, 1]`.
+fn unit(value: u64, shift: u32) -> f32 {
+    (value >> shift & 0xFFFF) as f32 / 0xFFFF as f32 * 2.0 - 1.0
+}
+
+/// Burst each broken block into particles, scattered through the block and flying out and up from
+/// its centre.
+fn spawn_break_particles(
+    mut commands: Commands,
+    mut broken: EventReader<BlockBroken>,
+    mut assets: ResMut<ParticleAssets>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in broken.read() {
+        let material = assets.material(event.block, &asset_server, &mut materials);
+        let center = event.pos.as_vec3() + Vec3::splat(0.5);
+        for draw in 0..PARTICLES_PER_BLOCK {
+            let roll = scatter(event.pos, draw);
+            let offset = Vec3::new(unit(roll, 0), unit(roll, 16), unit(roll, 32)) * 0.4;
+            // fling particles upwards more than down, so the burst hangs in the air a moment
+            let velocity = (offset + Vec3::Y * 0.5) * PARTICLE_SPEED * (0.5 + unit(roll, 48).abs());
+            commands.spawn((
+                PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(center + offset),
+                    ..default()
+                },
+                Particle {
+                    velocity,
+                    lifetime: Timer::from_seconds(PARTICLE_LIFETIME, TimerMode::Once),
+                },
+            ));
+        }
+    }
+}
+
+/// Move particles under gravity, resting them on the blocks they land on, and shrink them away
+/// over their lifetime.
+fn move_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    environment: Res<Environment>,
+    chunks: Res<Chunks>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform)>,
+) {
+    for (entity, mut particle, mut transform) in particles.iter_mut() {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        particle.velocity.y -= environment.gravity * time.delta_seconds();
+        let next = transform.translation + particle.velocity * time.delta_seconds();
+        let blocked = chunks
+            .block_at_world(next - Vec3::Y * PARTICLE_SIZE / 2.0)
+            .is_some_and(|block| block.definition().solid);
+        if blocked {
+            particle.velocity = Vec3::ZERO;
+        } else {
+            transform.translation = next;
+        }
+        transform.scale = Vec3::splat(particle.lifetime.fraction_remaining());
+    }
+}
@@ -0,0 +1,182 @@
+use bevy::{
+    math::{bounding::Aabb3d, Vec3A},
+    prelude::*,
+};
+use itertools::iproduct;
+
+use crate::chunk::{BlockPos, ChunkPos, Chunks};
+
+/// The distance kept between a moving box and the surfaces it collides with, so that resting
+/// contact does not register as overlap on the next sweep.
+const SKIN: f32 = 1e-3;
+
+/// The maximum number of surfaces a single move can slide along.
+const MAX_SLIDES: usize = 4;
+
+/// The result of sweeping a box through the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepHit {
+    /// The fraction of the motion completed before the hit, in `[0, 1]`.
+    pub time: f32,
+    /// The normal of the surface that was hit.
+    pub normal: Vec3,
+    /// The world block coordinates of the block that was hit.
+    pub block: IVec3,
+}
+
+/// The result of moving a box through the world one axis at a time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisMove {
+    /// The motion that was actually applied.
+    pub applied: Vec3,
+    /// Whether the motion along each axis was cut short by a solid block.
+    pub blocked: BVec3,
+}
+
+/// Return an iterator over the world block coordinates of solid blocks overlapping the box between
+/// the two world block coordinates (inclusive). Blocks in chunks that are not loaded are treated as
+/// empty.
+pub fn solid_blocks_in(
+    chunks: &Chunks,
+    min: IVec3,
+    max: IVec3,
+) -> impl Iterator<Item = IVec3> + '_ {
+    iproduct!(min.x..=max.x, min.y..=max.y, min.z..=max.z)
+        .map(|(x, y, z)| IVec3::new(x, y, z))
+        .filter(|&pos| {
+            let Some(chunk) = chunks.get(ChunkPos::from_world_block(pos)) else {
+                return false;
+            };
+            // skip the block lookup for empty regions of the chunk
+            let block_pos = BlockPos::from_world_block(pos);
+            chunk.occupancy().may_contain(block_pos) && chunk.block_at(block_pos).is_solid()
+        })
+}
+
+/// Sweep a box through the world along the given motion, returning the first solid block it hits.
+/// Unlike sampling the box at its destination, this cannot tunnel through thin walls at any speed.
+pub fn sweep(chunks: &Chunks, aabb: Aabb3d, motion: Vec3) -> Option<SweepHit> {
+    if motion == Vec3::ZERO {
+        return None;
+    }
+
+    // every block the box could touch along the way
+    let motion_a = Vec3A::from(motion);
+    let min = aabb.min.min(aabb.min + motion_a).floor().as_ivec3();
+    let max = aabb.max.max(aabb.max + motion_a).floor().as_ivec3();
+
+    solid_blocks_in(chunks, min, max)
+        .filter_map(|block| {
+            let target = Aabb3d {
+                min: block.as_vec3a(),
+                max: block.as_vec3a() + Vec3A::ONE,
+            };
+            sweep_aabb(&aabb, motion, &target).map(|(time, normal)| SweepHit {
+                time,
+                normal,
+                block,
+            })
+        })
+        .min_by(|a, b| a.time.total_cmp(&b.time))
+}
+
+/// Move a box through the world, stopping at solid blocks and sliding along them with the
+/// remaining motion. Returns the motion that was actually applied.
+pub fn move_and_slide(chunks: &Chunks, mut aabb: Aabb3d, motion: Vec3) -> Vec3 {
+    let mut remaining = motion;
+    let mut applied = Vec3::ZERO;
+
+    for _ in 0..MAX_SLIDES {
+        let Some(hit) = sweep(chunks, aabb, remaining) else {
+            applied += remaining;
+            break;
+        };
+
+        // stop just short of the surface
+        let length = remaining.length();
+        let time = (hit.time - SKIN / length).max(0.0);
+        let step = remaining * time;
+        applied += step;
+        aabb.min += Vec3A::from(step);
+        aabb.max += Vec3A::from(step);
+
+        // slide along the surface with whatever is left
+        remaining *= 1.0 - time;
+        remaining -= hit.normal * remaining.dot(hit.normal);
+        if remaining.length_squared() < SKIN * SKIN {
+            break;
+        }
+    }
+
+    applied
+}
+
+/// Move a box through the world one axis at a time, vertical first, stopping each axis at the
+/// first solid block in its way. Unlike [`move_and_slide`], motion blocked along one axis is not
+/// redirected along the others, which keeps walking against walls and standing on the ground
+/// steady.
+pub fn move_per_axis(chunks: &Chunks, mut aabb: Aabb3d, motion: Vec3) -> AxisMove {
+    let mut applied = Vec3::ZERO;
+    let mut blocked = [false; 3];
+
+    for axis in [1, 0, 2] {
+        let mut step = Vec3::ZERO;
+        step[axis] = motion[axis];
+        if let Some(hit) = sweep(chunks, aabb, step) {
+            // stop just short of the surface
+            step *= (hit.time - SKIN / step.length()).max(0.0);
+            blocked[axis] = true;
+        }
+        applied += step;
+        aabb.min += Vec3A::from(step);
+        aabb.max += Vec3A::from(step);
+    }
+
+    AxisMove {
+        applied,
+        blocked: BVec3::from(blocked),
+    }
+}
+
+/// Sweep a moving box against a static box, returning the time of impact in `[0, 1]` and the
+/// normal of the face that was hit.
+fn sweep_aabb(moving: &Aabb3d, motion: Vec3, target: &Aabb3d) -> Option<(f32, Vec3)> {
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = Vec3::ZERO;
+
+    for axis in 0..3 {
+        let velocity = motion[axis];
+        if velocity == 0.0 {
+            // no motion along this axis, so the boxes must already overlap on it
+            if moving.max[axis] <= target.min[axis] || moving.min[axis] >= target.max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let (axis_entry, axis_exit) = match velocity > 0.0 {
+            true => (
+                (target.min[axis] - moving.max[axis]) / velocity,
+                (target.max[axis] - moving.min[axis]) / velocity,
+            ),
+            false => (
+                (target.max[axis] - moving.min[axis]) / velocity,
+                (target.min[axis] - moving.max[axis]) / velocity,
+            ),
+        };
+
+        if axis_entry > entry {
+            entry = axis_entry;
+            normal = Vec3::ZERO;
+            normal[axis] = -velocity.signum();
+        }
+        exit = exit.min(axis_exit);
+    }
+
+    // boxes that already overlap are left to separate on their own, except for resting contact
+    if entry > exit || !(-SKIN..=1.0).contains(&entry) {
+        return None;
+    }
+    Some((entry.max(0.0), normal))
+}
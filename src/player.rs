@@ -1,70 +1,326 @@
 use bevy::{
     input::mouse::{MouseButtonInput, MouseMotion},
+    math::{
+        bounding::{Aabb3d, IntersectsVolume},
+        Vec3A,
+    },
     prelude::*,
+    render::primitives::Frustum,
     window::CursorGrabMode,
 };
-use itertools::iproduct;
+use itertools::{iproduct, Itertools};
 
-use crate::chunk::{ChunkCommand, ChunkPos, Chunks};
+use crate::{
+    audio::Footsteps,
+    breaking::REACH,
+    chunk::{
+        density::SculptBrush, micro::MicroResolution, queue::LoadQueue, BlockPos, BlockType,
+        ChunkCommand, ChunkPos, Chunks,
+    },
+    environment::Environment,
+    net::client::ClientConfig,
+    physics,
+    projectile::Projectile,
+    shadow::BlobShadow,
+};
+
+/// The distance between the player's ears, in blocks.
+const EAR_GAP: f32 = 4.0;
+
+/// The furthest the camera can look up or down, in radians, short of straight up or down so it
+/// never rolls over backwards.
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// How high the player jumps in walk mode, in blocks.
+const JUMP_HEIGHT: f32 = 1.25;
+
+/// The tallest ledge the player steps up onto without jumping in walk mode, in blocks.
+const STEP_HEIGHT: f32 = 1.0;
+
+/// The key switching between flying and walking. F is taken by the digging projectile.
+const MOVEMENT_TOGGLE_KEY: KeyCode = KeyCode::KeyV;
+
+/// The blocks the number keys select for placing, from 1 to 9.
+const HOTBAR: [BlockType; 9] = [
+    BlockType::STONE,
+    BlockType::DIRT,
+    BlockType::GRASS,
+    BlockType::SAND,
+    BlockType::GRAVEL,
+    BlockType::WOOD,
+    BlockType::LEAVES,
+    BlockType::GLASS,
+    BlockType::LAMP,
+];
+
+/// The number keys, in the order of [`HOTBAR`].
+const HOTBAR_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
 
 /// A marker component for player entities.
-#[derive(Component, Default)]
-struct Player;
+#[derive(Component)]
+pub struct Player;
+
+/// How the player moves.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MovementMode {
+    /// Fly freely, rising with Space and sinking with Shift.
+    #[default]
+    Fly,
+    /// Walk on the ground under gravity, jumping with Space and stepping up onto ledges.
+    Walk,
+}
+
+/// The direction the player is looking, as angles applied to the player and its camera each frame
+/// rather than rotations composed onto them.
+#[derive(Component, Debug, Default, Clone, Copy)]
+struct Look {
+    /// The rotation of the player about the vertical axis, in radians.
+    yaw: f32,
+    /// The rotation of the camera above the horizon, in radians, within [`MAX_PITCH`].
+    pitch: f32,
+}
+
+/// How mouse movement turns the camera.
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct MouseSettings {
+    /// The angle the camera turns per pixel of mouse movement, in radians.
+    pub sensitivity: f32,
+    /// Whether moving the mouse up looks down.
+    pub invert_y: bool,
+}
 
-/// A player entity.
-#[derive(Bundle, Default)]
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.001,
+            invert_y: false,
+        }
+    }
+}
+
+/// The vertical motion of a walking player.
+#[derive(Component, Debug, Default, Clone, Copy)]
+struct Falling {
+    /// The upward velocity of the player, in blocks per second.
+    velocity: f32,
+    /// Whether the player was standing on a solid block after its last move.
+    grounded: bool,
+}
+
+/// The size of a player's body and the height of its eyes, in blocks.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct PlayerBody {
+    /// The width and depth of the player's collision box.
+    pub width: f32,
+    /// The height of the player's collision box.
+    pub height: f32,
+    /// The height of the camera above the player's feet.
+    pub eye_height: f32,
+}
+
+impl Default for PlayerBody {
+    fn default() -> Self {
+        Self {
+            width: 0.6,
+            height: 1.8,
+            eye_height: 1.6,
+        }
+    }
+}
+
+impl PlayerBody {
+    /// Return the player's collision box, for a player standing with its feet at the given
+    /// position.
+    pub fn collider(&self, feet: Vec3) -> Aabb3d {
+        let half_width = self.width / 2.0;
+        Aabb3d {
+            min: Vec3A::from(feet) - Vec3A::new(half_width, 0.0, half_width),
+            max: Vec3A::from(feet) + Vec3A::new(half_width, self.height, half_width),
+        }
+    }
+}
+
+/// A player entity, whose position is that of its feet.
+#[derive(Bundle)]
 struct PlayerBundle {
     player: Player,
+    body: PlayerBody,
+    shadow: BlobShadow,
+    footsteps: Footsteps,
+    movement: MovementMode,
+    falling: Falling,
+    look: Look,
     transform: Transform,
     global_transform: GlobalTransform,
 }
 
+impl PlayerBundle {
+    /// Create a player with the given body and movement mode, standing at the given position.
+    fn new(body: PlayerBody, movement: MovementMode, feet: Vec3) -> Self {
+        Self {
+            player: Player,
+            body,
+            shadow: BlobShadow {
+                radius: body.width / 2.0,
+            },
+            footsteps: default(),
+            movement,
+            falling: default(),
+            look: default(),
+            transform: Transform::from_translation(feet),
+            global_transform: default(),
+        }
+    }
+}
+
+/// The block the player places with the right mouse button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Deref)]
+pub struct SelectedBlock(pub BlockType);
+
+impl Default for SelectedBlock {
+    fn default() -> Self {
+        Self(HOTBAR[0])
+    }
+}
+
+/// How far around the player chunks are kept loaded, in chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct RenderDistance {
+    /// The distance loaded along the horizontal axes.
+    pub horizontal: u32,
+    /// The distance loaded above and below the player.
+    pub vertical: u32,
+}
+
+impl Default for RenderDistance {
+    fn default() -> Self {
+        Self {
+            horizontal: 4,
+            vertical: 2,
+        }
+    }
+}
+
+impl RenderDistance {
+    /// Check if a chunk is within the render distance of the given centre, widened by the given
+    /// number of chunks along every axis.
+    pub fn contains(&self, center: ChunkPos, pos: ChunkPos, slack: u32) -> bool {
+        let diff = pos - center;
+        let horizontal = (self.horizontal + slack) as i64;
+        diff.x.abs() <= horizontal
+            && diff.z.abs() <= horizontal
+            && diff.y.abs() <= (self.vertical + slack) as i64
+    }
+}
+
 /// A plugin for handling player input and processing.
-pub struct PlayerPlugin;
+#[derive(Default)]
+pub struct PlayerPlugin {
+    /// The body players are spawned with, which games embedding the plugin can size to suit.
+    pub body: PlayerBody,
+    /// How players move when they are spawned.
+    pub movement: MovementMode,
+}
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player).add_systems(
-            Update,
-            (
-                // movement
-                lock_cursor,
-                move_player,
-                rotate_camera,
-                // chunk
-                load_chunks_near_player,
-            ),
-        );
+        app.insert_resource(SpawnBody(self.body, self.movement))
+            .init_resource::<SelectedBlock>()
+            .init_resource::<MouseSettings>()
+            .init_resource::<RenderDistance>()
+            .init_resource::<Environment>()
+            .add_systems(Startup, spawn_player)
+            .add_systems(
+                Update,
+                (
+                    // movement
+                    lock_cursor,
+                    toggle_movement_mode,
+                    move_player.after(toggle_movement_mode),
+                    rotate_camera,
+                    // interaction
+                    throw_projectile,
+                    select_block,
+                    // clicks that lock the cursor should not place anything
+                    place_block.before(lock_cursor),
+                    // chunk
+                    adjust_render_distance,
+                    // a server streams chunks to its clients instead
+                    load_chunks_near_player
+                        .after(adjust_render_distance)
+                        .run_if(not(resource_exists::<ClientConfig>)),
+                ),
+            );
     }
 }
 
-/// Spawn the player entity.
-fn spawn_player(mut commands: Commands) {
+/// The body and movement mode the player is spawned with.
+#[derive(Resource)]
+struct SpawnBody(PlayerBody, MovementMode);
+
+/// Spawn the player entity, with the camera at its eyes.
+fn spawn_player(mut commands: Commands, spawn: Res<SpawnBody>) {
+    let SpawnBody(body, movement) = *spawn;
+    let eyes = Transform::from_xyz(0.0, body.eye_height, 0.0);
     commands
-        .spawn(PlayerBundle {
-            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 10.0)),
-            ..Default::default()
-        })
+        .spawn(PlayerBundle::new(body, movement, Vec3::new(0.0, 0.0, 10.0)))
         .with_children(|parent| {
-            parent.spawn(Camera3dBundle::default());
+            parent.spawn((
+                Camera3dBundle {
+                    transform: eyes,
+                    ..default()
+                },
+                SpatialListener::new(EAR_GAP),
+            ));
             parent.spawn(PointLightBundle {
                 point_light: PointLight {
                     intensity: 100_000_000.0,
                     range: 1024.0,
                     ..default()
                 },
+                transform: eyes,
                 ..default()
             });
         });
 }
 
+/// Switch the player between flying and walking.
+fn toggle_movement_mode(
+    input: Res<ButtonInput<KeyCode>>,
+    mut query: Query<(&mut MovementMode, &mut Falling), With<Player>>,
+) {
+    if !input.just_pressed(MOVEMENT_TOGGLE_KEY) {
+        return;
+    }
+    let (mut movement, mut falling) = query.single_mut();
+    *movement = match *movement {
+        MovementMode::Fly => MovementMode::Walk,
+        MovementMode::Walk => MovementMode::Fly,
+    };
+    *falling = Falling::default();
+    info!("Movement mode: {:?}", *movement);
+}
+
+/// Move the player with the keyboard, stopping against solid blocks. Flying players rise and sink
+/// with Space and Shift, while walking players fall under gravity and jump with Space.
 fn move_player(
-    _: Commands,
     input: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
-    mut query: Query<&mut Transform, With<Player>>,
+    chunks: Res<Chunks>,
+    environment: Res<Environment>,
+    mut query: Query<(&mut Transform, &PlayerBody, &MovementMode, &mut Falling), With<Player>>,
 ) {
-    let mut transform = query.single_mut();
+    let (mut transform, body, movement, mut falling) = query.single_mut();
     let forward = transform.forward();
     let right = transform.right();
 
@@ -89,7 +345,21 @@ fn move_player(
         false => 5.0,
     };
 
-    transform.translation += direction.normalize_or_zero() * time.delta_seconds() * speed_factor;
+    let mut motion = direction.normalize_or_zero() * time.delta_seconds() * speed_factor;
+    let collider = body.collider(transform.translation);
+
+    if *movement == MovementMode::Walk {
+        transform.translation += walk(
+            &chunks,
+            collider,
+            motion,
+            input.pressed(KeyCode::Space),
+            environment.gravity,
+            time.delta_seconds(),
+            &mut falling,
+        );
+        return;
+    }
 
     // vertical movement
     let mut direction = 0.0;
@@ -101,32 +371,99 @@ fn move_player(
         direction -= 1.0;
     }
 
-    transform.translation.y += direction as f32 * time.delta_seconds() * 5.0;
+    motion.y += direction * time.delta_seconds() * 5.0;
+
+    transform.translation += physics::move_per_axis(&chunks, collider, motion).applied;
 }
 
+/// Move a walking player's collider along the given lateral motion for a frame of the given
+/// length, falling under gravity and jumping if asked to while on the ground. Blocked by a ledge
+/// while on the ground, the player steps up onto it if it is no taller than [`STEP_HEIGHT`].
+/// Returns the motion applied.
+fn walk(
+    chunks: &Chunks,
+    collider: Aabb3d,
+    lateral: Vec3,
+    jump: bool,
+    gravity: f32,
+    delta: f32,
+    falling: &mut Falling,
+) -> Vec3 {
+    falling.velocity -= gravity * delta;
+    if jump && falling.grounded {
+        falling.velocity = (2.0 * gravity * JUMP_HEIGHT).sqrt();
+    }
+
+    let motion = lateral + Vec3::Y * falling.velocity * delta;
+    let moved = physics::move_per_axis(chunks, collider, motion);
+    let mut applied = moved.applied;
+    let mut grounded = moved.blocked.y && falling.velocity < 0.0;
+
+    // step up onto a ledge, if the player was stopped by one and climbing it gets them further
+    if falling.grounded && (moved.blocked.x || moved.blocked.z) {
+        let shifted = |aabb: Aabb3d, by: Vec3| Aabb3d {
+            min: aabb.min + Vec3A::from(by),
+            max: aabb.max + Vec3A::from(by),
+        };
+        let up = physics::move_per_axis(chunks, collider, Vec3::Y * STEP_HEIGHT).applied;
+        let across = physics::move_per_axis(chunks, shifted(collider, up), lateral).applied;
+        let down = physics::move_per_axis(
+            chunks,
+            shifted(collider, up + across),
+            Vec3::NEG_Y * STEP_HEIGHT,
+        );
+        if across.xz().length_squared() > applied.xz().length_squared() {
+            applied = up + across + down.applied;
+            grounded = down.blocked.y;
+        }
+    }
+
+    if moved.blocked.y {
+        falling.velocity = 0.0;
+    }
+    falling.grounded = grounded;
+    applied
+}
+
+/// Turn the player and tilt its camera with the mouse, keeping the camera from looking past
+/// straight up or down.
 fn rotate_camera(
-    _: Commands,
     mut mouse_events: EventReader<MouseMotion>,
-    mut player_query: Query<&mut Transform, With<Player>>,
+    settings: Res<MouseSettings>,
+    mut player_query: Query<(&mut Transform, &mut Look), With<Player>>,
     mut camera_query: Query<&mut Transform, (With<Camera3d>, Without<Player>)>,
 ) {
-    let mut player_transform = player_query.single_mut();
+    let (mut player_transform, mut look) = player_query.single_mut();
     let mut camera_transform = camera_query.single_mut();
 
+    let invert = match settings.invert_y {
+        true => -1.0,
+        false => 1.0,
+    };
     for event in mouse_events.read() {
-        player_transform.rotate(Quat::from_rotation_y(-event.delta.x * 0.001));
-        camera_transform.rotate(Quat::from_rotation_x(-event.delta.y * 0.001));
+        look.yaw -= event.delta.x * settings.sensitivity;
+        look.pitch -= event.delta.y * settings.sensitivity * invert;
     }
+    look.yaw = look.yaw.rem_euclid(std::f32::consts::TAU);
+    look.pitch = look.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+
+    player_transform.rotation = Quat::from_rotation_y(look.yaw);
+    camera_transform.rotation = Quat::from_rotation_x(look.pitch);
 }
 
 fn lock_cursor(
     mut windows: Query<&mut Window>,
     mouse_events: EventReader<MouseButtonInput>,
     input: Res<ButtonInput<KeyCode>>,
+    interactions: Query<&Interaction>,
 ) {
     let mut window = windows.single_mut();
+    // leave the cursor free for clicks on the UI, and for selecting chunks while holding Alt
+    let on_ui = interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
     // lock cursor when mouse button is pressed (focus gained)
-    if !mouse_events.is_empty() {
+    if !mouse_events.is_empty() && !on_ui && !input.pressed(KeyCode::AltLeft) {
         window.cursor.grab_mode = CursorGrabMode::Locked;
         window.cursor.visible = false;
     }
@@ -137,32 +474,160 @@ fn lock_cursor(
     }
 }
 
+/// Throw a projectile in the direction the camera is facing, which breaks the block it hits when
+/// thrown with Q, carves a microblock out of it when thrown with E, and raises or digs the terrain
+/// around it when thrown with R or F.
+fn throw_projectile(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    camera_query: Query<&GlobalTransform, With<Camera3d>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(&key) = input.get_just_pressed().find(|key| {
+        matches!(
+            key,
+            KeyCode::KeyQ | KeyCode::KeyE | KeyCode::KeyR | KeyCode::KeyF
+        )
+    }) else {
+        return;
+    };
+    let camera_transform = camera_query.single();
+    let projectile = Projectile::new(camera_transform.forward() * 30.0);
+    let brush = |strength| SculptBrush {
+        radius: 3.0,
+        strength,
+    };
+    let projectile = match key {
+        KeyCode::KeyE => projectile.carving(MicroResolution::Four),
+        KeyCode::KeyR => projectile.sculpting(brush(0.5)),
+        KeyCode::KeyF => projectile.sculpting(brush(-0.5)),
+        _ => projectile.breaking_blocks(),
+    };
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::from_length(0.25)),
+            material: materials.add(StandardMaterial::from_color(Color::WHITE)),
+            transform: Transform::from_translation(camera_transform.translation()),
+            ..default()
+        },
+        projectile,
+        BlobShadow { radius: 0.125 },
+    ));
+}
+
+/// Select the block to place with the number keys.
+fn select_block(input: Res<ButtonInput<KeyCode>>, mut selected: ResMut<SelectedBlock>) {
+    let Some(block) = HOTBAR_KEYS
+        .iter()
+        .position(|key| input.just_pressed(*key))
+        .map(|slot| HOTBAR[slot])
+    else {
+        return;
+    };
+    selected.0 = block;
+    info!("Selected {:?}", block);
+}
+
+/// Place the selected block against the face of the block the camera is looking at when the right
+/// mouse button is clicked, if the space in front of the face can be built in and the block would
+/// not fill the player's own body.
+fn place_block(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    players: Query<(&Transform, &PlayerBody), With<Player>>,
+    chunks: Res<Chunks>,
+    selected: Res<SelectedBlock>,
+    mut chunk_commands: EventWriter<ChunkCommand>,
+) {
+    let locked = windows
+        .get_single()
+        .is_ok_and(|window| window.cursor.grab_mode == CursorGrabMode::Locked);
+    if !locked || !mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+    let (Ok(camera), Ok((player, body))) = (cameras.get_single(), players.get_single()) else {
+        return;
+    };
+    let Some(hit) = chunks.raycast(camera.translation(), camera.forward(), REACH) else {
+        return;
+    };
+    let target = hit.adjacent_block();
+    let replaceable = chunks
+        .block_at_world_block(target)
+        .is_some_and(|block| block.has_tag("#replaceable"));
+    let block_box = Aabb3d {
+        min: target.as_vec3a(),
+        max: target.as_vec3a() + Vec3A::ONE,
+    };
+    let in_body = selected.is_solid() && body.collider(player.translation).intersects(&block_box);
+    if !replaceable || in_body {
+        return;
+    }
+    chunk_commands.send(ChunkCommand::ModifyBlock(
+        ChunkPos::from_world_block(target),
+        BlockPos::from_world_block(target),
+        selected.0,
+    ));
+}
+
+/// Widen or narrow the render distance with the plus and minus keys.
+fn adjust_render_distance(
+    input: Res<ButtonInput<KeyCode>>,
+    mut render_distance: ResMut<RenderDistance>,
+) {
+    let horizontal = render_distance.horizontal;
+    if input.just_pressed(KeyCode::Equal) {
+        render_distance.horizontal = horizontal.saturating_add(1);
+    }
+    if input.just_pressed(KeyCode::Minus) {
+        render_distance.horizontal = horizontal.saturating_sub(1);
+    }
+    if render_distance.horizontal != horizontal {
+        info!("Render distance: {}", render_distance.horizontal);
+    }
+}
+
+/// Load the chunks within the render distance of the player, those in view of the camera first,
+/// and unload those that have fallen outside it.
 fn load_chunks_near_player(
     query: Query<&Transform, With<Player>>,
-    chunks: Res<Chunks>,
+    cameras: Query<&Frustum, With<Camera3d>>,
+    mut chunks: ResMut<Chunks>,
+    render_distance: Res<RenderDistance>,
+    mut queue: ResMut<LoadQueue>,
     mut events: EventWriter<ChunkCommand>,
 ) {
     let player_chunk = ChunkPos::from_world(query.single().translation);
+    queue.set_focus([player_chunk]);
+    chunks.set_load_areas([(player_chunk, *render_distance)]);
+    queue.set_view(cameras.get_single().ok().copied());
 
-    // unload chunks in 10x10 radius
+    // load missing chunks within the render distance, nearest first
+    let (horizontal, vertical) = (
+        render_distance.horizontal as i64,
+        render_distance.vertical as i64,
+    );
     events.send_batch(
-        iproduct!(0..2, 0..2, 0..2)
-            .filter_map(|diff| {
-                let pos = player_chunk + diff.into();
-                match chunks.is_unloaded(pos) {
-                    true => Some(pos),
-                    false => None,
-                }
-            })
-            .map(|pos| ChunkCommand::Load(pos)),
+        iproduct!(
+            -horizontal..=horizontal,
+            -vertical..=vertical,
+            -horizontal..=horizontal
+        )
+        .map(|diff| player_chunk + diff.into())
+        .filter(|&pos| chunks.in_bounds(pos) && chunks.is_unloaded(pos))
+        .sorted_by_key(|pos| pos.distance_squared(player_chunk))
+        .map(ChunkCommand::Load),
     );
 
-    // unload far chunks
-    events.send_batch(chunks.iter().filter_map(|chunk| {
-        let distance = (chunk.position - player_chunk).max().abs();
-        match distance {
-            0..=10 => None,
-            _ => Some(ChunkCommand::Unload(chunk.position)),
-        }
-    }));
+    // unload chunks beyond the render distance, with a chunk of slack so chunks on the edge are
+    // not unloaded and loaded again as the player moves back and forth across it
+    events.send_batch(
+        chunks
+            .iter()
+            .map(|chunk| chunk.position)
+            .filter(|&pos| !render_distance.contains(player_chunk, pos, 1))
+            .map(ChunkCommand::Unload),
+    );
 }
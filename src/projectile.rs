@@ -0,0 +1,147 @@
+use bevy::{
+    math::{bounding::Aabb3d, Vec3A},
+    prelude::*,
+};
+
+use crate::{
+    chunk::{density::SculptBrush, micro::MicroResolution, BlockBroken, BlockType, Chunks},
+    environment::Environment,
+    physics,
+};
+
+/// The half-size of a projectile's collision box.
+const PROJECTILE_HALF_SIZE: f32 = 0.125;
+
+/// A component for entities that fly through the world until they hit a block.
+#[derive(Component)]
+pub struct Projectile {
+    /// The velocity of the projectile, in blocks per second.
+    pub velocity: Vec3,
+    /// The share of the dimension's [`Environment::gravity`] applied to the projectile.
+    pub gravity: f32,
+    /// Whether the projectile breaks the block it hits.
+    pub breaks_blocks: bool,
+    /// The resolution to carve the block it hits at, if the projectile carves blocks instead of
+    /// breaking them whole.
+    pub carves: Option<MicroResolution>,
+    /// The brush to sculpt the terrain around the impact with, if the projectile sculpts.
+    pub sculpts: Option<SculptBrush>,
+    /// The time left before the projectile despawns without hitting anything.
+    pub lifetime: Timer,
+}
+
+impl Projectile {
+    /// Create a projectile with the given velocity, affected by gravity.
+    pub fn new(velocity: Vec3) -> Self {
+        Self {
+            velocity,
+            gravity: 1.0,
+            breaks_blocks: false,
+            carves: None,
+            sculpts: None,
+            lifetime: Timer::from_seconds(10.0, TimerMode::Once),
+        }
+    }
+
+    /// Make the projectile break the block it hits.
+    pub fn breaking_blocks(mut self) -> Self {
+        self.breaks_blocks = true;
+        self
+    }
+
+    /// Make the projectile sculpt the terrain where it hits with the given brush.
+    pub fn sculpting(mut self, brush: SculptBrush) -> Self {
+        self.sculpts = Some(brush);
+        self
+    }
+
+    /// Make the projectile carve a single sub-voxel out of the block it hits.
+    pub fn carving(mut self, resolution: MicroResolution) -> Self {
+        self.carves = Some(resolution);
+        self
+    }
+}
+
+/// An event sent when a projectile hits a block.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ProjectileImpact {
+    /// The projectile that hit the block. It is despawned in the same frame.
+    pub projectile: Entity,
+    /// The position of the projectile at the moment of impact.
+    pub position: Vec3,
+    /// The world block coordinates of the block that was hit.
+    pub block: IVec3,
+    /// The type of the block that was hit.
+    pub block_type: BlockType,
+    /// The normal of the face that was hit.
+    pub normal: Vec3,
+}
+
+/// A plugin for moving projectiles and resolving their impacts with the world.
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ProjectileImpact>()
+            .init_resource::<Environment>()
+            .add_systems(Update, move_projectiles);
+    }
+}
+
+/// Move projectiles along their velocity, sweeping them against the world so fast projectiles
+/// cannot pass through thin walls.
+fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    environment: Res<Environment>,
+    mut chunks: ResMut<Chunks>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+    mut impacts: EventWriter<ProjectileImpact>,
+    mut broken: EventWriter<BlockBroken>,
+) {
+    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
+        if projectile.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let gravity = projectile.gravity * environment.gravity;
+        projectile.velocity.y -= gravity * time.delta_seconds();
+        let motion = projectile.velocity * time.delta_seconds();
+
+        let aabb = Aabb3d::new(
+            Vec3A::from(transform.translation),
+            Vec3::splat(PROJECTILE_HALF_SIZE),
+        );
+        let Some(hit) = physics::sweep(&chunks, aabb, motion) else {
+            transform.translation += motion;
+            continue;
+        };
+
+        let position = transform.translation + motion * hit.time;
+        let block_type = chunks.block_at_world_block(hit.block).unwrap_or_default();
+        if let Some(brush) = projectile.sculpts {
+            chunks.sculpt(position, brush);
+        } else if let Some(resolution) = projectile.carves {
+            // carve just inside the face that was hit
+            let point = position - hit.normal * (PROJECTILE_HALF_SIZE + 0.01);
+            chunks.carve_at_world_block(hit.block, point, resolution);
+        } else if projectile.breaks_blocks {
+            let previous = chunks.set_block_at_world_block(hit.block, BlockType::EMPTY);
+            if let Some(block) = previous.filter(|&block| block != BlockType::EMPTY) {
+                broken.send(BlockBroken {
+                    pos: hit.block,
+                    block,
+                });
+            }
+        }
+        impacts.send(ProjectileImpact {
+            projectile: entity,
+            position,
+            block: hit.block,
+            block_type,
+            normal: hit.normal,
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use crate::chunk::Chunks;
+
+/// How far below an entity its shadow is still drawn, in blocks.
+const MAX_SHADOW_DISTANCE: f32 = 16.0;
+
+/// The opacity of a shadow cast by an entity resting on the ground.
+const SHADOW_OPACITY: f32 = 0.6;
+
+/// How far above the ground shadows are drawn, so they do not flicker against the block faces.
+const SHADOW_LIFT: f32 = 0.01;
+
+/// The width and height of the shadow texture, in pixels.
+const SHADOW_TEXTURE_SIZE: u32 = 32;
+
+/// A component for entities that cast a round shadow onto the blocks beneath them.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BlobShadow {
+    /// The radius of the shadow when the entity is on the ground, in blocks.
+    pub radius: f32,
+}
+
+/// A component for the entities drawing the shadow of another entity.
+#[derive(Component)]
+struct ShadowOf(Entity);
+
+/// The mesh and texture shared by every shadow.
+#[derive(Resource)]
+struct ShadowAssets {
+    mesh: Handle<Mesh>,
+    texture: Handle<Image>,
+}
+
+impl FromWorld for ShadowAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Plane3d::default().mesh().size(1.0, 1.0));
+        let texture = world.resource_mut::<Assets<Image>>().add(shadow_texture());
+        Self { mesh, texture }
+    }
+}
+
+/// Build a white texture that fades from opaque in the centre to clear at the edge of its circle.
+fn shadow_texture() -> Image {
+    let half = SHADOW_TEXTURE_SIZE as f32 / 2.0;
+    let data = (0..SHADOW_TEXTURE_SIZE * SHADOW_TEXTURE_SIZE)
+        .flat_map(|i| {
+            let x = (i % SHADOW_TEXTURE_SIZE) as f32 + 0.5 - half;
+            let y = (i / SHADOW_TEXTURE_SIZE) as f32 + 0.5 - half;
+            let falloff = (1.0 - Vec2::new(x, y).length() / half).clamp(0.0, 1.0);
+            [255, 255, 255, (falloff.sqrt() * 255.0) as u8]
+        })
+        .collect();
+    Image::new(
+        Extent3d {
+            width: SHADOW_TEXTURE_SIZE,
+            height: SHADOW_TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// A plugin for drawing blob shadows under [`BlobShadow`] entities: a fading quad on the top of the
+/// block straight beneath each, a cheap stand-in for shadow mapping, which the world's lights leave
+/// disabled.
+pub struct BlobShadowPlugin;
+
+impl Plugin for BlobShadowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadowAssets>().add_systems(
+            PostUpdate,
+            (spawn_shadows, place_shadows, despawn_orphaned_shadows)
+                .chain()
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Spawn a shadow for each new [`BlobShadow`] entity.
+fn spawn_shadows(
+    mut commands: Commands,
+    casters: Query<Entity, Added<BlobShadow>>,
+    assets: Res<ShadowAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for caster in &casters {
+        // each shadow has its own material, so it can fade on its own
+        let material = materials.add(StandardMaterial {
+            base_color: Color::BLACK.with_alpha(SHADOW_OPACITY),
+            base_color_texture: Some(assets.texture.clone()),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        });
+        commands.spawn((
+            PbrBundle {
+                mesh: assets.mesh.clone(),
+                material,
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ShadowOf(caster),
+        ));
+    }
+}
+
+/// Move each shadow onto the block beneath its caster, fading and shrinking it with the height of
+/// the caster above the block, and hiding it if there is no block close enough.
+fn place_shadows(
+    chunks: Res<Chunks>,
+    casters: Query<(&GlobalTransform, &BlobShadow)>,
+    mut shadows: Query<(
+        &ShadowOf,
+        &mut Transform,
+        &mut Visibility,
+        &Handle<StandardMaterial>,
+    )>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (&ShadowOf(caster), mut transform, mut visibility, material) in &mut shadows {
+        let Ok((caster, shadow)) = casters.get(caster) else {
+            continue;
+        };
+        let origin = caster.translation();
+        let Some(hit) = chunks.raycast(origin, Dir3::NEG_Y, MAX_SHADOW_DISTANCE) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Inherited;
+        let ground = (hit.world_block().y + 1) as f32 + SHADOW_LIFT;
+        let fade = 1.0 - hit.distance / MAX_SHADOW_DISTANCE;
+        *transform = Transform::from_xyz(origin.x, ground, origin.z)
+            .with_scale(Vec3::splat(shadow.radius * 2.0 * (0.5 + fade / 2.0)));
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color.set_alpha(SHADOW_OPACITY * fade);
+        }
+    }
+}
+
+/// Despawn the shadows of entities that have been despawned or no longer cast shadows.
+fn despawn_orphaned_shadows(
+    mut commands: Commands,
+    shadows: Query<(Entity, &ShadowOf)>,
+    casters: Query<(), With<BlobShadow>>,
+) {
+    for (shadow, &ShadowOf(caster)) in &shadows {
+        if !casters.contains(caster) {
+            commands.entity(shadow).despawn();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+use super::AutosaveState;
+
+/// The chunks waiting to be written by the current autosave.
+pub const CHUNKS_PENDING: DiagnosticPath = DiagnosticPath::const_new("storage/pending");
+
+/// How long the current autosave has been running, in seconds, or zero if none is.
+pub const SAVE_LAG: DiagnosticPath = DiagnosticPath::const_new("storage/save_lag");
+
+/// The mean time taken to write a chunk to the store, in milliseconds.
+pub const WRITE_TIME: DiagnosticPath = DiagnosticPath::const_new("storage/write_time");
+
+/// Running totals of chunk writes, shared with the tasks writing them.
+#[derive(Debug, Default, Clone)]
+pub struct SaveCounters {
+    /// The number of writes started but not yet finished.
+    pub in_flight: Arc<AtomicU64>,
+    /// The total number of writes finished.
+    pub written: Arc<AtomicU64>,
+    /// The total time spent writing, in microseconds.
+    pub write_micros: Arc<AtomicU64>,
+}
+
+impl SaveCounters {
+    /// Record a write starting.
+    pub(super) fn start(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a write finishing after the given time.
+    pub(super) fn finish(&self, elapsed: Duration) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.written.fetch_add(1, Ordering::Relaxed);
+        self.write_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// A plugin for measuring how far autosaves lag behind through Bevy diagnostics.
+pub struct StorageMetricsPlugin;
+
+impl Plugin for StorageMetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutosaveState>()
+            .register_diagnostic(Diagnostic::new(CHUNKS_PENDING))
+            .register_diagnostic(Diagnostic::new(SAVE_LAG).with_suffix(" s"))
+            .register_diagnostic(Diagnostic::new(WRITE_TIME).with_suffix(" ms"))
+            .add_systems(Last, measure_saves);
+    }
+}
+
+/// Measure the autosave backlog, and the mean time of the writes finished since the last frame.
+fn measure_saves(
+    mut diagnostics: Diagnostics,
+    state: Res<AutosaveState>,
+    time: Res<Time>,
+    mut last: Local<(u64, u64)>,
+) {
+    diagnostics.add_measurement(&CHUNKS_PENDING, || state.pending() as f64);
+    diagnostics.add_measurement(&SAVE_LAG, || state.lag(time.elapsed()).as_secs_f64());
+
+    let counters = state.counters();
+    let written = counters.written.load(Ordering::Relaxed);
+    let micros = counters.write_micros.load(Ordering::Relaxed);
+    let (last_written, last_micros) = std::mem::replace(&mut *last, (written, micros));
+    if written > last_written {
+        diagnostics.add_measurement(&WRITE_TIME, || {
+            (micros - last_micros) as f64 / (written - last_written) as f64 / 1000.0
+        });
+    }
+}
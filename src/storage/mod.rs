@@ -0,0 +1,375 @@
+pub mod metrics;
+
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, IoTaskPool, Task},
+};
+
+#[cfg(feature = "sqlite")]
+pub use chunky_core::storage::sqlite;
+pub use chunky_core::storage::{
+    codec, integrity, memory, metadata, region, ChunkStore, StorageBackend,
+};
+
+use codec::Compression;
+use integrity::IntegrityCheck;
+use metadata::WorldMetadata;
+use metrics::SaveCounters;
+
+use crate::chunk::{scheduled::WorldClock, ChunkPos, Chunks};
+
+/// Where the world is saved, if it is saved at all.
+#[derive(Default, Clone, Resource)]
+pub struct WorldStorage {
+    /// The store chunks are loaded from before falling back to generation.
+    pub store: Option<Arc<dyn ChunkStore>>,
+    /// The directory the world's metadata is kept in.
+    pub path: Option<PathBuf>,
+}
+
+impl WorldStorage {
+    /// Open the world in the given directory with a store of the given kind. Worlds kept in memory
+    /// have no directory.
+    pub fn open(
+        backend: StorageBackend,
+        path: impl Into<PathBuf>,
+        compression: Compression,
+    ) -> anyhow::Result<Self> {
+        let path = path.into();
+        Ok(Self {
+            store: Some(backend.open(&path, compression)?),
+            path: (backend != StorageBackend::Memory).then_some(path),
+        })
+    }
+
+    /// Record the world's clock in its metadata, so scheduled block changes stay in step with it
+    /// across restarts.
+    pub fn save_tick(&self, tick: u64) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut metadata = WorldMetadata::load(path)?;
+        metadata.tick = tick;
+        metadata.save(path)
+    }
+}
+
+/// The environment variable asking for the world to be checked for damage as it is opened, either
+/// `check` to report problems or `repair` to also fix them.
+pub const CHECK_ENV: &str = "CHUNKY_CHECK_WORLD";
+
+/// How the world is checked for damage at startup, if at all.
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct IntegritySettings(pub Option<IntegrityCheck>);
+
+impl IntegritySettings {
+    /// Check the world if [`CHECK_ENV`] asks for it.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let repair = match std::env::var(CHECK_ENV).as_deref() {
+            Ok("check") => false,
+            Ok("repair") => true,
+            Ok(value) => anyhow::bail!(
+                "invalid {} {:?}, expected check or repair",
+                CHECK_ENV,
+                value
+            ),
+            Err(_) => return Ok(Self(None)),
+        };
+        Ok(Self(Some(IntegrityCheck {
+            repair,
+            ..default()
+        })))
+    }
+}
+
+/// Check the world's store for damage before anything is loaded from it, logging every problem
+/// found.
+pub fn check_integrity(storage: Res<WorldStorage>, settings: Res<IntegritySettings>) {
+    let (Some(store), Some(check)) = (&storage.store, settings.0) else {
+        return;
+    };
+    info!("Checking world integrity");
+    let report = match integrity::check(store.as_ref(), check) {
+        Ok(report) => report,
+        Err(err) => {
+            error!("Failed to check world integrity: {:?}", err);
+            return;
+        }
+    };
+    for problem in &report.layout {
+        warn!("Damaged world: {}", problem);
+    }
+    for pos in &report.corrupt {
+        warn!("Damaged world: chunk {:?} is corrupt", pos);
+    }
+    for (pos, block) in &report.orphans {
+        warn!(
+            "Damaged world: block data at {:?} in chunk {:?} has no block",
+            block, pos
+        );
+    }
+    match report.is_clean() {
+        true => info!("World integrity: {}", report),
+        false => warn!("World integrity: {}", report),
+    }
+}
+
+/// Settings for periodically saving chunks in the background.
+#[derive(Debug, Clone, Resource)]
+pub struct AutosaveSettings {
+    /// The time between autosaves of the chunks modified since they were last saved.
+    pub interval: Duration,
+    /// The time between full snapshots, which write every loaded chunk whether or not it was
+    /// modified, so a write that failed in the background is not lost for good.
+    pub snapshot_interval: Duration,
+    /// The maximum number of chunks written per frame, so saving does not cause frame spikes.
+    pub chunks_per_frame: usize,
+    /// The most time spent preparing writes per frame, past which the rest wait for the next.
+    pub frame_budget: Duration,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            snapshot_interval: Duration::from_secs(30 * 60),
+            chunks_per_frame: 8,
+            frame_budget: Duration::from_millis(2),
+        }
+    }
+}
+
+/// The progress of the current autosave.
+#[derive(Default, Resource)]
+pub struct AutosaveState {
+    /// The time until the next autosave starts.
+    timer: Option<Timer>,
+    /// The time until the next full snapshot starts.
+    snapshot_timer: Option<Timer>,
+    /// The chunks still to be written in the current autosave.
+    pending: VecDeque<ChunkPos>,
+    /// Whether the current autosave is a full snapshot, writing chunks that were not modified.
+    full: bool,
+    /// The time since startup the current autosave started at, if one is running.
+    started: Option<Duration>,
+    /// The totals of the writes started so far.
+    counters: SaveCounters,
+    /// The writes still running in the background.
+    writes: Vec<ChunkWrite>,
+}
+
+/// A write of a chunk running in the background. The chunk is busy until it finishes, so it is not
+/// written by anything else, such as by unloading, at the same time.
+struct ChunkWrite {
+    /// The position of the chunk.
+    position: ChunkPos,
+    /// The chunk's [`Chunk::changes`](crate::chunk::Chunk::changes) when it was copied for writing.
+    changes: u64,
+    /// The task writing the chunk.
+    task: Task<anyhow::Result<()>>,
+}
+
+impl ChunkWrite {
+    /// Mark the chunk idle now the write has finished, and clean if it succeeded and the chunk has
+    /// not been modified since it was copied.
+    fn finish(self, chunks: &mut Chunks, result: anyhow::Result<()>) {
+        chunks.mark_idle(self.position);
+        match result {
+            Ok(()) => {
+                if let Some(chunk) = chunks.peek_mut(self.position) {
+                    chunk.mark_saved(self.changes);
+                }
+            }
+            Err(err) => error!("Failed to save chunk {:?}: {:?}", self.position, err),
+        }
+    }
+}
+
+impl AutosaveState {
+    /// Return the number of chunks still to be written in the current autosave.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Apply the results of the writes that have finished.
+    fn poll_writes(&mut self, chunks: &mut Chunks) {
+        let mut running = Vec::with_capacity(self.writes.len());
+        for mut write in self.writes.drain(..) {
+            match block_on(poll_once(&mut write.task)) {
+                Some(result) => write.finish(chunks, result),
+                None => running.push(write),
+            }
+        }
+        self.writes = running;
+    }
+
+    /// Wait for every write still running to finish, and apply their results.
+    fn finish_writes(&mut self, chunks: &mut Chunks) {
+        for mut write in self.writes.drain(..) {
+            let result = block_on(&mut write.task);
+            write.finish(chunks, result);
+        }
+    }
+
+    /// Return how long the current autosave has been running at the given time since startup, or
+    /// zero if none is.
+    pub fn lag(&self, now: Duration) -> Duration {
+        self.started
+            .map_or(Duration::ZERO, |started| now.saturating_sub(started))
+    }
+
+    /// Return the totals of the writes started so far.
+    pub fn counters(&self) -> &SaveCounters {
+        &self.counters
+    }
+
+    /// Start an autosave of the given chunks at the given time since startup. A full snapshot
+    /// takes over any autosave still running, since it writes every chunk anyway.
+    fn start(&mut self, chunks: impl IntoIterator<Item = ChunkPos>, full: bool, now: Duration) {
+        self.pending.clear();
+        self.pending.extend(chunks);
+        self.full = full;
+        self.started = (!self.pending.is_empty()).then_some(now);
+    }
+}
+
+/// Check if the world has a store to save chunks to.
+pub fn has_store(storage: Res<WorldStorage>) -> bool {
+    storage.store.is_some()
+}
+
+/// Periodically write chunks to the world's store, spreading the writes over many frames within a
+/// budget so saving never stalls a tick. Modified chunks are saved every interval, and every loaded
+/// chunk in a full snapshot on a longer one. Chunks are snapshotted and written on the IO task
+/// pool, and stay busy until their write finishes, so they are only marked clean once what was
+/// written has reached the store.
+pub fn autosave(
+    mut state: ResMut<AutosaveState>,
+    mut chunks: ResMut<Chunks>,
+    storage: Res<WorldStorage>,
+    settings: Res<AutosaveSettings>,
+    time: Res<Time>,
+    clock: Res<WorldClock>,
+) {
+    let Some(store) = &storage.store else {
+        return;
+    };
+
+    let state = &mut *state;
+    state.poll_writes(&mut chunks);
+    let timer = state
+        .timer
+        .get_or_insert_with(|| Timer::new(settings.interval, TimerMode::Repeating));
+    let incremental = timer.tick(time.delta()).just_finished();
+    let snapshot_timer = state
+        .snapshot_timer
+        .get_or_insert_with(|| Timer::new(settings.snapshot_interval, TimerMode::Repeating));
+    let snapshot = snapshot_timer.tick(time.delta()).just_finished();
+    if snapshot {
+        let loaded = chunks
+            .iter()
+            .map(|chunk| chunk.position)
+            .collect::<Vec<_>>();
+        info!("Snapshotting {} chunks", loaded.len());
+        state.start(loaded, true, time.elapsed());
+    } else if incremental && state.pending.is_empty() {
+        let dirty = chunks
+            .iter()
+            .filter(|chunk| chunk.is_dirty())
+            .map(|chunk| chunk.position)
+            .collect::<Vec<_>>();
+        if !dirty.is_empty() {
+            info!("Autosaving {} chunks", dirty.len());
+        }
+        state.start(dirty, false, time.elapsed());
+    }
+    if snapshot || incremental {
+        if let Err(err) = storage.save_tick(clock.tick()) {
+            error!("Failed to save world metadata: {:?}", err);
+        }
+    }
+
+    let pool = IoTaskPool::get();
+    let deadline = Instant::now() + settings.frame_budget;
+    for _ in 0..settings.chunks_per_frame {
+        if Instant::now() >= deadline {
+            break;
+        }
+        let Some(pos) = state.pending.pop_front() else {
+            break;
+        };
+        // the chunk may have been unloaded, and so saved, since the autosave started, and a chunk
+        // that is busy is still being written, or is being unloaded or regenerated, so it is left
+        // dirty for the next autosave. Saving a chunk does not count as using it, so it is peeked
+        // at rather than looked up
+        let full = state.full;
+        if chunks.is_busy(pos) {
+            continue;
+        }
+        let Some(chunk) = chunks.peek(pos).filter(|chunk| full || chunk.is_dirty()) else {
+            continue;
+        };
+        let snapshot = chunk.clone();
+        let changes = chunk.changes();
+        chunks.mark_busy(pos);
+        let store = store.clone();
+        let counters = state.counters.clone();
+        counters.start();
+        let task = pool.spawn(async move {
+            let started = Instant::now();
+            let result = store.write_chunk(&snapshot);
+            counters.finish(started.elapsed());
+            result
+        });
+        state.writes.push(ChunkWrite {
+            position: pos,
+            changes,
+            task,
+        });
+    }
+    if state.pending.is_empty() {
+        state.started = None;
+    }
+}
+
+/// Write every modified chunk to the world's store when the app exits, since chunks are otherwise
+/// only saved as they unload. Autosave writes still running are waited for first, so chunks they
+/// failed to write, or that were modified while they ran, are written again.
+pub fn save_on_exit(
+    mut exit: EventReader<AppExit>,
+    mut state: ResMut<AutosaveState>,
+    mut chunks: ResMut<Chunks>,
+    storage: Res<WorldStorage>,
+    clock: Res<WorldClock>,
+) {
+    if exit.is_empty() {
+        return;
+    }
+    exit.clear();
+    if let Err(err) = storage.save_tick(clock.tick()) {
+        error!("Failed to save world metadata: {:?}", err);
+    }
+    let Some(store) = &storage.store else {
+        return;
+    };
+
+    state.finish_writes(&mut chunks);
+    let mut saved = 0;
+    for chunk in chunks.iter_mut().filter(|chunk| chunk.is_dirty()) {
+        match store.write_chunk(chunk) {
+            Ok(()) => {
+                chunk.mark_clean();
+                saved += 1;
+            }
+            Err(err) => error!("Failed to save chunk {:?}: {:?}", chunk.position, err),
+        }
+    }
+    info!("Saved {} chunks", saved);
+}
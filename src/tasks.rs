@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bevy::{
+    core::{TaskPoolOptions, TaskPoolThreadAssignmentPolicy},
+    prelude::*,
+    tasks::{
+        available_parallelism, AsyncComputeTaskPool, ComputeTaskPool, IoTaskPool, TaskPoolBuilder,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// The number of threads given to each of the engine's task pools, and whether they are pinned to
+/// cores. Chunks are generated and meshed on the async compute pool and saved on the IO pool, while
+/// the compute pool runs systems in parallel. Pools left unset are sized from the available
+/// parallelism.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskPoolConfig {
+    /// The threads running systems in parallel. Defaults to the cores left by the other pools.
+    pub compute_threads: Option<usize>,
+    /// The threads generating and meshing chunks. Defaults to half the cores.
+    pub async_compute_threads: Option<usize>,
+    /// The threads saving and loading chunks. Defaults to an eighth of the cores, between one and
+    /// four.
+    pub io_threads: Option<usize>,
+    /// Pin each thread to a core of its own, in turn, so the pools do not contend for cores. Only
+    /// supported on Linux, and ignored with a warning elsewhere.
+    pub pin_threads: bool,
+}
+
+/// The number of threads given to each task pool, once the defaults are filled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolSizes {
+    /// The threads running systems in parallel.
+    pub compute: usize,
+    /// The threads generating and meshing chunks.
+    pub async_compute: usize,
+    /// The threads saving and loading chunks.
+    pub io: usize,
+}
+
+impl TaskPoolConfig {
+    /// Return the number of threads each pool gets on a machine with the given number of cores.
+    pub fn sizes(&self, cores: usize) -> PoolSizes {
+        let cores = cores.max(1);
+        let io = self.io_threads.unwrap_or((cores / 8).clamp(1, 4)).max(1);
+        let async_compute = self.async_compute_threads.unwrap_or(cores / 2).max(1);
+        let compute = self
+            .compute_threads
+            .unwrap_or(cores.saturating_sub(io + async_compute))
+            .max(1);
+        PoolSizes {
+            compute,
+            async_compute,
+            io,
+        }
+    }
+
+    /// Return the plugin creating the task pools. Pinned pools are created here instead, since the
+    /// plugin's options cannot run code on threads as they start, and it keeps pools that exist.
+    pub fn plugin(&self) -> TaskPoolPlugin {
+        let sizes = self.sizes(available_parallelism());
+        if self.pin_threads {
+            init_pinned_pools(sizes);
+        }
+        let exactly = |threads| TaskPoolThreadAssignmentPolicy {
+            min_threads: threads,
+            max_threads: threads,
+            percent: 0.0,
+        };
+        TaskPoolPlugin {
+            task_pool_options: TaskPoolOptions {
+                io: exactly(sizes.io),
+                async_compute: exactly(sizes.async_compute),
+                compute: exactly(sizes.compute),
+                ..default()
+            },
+        }
+    }
+}
+
+/// The core the next task pool thread to start is pinned to, counting up past the last core.
+static NEXT_CORE: AtomicUsize = AtomicUsize::new(0);
+
+/// Create the task pools with each of their threads pinned to the next core in turn.
+fn init_pinned_pools(sizes: PoolSizes) {
+    // logging is not set up until the app is built, so this goes straight to stderr
+    if !cfg!(target_os = "linux") {
+        eprintln!("Pinning task pool threads is only supported on Linux");
+        return;
+    }
+    let cores = available_parallelism();
+    let pinned = |name: &str, threads| {
+        TaskPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(name.into())
+            .on_thread_spawn(move || pin_thread(NEXT_CORE.fetch_add(1, Ordering::Relaxed) % cores))
+            .build()
+    };
+    IoTaskPool::get_or_init(|| pinned("IO Task Pool", sizes.io));
+    AsyncComputeTaskPool::get_or_init(|| pinned("Async Compute Task Pool", sizes.async_compute));
+    ComputeTaskPool::get_or_init(|| pinned("Compute Task Pool", sizes.compute));
+}
+
+/// Pin the current thread to the given core.
+#[cfg(target_os = "linux")]
+fn pin_thread(core: usize) {
+    // SAFETY: the set is zeroed before use, and only read by the call that pins the thread
+    let result = unsafe {
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_SET(core, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of_val(&set), &set)
+    };
+    if result != 0 {
+        eprintln!(
+            "Failed to pin a task pool thread to core {}: {}",
+            core,
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_thread(_core: usize) {}
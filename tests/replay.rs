@@ -0,0 +1,177 @@
+//! Replays a scripted play session against a fixed seed in a headless app, and checks the world it
+//! leaves behind, guarding the whole chunk pipeline (generation, structures, editing, saving, and
+//! reloading) against regressions.
+
+use std::{thread, time::Duration};
+
+use bevy::prelude::*;
+use chunky::{
+    chunk::{
+        generation::seed::TerrainNoise, random_tick::RandomTickSettings, scheduled::BlockTicks,
+        updates::BlockUpdateQueue, BlockPos, BlockType, ChunkCommand, ChunkPlugin, ChunkPos,
+        Chunks,
+    },
+    storage::{codec::Compression, StorageBackend, WorldStorage},
+};
+use itertools::iproduct;
+
+/// The seed the session's world is generated from.
+const SEED: u32 = 1549;
+
+/// The hash of the blocks of every chunk loaded at the end of the session. If generation or
+/// editing is changed on purpose, replace this with the hash the test reports.
+const EXPECTED_HASH: u64 = 0x5509_0ac4_6829_c588;
+
+/// The distance around the player that chunks are kept loaded, in chunks.
+const VIEW_RADIUS: i64 = 1;
+
+/// The most frames the pipeline may take to settle after a step before it is considered stuck.
+const MAX_FRAMES: usize = 10_000;
+
+/// A step of a play session.
+enum Step {
+    /// Move the player into the given chunk, streaming chunks in and out around it.
+    MoveTo(ChunkPos),
+    /// Set the block at the given world block coordinates.
+    Place(IVec3, BlockType),
+    /// Fill the box between two world block coordinates (inclusive) with a block.
+    Fill(IVec3, IVec3, BlockType),
+}
+
+/// The session: wander away from the origin and back, editing the world on the way, so edits are
+/// saved when their chunks unload and restored when they load again.
+fn session() -> Vec<Step> {
+    vec![
+        Step::MoveTo(ChunkPos::new(0, 0, 0)),
+        Step::Fill(
+            IVec3::new(-4, 10, -4),
+            IVec3::new(4, 14, 4),
+            BlockType::SAND,
+        ),
+        Step::Place(IVec3::new(0, 40, 0), BlockType::WOOD),
+        Step::Place(IVec3::new(5, 5, 5), BlockType::EMPTY),
+        Step::MoveTo(ChunkPos::new(1, 0, 0)),
+        Step::MoveTo(ChunkPos::new(2, 0, 0)),
+        Step::Fill(
+            IVec3::new(70, 0, 0),
+            IVec3::new(72, 31, 2),
+            BlockType::EMPTY,
+        ),
+        Step::MoveTo(ChunkPos::new(2, 0, 2)),
+        Step::Place(IVec3::new(80, 20, 80), BlockType::SNOW),
+        Step::MoveTo(ChunkPos::new(0, 0, 0)),
+        Step::Fill(
+            IVec3::new(-2, 12, -2),
+            IVec3::new(2, 12, 2),
+            BlockType::WATER,
+        ),
+        Step::MoveTo(ChunkPos::new(0, -1, 0)),
+    ]
+}
+
+/// Build a headless app generating from [`SEED`], saving chunks to memory.
+fn app() -> App {
+    let storage = WorldStorage::open(StorageBackend::Memory, "", Compression::default())
+        .expect("failed to open memory store");
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ChunkPlugin { headless: true }))
+        .insert_resource(storage)
+        .insert_resource(TerrainNoise::new(SEED))
+        // the world should only change by the session's steps, however fast the test runs
+        .insert_resource(RandomTickSettings {
+            blocks_per_chunk: 0,
+        });
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Send the commands a step calls for.
+fn apply(app: &mut App, step: &Step) {
+    let commands = match step {
+        Step::MoveTo(center) => {
+            let chunks = app.world().resource::<Chunks>();
+            let load = iproduct!(
+                -VIEW_RADIUS..=VIEW_RADIUS,
+                -VIEW_RADIUS..=VIEW_RADIUS,
+                -VIEW_RADIUS..=VIEW_RADIUS
+            )
+            .map(|offset| *center + offset.into())
+            .filter(|&pos| chunks.is_unloaded(pos))
+            .map(ChunkCommand::Load);
+            let unload = chunks
+                .iter()
+                .map(|chunk| chunk.position)
+                .filter(|&pos| center.distance(pos) > VIEW_RADIUS)
+                .map(ChunkCommand::Unload);
+            load.chain(unload).collect()
+        }
+        Step::Place(pos, block) => {
+            let chunk = ChunkPos::from_world_block(*pos);
+            let block_pos = BlockPos::from_world_block(*pos);
+            vec![ChunkCommand::ModifyBlock(chunk, block_pos, *block)]
+        }
+        Step::Fill(min, max, block) => vec![ChunkCommand::FillRegion(*min, *max, *block)],
+    };
+    app.world_mut().send_event_batch(commands);
+}
+
+/// Run frames until every command has been carried out, every task has finished, and the blocks
+/// the steps disturbed have come to rest, panicking if that takes more than [`MAX_FRAMES`].
+fn settle(app: &mut App) {
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        let chunks = app.world().resource::<Chunks>();
+        let ticks = app.world().resource::<BlockTicks>();
+        let updates = app.world().resource::<BlockUpdateQueue>();
+        let at_rest = ticks.is_empty() && updates.is_empty();
+        // chunk tasks and falling blocks are the only entities in a headless app
+        if chunks.is_settled() && at_rest && app.world().entities().is_empty() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("chunk pipeline did not settle");
+}
+
+/// Hash the blocks of every loaded chunk, in order of position, with FNV-1a so the hash is the
+/// same on every platform and toolchain.
+fn world_hash(chunks: &Chunks) -> u64 {
+    let mut loaded = chunks.iter().collect::<Vec<_>>();
+    loaded.sort_by_key(|chunk| (chunk.position.x, chunk.position.y, chunk.position.z));
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut write = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    for chunk in loaded {
+        let pos = chunk.position;
+        for coordinate in [pos.x, pos.y, pos.z] {
+            write(&coordinate.to_le_bytes());
+        }
+        for pos in BlockPos::all() {
+            write(&chunk.block_at(pos).id().to_le_bytes());
+        }
+    }
+    hash
+}
+
+#[test]
+fn replay_session() {
+    let mut app = app();
+    for step in session() {
+        apply(&mut app, &step);
+        settle(&mut app);
+    }
+
+    let chunks = app.world().resource::<Chunks>();
+    let expected = (VIEW_RADIUS * 2 + 1).pow(3) as usize;
+    assert_eq!(
+        chunks.iter().count(),
+        expected,
+        "wrong number of chunks loaded"
+    );
+    let hash = world_hash(chunks);
+    assert_eq!(hash, EXPECTED_HASH, "world hash changed: {:#018x}", hash);
+}